@@ -0,0 +1,468 @@
+//! `koala-clash-cli`，通过单例端口上的本地接口控制正在运行的实例
+
+use crate::{
+    config::IVerge,
+    utils::{dirs, help},
+};
+use anyhow::{anyhow, Result};
+use reqwest::{Client, Method, StatusCode};
+use serde_json::{json, Value};
+use std::{process::ExitCode, time::Duration};
+
+const TOKEN_ENV: &str = "KOALA_CLASH_TOKEN";
+/// 更新订阅可能较慢
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+const USAGE: &str = "Usage: koala-clash-cli [--json] [--token <token>] [--port <port>] <command>
+
+Commands:
+  status                          Show the state of the running instance
+  profile list                    List profiles
+  profile use <uid|name>          Switch the current profile
+  profile update [uid|name]       Update a profile, the current one by default
+  mode rule|global|direct         Set the clash mode
+  proxy select <group> <node>     Select a node in a group
+  sysproxy on|off                 Toggle the system proxy
+  tun on|off                      Toggle TUN mode
+  import <url>                    Import a subscription
+  backup create                   Create a backup and upload it to the backup storage
+
+The local API must be enabled in the settings. The token is read from
+--token, the KOALA_CLASH_TOKEN environment variable or the app config.
+
+Exit codes: 0 success, 1 request failed, 2 usage error,
+            3 instance not running, 4 unauthorized or local API disabled";
+
+/// 退出码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Exit {
+    Failed = 1,
+    Usage = 2,
+    NotRunning = 3,
+    Unauthorized = 4,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Command {
+    Help,
+    Status,
+    ProfileList,
+    ProfileUse(String),
+    ProfileUpdate(Option<String>),
+    Mode(String),
+    ProxySelect(String, String),
+    SysProxy(bool),
+    Tun(bool),
+    Import(String),
+    BackupCreate,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Cli {
+    json: bool,
+    token: Option<String>,
+    port: Option<u16>,
+    command: Command,
+}
+
+fn parse_switch(value: &str) -> Result<bool, String> {
+    match value {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err("expected `on` or `off`".into()),
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Cli, String> {
+    let mut json = false;
+    let mut token = None;
+    let mut port = None;
+    let mut words = Vec::new();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "-h" | "--help" => words = vec!["help"],
+            "--token" => {
+                token = Some(iter.next().ok_or("--token requires a value")?.clone());
+            }
+            "--port" => {
+                let value = iter.next().ok_or("--port requires a value")?;
+                port = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("invalid port `{value}`"))?,
+                );
+            }
+            flag if flag.starts_with("--") => return Err(format!("unknown option `{flag}`")),
+            word => words.push(word),
+        }
+    }
+
+    let command = match words.as_slice() {
+        [] | ["help"] => Command::Help,
+        ["status"] => Command::Status,
+        ["profile", "list"] => Command::ProfileList,
+        ["profile", "use", profile] => Command::ProfileUse(profile.to_string()),
+        ["profile", "update"] => Command::ProfileUpdate(None),
+        ["profile", "update", profile] => Command::ProfileUpdate(Some(profile.to_string())),
+        ["mode", mode @ ("rule" | "global" | "direct")] => Command::Mode(mode.to_string()),
+        ["proxy", "select", group, node] => {
+            Command::ProxySelect(group.to_string(), node.to_string())
+        }
+        ["sysproxy", value] => Command::SysProxy(parse_switch(value)?),
+        ["tun", value] => Command::Tun(parse_switch(value)?),
+        ["import", url] => Command::Import(url.to_string()),
+        ["backup", "create"] => Command::BackupCreate,
+        _ => return Err(format!("invalid command `{}`", words.join(" "))),
+    };
+    Ok(Cli {
+        json,
+        token,
+        port,
+        command,
+    })
+}
+
+/// 请求失败时附带退出码
+struct CliError {
+    exit: Exit,
+    message: String,
+}
+
+impl From<anyhow::Error> for CliError {
+    fn from(err: anyhow::Error) -> Self {
+        Self {
+            exit: Exit::Failed,
+            message: err.to_string(),
+        }
+    }
+}
+
+struct Instance {
+    client: Client,
+    base: String,
+    token: String,
+}
+
+impl Instance {
+    fn new(cli: &Cli) -> Result<Self> {
+        let port = cli.port.unwrap_or_else(IVerge::get_singleton_port);
+        let token = cli
+            .token
+            .clone()
+            .or_else(|| std::env::var(TOKEN_ENV).ok())
+            .or_else(config_token)
+            .unwrap_or_default();
+        let client = Client::builder()
+            .no_proxy()
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+        Ok(Self {
+            client,
+            base: format!("http://127.0.0.1:{port}/api"),
+            token,
+        })
+    }
+
+    async fn request(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<Value>,
+    ) -> Result<Value, CliError> {
+        let mut request = self
+            .client
+            .request(method, format!("{}{path}", self.base))
+            .bearer_auth(&self.token);
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        let response = request.send().await.map_err(|err| CliError {
+            exit: if err.is_connect() {
+                Exit::NotRunning
+            } else {
+                Exit::Failed
+            },
+            message: if err.is_connect() {
+                "koala clash is not running".into()
+            } else {
+                err.to_string()
+            },
+        })?;
+
+        let status = response.status();
+        let value = response.json::<Value>().await.unwrap_or(Value::Null);
+        if status.is_success() {
+            return Ok(value);
+        }
+        let message = value
+            .get("error")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .unwrap_or_else(|| status.to_string());
+        let exit = match status {
+            StatusCode::UNAUTHORIZED | StatusCode::NOT_FOUND
+                if message == "invalid token" || message == "local api is disabled" =>
+            {
+                Exit::Unauthorized
+            }
+            _ => Exit::Failed,
+        };
+        Err(CliError { exit, message })
+    }
+
+    async fn get(&self, path: &str) -> Result<Value, CliError> {
+        self.request(Method::GET, path, None).await
+    }
+
+    /// 根据 uid 或名称查找订阅
+    async fn resolve_profile(&self, profile: Option<&str>) -> Result<String, CliError> {
+        let profiles = self.get("/profiles").await?;
+        let profiles = profiles.as_array().cloned().unwrap_or_default();
+        let found = profiles.iter().find(|item| match profile {
+            Some(profile) => {
+                item.get("uid").and_then(Value::as_str) == Some(profile)
+                    || item.get("name").and_then(Value::as_str) == Some(profile)
+            }
+            None => item.get("current").and_then(Value::as_bool) == Some(true),
+        });
+        found
+            .and_then(|item| item.get("uid").and_then(Value::as_str))
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("profile `{}` not found", profile.unwrap_or("current")).into())
+    }
+}
+
+/// 从配置文件读取本地接口 token
+fn config_token() -> Option<String> {
+    let _ = dirs::init_portable_flag();
+    let verge = help::read_yaml::<IVerge>(&dirs::verge_path().ok()?).ok()?;
+    verge.local_api_token
+}
+
+fn encode(segment: &str) -> String {
+    percent_encoding::utf8_percent_encode(segment, percent_encoding::NON_ALPHANUMERIC).to_string()
+}
+
+async fn execute(cli: &Cli) -> Result<Value, CliError> {
+    let instance = Instance::new(cli)?;
+    match &cli.command {
+        Command::Help => Ok(Value::Null),
+        Command::Status => instance.get("/status").await,
+        Command::ProfileList => instance.get("/profiles").await,
+        Command::ProfileUse(profile) => {
+            let uid = instance.resolve_profile(Some(profile)).await?;
+            instance
+                .request(
+                    Method::PUT,
+                    "/profiles/current",
+                    Some(json!({ "uid": uid })),
+                )
+                .await
+        }
+        Command::ProfileUpdate(profile) => {
+            let uid = instance.resolve_profile(profile.as_deref()).await?;
+            let path = format!("/profiles/{}/update", encode(&uid));
+            instance.request(Method::POST, &path, None).await
+        }
+        Command::Mode(mode) => {
+            instance
+                .request(Method::PUT, "/mode", Some(json!({ "mode": mode })))
+                .await
+        }
+        Command::ProxySelect(group, node) => {
+            let path = format!("/proxies/{}", encode(group));
+            instance
+                .request(Method::PUT, &path, Some(json!({ "name": node })))
+                .await
+        }
+        Command::SysProxy(enable) => {
+            instance
+                .request(Method::PUT, "/sysproxy", Some(json!({ "enable": enable })))
+                .await
+        }
+        Command::Tun(enable) => {
+            instance
+                .request(Method::PUT, "/tun", Some(json!({ "enable": enable })))
+                .await
+        }
+        Command::Import(url) => {
+            instance
+                .request(
+                    Method::POST,
+                    "/profiles/import",
+                    Some(json!({ "url": url })),
+                )
+                .await
+        }
+        Command::BackupCreate => instance.request(Method::POST, "/backup", None).await,
+    }
+}
+
+fn on_off(value: &Value) -> &'static str {
+    if value.as_bool().unwrap_or(false) {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+/// 面向用户的输出
+fn format_human(command: &Command, value: &Value) -> String {
+    let text = |key: &str| {
+        value
+            .get(key)
+            .and_then(Value::as_str)
+            .unwrap_or("-")
+            .to_string()
+    };
+    match command {
+        Command::Status => [
+            format!("version:      {}", text("version")),
+            format!(
+                "core:         {}",
+                if value["core_running"].as_bool().unwrap_or(false) {
+                    "running"
+                } else {
+                    "stopped"
+                }
+            ),
+            format!("mode:         {}", text("mode")),
+            format!("mixed port:   {}", value["mixed_port"]),
+            format!("system proxy: {}", on_off(&value["system_proxy"])),
+            format!("tun:          {}", on_off(&value["tun"])),
+            format!("lightweight:  {}", on_off(&value["lightweight"])),
+            format!("profile:      {}", text("current_profile")),
+        ]
+        .join("\n"),
+        Command::ProfileList => value
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|item| {
+                format!(
+                    "{} {:<16} {}",
+                    if item["current"].as_bool().unwrap_or(false) {
+                        "*"
+                    } else {
+                        " "
+                    },
+                    item["uid"].as_str().unwrap_or_default(),
+                    item["name"].as_str().unwrap_or_default()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Command::ProfileUse(_) => format!("switched to {}", text("current")),
+        Command::ProfileUpdate(_) => format!("updated {}", text("updated")),
+        Command::Mode(_) => format!("mode: {}", text("mode")),
+        Command::ProxySelect(..) => format!("{}: {}", text("group"), text("now")),
+        Command::SysProxy(_) => format!("system proxy: {}", on_off(&value["system_proxy"])),
+        Command::Tun(_) => format!("tun: {}", on_off(&value["tun"])),
+        Command::Import(_) => format!("imported {}", text("imported")),
+        Command::BackupCreate => "backup uploaded".into(),
+        Command::Help => USAGE.into(),
+    }
+}
+
+/// 命令行入口
+pub fn run() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let cli = match parse_args(&args) {
+        Ok(cli) => cli,
+        Err(err) => {
+            eprintln!("error: {err}\n\n{USAGE}");
+            return ExitCode::from(Exit::Usage as u8);
+        }
+    };
+    if cli.command == Command::Help {
+        println!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+
+    let runtime = match tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(runtime) => runtime,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return ExitCode::from(Exit::Failed as u8);
+        }
+    };
+
+    match runtime.block_on(execute(&cli)) {
+        Ok(value) => {
+            if cli.json {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&value).unwrap_or_default()
+                );
+            } else {
+                println!("{}", format_human(&cli.command, &value));
+            }
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            if cli.json {
+                println!("{}", json!({ "error": err.message }));
+            } else {
+                eprintln!("error: {}", err.message);
+            }
+            ExitCode::from(err.exit as u8)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Cli, String> {
+        let args = args
+            .split_whitespace()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        parse_args(&args)
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let cli = parse("--json status --port 1234").unwrap();
+        assert!(cli.json);
+        assert_eq!(cli.port, Some(1234));
+        assert_eq!(cli.command, Command::Status);
+
+        assert_eq!(parse("").unwrap().command, Command::Help);
+        assert_eq!(
+            parse("profile update").unwrap().command,
+            Command::ProfileUpdate(None)
+        );
+        assert_eq!(
+            parse("--token abc profile use work").unwrap(),
+            Cli {
+                json: false,
+                token: Some("abc".into()),
+                port: None,
+                command: Command::ProfileUse("work".into()),
+            }
+        );
+        assert_eq!(
+            parse("proxy select Proxy HK-01").unwrap().command,
+            Command::ProxySelect("Proxy".into(), "HK-01".into())
+        );
+        assert_eq!(parse("tun off").unwrap().command, Command::Tun(false));
+        assert_eq!(
+            parse("mode global").unwrap().command,
+            Command::Mode("global".into())
+        );
+
+        assert!(parse("mode script").is_err());
+        assert!(parse("sysproxy maybe").is_err());
+        assert!(parse("--port abc status").is_err());
+        assert!(parse("--verbose status").is_err());
+        assert!(parse("backup").is_err());
+    }
+}
@@ -1,6 +1,7 @@
 use super::CmdResult;
 use crate::{
     feat, logging,
+    state::degraded::DegradedState,
     utils::{dirs, logging::Type},
     wrap_err,
 };
@@ -74,6 +75,12 @@ pub fn get_app_dir() -> CmdResult<String> {
     Ok(app_home_dir)
 }
 
+/// 获取降级状态，使用最近可用配置启动时为降级
+#[tauri::command]
+pub fn get_degraded_state() -> CmdResult<DegradedState> {
+    Ok(DegradedState::get())
+}
+
 /// 获取当前自启动状态
 #[tauri::command]
 pub fn get_auto_launch_status() -> CmdResult<bool> {
@@ -0,0 +1,76 @@
+use super::CmdResult;
+use crate::{
+    config::*,
+    core::{self, backup::BackupFile},
+    feat, wrap_err,
+};
+
+/// 保存 WebDAV 配置
+#[tauri::command]
+pub async fn save_webdav_config(url: String, username: String, password: String) -> CmdResult<()> {
+    let patch = IVerge {
+        webdav_url: Some(url),
+        webdav_username: Some(username),
+        webdav_password: Some(password),
+        ..IVerge::default()
+    };
+    Config::verge().draft().patch_config(patch.clone());
+    Config::verge().apply();
+    Config::verge()
+        .data()
+        .save_file()
+        .map_err(|err| err.to_string())?;
+    core::backup::WebDavClient::global().reset();
+    Ok(())
+}
+
+/// 保存备份存储的选择和配置，`storage` 为 webdav、local、s3 或 sftp
+#[tauri::command]
+pub async fn save_backup_storage(
+    storage: String,
+    local_dir: Option<String>,
+    s3: Option<IVergeS3Backup>,
+    sftp: Option<IVergeSftpBackup>,
+) -> CmdResult<()> {
+    if !["webdav", "local", "s3", "sftp"].contains(&storage.as_str()) {
+        return Err(format!("Unknown backup storage: {storage}"));
+    }
+    let patch = IVerge {
+        backup_storage: Some(storage),
+        backup_local_dir: local_dir,
+        backup_s3: s3,
+        backup_sftp: sftp,
+        ..IVerge::default()
+    };
+    Config::verge().draft().patch_config(patch);
+    Config::verge().apply();
+    Config::verge()
+        .data()
+        .save_file()
+        .map_err(|err| err.to_string())?;
+    Ok(())
+}
+
+/// 创建备份并上传到当前的备份存储
+#[tauri::command]
+pub async fn create_backup() -> CmdResult<()> {
+    wrap_err!(feat::create_backup_and_upload().await)
+}
+
+/// 列出备份存储中的备份文件
+#[tauri::command]
+pub async fn list_backup() -> CmdResult<Vec<BackupFile>> {
+    wrap_err!(feat::list_backup().await)
+}
+
+/// 删除备份存储中的备份文件
+#[tauri::command]
+pub async fn delete_backup(filename: String) -> CmdResult<()> {
+    wrap_err!(feat::delete_backup(filename).await)
+}
+
+/// 从备份存储恢复备份文件
+#[tauri::command]
+pub async fn restore_backup(filename: String) -> CmdResult<()> {
+    wrap_err!(feat::restore_backup(filename).await)
+}
@@ -1,6 +1,14 @@
 use super::CmdResult;
 use crate::{
-    config::*, core::*, feat, module::mihomo::MihomoManager, process::AsyncHandler, wrap_err,
+    config::*,
+    core::*,
+    feat,
+    module::{
+        latency::{self, LatencyHistory, LatencyStats},
+        latency_batch::{self, BatchDelaySummary},
+    },
+    process::AsyncHandler,
+    wrap_err,
 };
 use serde_yaml::Mapping;
 
@@ -90,9 +98,48 @@ pub async fn clash_api_get_proxy_delay(
     url: Option<String>,
     timeout: i32,
 ) -> CmdResult<serde_json::Value> {
-    MihomoManager::global()
-        .test_proxy_delay(&name, url, timeout)
+    latency::test_proxy_delay(&name, url, timeout).await
+}
+
+/// 批量测试节点延迟，`groups` 中的节点会被展开并去重，进度通过事件通知前端
+#[tauri::command]
+pub async fn batch_test_delay(
+    batch_id: String,
+    nodes: Option<Vec<String>>,
+    groups: Option<Vec<String>>,
+    url: Option<String>,
+    concurrency: Option<usize>,
+) -> CmdResult<BatchDelaySummary> {
+    wrap_err!(
+        latency_batch::run(
+            batch_id,
+            nodes.unwrap_or_default(),
+            groups.unwrap_or_default(),
+            url,
+            concurrency,
+        )
         .await
+    )
+}
+
+/// 取消批量延迟测试
+#[tauri::command]
+pub fn cancel_batch_test_delay(batch_id: String) -> CmdResult<bool> {
+    Ok(latency_batch::cancel(&batch_id))
+}
+
+/// 获取当前订阅中节点的延迟统计，`name` 为空时返回全部节点
+#[tauri::command]
+pub fn get_latency_stats(
+    name: Option<String>,
+    window_secs: Option<i64>,
+    buckets: Option<usize>,
+) -> CmdResult<Vec<LatencyStats>> {
+    Ok(LatencyHistory::global().stats(
+        name.as_deref(),
+        window_secs.unwrap_or(24 * 60 * 60),
+        buckets.unwrap_or(24),
+    ))
 }
 
 /// 测试URL延迟
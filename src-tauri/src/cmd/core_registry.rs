@@ -0,0 +1,70 @@
+use super::CmdResult;
+use crate::{
+    core::{
+        core_registry::{CoreRecord, CoreRegistry, ICoreRegistry},
+        handle, CoreManager,
+    },
+    logging,
+    utils::logging::Type,
+    wrap_err,
+};
+
+/// 切换内核后重启，失败时回滚到之前的内核
+async fn restart_with_core(id: Option<String>) -> CmdResult {
+    let previous = CoreRegistry::global().get().active;
+    wrap_err!(CoreRegistry::global().switch(id.clone()))?;
+
+    if let Err(err) = CoreManager::global().restart_core().await {
+        logging!(
+            error,
+            Type::Core,
+            true,
+            "Failed to restart with core {:?}, rolling back: {}",
+            id,
+            err
+        );
+        wrap_err!(CoreRegistry::global().switch(previous))?;
+        wrap_err!(CoreManager::global().restart_core().await)?;
+        return Err(err.to_string());
+    }
+
+    handle::Handle::refresh_clash();
+    Ok(())
+}
+
+/// 获取已安装的内核
+#[tauri::command]
+pub fn get_core_registry() -> CmdResult<ICoreRegistry> {
+    Ok(CoreRegistry::global().get())
+}
+
+/// 从本地压缩包或 URL 安装内核，`sha256` 为压缩包的校验值
+#[tauri::command]
+pub async fn install_core(source: String, sha256: String) -> CmdResult<CoreRecord> {
+    wrap_err!(CoreRegistry::global().install(source, sha256).await)
+}
+
+/// 添加自定义路径的内核
+#[tauri::command]
+pub async fn add_custom_core(path: String) -> CmdResult<CoreRecord> {
+    wrap_err!(CoreRegistry::global().add_custom(path).await)
+}
+
+/// 切换到指定的内核，`id` 为空时使用内置内核
+#[tauri::command]
+pub async fn switch_core(id: Option<String>) -> CmdResult {
+    restart_with_core(id).await
+}
+
+/// 回滚到上一次使用的内核
+#[tauri::command]
+pub async fn rollback_core() -> CmdResult {
+    let previous = CoreRegistry::global().get().previous;
+    restart_with_core(previous).await
+}
+
+/// 删除已安装的内核
+#[tauri::command]
+pub fn remove_core(id: String) -> CmdResult {
+    wrap_err!(CoreRegistry::global().remove(&id))
+}
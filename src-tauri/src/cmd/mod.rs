@@ -5,7 +5,9 @@ pub type CmdResult<T = ()> = Result<T, String>;
 
 // Command modules
 pub mod app;
+pub mod backup;
 pub mod clash;
+pub mod core_registry;
 pub mod lightweight;
 pub mod media_unlock_checker;
 pub mod network;
@@ -13,16 +15,19 @@ pub mod profile;
 pub mod proxy;
 pub mod runtime;
 pub mod save_profile;
+pub mod schedule;
+pub mod script;
 pub mod service;
 pub mod system;
 pub mod uwp;
 pub mod validate;
 pub mod verge;
-pub mod webdav;
 
 // Re-export all command functions for backwards compatibility
 pub use app::*;
+pub use backup::*;
 pub use clash::*;
+pub use core_registry::*;
 pub use lightweight::*;
 pub use media_unlock_checker::*;
 pub use network::*;
@@ -30,9 +35,10 @@ pub use profile::*;
 pub use proxy::*;
 pub use runtime::*;
 pub use save_profile::*;
+pub use schedule::*;
+pub use script::*;
 pub use service::*;
 pub use system::*;
 pub use uwp::*;
 pub use validate::*;
 pub use verge::*;
-pub use webdav::*;
@@ -1,5 +1,7 @@
 use super::CmdResult;
+use crate::config::INetworkRules;
 use crate::core::{async_proxy_query::AsyncProxyQuery, EventDrivenProxyManager};
+use crate::module::network_rules;
 use crate::wrap_err;
 use network_interface::NetworkInterface;
 use serde_yaml::Mapping;
@@ -93,3 +95,27 @@ pub fn get_network_interfaces_info() -> CmdResult<Vec<NetworkInterface>> {
 
     Ok(result)
 }
+
+/// 获取网络环境规则
+#[tauri::command]
+pub fn get_network_rules() -> CmdResult<INetworkRules> {
+    Ok(INetworkRules::new())
+}
+
+/// 保存网络环境规则并立即重新评估
+#[tauri::command]
+pub async fn save_network_rules(rules: INetworkRules) -> CmdResult {
+    wrap_err!(network_rules::save_rules(rules).await)
+}
+
+/// 暂停或恢复网络环境规则的自动切换
+#[tauri::command]
+pub async fn set_network_rules_paused(paused: bool) -> CmdResult {
+    wrap_err!(network_rules::set_paused(paused).await)
+}
+
+/// 立即评估网络环境规则，返回匹配的规则名称
+#[tauri::command]
+pub async fn evaluate_network_rules() -> CmdResult<Option<String>> {
+    wrap_err!(network_rules::evaluate().await)
+}
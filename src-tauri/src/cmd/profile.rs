@@ -171,47 +171,7 @@ pub async fn enhance_profiles() -> CmdResult {
 /// 导入配置文件
 #[tauri::command]
 pub async fn import_profile(url: String, option: Option<PrfOption>) -> CmdResult {
-    let existing_uid = {
-        let profiles = Config::profiles();
-        let profiles = profiles.latest();
-
-        profiles
-            .items
-            .as_ref()
-            .and_then(|items| items.iter().find(|item| item.url.as_deref() == Some(&url)))
-            .and_then(|item| item.uid.clone())
-    };
-
-    if let Some(uid) = existing_uid {
-        logging!(
-            info,
-            Type::Cmd,
-            true,
-            "The profile with URL {} already exists (UID: {}). Running the update...",
-            url,
-            uid
-        );
-        update_profile(uid, option).await
-    } else {
-        logging!(
-            info,
-            Type::Cmd,
-            true,
-            "Profile with URL {} not found. Create a new one...",
-            url
-        );
-        let item = wrap_err!(PrfItem::from_url(&url, None, None, option).await)?;
-        let new_uid = item.uid.clone().unwrap_or_default();
-        wrap_err!(Config::profiles().data().append_item(item))?;
-        if !new_uid.is_empty() {
-            let _ = patch_profiles_config(IProfiles {
-                current: Some(new_uid),
-                items: None,
-            })
-            .await?;
-        }
-        Ok(())
-    }
+    wrap_err!(feat::import_profile(url, option).await)
 }
 
 /// 重新排序配置文件
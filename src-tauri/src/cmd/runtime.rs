@@ -1,5 +1,9 @@
 use super::CmdResult;
-use crate::{config::*, wrap_err};
+use crate::{
+    config::*,
+    core::core_log::{self, CoreLogEntry, CoreLogQuery},
+    wrap_err,
+};
 use anyhow::Context;
 use serde_yaml::Mapping;
 use std::collections::HashMap;
@@ -34,3 +38,13 @@ pub fn get_runtime_exists() -> CmdResult<Vec<String>> {
 pub fn get_runtime_logs() -> CmdResult<HashMap<String, Vec<(String, String)>>> {
     Ok(Config::runtime().latest().chain_logs.clone())
 }
+
+/// 查询内核日志，按等级、时间范围和关键字过滤
+#[tauri::command]
+pub async fn get_core_logs(query: Option<CoreLogQuery>) -> CmdResult<Vec<CoreLogEntry>> {
+    let query = query.unwrap_or_default();
+    let logs = tokio::task::spawn_blocking(move || core_log::query_logs(&query))
+        .await
+        .map_err(|err| err.to_string())?;
+    wrap_err!(logs)
+}
@@ -0,0 +1,33 @@
+use super::CmdResult;
+use crate::{core::schedule, wrap_err};
+use anyhow::anyhow;
+
+const PREVIEW_COUNT: usize = 5;
+/// 预览数量上限，避免前端传入过大的值
+const MAX_PREVIEW_COUNT: usize = 100;
+
+/// 获取定时任务及接下来的触发时间
+#[tauri::command]
+pub fn get_schedules() -> CmdResult<Vec<schedule::ScheduleInfo>> {
+    Ok(schedule::list(PREVIEW_COUNT))
+}
+
+/// 预览 cron 表达式接下来的触发时间
+#[tauri::command]
+pub fn preview_schedule(cron: String, count: Option<usize>) -> CmdResult<Vec<i64>> {
+    wrap_err!(schedule::preview(
+        &cron,
+        count.unwrap_or(PREVIEW_COUNT).min(MAX_PREVIEW_COUNT)
+    ))
+}
+
+/// 立即执行一个定时任务，用于测试
+#[tauri::command]
+pub async fn run_schedule(name: String) -> CmdResult {
+    let target = schedule::schedules()
+        .into_iter()
+        .find(|schedule| schedule.name == name)
+        .ok_or(anyhow!("schedule `{name}` not found"));
+    let target = wrap_err!(target)?;
+    wrap_err!(schedule::run(&target.action).await)
+}
@@ -0,0 +1,77 @@
+use super::CmdResult;
+use crate::{
+    config::Config,
+    enhance::harness::{self, ScriptReport},
+    logging,
+    utils::{dirs, help, logging::Type},
+    wrap_err,
+};
+use serde::Deserialize;
+use serde_yaml::Mapping;
+
+/// 脚本测试使用的配置来源
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScriptFixture {
+    /// 已保存的订阅 uid
+    Profile(String),
+    /// 内联的 YAML 配置
+    Yaml(String),
+}
+
+fn load_fixture(fixture: Option<ScriptFixture>) -> anyhow::Result<Mapping> {
+    let profiles = Config::profiles();
+    let profiles = profiles.latest();
+
+    match fixture {
+        Some(ScriptFixture::Profile(uid)) => {
+            let item = profiles.get_item(&uid)?;
+            let file = item
+                .file
+                .clone()
+                .ok_or_else(|| anyhow::anyhow!("profile `{uid}` has no file"))?;
+            help::read_mapping(&dirs::app_profiles_dir()?.join(file))
+        }
+        Some(ScriptFixture::Yaml(yaml)) => Ok(serde_yaml::from_str::<Mapping>(&yaml)?),
+        None => profiles.current_mapping(),
+    }
+}
+
+/// 在指定配置上测试脚本，返回输出、日志、耗时、差异和测试用例结果
+#[tauri::command]
+pub async fn test_profile_script(
+    uid: String,
+    fixture: Option<ScriptFixture>,
+) -> CmdResult<ScriptReport> {
+    let (script, name) = {
+        let profiles = Config::profiles();
+        let profiles = profiles.latest();
+        let item = wrap_err!(profiles.get_item(&uid))?;
+        let script = wrap_err!(item.read_file())?;
+        let name = profiles
+            .get_item(&profiles.get_current().unwrap_or_default())
+            .ok()
+            .and_then(|item| item.name.clone())
+            .unwrap_or_default();
+        (script, name)
+    };
+    let fixture = wrap_err!(load_fixture(fixture))?;
+
+    let report = tokio::task::spawn_blocking(move || harness::run_script(script, fixture, name))
+        .await
+        .map_err(|err| err.to_string())?;
+    let report = wrap_err!(report)?;
+
+    logging!(
+        info,
+        Type::Cmd,
+        true,
+        "Script {} tested in {}ms, {} cases, passed: {}",
+        uid,
+        report.elapsed_ms,
+        report.tests.len(),
+        report.passed()
+    );
+
+    Ok(report)
+}
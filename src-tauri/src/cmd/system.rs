@@ -1,14 +1,22 @@
 use super::CmdResult;
 use crate::{
-    core::{handle, CoreManager},
-    module::sysinfo::PlatformSpecification,
+    core::CoreManager,
+    module::{
+        diagnostics::{
+            self,
+            bundle::{self, BundleOptions},
+            probes::LiveProbes,
+            DiagnosticReport, DiagnosticSettings,
+        },
+        sysinfo::PlatformSpecification,
+    },
+    wrap_err,
 };
 use once_cell::sync::Lazy;
 use std::{
     sync::atomic::{AtomicI64, Ordering},
     time::{SystemTime, UNIX_EPOCH},
 };
-use tauri_plugin_clipboard_manager::ClipboardExt;
 
 // 存储应用启动时间的全局变量
 static APP_START_TIME: Lazy<AtomicI64> = Lazy::new(|| {
@@ -21,17 +29,28 @@ static APP_START_TIME: Lazy<AtomicI64> = Lazy::new(|| {
     AtomicI64::new(now)
 });
 
+/// 导出脱敏后的诊断包，返回文件路径
 #[tauri::command]
-pub async fn export_diagnostic_info() -> CmdResult<()> {
-    let sysinfo = PlatformSpecification::new_async().await;
-    let info = format!("{sysinfo:?}");
+pub async fn export_diagnostic_info(
+    redact_servers: Option<bool>,
+    log_limit_mb: Option<u64>,
+) -> CmdResult<String> {
+    let options = BundleOptions {
+        redact_servers: redact_servers.unwrap_or(false),
+        log_limit: log_limit_mb
+            .unwrap_or(bundle::DEFAULT_LOG_LIMIT_MB)
+            .saturating_mul(1024 * 1024),
+    };
+    let path = wrap_err!(bundle::export(&LiveProbes::from_config(), &options).await)?;
+    Ok(path.to_string_lossy().into_owned())
+}
 
-    let app_handle = handle::Handle::global().app_handle().unwrap();
-    let cliboard = app_handle.clipboard();
-    if cliboard.write_text(info).is_err() {
-        log::error!(target: "app", "Failed to write to clipboard");
-    }
-    Ok(())
+/// 运行连接诊断
+#[tauri::command]
+pub async fn run_diagnostics() -> CmdResult<DiagnosticReport> {
+    let probes = LiveProbes::from_config();
+    let settings = DiagnosticSettings::from_config();
+    Ok(diagnostics::run(&probes, &settings).await)
 }
 
 #[tauri::command]
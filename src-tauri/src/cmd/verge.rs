@@ -14,3 +14,20 @@ pub fn get_verge_config() -> CmdResult<IVergeResponse> {
 pub async fn patch_verge_config(payload: IVerge) -> CmdResult {
     wrap_err!(feat::patch_verge(payload, false).await)
 }
+
+/// 生成新的本地接口 token，旧 token 立即失效
+#[tauri::command]
+pub async fn reset_local_api_token() -> CmdResult<String> {
+    let token = nanoid::nanoid!(32);
+    wrap_err!(
+        feat::patch_verge(
+            IVerge {
+                local_api_token: Some(token.clone()),
+                ..IVerge::default()
+            },
+            false,
+        )
+        .await
+    )?;
+    Ok(token)
+}
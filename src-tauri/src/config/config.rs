@@ -99,11 +99,11 @@ impl Config {
                             warn,
                             Type::Config,
                             true,
-                            "[First launch] Config validation failed, starting with minimal default config: {}",
+                            "[First launch] Config validation failed, starting with last known good config: {}",
                             error_msg
                         );
                         CoreManager::global()
-                            .use_default_config("config_validate::boot_error", &error_msg)
+                            .use_last_good_config("config_validate::boot_error", &error_msg)
                             .await?;
                         Some(("config_validate::boot_error", error_msg))
                     } else {
@@ -119,23 +119,29 @@ impl Config {
                         "Validation process execution failed: {}",
                         err
                     );
+                    let error_msg = err.to_string();
                     CoreManager::global()
-                        .use_default_config("config_validate::process_terminated", "")
+                        .use_last_good_config("config_validate::process_terminated", &error_msg)
                         .await?;
-                    Some(("config_validate::process_terminated", String::new()))
+                    Some(("config_validate::process_terminated", error_msg))
                 }
             }
         } else {
+            let error_msg = config_result
+                .err()
+                .map(|err| err.to_string())
+                .unwrap_or_default();
             logging!(
                 warn,
                 Type::Config,
                 true,
-                "Failed to generate config file; using default config"
+                "Failed to generate config file; using last known good config: {}",
+                error_msg
             );
             CoreManager::global()
-                .use_default_config("config_validate::error", "")
+                .use_last_good_config("config_validate::error", &error_msg)
                 .await?;
-            Some(("config_validate::error", String::new()))
+            Some(("config_validate::error", error_msg))
         };
 
         // 在单独的任务中发送通知
@@ -0,0 +1,151 @@
+use super::Config;
+use crate::{
+    logging,
+    state::degraded::DegradedState,
+    utils::{dirs, help, logging::Type},
+};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_yaml::Mapping;
+use std::path::PathBuf;
+
+/// 最近一次成功应用到内核的运行时配置
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ILastGood {
+    /// 生成该配置时的当前订阅
+    pub uid: Option<String>,
+    /// 保存时间
+    pub updated: i64,
+    pub config: Mapping,
+}
+
+impl ILastGood {
+    /// 读取快照，不存在时返回 None
+    fn load_from(path: &PathBuf) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        help::read_yaml::<Self>(path).map(Some)
+    }
+
+    /// 用当前的运行时配置生成快照
+    pub fn from_runtime() -> Result<Self> {
+        let config = Config::runtime()
+            .latest()
+            .config
+            .clone()
+            .ok_or(anyhow!("failed to get runtime config"))?;
+
+        Ok(Self {
+            uid: Config::profiles().latest().get_current(),
+            updated: chrono::Local::now().timestamp(),
+            config,
+        })
+    }
+
+    /// 保存快照，降级状态下运行的是旧快照，不覆盖并返回 false
+    pub fn save_file(&self) -> Result<bool> {
+        self.save_to(&dirs::last_good_path()?, DegradedState::is_degraded())
+    }
+
+    fn save_to(&self, path: &PathBuf, degraded: bool) -> Result<bool> {
+        if degraded {
+            return Ok(false);
+        }
+        help::save_yaml(
+            path,
+            self,
+            Some("# Last known good runtime config for Koala Clash"),
+        )?;
+        Ok(true)
+    }
+}
+
+/// 降级时使用的运行时配置来源
+#[derive(Debug)]
+pub enum Fallback {
+    LastGood(ILastGood),
+    /// 没有可用的快照，使用默认配置
+    Default,
+}
+
+impl Fallback {
+    pub fn load() -> Self {
+        match dirs::last_good_path() {
+            Ok(path) => Self::load_from(&path),
+            Err(err) => {
+                logging!(error, Type::Core, true, "{}", err);
+                Self::Default
+            }
+        }
+    }
+
+    fn load_from(path: &PathBuf) -> Self {
+        match ILastGood::load_from(path) {
+            Ok(Some(snapshot)) => Self::LastGood(snapshot),
+            Ok(None) => {
+                logging!(
+                    warn,
+                    Type::Core,
+                    true,
+                    "No last known good config found, using default config"
+                );
+                Self::Default
+            }
+            Err(err) => {
+                logging!(
+                    error,
+                    Type::Core,
+                    true,
+                    "Failed to read last known good config: {}",
+                    err
+                );
+                Self::Default
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot() -> ILastGood {
+        ILastGood {
+            uid: Some("profile".into()),
+            updated: 1_700_000_000,
+            config: serde_yaml::from_str("mixed-port: 7890\nmode: rule\n").unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_save_skipped_while_degraded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("last_good.yaml");
+
+        assert!(!snapshot().save_to(&path, true).unwrap());
+        assert!(!path.exists());
+
+        assert!(snapshot().save_to(&path, false).unwrap());
+        let loaded = ILastGood::load_from(&path).unwrap().unwrap();
+        assert_eq!(loaded.uid.as_deref(), Some("profile"));
+        assert_eq!(loaded.config, snapshot().config);
+    }
+
+    #[test]
+    fn test_fallback_to_default_without_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("last_good.yaml");
+        assert!(ILastGood::load_from(&path).unwrap().is_none());
+        assert!(matches!(Fallback::load_from(&path), Fallback::Default));
+
+        std::fs::write(&path, "config: [not, a, mapping").unwrap();
+        assert!(matches!(Fallback::load_from(&path), Fallback::Default));
+
+        snapshot().save_to(&path, false).unwrap();
+        match Fallback::load_from(&path) {
+            Fallback::LastGood(loaded) => assert_eq!(loaded.updated, 1_700_000_000),
+            Fallback::Default => panic!("snapshot should be used"),
+        }
+    }
+}
@@ -3,13 +3,16 @@ mod clash;
 mod config;
 mod draft;
 mod encrypt;
+mod last_good;
+mod network_rules;
 mod prfitem;
 mod profiles;
 mod runtime;
 mod verge;
 
 pub use self::{
-    clash::*, config::*, draft::*, encrypt::*, prfitem::*, profiles::*, runtime::*, verge::*,
+    clash::*, config::*, draft::*, encrypt::*, last_good::*, network_rules::*, prfitem::*,
+    profiles::*, runtime::*, verge::*,
 };
 
 pub const DEFAULT_PAC: &str = r#"function FindProxyForURL(url, host) {
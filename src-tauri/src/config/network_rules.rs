@@ -0,0 +1,95 @@
+use crate::utils::{dirs, help};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Define the `network_rules.yaml` schema
+#[derive(Default, Debug, Clone, Deserialize, Serialize)]
+pub struct INetworkRules {
+    /// 手动暂停自动切换
+    pub paused: Option<bool>,
+
+    pub rules: Option<Vec<NetworkRule>>,
+}
+
+/// 根据当前网络环境选择订阅和代理模式的规则
+#[derive(Default, Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct NetworkRule {
+    pub name: String,
+
+    pub enable: Option<bool>,
+
+    /// 数值越大越优先，相同时保持文件中的顺序
+    pub priority: Option<i32>,
+
+    /// 所有填写的条件都满足才算匹配，全部留空时作为兜底规则
+    #[serde(default)]
+    pub conditions: NetworkCondition,
+
+    /// 匹配后切换到的订阅 uid
+    pub profile: Option<String>,
+
+    /// 匹配后切换到的模式 rule / global / direct
+    pub mode: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct NetworkCondition {
+    /// 网卡名称，支持 `*` 通配
+    pub interface: Option<String>,
+
+    pub gateway_ip: Option<String>,
+
+    pub gateway_mac: Option<String>,
+
+    /// 本机任一地址落在该网段内，例如 192.168.1.0/24
+    pub subnet: Option<String>,
+
+    /// 系统 DNS 搜索域
+    pub dns_suffix: Option<String>,
+
+    /// 内网主机 host:port 可以建立 TCP 连接，开启 TUN 或透明代理时视为不满足
+    pub reachable: Option<String>,
+}
+
+impl INetworkRules {
+    pub fn new() -> Self {
+        match dirs::network_rules_path().and_then(|path| help::read_yaml::<Self>(&path)) {
+            Ok(rules) => rules,
+            Err(err) => {
+                log::error!(target: "app", "{err}");
+                Self::template()
+            }
+        }
+    }
+
+    pub fn template() -> Self {
+        Self {
+            paused: Some(false),
+            rules: Some(vec![]),
+        }
+    }
+
+    pub fn save_file(&self) -> Result<()> {
+        help::save_yaml(
+            &dirs::network_rules_path()?,
+            self,
+            Some("# Network Rules Config for Koala Clash"),
+        )
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.unwrap_or(false)
+    }
+
+    /// 已启用的规则，按优先级从高到低排列
+    pub fn sorted_rules(&self) -> Vec<&NetworkRule> {
+        let mut rules = self
+            .rules
+            .iter()
+            .flatten()
+            .filter(|rule| rule.enable.unwrap_or(true))
+            .collect::<Vec<_>>();
+        rules.sort_by_key(|rule| std::cmp::Reverse(rule.priority.unwrap_or(0)));
+        rules
+    }
+}
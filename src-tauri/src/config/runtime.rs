@@ -12,6 +12,23 @@ pub struct IRuntime {
 }
 
 impl IRuntime {
+    /// 内核支持热更新的字段
+    pub const PATCHABLE_KEYS: [&'static str; 13] = [
+        "mode",
+        "log-level",
+        "allow-lan",
+        "ipv6",
+        "bind-address",
+        "port",
+        "socks-port",
+        "mixed-port",
+        "redir-port",
+        "tproxy-port",
+        "tcp-concurrent",
+        "find-process-mode",
+        "interface-name",
+    ];
+
     pub fn new() -> Self {
         Self::default()
     }
@@ -46,4 +63,56 @@ impl IRuntime {
             }
         }
     }
+
+    /// 对比已应用的配置和新配置
+    ///
+    /// 只有可以通过 `PATCH /configs` 热更新的字段发生变化时返回需要 PATCH 的字段，
+    /// 有其他字段变化或字段被删除时返回 None，需要完整重载
+    pub fn patchable_diff(applied: &Mapping, latest: &Mapping) -> Option<Mapping> {
+        let removed = applied.keys().any(|key| !latest.contains_key(key));
+        if removed {
+            return None;
+        }
+
+        let mut patch = Mapping::new();
+        for (key, value) in latest.iter() {
+            if applied.get(key) == Some(value) {
+                continue;
+            }
+            let patchable = key
+                .as_str()
+                .is_some_and(|key| Self::PATCHABLE_KEYS.contains(&key));
+            if !patchable {
+                return None;
+            }
+            patch.insert(key.clone(), value.clone());
+        }
+        Some(patch)
+    }
+}
+
+#[test]
+fn test_patchable_diff() {
+    let applied: Mapping =
+        serde_yaml::from_str("mode: rule\nlog-level: info\nproxies:\n  - name: a\n    type: ss\n")
+            .unwrap();
+
+    let latest: Mapping = serde_yaml::from_str(
+        "mode: global\nlog-level: info\nproxies:\n  - name: a\n    type: ss\n",
+    )
+    .unwrap();
+    let patch = IRuntime::patchable_diff(&applied, &latest).unwrap();
+    assert_eq!(patch.len(), 1);
+    assert_eq!(patch.get("mode"), Some(&Value::from("global")));
+
+    assert!(IRuntime::patchable_diff(&applied, &applied)
+        .unwrap()
+        .is_empty());
+
+    let latest: Mapping =
+        serde_yaml::from_str("mode: rule\nlog-level: info\nproxies: []\n").unwrap();
+    assert!(IRuntime::patchable_diff(&applied, &latest).is_none());
+
+    let latest: Mapping = serde_yaml::from_str("mode: rule\nlog-level: info\n").unwrap();
+    assert!(IRuntime::patchable_diff(&applied, &latest).is_none());
 }
@@ -84,6 +84,9 @@ pub struct IVerge {
     /// set system proxy bypass
     pub system_proxy_bypass: Option<String>,
 
+    /// Linux 系统代理后端：auto, gnome, kde, env
+    pub linux_proxy_backend: Option<String>,
+
     /// proxy guard duration
     pub proxy_guard_duration: Option<u64>,
 
@@ -93,6 +96,15 @@ pub struct IVerge {
     /// pac script content
     pub pac_file_content: Option<String>,
 
+    /// generate pac script from the rules of runtime config
+    pub pac_generated: Option<bool>,
+
+    /// enable the token-protected local api on the embed server
+    pub enable_local_api: Option<bool>,
+
+    /// bearer token of the local api
+    pub local_api_token: Option<String>,
+
     /// proxy host address
     pub proxy_host: Option<String>,
 
@@ -158,6 +170,22 @@ pub struct IVerge {
     #[cfg(target_os = "linux")]
     pub verge_tproxy_enabled: Option<bool>,
 
+    /// 为 redir/tproxy 端口配置 nftables/iptables 规则
+    #[cfg(target_os = "linux")]
+    pub enable_transparent_rules: Option<bool>,
+
+    /// 内核出站流量的 routing-mark，防火墙规则据此放行
+    #[cfg(target_os = "linux")]
+    pub transparent_routing_mark: Option<u32>,
+
+    /// 不经过透明代理的用户 uid
+    #[cfg(target_os = "linux")]
+    pub transparent_bypass_uid: Option<u32>,
+
+    /// 不经过透明代理的目标网段
+    #[cfg(target_os = "linux")]
+    pub transparent_bypass_cidrs: Option<Vec<String>>,
+
     pub verge_mixed_port: Option<u16>,
 
     pub verge_socks_port: Option<u16>,
@@ -210,6 +238,122 @@ pub struct IVerge {
 
     /// 服务状态跟踪
     pub service_state: Option<crate::core::service::ServiceState>,
+
+    /// 内核崩溃后自动重启
+    pub enable_core_watchdog: Option<bool>,
+
+    /// 代理组定时健康检查
+    pub group_health_checks: Option<Vec<IVergeHealthCheck>>,
+
+    /// 监听网络变化
+    pub enable_network_monitor: Option<bool>,
+
+    /// 网络变化后的操作：sysproxy, flush_dns, close_connections, health_check, switch_profile
+    pub network_change_actions: Option<Vec<String>>,
+
+    /// switch_profile 操作切换到的订阅 uid
+    pub network_change_profile: Option<String>,
+
+    /// 定时任务
+    pub schedules: Option<Vec<IVergeSchedule>>,
+
+    /// 诊断时用于直连和代理访问测试的地址
+    pub diagnostic_targets: Option<Vec<String>>,
+
+    /// 备份存储：webdav（默认）、local、s3、sftp
+    pub backup_storage: Option<String>,
+
+    /// 本地或挂载目录备份的根目录
+    pub backup_local_dir: Option<String>,
+
+    /// S3 兼容存储配置 (加密存储)
+    #[serde(
+        serialize_with = "serialize_encrypted",
+        deserialize_with = "deserialize_encrypted",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub backup_s3: Option<IVergeS3Backup>,
+
+    /// SFTP 配置 (加密存储)
+    #[serde(
+        serialize_with = "serialize_encrypted",
+        deserialize_with = "deserialize_encrypted",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub backup_sftp: Option<IVergeSftpBackup>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct IVergeHealthCheck {
+    /// 代理组名称
+    pub group: String,
+    /// 检查间隔（分钟）
+    pub interval: u64,
+    /// 使用代理组测速接口，否则逐个节点测速
+    pub use_group_delay: Option<bool>,
+    /// 逐个节点测速时的并发数
+    pub concurrency: Option<usize>,
+    pub url: Option<String>,
+    pub timeout: Option<i32>,
+    /// 当前节点连续失败后自动切换到最快的可用节点
+    pub failover: Option<bool>,
+    /// 连续失败多少次后切换
+    pub failure_threshold: Option<u32>,
+}
+
+/// 按 cron 表达式定时执行的操作
+#[derive(Default, Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct IVergeSchedule {
+    pub name: String,
+    /// 分 时 日 月 周，也支持 @hourly、@daily、@weekly、@monthly
+    pub cron: String,
+    pub enable: Option<bool>,
+    pub action: IVergeScheduleAction,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum IVergeScheduleAction {
+    /// rule / global / direct
+    Mode(String),
+    /// 订阅 uid
+    Profile(String),
+    Sysproxy(bool),
+    Tun(bool),
+    #[default]
+    CloseConnections,
+    UpdateSubscriptions,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct IVergeS3Backup {
+    /// 例如 `https://s3.amazonaws.com`、`http://127.0.0.1:9000`
+    pub endpoint: String,
+    /// 默认 us-east-1
+    pub region: Option<String>,
+    pub bucket: String,
+    pub access_key: String,
+    pub secret_key: String,
+    /// 使用 `endpoint/bucket/key` 形式的地址，默认开启，MinIO 等需要
+    pub path_style: Option<bool>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct IVergeSftpBackup {
+    pub host: String,
+    /// 默认 22
+    pub port: Option<u16>,
+    pub username: String,
+    pub password: Option<String>,
+    /// 私钥文件路径，设置后优先使用
+    pub private_key: Option<String>,
+    pub passphrase: Option<String>,
+    /// 远程目录，默认登录目录
+    pub dir: Option<String>,
+    /// 主机公钥指纹，形如 `SHA256:...`，设置后连接时校验
+    pub host_key: Option<String>,
 }
 
 #[derive(Default, Debug, Clone, Deserialize, Serialize)]
@@ -378,6 +522,8 @@ impl IVerge {
             enable_system_proxy: Some(false),
             proxy_auto_config: Some(false),
             pac_file_content: Some(DEFAULT_PAC.into()),
+            pac_generated: Some(false),
+            enable_local_api: Some(false),
             proxy_host: Some("127.0.0.1".into()),
             enable_random_port: Some(false),
             #[cfg(not(target_os = "windows"))]
@@ -413,6 +559,10 @@ impl IVerge {
             primary_action: Some("tun-mode".into()),
             home_cards: None,
             service_state: None,
+            enable_core_watchdog: Some(true),
+            group_health_checks: Some(vec![]),
+            enable_network_monitor: Some(false),
+            schedules: Some(vec![]),
             ..Self::default()
         }
     }
@@ -463,6 +613,14 @@ impl IVerge {
         patch!(verge_tproxy_port);
         #[cfg(target_os = "linux")]
         patch!(verge_tproxy_enabled);
+        #[cfg(target_os = "linux")]
+        patch!(enable_transparent_rules);
+        #[cfg(target_os = "linux")]
+        patch!(transparent_routing_mark);
+        #[cfg(target_os = "linux")]
+        patch!(transparent_bypass_uid);
+        #[cfg(target_os = "linux")]
+        patch!(transparent_bypass_cidrs);
         patch!(verge_mixed_port);
         patch!(verge_socks_port);
         patch!(verge_socks_enabled);
@@ -472,9 +630,13 @@ impl IVerge {
         patch!(enable_proxy_guard);
         patch!(use_default_bypass);
         patch!(system_proxy_bypass);
+        patch!(linux_proxy_backend);
         patch!(proxy_guard_duration);
         patch!(proxy_auto_config);
         patch!(pac_file_content);
+        patch!(pac_generated);
+        patch!(enable_local_api);
+        patch!(local_api_token);
         patch!(proxy_host);
         patch!(theme_setting);
         patch!(web_ui_list);
@@ -503,6 +665,17 @@ impl IVerge {
         patch!(primary_action);
         patch!(home_cards);
         patch!(service_state);
+        patch!(enable_core_watchdog);
+        patch!(group_health_checks);
+        patch!(enable_network_monitor);
+        patch!(network_change_actions);
+        patch!(network_change_profile);
+        patch!(schedules);
+        patch!(diagnostic_targets);
+        patch!(backup_storage);
+        patch!(backup_local_dir);
+        patch!(backup_s3);
+        patch!(backup_sftp);
     }
 
     /// 在初始化前尝试拿到单例端口的值
@@ -558,9 +731,13 @@ pub struct IVergeResponse {
     pub enable_global_hotkey: Option<bool>,
     pub use_default_bypass: Option<bool>,
     pub system_proxy_bypass: Option<String>,
+    pub linux_proxy_backend: Option<String>,
     pub proxy_guard_duration: Option<u64>,
     pub proxy_auto_config: Option<bool>,
     pub pac_file_content: Option<String>,
+    pub pac_generated: Option<bool>,
+    pub enable_local_api: Option<bool>,
+    pub local_api_token: Option<String>,
     pub proxy_host: Option<String>,
     pub theme_setting: Option<IVergeTheme>,
     pub web_ui_list: Option<Vec<String>>,
@@ -583,6 +760,14 @@ pub struct IVergeResponse {
     pub verge_tproxy_port: Option<u16>,
     #[cfg(target_os = "linux")]
     pub verge_tproxy_enabled: Option<bool>,
+    #[cfg(target_os = "linux")]
+    pub enable_transparent_rules: Option<bool>,
+    #[cfg(target_os = "linux")]
+    pub transparent_routing_mark: Option<u32>,
+    #[cfg(target_os = "linux")]
+    pub transparent_bypass_uid: Option<u32>,
+    #[cfg(target_os = "linux")]
+    pub transparent_bypass_cidrs: Option<Vec<String>>,
     pub verge_mixed_port: Option<u16>,
     pub verge_socks_port: Option<u16>,
     pub verge_socks_enabled: Option<bool>,
@@ -601,6 +786,17 @@ pub struct IVergeResponse {
     pub home_cards: Option<serde_json::Value>,
     pub enable_hover_jump_navigator: Option<bool>,
     pub service_state: Option<crate::core::service::ServiceState>,
+    pub enable_core_watchdog: Option<bool>,
+    pub group_health_checks: Option<Vec<IVergeHealthCheck>>,
+    pub enable_network_monitor: Option<bool>,
+    pub network_change_actions: Option<Vec<String>>,
+    pub network_change_profile: Option<String>,
+    pub schedules: Option<Vec<IVergeSchedule>>,
+    pub diagnostic_targets: Option<Vec<String>>,
+    pub backup_storage: Option<String>,
+    pub backup_local_dir: Option<String>,
+    pub backup_s3: Option<IVergeS3Backup>,
+    pub backup_sftp: Option<IVergeSftpBackup>,
 }
 
 impl From<IVerge> for IVergeResponse {
@@ -632,9 +828,13 @@ impl From<IVerge> for IVergeResponse {
             enable_global_hotkey: verge.enable_global_hotkey,
             use_default_bypass: verge.use_default_bypass,
             system_proxy_bypass: verge.system_proxy_bypass,
+            linux_proxy_backend: verge.linux_proxy_backend,
             proxy_guard_duration: verge.proxy_guard_duration,
             proxy_auto_config: verge.proxy_auto_config,
             pac_file_content: verge.pac_file_content,
+            pac_generated: verge.pac_generated,
+            enable_local_api: verge.enable_local_api,
+            local_api_token: verge.local_api_token,
             proxy_host: verge.proxy_host,
             theme_setting: verge.theme_setting,
             web_ui_list: verge.web_ui_list,
@@ -657,6 +857,14 @@ impl From<IVerge> for IVergeResponse {
             verge_tproxy_port: verge.verge_tproxy_port,
             #[cfg(target_os = "linux")]
             verge_tproxy_enabled: verge.verge_tproxy_enabled,
+            #[cfg(target_os = "linux")]
+            enable_transparent_rules: verge.enable_transparent_rules,
+            #[cfg(target_os = "linux")]
+            transparent_routing_mark: verge.transparent_routing_mark,
+            #[cfg(target_os = "linux")]
+            transparent_bypass_uid: verge.transparent_bypass_uid,
+            #[cfg(target_os = "linux")]
+            transparent_bypass_cidrs: verge.transparent_bypass_cidrs,
             verge_mixed_port: verge.verge_mixed_port,
             verge_socks_port: verge.verge_socks_port,
             verge_socks_enabled: verge.verge_socks_enabled,
@@ -675,6 +883,17 @@ impl From<IVerge> for IVergeResponse {
             home_cards: verge.home_cards,
             enable_hover_jump_navigator: verge.enable_hover_jump_navigator,
             service_state: verge.service_state,
+            enable_core_watchdog: verge.enable_core_watchdog,
+            group_health_checks: verge.group_health_checks,
+            enable_network_monitor: verge.enable_network_monitor,
+            network_change_actions: verge.network_change_actions,
+            network_change_profile: verge.network_change_profile,
+            schedules: verge.schedules,
+            diagnostic_targets: verge.diagnostic_targets,
+            backup_storage: verge.backup_storage,
+            backup_local_dir: verge.backup_local_dir,
+            backup_s3: verge.backup_s3,
+            backup_sftp: verge.backup_sftp,
         }
     }
 }
@@ -205,6 +205,11 @@ impl AsyncProxyQuery {
     #[cfg(target_os = "linux")]
     async fn get_auto_proxy_impl() -> Result<AsyncAutoproxy> {
         // Linux: 检查环境变量和GNOME设置
+        // KDE 和环境变量后端直接读取写入的配置文件
+        let backend = super::linux_proxy::ProxyBackend::current();
+        if let Some(proxy) = backend.query_auto_proxy() {
+            return Ok(proxy);
+        }
 
         // 首先检查环境变量
         if let Ok(auto_proxy) = std::env::var("auto_proxy") {
@@ -427,6 +432,10 @@ impl AsyncProxyQuery {
     #[cfg(target_os = "linux")]
     async fn get_system_proxy_impl() -> Result<AsyncSysproxy> {
         // Linux: 检查环境变量和桌面环境设置
+        let backend = super::linux_proxy::ProxyBackend::current();
+        if let Some(proxy) = backend.query_system_proxy() {
+            return Ok(proxy);
+        }
 
         // 首先检查环境变量
         if let Ok(http_proxy) = std::env::var("http_proxy") {
@@ -0,0 +1,89 @@
+use super::{check_file_name, BackupFile, BackupStorage};
+use crate::utils::dirs;
+use anyhow::Result;
+use async_trait::async_trait;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+/// 本地或挂载目录（网络共享、同步盘等）
+pub struct LocalStorage {
+    dir: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: root.into().join(dirs::BACKUP_DIR),
+        }
+    }
+}
+
+#[async_trait]
+impl BackupStorage for LocalStorage {
+    async fn upload(&self, file_path: &Path, file_name: &str) -> Result<()> {
+        check_file_name(file_name)?;
+        fs::create_dir_all(&self.dir)?;
+        // 先写临时文件再改名，避免同步盘读到写了一半的备份
+        let partial = self.dir.join(format!(".{file_name}.partial"));
+        fs::copy(file_path, &partial)?;
+        fs::rename(partial, self.dir.join(file_name))?;
+        Ok(())
+    }
+
+    async fn list(&self) -> Result<Vec<BackupFile>> {
+        if !self.dir.exists() {
+            return Ok(vec![]);
+        }
+        let mut files = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let meta = entry.metadata()?;
+            let filename = entry.file_name().to_string_lossy().into_owned();
+            if !meta.is_file() || filename.starts_with('.') {
+                continue;
+            }
+            let last_modified = meta
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |time| time.as_secs() as i64);
+            files.push(BackupFile {
+                filename,
+                last_modified,
+                content_length: meta.len(),
+            });
+        }
+        Ok(files)
+    }
+
+    async fn download(&self, file_name: &str, storage_path: &Path) -> Result<()> {
+        check_file_name(file_name)?;
+        fs::copy(self.dir.join(file_name), storage_path)?;
+        Ok(())
+    }
+
+    async fn delete(&self, file_name: &str) -> Result<()> {
+        check_file_name(file_name)?;
+        fs::remove_file(self.dir.join(file_name))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::backup::roundtrip;
+
+    #[tokio::test]
+    async fn test_local_storage_roundtrip() {
+        let root = tempfile::tempdir().unwrap();
+        let storage = LocalStorage::new(root.path());
+        assert!(storage.list().await.unwrap().is_empty());
+
+        roundtrip(&storage).await;
+        assert!(storage.list().await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,163 @@
+//! 备份：打包配置文件，并通过所选存储上传、列出、下载和删除
+
+mod local;
+mod s3;
+#[cfg(feature = "sftp")]
+mod sftp;
+mod webdav;
+
+pub use local::LocalStorage;
+pub use s3::S3Storage;
+#[cfg(feature = "sftp")]
+pub use sftp::SftpStorage;
+pub use webdav::WebDavClient;
+
+use crate::{config::Config, utils::dirs};
+use anyhow::{anyhow, bail, Error, Result};
+use async_trait::async_trait;
+use serde::Serialize;
+use std::{
+    env::{consts::OS, temp_dir},
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use zip::write::SimpleFileOptions;
+
+const TIMEOUT_UPLOAD: u64 = 300; // 上传超时 5 分钟
+const TIMEOUT_DOWNLOAD: u64 = 300; // 下载超时 5 分钟
+const TIMEOUT_LIST: u64 = 30; // 列表超时 30 秒
+const TIMEOUT_DELETE: u64 = 30; // 删除超时 30 秒
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BackupFile {
+    pub filename: String,
+    /// Unix 时间戳（秒）
+    pub last_modified: i64,
+    pub content_length: u64,
+}
+
+/// 备份存储，文件统一放在存储根目录下的 `BACKUP_DIR` 中
+#[async_trait]
+pub trait BackupStorage: Send + Sync {
+    async fn upload(&self, file_path: &Path, file_name: &str) -> Result<()>;
+
+    async fn list(&self) -> Result<Vec<BackupFile>>;
+
+    async fn download(&self, file_name: &str, storage_path: &Path) -> Result<()>;
+
+    async fn delete(&self, file_name: &str) -> Result<()>;
+}
+
+/// 按 `backup_storage` 选择当前的备份存储
+pub fn storage() -> Result<Arc<dyn BackupStorage>> {
+    let verge = Config::verge().latest().clone();
+    let storage: Arc<dyn BackupStorage> = match verge.backup_storage.as_deref() {
+        None | Some("webdav") => Arc::new(WebDavClient::global().clone()),
+        Some("local") => {
+            let dir = verge
+                .backup_local_dir
+                .filter(|dir| !dir.is_empty())
+                .ok_or(anyhow!("Backup directory is not configured"))?;
+            Arc::new(LocalStorage::new(dir))
+        }
+        Some("s3") => Arc::new(S3Storage::new(
+            verge
+                .backup_s3
+                .ok_or(anyhow!("S3 backup storage is not configured"))?,
+        )?),
+        #[cfg(feature = "sftp")]
+        Some("sftp") => Arc::new(SftpStorage::new(
+            verge
+                .backup_sftp
+                .ok_or(anyhow!("SFTP backup storage is not configured"))?,
+        )?),
+        #[cfg(not(feature = "sftp"))]
+        Some("sftp") => bail!("SFTP backup storage is not available in this build"),
+        Some(other) => bail!("Unknown backup storage: {other}"),
+    };
+    Ok(storage)
+}
+
+/// 文件名只能是单独的一级名称，避免写到备份目录之外
+pub fn check_file_name(file_name: &str) -> Result<()> {
+    if file_name.is_empty()
+        || file_name == "."
+        || file_name == ".."
+        || file_name.contains(['/', '\\'])
+    {
+        bail!("Invalid backup file name: {file_name}");
+    }
+    Ok(())
+}
+
+/// 各存储共用的上传、列出、下载、删除测试，存储中可以已有其他文件
+#[cfg(test)]
+async fn roundtrip(storage: &dyn BackupStorage) {
+    let root = tempfile::tempdir().unwrap();
+    let source = root.path().join("source.zip");
+    fs::write(&source, b"backup").unwrap();
+    let name = "linux-backup-2025-01-01_00-00-00.zip";
+
+    storage.upload(&source, name).await.unwrap();
+    assert!(storage.upload(&source, "../escape.zip").await.is_err());
+    let files = storage.list().await.unwrap();
+    let file = files.iter().find(|file| file.filename == name).unwrap();
+    assert_eq!(file.content_length, 6);
+
+    let restored = root.path().join("restored.zip");
+    storage.download(name, &restored).await.unwrap();
+    assert_eq!(fs::read(&restored).unwrap(), b"backup");
+
+    storage.delete(name).await.unwrap();
+    assert!(storage
+        .list()
+        .await
+        .unwrap()
+        .iter()
+        .all(|file| file.filename != name));
+    assert!(storage.download(name, &restored).await.is_err());
+}
+
+pub fn create_backup() -> Result<(String, PathBuf), Error> {
+    let now = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S").to_string();
+    let zip_file_name = format!("{OS}-backup-{now}.zip");
+    let zip_path = temp_dir().join(&zip_file_name);
+
+    let file = fs::File::create(&zip_path)?;
+    let mut zip = zip::ZipWriter::new(file);
+    zip.add_directory("profiles/", SimpleFileOptions::default())?;
+    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    if let Ok(entries) = fs::read_dir(dirs::app_profiles_dir()?) {
+        for entry in entries {
+            let entry = entry.unwrap();
+            let path = entry.path();
+            if path.is_file() {
+                let backup_path = format!("profiles/{}", entry.file_name().to_str().unwrap());
+                zip.start_file(backup_path, options)?;
+                zip.write_all(fs::read(path).unwrap().as_slice())?;
+            }
+        }
+    }
+    zip.start_file(dirs::CLASH_CONFIG, options)?;
+    zip.write_all(fs::read(dirs::clash_path()?)?.as_slice())?;
+
+    let mut verge_config: serde_json::Value =
+        serde_yaml::from_str(&fs::read_to_string(dirs::verge_path()?)?)?;
+    if let Some(obj) = verge_config.as_object_mut() {
+        obj.remove("webdav_username");
+        obj.remove("webdav_password");
+        obj.remove("webdav_url");
+        obj.remove("backup_s3");
+        obj.remove("backup_sftp");
+        obj.remove("local_api_token");
+    }
+    zip.start_file(dirs::VERGE_CONFIG, options)?;
+    zip.write_all(serde_yaml::to_string(&verge_config)?.as_bytes())?;
+
+    zip.start_file(dirs::PROFILE_YAML, options)?;
+    zip.write_all(fs::read(dirs::profiles_path()?)?.as_slice())?;
+    zip.finish()?;
+    Ok((zip_file_name, zip_path))
+}
@@ -0,0 +1,257 @@
+use super::{check_file_name, BackupFile, BackupStorage, TIMEOUT_LIST};
+use crate::{
+    config::{Config, IVerge, IVergeSftpBackup},
+    feat, logging,
+    utils::{dirs, logging::Type},
+};
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD_NO_PAD, Engine};
+use ssh2::{HashType, Session, Sftp};
+use std::{
+    fs,
+    io::{Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// SFTP 服务器，ssh2 为同步接口，所有操作放在阻塞线程中执行
+#[derive(Clone)]
+pub struct SftpStorage {
+    config: IVergeSftpBackup,
+}
+
+impl SftpStorage {
+    pub fn new(config: IVergeSftpBackup) -> Result<Self> {
+        if config.host.is_empty() || config.username.is_empty() {
+            bail!("SFTP host and username are required");
+        }
+        Ok(Self { config })
+    }
+
+    fn remote_dir(&self) -> PathBuf {
+        let root = self
+            .config
+            .dir
+            .as_deref()
+            .filter(|dir| !dir.is_empty())
+            .unwrap_or(".");
+        Path::new(root).join(dirs::BACKUP_DIR)
+    }
+
+    /// 返回的指纹尚未记录，需要在连接成功后调用 `pin_host_key`
+    fn connect(&self) -> Result<(Sftp, Option<String>)> {
+        let config = &self.config;
+        let addr = (config.host.as_str(), config.port.unwrap_or(22))
+            .to_socket_addrs()?
+            .next()
+            .ok_or(anyhow!("Failed to resolve {}", config.host))?;
+        let tcp = TcpStream::connect_timeout(&addr, Duration::from_secs(TIMEOUT_LIST))?;
+
+        let mut session = Session::new()?;
+        session.set_tcp_stream(tcp);
+        session.set_timeout(TIMEOUT_LIST as u32 * 1000);
+        session.handshake()?;
+
+        let fingerprint = session
+            .host_key_hash(HashType::Sha256)
+            .map(fingerprint)
+            .ok_or(anyhow!("Failed to read SFTP host key"))?;
+        let unpinned = match config.host_key.as_deref().filter(|key| !key.is_empty()) {
+            Some(expected) if !fingerprint_matches(expected, &fingerprint) => {
+                bail!("SFTP host key mismatch: expected {expected}, got {fingerprint}")
+            }
+            Some(_) => None,
+            None => Some(fingerprint),
+        };
+
+        match config.private_key.as_deref().filter(|key| !key.is_empty()) {
+            Some(key) => session.userauth_pubkey_file(
+                &config.username,
+                None,
+                Path::new(key),
+                config
+                    .passphrase
+                    .as_deref()
+                    .filter(|phrase| !phrase.is_empty()),
+            )?,
+            None => session.userauth_password(
+                &config.username,
+                config.password.as_deref().unwrap_or_default(),
+            )?,
+        }
+        if !session.authenticated() {
+            bail!("SFTP authentication failed");
+        }
+
+        let sftp = session.sftp()?;
+        let dir = self.remote_dir();
+        if sftp.stat(&dir).is_err() {
+            sftp.mkdir(&dir, 0o755)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        Ok((sftp, unpinned))
+    }
+
+    /// 首次连接时记录服务器指纹，之后指纹不一致会拒绝连接
+    async fn pin_host_key(&self, fingerprint: &str) -> Result<()> {
+        let pinned = Config::verge().latest().backup_sftp.clone().filter(|sftp| {
+            sftp.host == self.config.host
+                && sftp.port == self.config.port
+                && sftp.host_key.as_deref().is_none_or(str::is_empty)
+        });
+        let Some(mut sftp) = pinned else {
+            return Ok(());
+        };
+        logging!(
+            info,
+            Type::Backup,
+            true,
+            "Pinning SFTP host key {} for {}",
+            fingerprint,
+            self.config.host
+        );
+        sftp.host_key = Some(fingerprint.to_string());
+        feat::patch_verge(
+            IVerge {
+                backup_sftp: Some(sftp),
+                ..IVerge::default()
+            },
+            false,
+        )
+        .await
+    }
+
+    async fn run<T, F>(&self, op: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Sftp, PathBuf) -> Result<T> + Send + 'static,
+    {
+        let storage = self.clone();
+        let (unpinned, result) = tokio::task::spawn_blocking(move || {
+            let (sftp, unpinned) = storage.connect()?;
+            anyhow::Ok((unpinned, op(&sftp, storage.remote_dir())))
+        })
+        .await??;
+        if let Some(fingerprint) = unpinned {
+            self.pin_host_key(&fingerprint).await?;
+        }
+        result
+    }
+}
+
+/// OpenSSH 格式的指纹：`SHA256:` 加不带填充的 base64
+fn fingerprint(hash: &[u8]) -> String {
+    format!("SHA256:{}", STANDARD_NO_PAD.encode(hash))
+}
+
+fn fingerprint_matches(expected: &str, actual: &str) -> bool {
+    let normalize = |key: &str| {
+        let key = key.trim().trim_end_matches('=');
+        key.strip_prefix("SHA256:").unwrap_or(key).to_string()
+    };
+    normalize(expected) == normalize(actual)
+}
+
+#[async_trait]
+impl BackupStorage for SftpStorage {
+    async fn upload(&self, file_path: &Path, file_name: &str) -> Result<()> {
+        check_file_name(file_name)?;
+        let content = fs::read(file_path)?;
+        let file_name = file_name.to_string();
+        self.run(move |sftp, dir| {
+            // 先上传临时文件再改名，避免中断后留下不完整的备份
+            let partial = dir.join(format!(".{file_name}.partial"));
+            sftp.create(&partial)?.write_all(&content)?;
+            let target = dir.join(&file_name);
+            let _ = sftp.unlink(&target);
+            sftp.rename(&partial, &target, None)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn list(&self) -> Result<Vec<BackupFile>> {
+        self.run(|sftp, dir| {
+            Ok(sftp
+                .readdir(&dir)?
+                .into_iter()
+                .filter(|(_, stat)| stat.is_file())
+                .filter_map(|(path, stat)| {
+                    let filename = path.file_name()?.to_string_lossy().into_owned();
+                    (!filename.starts_with('.')).then(|| BackupFile {
+                        filename,
+                        last_modified: stat.mtime.unwrap_or_default() as i64,
+                        content_length: stat.size.unwrap_or_default(),
+                    })
+                })
+                .collect())
+        })
+        .await
+    }
+
+    async fn download(&self, file_name: &str, storage_path: &Path) -> Result<()> {
+        check_file_name(file_name)?;
+        let file_name = file_name.to_string();
+        let content = self
+            .run(move |sftp, dir| {
+                let mut content = vec![];
+                sftp.open(dir.join(file_name))?.read_to_end(&mut content)?;
+                Ok(content)
+            })
+            .await?;
+        fs::write(storage_path, content)?;
+        Ok(())
+    }
+
+    async fn delete(&self, file_name: &str) -> Result<()> {
+        check_file_name(file_name)?;
+        let file_name = file_name.to_string();
+        self.run(move |sftp, dir| Ok(sftp.unlink(&dir.join(file_name))?))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_matches() {
+        let actual = fingerprint(&[0xab; 32]);
+        assert_eq!(actual, "SHA256:q6urq6urq6urq6urq6urq6urq6urq6urq6urq6urq6s");
+        assert!(fingerprint_matches(&actual, &actual));
+        assert!(fingerprint_matches(
+            "q6urq6urq6urq6urq6urq6urq6urq6urq6urq6urq6s=",
+            &actual
+        ));
+        assert!(!fingerprint_matches("SHA256:AAAA", &actual));
+    }
+
+    /// 需要可用的 SFTP 服务器：
+    /// `KOALA_SFTP_HOST=127.0.0.1 KOALA_SFTP_PORT=2222 KOALA_SFTP_USERNAME=... KOALA_SFTP_PASSWORD=... KOALA_SFTP_HOST_KEY=SHA256:... cargo test --features sftp -- --ignored sftp_storage`
+    #[tokio::test]
+    #[ignore]
+    async fn test_sftp_storage_against_server() {
+        let env = |name: &str| std::env::var(name).ok();
+        let storage = SftpStorage::new(IVergeSftpBackup {
+            host: env("KOALA_SFTP_HOST").unwrap(),
+            port: env("KOALA_SFTP_PORT").map(|port| port.parse().unwrap()),
+            username: env("KOALA_SFTP_USERNAME").unwrap(),
+            password: env("KOALA_SFTP_PASSWORD"),
+            dir: env("KOALA_SFTP_DIR"),
+            host_key: env("KOALA_SFTP_HOST_KEY"),
+            ..Default::default()
+        })
+        .unwrap();
+        crate::core::backup::roundtrip(&storage).await;
+
+        let mismatch = SftpStorage::new(IVergeSftpBackup {
+            host_key: Some("SHA256:AAAA".into()),
+            ..storage.config.clone()
+        })
+        .unwrap();
+        assert!(mismatch.list().await.is_err());
+    }
+}
@@ -1,28 +1,20 @@
+use super::{
+    check_file_name, BackupFile, BackupStorage, TIMEOUT_DELETE, TIMEOUT_DOWNLOAD, TIMEOUT_LIST,
+    TIMEOUT_UPLOAD,
+};
 use crate::{config::Config, utils::dirs};
 use anyhow::Error;
+use async_trait::async_trait;
 use once_cell::sync::OnceCell;
 use parking_lot::Mutex;
-use reqwest_dav::list_cmd::{ListEntity, ListFile};
-use std::{
-    collections::HashMap,
-    env::{consts::OS, temp_dir},
-    fs,
-    io::Write,
-    path::PathBuf,
-    sync::Arc,
-    time::Duration,
-};
+use percent_encoding::percent_decode_str;
+use reqwest_dav::list_cmd::ListEntity;
+use std::{collections::HashMap, env::consts::OS, fs, path::Path, sync::Arc, time::Duration};
 use tokio::time::timeout;
-use zip::write::SimpleFileOptions;
 
 // 应用版本常量，来自 tauri.conf.json
 const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
 
-const TIMEOUT_UPLOAD: u64 = 300; // 上传超时 5 分钟
-const TIMEOUT_DOWNLOAD: u64 = 300; // 下载超时 5 分钟
-const TIMEOUT_LIST: u64 = 30; // 列表超时 30 秒
-const TIMEOUT_DELETE: u64 = 30; // 删除超时 30 秒
-
 #[derive(Clone)]
 struct WebDavConfig {
     url: String,
@@ -49,6 +41,7 @@ impl Operation {
     }
 }
 
+#[derive(Clone)]
 pub struct WebDavClient {
     config: Arc<Mutex<Option<WebDavConfig>>>,
     clients: Arc<Mutex<HashMap<Operation, reqwest_dav::Client>>>,
@@ -146,13 +139,17 @@ impl WebDavClient {
         *self.config.lock() = None;
         self.clients.lock().clear();
     }
+}
 
-    pub async fn upload(&self, file_path: PathBuf, file_name: String) -> Result<(), Error> {
+#[async_trait]
+impl BackupStorage for WebDavClient {
+    async fn upload(&self, file_path: &Path, file_name: &str) -> Result<(), Error> {
+        check_file_name(file_name)?;
         let client = self.get_client(Operation::Upload).await?;
         let webdav_path: String = format!("{}/{}", dirs::BACKUP_DIR, file_name);
 
         // 读取文件并上传，如果失败尝试一次重试
-        let file_content = fs::read(&file_path)?;
+        let file_content = fs::read(file_path)?;
 
         // 添加超时保护
         let upload_result = timeout(
@@ -187,14 +184,15 @@ impl WebDavClient {
         }
     }
 
-    pub async fn download(&self, filename: String, storage_path: PathBuf) -> Result<(), Error> {
+    async fn download(&self, filename: &str, storage_path: &Path) -> Result<(), Error> {
+        check_file_name(filename)?;
         let client = self.get_client(Operation::Download).await?;
         let path = format!("{}/{}", dirs::BACKUP_DIR, filename);
 
         let fut = async {
             let response = client.get(path.as_str()).await?;
             let content = response.bytes().await?;
-            fs::write(&storage_path, &content)?;
+            fs::write(storage_path, &content)?;
             Ok::<(), Error>(())
         };
 
@@ -202,7 +200,7 @@ impl WebDavClient {
         Ok(())
     }
 
-    pub async fn list(&self) -> Result<Vec<ListFile>, Error> {
+    async fn list(&self) -> Result<Vec<BackupFile>, Error> {
         let client = self.get_client(Operation::List).await?;
         let path = format!("{}/", dirs::BACKUP_DIR);
 
@@ -213,16 +211,24 @@ impl WebDavClient {
             let mut final_files = Vec::new();
             for file in files {
                 if let ListEntity::File(file) = file {
-                    final_files.push(file);
+                    let filename = file.href.rsplit('/').next().unwrap_or_default();
+                    final_files.push(BackupFile {
+                        filename: percent_decode_str(filename)
+                            .decode_utf8_lossy()
+                            .into_owned(),
+                        last_modified: file.last_modified.timestamp(),
+                        content_length: file.content_length.max(0) as u64,
+                    });
                 }
             }
-            Ok::<Vec<ListFile>, Error>(final_files)
+            Ok::<Vec<BackupFile>, Error>(final_files)
         };
 
         timeout(Duration::from_secs(TIMEOUT_LIST), fut).await?
     }
 
-    pub async fn delete(&self, file_name: String) -> Result<(), Error> {
+    async fn delete(&self, file_name: &str) -> Result<(), Error> {
+        check_file_name(file_name)?;
         let client = self.get_client(Operation::Delete).await?;
         let path = format!("{}/{}", dirs::BACKUP_DIR, file_name);
 
@@ -231,42 +237,3 @@ impl WebDavClient {
         Ok(())
     }
 }
-
-pub fn create_backup() -> Result<(String, PathBuf), Error> {
-    let now = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S").to_string();
-    let zip_file_name = format!("{OS}-backup-{now}.zip");
-    let zip_path = temp_dir().join(&zip_file_name);
-
-    let file = fs::File::create(&zip_path)?;
-    let mut zip = zip::ZipWriter::new(file);
-    zip.add_directory("profiles/", SimpleFileOptions::default())?;
-    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
-    if let Ok(entries) = fs::read_dir(dirs::app_profiles_dir()?) {
-        for entry in entries {
-            let entry = entry.unwrap();
-            let path = entry.path();
-            if path.is_file() {
-                let backup_path = format!("profiles/{}", entry.file_name().to_str().unwrap());
-                zip.start_file(backup_path, options)?;
-                zip.write_all(fs::read(path).unwrap().as_slice())?;
-            }
-        }
-    }
-    zip.start_file(dirs::CLASH_CONFIG, options)?;
-    zip.write_all(fs::read(dirs::clash_path()?)?.as_slice())?;
-
-    let mut verge_config: serde_json::Value =
-        serde_yaml::from_str(&fs::read_to_string(dirs::verge_path()?)?)?;
-    if let Some(obj) = verge_config.as_object_mut() {
-        obj.remove("webdav_username");
-        obj.remove("webdav_password");
-        obj.remove("webdav_url");
-    }
-    zip.start_file(dirs::VERGE_CONFIG, options)?;
-    zip.write_all(serde_yaml::to_string(&verge_config)?.as_bytes())?;
-
-    zip.start_file(dirs::PROFILE_YAML, options)?;
-    zip.write_all(fs::read(dirs::profiles_path()?)?.as_slice())?;
-    zip.finish()?;
-    Ok((zip_file_name, zip_path))
-}
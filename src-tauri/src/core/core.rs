@@ -1,11 +1,14 @@
 use crate::{
     config::*,
     core::{
+        core_log::{CoreLogWriter, CoreStream},
+        core_registry::CoreRegistry,
         handle,
         service::{self},
     },
     logging, logging_error,
     module::mihomo::MihomoManager,
+    state::degraded::DegradedState,
     utils::{
         dirs,
         help::{self},
@@ -13,22 +16,54 @@ use crate::{
     },
 };
 use anyhow::Result;
-use chrono::Local;
 use once_cell::sync::OnceCell;
-use std::{
-    fmt,
-    fs::{create_dir_all, File},
-    io::Write,
-    path::PathBuf,
-    sync::Arc,
+use std::{fmt, path::PathBuf, process::Stdio, sync::Arc};
+use tauri::AppHandle;
+use tauri_plugin_shell::{
+    process::{Command, CommandChild, CommandEvent},
+    ShellExt,
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    sync::{mpsc, Mutex},
 };
-use tauri_plugin_shell::{process::CommandChild, ShellExt};
-use tokio::sync::Mutex;
 
 #[derive(Debug)]
 pub struct CoreManager {
     running: Arc<Mutex<RunningMode>>,
-    child_sidecar: Arc<Mutex<Option<CommandChild>>>,
+    child_sidecar: Arc<Mutex<Option<SidecarChild>>>,
+}
+
+/// sidecar 内核进程，无界面模式下没有 AppHandle，直接由 tokio 启动
+#[derive(Debug)]
+enum SidecarChild {
+    Shell(CommandChild),
+    Process(tokio::process::Child),
+}
+
+impl SidecarChild {
+    fn pid(&self) -> u32 {
+        match self {
+            Self::Shell(child) => child.pid(),
+            Self::Process(child) => child.id().unwrap_or_default(),
+        }
+    }
+
+    fn kill(self) -> Result<()> {
+        match self {
+            Self::Shell(child) => child.kill()?,
+            Self::Process(mut child) => child.start_kill()?,
+        }
+        Ok(())
+    }
+}
+
+/// 内核命令的输出
+struct CoreOutput {
+    success: bool,
+    code: Option<i32>,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
 }
 
 /// 内核运行模式
@@ -151,6 +186,59 @@ impl CoreManager {
         handle::Handle::notice_message(msg_type, msg_content);
         Ok(())
     }
+    /// 使用最近一次成功应用的配置启动，并进入降级状态，没有快照时使用默认配置
+    pub async fn use_last_good_config(&self, msg_type: &str, msg_content: &str) -> Result<()> {
+        let snapshot = match Fallback::load() {
+            Fallback::LastGood(snapshot) => snapshot,
+            Fallback::Default => return self.use_default_config(msg_type, msg_content).await,
+        };
+
+        logging!(
+            warn,
+            Type::Core,
+            true,
+            "Falling back to last known good config (profile: {:?}, saved at {})",
+            snapshot.uid,
+            snapshot.updated
+        );
+        let runtime_path = dirs::app_home_dir()?.join(RUNTIME_CONFIG);
+        **Config::runtime().draft() = IRuntime {
+            config: Some(snapshot.config.clone()),
+            exists_keys: vec![],
+            chain_logs: Default::default(),
+        };
+        help::save_yaml(
+            &runtime_path,
+            &snapshot.config,
+            Some("# Koala Clash Runtime"),
+        )?;
+
+        DegradedState::enter(
+            msg_content.to_string(),
+            snapshot.uid.clone(),
+            Some(snapshot.updated),
+        );
+        handle::Handle::notice_message(msg_type, msg_content);
+        Ok(())
+    }
+    /// 将当前运行时配置保存为最近可用的快照，降级状态下不覆盖快照
+    pub fn save_last_good_config(&self) {
+        match ILastGood::from_runtime().and_then(|snapshot| snapshot.save_file()) {
+            Ok(false) => {}
+            Ok(true) => {
+                logging!(debug, Type::Core, true, "Last known good config saved");
+            }
+            Err(err) => {
+                logging!(
+                    warn,
+                    Type::Core,
+                    true,
+                    "Failed to save last known good config: {}",
+                    err
+                );
+            }
+        }
+    }
     /// 验证运行时配置
     pub async fn validate_config(&self) -> Result<(bool, String)> {
         logging!(
@@ -265,7 +353,6 @@ impl CoreManager {
         let clash_core = Config::verge().latest().get_valid_clash_core();
         logging!(info, Type::Config, true, "Using core: {}", clash_core);
 
-        let app_handle = handle::Handle::global().app_handle().unwrap();
         let app_dir = dirs::app_home_dir()?;
         let app_dir_str = dirs::path_to_str(&app_dir)?;
         logging!(
@@ -277,20 +364,15 @@ impl CoreManager {
         );
 
         // 使用子进程运行clash验证配置
-        let output = app_handle
-            .shell()
-            .sidecar(clash_core)?
-            .args(["-t", "-d", app_dir_str, "-f", config_path])
-            .output()
-            .await?;
+        let output =
+            Self::core_output(&clash_core, &["-t", "-d", app_dir_str, "-f", config_path]).await?;
 
         let stderr = String::from_utf8_lossy(&output.stderr);
         let stdout = String::from_utf8_lossy(&output.stdout);
 
         // 检查进程退出状态和错误输出
         let error_keywords = ["FATA", "fatal", "Parse config error", "level=fatal"];
-        let has_error =
-            !output.status.success() || error_keywords.iter().any(|&kw| stderr.contains(kw));
+        let has_error = !output.success || error_keywords.iter().any(|&kw| stderr.contains(kw));
 
         logging!(
             info,
@@ -314,7 +396,7 @@ impl CoreManager {
                 stdout.to_string()
             } else if !stderr.is_empty() {
                 stderr.to_string()
-            } else if let Some(code) = output.status.code() {
+            } else if let Some(code) = output.code {
                 format!("Validation process exited abnormally, exit code: {code}")
             } else {
                 "Validation process was terminated".to_string()
@@ -399,7 +481,7 @@ impl CoreManager {
         use boa_engine::{Context, Source};
 
         let mut context = Context::default();
-        let result = context.eval(Source::from_bytes(&content));
+        let result = context.eval(Source::from_bytes(&crate::enhance::strip_exports(&content)));
 
         match result {
             Ok(_) => {
@@ -438,8 +520,15 @@ impl CoreManager {
             }
         }
     }
-    /// 更新proxies等配置
+    /// 更新proxies等配置，总是完整重载，订阅、规则集、脚本等文件的改动才能生效
     pub async fn update_config(&self) -> Result<(bool, String)> {
+        self.update_config_with(false).await
+    }
+    /// 模式、端口、TUN 开关等小范围改动，能 PATCH 时只 PATCH，配置未变化时跳过重载
+    pub async fn update_config_patch(&self) -> Result<(bool, String)> {
+        self.update_config_with(true).await
+    }
+    async fn update_config_with(&self, allow_patch: bool) -> Result<(bool, String)> {
         // 检查程序是否正在退出，如果是则跳过完整验证流程
         if handle::Handle::global().is_exiting() {
             logging!(
@@ -469,7 +558,23 @@ impl CoreManager {
                 // 4. 验证通过后，生成正式的运行时配置
                 logging!(info, Type::Config, true, "Generating runtime configuration");
                 let run_path = Config::generate_file(ConfigType::Run)?;
-                logging_error!(Type::Config, true, self.put_configs_force(run_path).await);
+                let applied = self
+                    .apply_runtime_config(run_path, allow_patch)
+                    .await
+                    .is_ok();
+                #[cfg(target_os = "linux")]
+                if applied {
+                    logging_error!(Type::System, true, crate::utils::linux_dns::sync().await);
+                }
+                if DegradedState::on_config_applied(applied) {
+                    logging!(
+                        info,
+                        Type::Config,
+                        true,
+                        "New configuration applied, leaving degraded state"
+                    );
+                    self.save_last_good_config();
+                }
                 Ok((true, String::new()))
             }
             Ok((false, error_msg)) => {
@@ -496,6 +601,82 @@ impl CoreManager {
             }
         }
     }
+    /// 应用新的运行时配置，只有可热更新的字段变化时使用 PATCH，否则完整重载
+    async fn apply_runtime_config(
+        &self,
+        path_buf: PathBuf,
+        allow_patch: bool,
+    ) -> Result<(), String> {
+        if !allow_patch {
+            return self.put_configs_force(path_buf).await;
+        }
+        let patch = {
+            let runtime = Config::runtime();
+            let applied = runtime.data().config.clone();
+            let latest = runtime.latest().config.clone();
+            match (applied, latest) {
+                (Some(applied), Some(latest)) => IRuntime::patchable_diff(&applied, &latest),
+                _ => None,
+            }
+        };
+
+        match patch {
+            Some(patch) if patch.is_empty() => {
+                logging!(
+                    info,
+                    Type::Core,
+                    true,
+                    "Runtime config unchanged, skipping reload"
+                );
+                Config::runtime().apply();
+                Ok(())
+            }
+            Some(patch) => {
+                let keys = patch
+                    .keys()
+                    .filter_map(|key| key.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                logging!(
+                    info,
+                    Type::Core,
+                    true,
+                    "Applying runtime config via PATCH: {}",
+                    keys
+                );
+                let result = match serde_json::to_value(&patch) {
+                    Ok(json) => MihomoManager::global().patch_configs(json).await,
+                    Err(err) => Err(err.to_string()),
+                };
+                match result {
+                    Ok(_) => {
+                        Config::runtime().apply();
+                        self.save_last_good_config();
+                        Ok(())
+                    }
+                    Err(err) => {
+                        logging!(
+                            warn,
+                            Type::Core,
+                            true,
+                            "PATCH failed, falling back to full reload: {}",
+                            err
+                        );
+                        self.put_configs_force(path_buf).await
+                    }
+                }
+            }
+            None => {
+                logging!(
+                    info,
+                    Type::Core,
+                    true,
+                    "Structural config change, applying via full reload"
+                );
+                self.put_configs_force(path_buf).await
+            }
+        }
+    }
     pub async fn put_configs_force(&self, path_buf: PathBuf) -> Result<(), String> {
         let run_path_str = dirs::path_to_str(&path_buf).map_err(|e| {
             let msg = e.to_string();
@@ -509,6 +690,7 @@ impl CoreManager {
             Ok(_) => {
                 Config::runtime().apply();
                 logging!(info, Type::Core, true, "Configuration updated successfully");
+                self.save_last_good_config();
                 Ok(())
             }
             Err(e) => {
@@ -838,51 +1020,145 @@ impl CoreManager {
         }
     }
 
+    /// 内核命令，启用托管内核时使用其路径，否则使用内置的 sidecar
+    fn core_command(app_handle: &AppHandle, clash_core: &str) -> Result<Command> {
+        match CoreRegistry::global().active_core_path() {
+            Some(path) => Ok(app_handle.shell().command(path)),
+            None => Ok(app_handle.shell().sidecar(clash_core)?),
+        }
+    }
+
+    /// 没有 AppHandle 时的内核路径，与 sidecar 一样位于主程序所在目录
+    fn core_program(clash_core: &str) -> Result<PathBuf> {
+        if let Some(path) = CoreRegistry::global().active_core_path() {
+            return Ok(path);
+        }
+        let exe = tauri::utils::platform::current_exe()?;
+        let dir = exe
+            .parent()
+            .ok_or(anyhow::anyhow!("failed to get executable directory"))?;
+        Ok(dir.join(format!("{clash_core}{}", std::env::consts::EXE_SUFFIX)))
+    }
+
+    async fn core_output(clash_core: &str, args: &[&str]) -> Result<CoreOutput> {
+        if let Some(app_handle) = handle::Handle::global().app_handle() {
+            let output = Self::core_command(&app_handle, clash_core)?
+                .args(args)
+                .output()
+                .await?;
+            return Ok(CoreOutput {
+                success: output.status.success(),
+                code: output.status.code(),
+                stdout: output.stdout,
+                stderr: output.stderr,
+            });
+        }
+        let output = tokio::process::Command::new(Self::core_program(clash_core)?)
+            .args(args)
+            .output()
+            .await?;
+        Ok(CoreOutput {
+            success: output.status.success(),
+            code: output.status.code(),
+            stdout: output.stdout,
+            stderr: output.stderr,
+        })
+    }
+
+    /// 启动内核并把输出按行发送到 channel
+    fn spawn_core(
+        clash_core: &str,
+        args: &[&str],
+    ) -> Result<(mpsc::UnboundedReceiver<(Vec<u8>, CoreStream)>, SidecarChild)> {
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        if let Some(app_handle) = handle::Handle::global().app_handle() {
+            let (mut events, child) = Self::core_command(&app_handle, clash_core)?
+                .args(args)
+                .spawn()?;
+            tokio::spawn(async move {
+                while let Some(event) = events.recv().await {
+                    let line = match event {
+                        CommandEvent::Stdout(line) => (line, CoreStream::Stdout),
+                        CommandEvent::Stderr(line) => (line, CoreStream::Stderr),
+                        _ => continue,
+                    };
+                    if tx.send(line).is_err() {
+                        break;
+                    }
+                }
+            });
+            return Ok((rx, SidecarChild::Shell(child)));
+        }
+
+        let mut child = tokio::process::Command::new(Self::core_program(clash_core)?)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+        if let Some(stdout) = child.stdout.take() {
+            Self::forward_lines(stdout, CoreStream::Stdout, tx.clone());
+        }
+        if let Some(stderr) = child.stderr.take() {
+            Self::forward_lines(stderr, CoreStream::Stderr, tx);
+        }
+        Ok((rx, SidecarChild::Process(child)))
+    }
+
+    fn forward_lines<R>(
+        reader: R,
+        stream: CoreStream,
+        tx: mpsc::UnboundedSender<(Vec<u8>, CoreStream)>,
+    ) where
+        R: AsyncRead + Unpin + Send + 'static,
+    {
+        tokio::spawn(async move {
+            let mut reader = BufReader::new(reader);
+            let mut line = Vec::new();
+            while matches!(reader.read_until(b'\n', &mut line).await, Ok(n) if n > 0) {
+                if tx.send((std::mem::take(&mut line), stream)).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
     async fn start_core_by_sidecar(&self) -> Result<()> {
         logging!(trace, Type::Core, true, "Running core by sidecar");
         let config_file = &Config::generate_file(ConfigType::Run)?;
-        let app_handle = handle::Handle::global()
-            .app_handle()
-            .ok_or(anyhow::anyhow!("failed to get app handle"))?;
         let clash_core = Config::verge().latest().get_valid_clash_core();
         let config_dir = dirs::app_home_dir()?;
 
-        let service_log_dir = dirs::app_home_dir()?.join("logs").join("service");
-        create_dir_all(&service_log_dir)?;
+        let mut log_writer = CoreLogWriter::new()?;
 
-        let now = Local::now();
-        let timestamp = now.format("%Y%m%d_%H%M%S").to_string();
-
-        let log_path = service_log_dir.join(format!("sidecar_{timestamp}.log"));
-
-        let mut log_file = File::create(log_path)?;
-
-        let (mut rx, child) = app_handle
-            .shell()
-            .sidecar(&clash_core)?
-            .args([
+        let (mut rx, child) = Self::spawn_core(
+            &clash_core,
+            &[
                 "-d",
                 dirs::path_to_str(&config_dir)?,
                 "-f",
                 dirs::path_to_str(config_file)?,
-            ])
-            .spawn()?;
+            ],
+        )?;
 
-        tokio::spawn(async move {
-            while let Some(event) = rx.recv().await {
-                if let tauri_plugin_shell::process::CommandEvent::Stdout(line) = event {
-                    if let Err(e) = writeln!(log_file, "{}", String::from_utf8_lossy(&line)) {
+        // 写文件和轮转压缩都是阻塞操作，放在单独的线程中
+        std::thread::Builder::new()
+            .name("core-log-writer".into())
+            .spawn(move || {
+                while let Some((line, stream)) = rx.blocking_recv() {
+                    if let Err(e) = log_writer.write_line(&String::from_utf8_lossy(&line), stream) {
                         logging!(
                             error,
                             Type::Core,
                             true,
-                            "[Sidecar] Failed to write stdout to file: {}",
+                            "[Sidecar] Failed to write core output to file: {}",
                             e
                         );
                     }
                 }
-            }
-        });
+            })?;
 
         let pid = child.pid();
         logging!(
@@ -917,6 +1193,16 @@ impl CoreManager {
 
 impl CoreManager {
     async fn start_core_by_service(&self) -> Result<()> {
+        // 服务只运行安装目录中的内置内核，托管内核以当前用户身份运行
+        if CoreRegistry::global().active_core_path().is_some() {
+            logging!(
+                info,
+                Type::Core,
+                true,
+                "Managed core is active, running it by sidecar instead of the service"
+            );
+            return self.start_core_by_sidecar().await;
+        }
         logging!(trace, Type::Core, true, "Running core by service");
         let config_file = &Config::generate_file(ConfigType::Run)?;
         service::run_core_by_service(config_file).await?;
@@ -1197,6 +1483,29 @@ impl CoreManager {
         (*guard).clone()
     }
 
+    /// 检查内核进程是否存活以及 `/version` 是否可用
+    pub async fn check_core_health(&self) -> Result<()> {
+        if self.get_running_mode().await == RunningMode::Sidecar {
+            let pid = {
+                self.child_sidecar
+                    .lock()
+                    .await
+                    .as_ref()
+                    .map(|child| child.pid())
+            };
+            if let Some(pid) = pid {
+                if !self.is_process_running(pid).await.unwrap_or(true) {
+                    anyhow::bail!("sidecar process {pid} is not running");
+                }
+            }
+        }
+
+        MihomoManager::global()
+            .is_mihomo_running()
+            .await
+            .map_err(|e| anyhow::anyhow!("core api is unavailable: {e}"))
+    }
+
     /// 启动核心
     pub async fn start_core(&self) -> Result<()> {
         if service::is_service_available().await.is_ok() {
@@ -1231,11 +1540,36 @@ impl CoreManager {
                 self.start_core_by_sidecar().await?;
             }
         }
+        // 透明代理规则与 DNS 接管只在内核运行时生效
+        #[cfg(target_os = "linux")]
+        {
+            logging_error!(
+                Type::System,
+                true,
+                crate::core::tproxy::TransparentProxy::global().sync().await
+            );
+            logging_error!(Type::System, true, crate::utils::linux_dns::sync().await);
+        }
         Ok(())
     }
 
-    /// 停止核心运行
+    /// 停止核心运行，先移除透明代理规则并恢复系统 DNS，避免流量被转发到已关闭的端口
     pub async fn stop_core(&self) -> Result<()> {
+        #[cfg(target_os = "linux")]
+        {
+            logging_error!(
+                Type::System,
+                true,
+                crate::core::tproxy::TransparentProxy::global()
+                    .clear()
+                    .await
+            );
+            logging_error!(Type::System, true, crate::utils::linux_dns::restore().await);
+        }
+        self.stop_core_process().await
+    }
+
+    async fn stop_core_process(&self) -> Result<()> {
         match self.get_running_mode().await {
             RunningMode::Service => self.stop_core_by_service().await,
             RunningMode::Sidecar => self.stop_core_by_sidecar().await,
@@ -1243,9 +1577,9 @@ impl CoreManager {
         }
     }
 
-    /// 重启内核
+    /// 重启内核，透明代理规则保持不变，避免重复提权
     pub async fn restart_core(&self) -> Result<()> {
-        self.stop_core().await?;
+        self.stop_core_process().await?;
 
         self.start_core().await?;
         Ok(())
@@ -1265,6 +1599,11 @@ impl CoreManager {
             return Err(error_message);
         }
 
+        // 切换到内置内核时停用托管内核
+        CoreRegistry::global()
+            .switch(None)
+            .map_err(|e| e.to_string())?;
+
         Config::verge().draft().clash_core = clash_core.clone();
         Config::verge().apply();
         logging_error!(Type::Core, true, Config::verge().latest().save_file());
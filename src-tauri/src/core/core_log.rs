@@ -0,0 +1,363 @@
+use crate::utils::dirs;
+use anyhow::{bail, Result};
+use chrono::{DateTime, Local};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+};
+
+/// 单个日志文件的最大大小，超过后轮转
+const MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
+/// 保留的压缩日志数量
+const MAX_ARCHIVES: usize = 5;
+const CURRENT_LOG: &str = "core.log";
+
+/// mihomo 的日志格式: time="..." level=info msg="..."
+static LINE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"^time="([^"]*)" level=(\w+) msg="(.*)"$"#).unwrap());
+
+/// 内核日志目录
+pub fn core_logs_dir() -> Result<PathBuf> {
+    Ok(dirs::app_logs_dir()?.join("core"))
+}
+
+/// 内核输出流
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoreStream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CoreLogEntry {
+    pub time: String,
+    /// 毫秒时间戳，时间无法解析时为 None
+    pub timestamp: Option<i64>,
+    pub level: String,
+    pub message: String,
+}
+
+impl CoreLogEntry {
+    /// 解析一行内核日志，无法识别格式时按输出流推断日志等级
+    pub fn parse(line: &str, stream: CoreStream) -> Self {
+        if let Some(caps) = LINE_REGEX.captures(line.trim_end()) {
+            let time = caps[1].to_string();
+            return Self {
+                timestamp: parse_timestamp(&time),
+                time,
+                level: normalize_level(&caps[2]).to_string(),
+                message: caps[3].replace(r#"\""#, "\""),
+            };
+        }
+
+        let now = Local::now();
+        Self {
+            time: now.to_rfc3339(),
+            timestamp: Some(now.timestamp_millis()),
+            level: match stream {
+                CoreStream::Stdout => "info",
+                CoreStream::Stderr => "error",
+            }
+            .to_string(),
+            message: line.trim_end().to_string(),
+        }
+    }
+
+    /// 按 mihomo 的格式输出，保证写入文件的每一行都能被重新解析
+    fn to_line(&self) -> String {
+        format!(
+            r#"time="{}" level={} msg="{}""#,
+            self.time,
+            self.level,
+            self.message.replace('"', r#"\""#)
+        )
+    }
+}
+
+fn parse_timestamp(time: &str) -> Option<i64> {
+    DateTime::parse_from_rfc3339(time)
+        .ok()
+        .map(|time| time.timestamp_millis())
+}
+
+fn normalize_level(level: &str) -> &'static str {
+    match level.to_lowercase().as_str() {
+        "debug" | "trace" => "debug",
+        "warn" | "warning" => "warning",
+        "error" => "error",
+        "fatal" | "panic" => "fatal",
+        _ => "info",
+    }
+}
+
+fn level_rank(level: &str) -> u8 {
+    match normalize_level(level) {
+        "debug" => 0,
+        "info" => 1,
+        "warning" => 2,
+        "error" => 3,
+        _ => 4,
+    }
+}
+
+/// 按大小轮转的内核日志写入器，旧文件使用 gzip 压缩
+pub struct CoreLogWriter {
+    dir: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl CoreLogWriter {
+    pub fn new() -> Result<Self> {
+        Self::with_dir(core_logs_dir()?)
+    }
+
+    pub fn with_dir(dir: PathBuf) -> Result<Self> {
+        fs::create_dir_all(&dir)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(CURRENT_LOG))?;
+        let size = file.metadata()?.len();
+        Ok(Self { dir, file, size })
+    }
+
+    pub fn write_line(&mut self, line: &str, stream: CoreStream) -> Result<()> {
+        let line = CoreLogEntry::parse(line, stream).to_line();
+        let len = line.len() as u64 + 1;
+        if self.size > 0 && self.size + len > MAX_FILE_SIZE {
+            self.rotate()?;
+        }
+        writeln!(self.file, "{line}")?;
+        self.size += len;
+        Ok(())
+    }
+
+    /// 压缩当前日志并新建文件，只保留最近的 `MAX_ARCHIVES` 个压缩日志
+    fn rotate(&mut self) -> Result<()> {
+        self.file.flush()?;
+        let current = self.dir.join(CURRENT_LOG);
+        let timestamp = Local::now().format("%Y%m%d_%H%M%S%3f").to_string();
+        let mut encoder = GzEncoder::new(
+            create_archive(&self.dir, &timestamp)?,
+            Compression::default(),
+        );
+        io::copy(&mut File::open(&current)?, &mut encoder)?;
+        encoder.finish()?;
+
+        self.file = File::create(&current)?;
+        self.size = 0;
+
+        let mut archives = list_archives(&self.dir)?;
+        while archives.len() > MAX_ARCHIVES {
+            let _ = fs::remove_file(archives.remove(0));
+        }
+        Ok(())
+    }
+}
+
+/// 新建压缩日志文件，同一毫秒内多次轮转时加序号，不覆盖已有的文件
+fn create_archive(dir: &Path, timestamp: &str) -> Result<File> {
+    for seq in 0..100 {
+        let name = match seq {
+            0 => format!("core_{timestamp}.log.gz"),
+            _ => format!("core_{timestamp}_{seq:02}.log.gz"),
+        };
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(dir.join(name))
+        {
+            Ok(file) => return Ok(file),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err.into()),
+        }
+    }
+    bail!("Too many core log archives created at {timestamp}")
+}
+
+/// 按时间顺序排列的压缩日志
+fn list_archives(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut archives = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("core_") && name.ends_with(".log.gz"))
+        })
+        .collect::<Vec<_>>();
+    archives.sort();
+    Ok(archives)
+}
+
+/// 日志查询条件
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CoreLogQuery {
+    /// 最低日志等级
+    pub level: Option<String>,
+    /// 起始时间，毫秒时间戳
+    pub since: Option<i64>,
+    /// 结束时间，毫秒时间戳
+    pub until: Option<i64>,
+    /// 消息包含的文本，忽略大小写
+    pub keyword: Option<String>,
+    /// 最多返回最近的多少条
+    pub limit: Option<usize>,
+}
+
+impl CoreLogQuery {
+    fn matches(&self, entry: &CoreLogEntry, keyword: &Option<String>) -> bool {
+        if let Some(level) = &self.level {
+            if level_rank(&entry.level) < level_rank(level) {
+                return false;
+            }
+        }
+        if self.since.is_some() || self.until.is_some() {
+            let Some(timestamp) = entry.timestamp else {
+                return false;
+            };
+            if self.since.is_some_and(|since| timestamp < since)
+                || self.until.is_some_and(|until| timestamp > until)
+            {
+                return false;
+            }
+        }
+        match keyword {
+            Some(keyword) => entry.message.to_lowercase().contains(keyword),
+            None => true,
+        }
+    }
+}
+
+/// 查询内核日志，包含已压缩的旧日志
+pub fn query_logs(query: &CoreLogQuery) -> Result<Vec<CoreLogEntry>> {
+    query_logs_in(&core_logs_dir()?, query)
+}
+
+fn query_logs_in(dir: &Path, query: &CoreLogQuery) -> Result<Vec<CoreLogEntry>> {
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let keyword = query
+        .keyword
+        .as_ref()
+        .filter(|keyword| !keyword.is_empty())
+        .map(|keyword| keyword.to_lowercase());
+    let mut entries = vec![];
+    let mut collect = |reader: &mut dyn BufRead| {
+        for line in reader.lines().map_while(|line| line.ok()) {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry = CoreLogEntry::parse(&line, CoreStream::Stdout);
+            if query.matches(&entry, &keyword) {
+                entries.push(entry);
+            }
+        }
+    };
+
+    for archive in list_archives(dir)? {
+        let mut content = String::new();
+        GzDecoder::new(File::open(&archive)?).read_to_string(&mut content)?;
+        collect(&mut content.as_bytes());
+    }
+    let current = dir.join(CURRENT_LOG);
+    if current.exists() {
+        collect(&mut BufReader::new(File::open(current)?));
+    }
+
+    if let Some(limit) = query.limit {
+        let skip = entries.len().saturating_sub(limit);
+        entries.drain(..skip);
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        let entry = CoreLogEntry::parse(
+            r#"time="2025-01-02T03:04:05.678+08:00" level=warning msg="[TCP] dial \"a\" failed""#,
+            CoreStream::Stdout,
+        );
+        assert_eq!(entry.level, "warning");
+        assert_eq!(entry.message, r#"[TCP] dial "a" failed"#);
+        assert!(entry.timestamp.is_some());
+        assert_eq!(
+            CoreLogEntry::parse(&entry.to_line(), CoreStream::Stdout),
+            entry
+        );
+
+        let entry = CoreLogEntry::parse("panic: runtime error", CoreStream::Stderr);
+        assert_eq!(entry.level, "error");
+        assert_eq!(entry.message, "panic: runtime error");
+    }
+
+    #[test]
+    fn test_rotate_and_query() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path().to_path_buf();
+        let mut writer = CoreLogWriter::with_dir(dir.clone()).unwrap();
+
+        writer
+            .write_line(
+                r#"time="2025-01-02T03:04:05+08:00" level=info msg="Start initial configuration""#,
+                CoreStream::Stdout,
+            )
+            .unwrap();
+        writer.rotate().unwrap();
+        writer
+            .write_line(
+                r#"time="2025-01-02T03:05:05+08:00" level=error msg="proxy ABC unreachable""#,
+                CoreStream::Stdout,
+            )
+            .unwrap();
+        assert_eq!(list_archives(&dir).unwrap().len(), 1);
+
+        // 同一毫秒内的连续轮转不会互相覆盖
+        let other = tempfile::tempdir().unwrap();
+        for _ in 0..3 {
+            create_archive(other.path(), "20250102_030405678").unwrap();
+        }
+        let names = list_archives(other.path())
+            .unwrap()
+            .into_iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                "core_20250102_030405678.log.gz",
+                "core_20250102_030405678_01.log.gz",
+                "core_20250102_030405678_02.log.gz",
+            ]
+        );
+
+        let all = query_logs_in(&dir, &CoreLogQuery::default()).unwrap();
+        assert_eq!(all.len(), 2);
+
+        let query = CoreLogQuery {
+            level: Some("warning".into()),
+            ..Default::default()
+        };
+        assert_eq!(query_logs_in(&dir, &query).unwrap().len(), 1);
+
+        let query = CoreLogQuery {
+            keyword: Some("initial".into()),
+            until: parse_timestamp("2025-01-02T03:04:30+08:00"),
+            ..Default::default()
+        };
+        let entries = query_logs_in(&dir, &query).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].message, "Start initial configuration");
+    }
+}
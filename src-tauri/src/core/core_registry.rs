@@ -0,0 +1,538 @@
+use crate::{
+    logging,
+    utils::{
+        dirs, help,
+        logging::Type,
+        network::{NetworkManager, ProxyType},
+    },
+};
+use anyhow::{anyhow, bail, Context, Result};
+use flate2::read::GzDecoder;
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    io::{self, Cursor, Read},
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::{Duration, SystemTime},
+};
+
+const REGISTRY_FILE: &str = "registry.yaml";
+/// 兼容性检查等待 `-v` 输出的时间
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 托管内核的目录
+pub fn app_cores_dir() -> Result<PathBuf> {
+    Ok(dirs::app_home_dir()?.join("cores"))
+}
+
+/// 内核来源
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CoreSource {
+    /// 从本地压缩包安装
+    Archive(String),
+    /// 从 URL 下载安装
+    Url(String),
+    /// 用户指定的内核路径，不复制到内核目录
+    Custom(String),
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CoreRecord {
+    pub id: String,
+    /// `-v` 输出的版本号
+    pub version: String,
+    /// 内核可执行文件
+    pub path: String,
+    /// 内核可执行文件的 SHA-256
+    pub sha256: String,
+    pub source: CoreSource,
+    pub installed: i64,
+}
+
+/// 已安装的内核，未启用托管内核时使用内置的 sidecar
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ICoreRegistry {
+    /// 正在使用的内核
+    pub active: Option<String>,
+    /// 上一次使用的内核，用于回滚，None 表示内置内核
+    pub previous: Option<String>,
+    pub cores: Vec<CoreRecord>,
+}
+
+impl ICoreRegistry {
+    fn load() -> Self {
+        let path = match app_cores_dir() {
+            Ok(dir) => dir.join(REGISTRY_FILE),
+            Err(_) => return Self::default(),
+        };
+        if !path.exists() {
+            return Self::default();
+        }
+        help::read_yaml::<Self>(&path).unwrap_or_else(|err| {
+            logging!(
+                error,
+                Type::Core,
+                true,
+                "Failed to read core registry: {}",
+                err
+            );
+            Self::default()
+        })
+    }
+
+    fn save_file(&self) -> Result<()> {
+        let dir = app_cores_dir()?;
+        fs::create_dir_all(&dir)?;
+        help::save_yaml(
+            &dir.join(REGISTRY_FILE),
+            self,
+            Some("# Managed cores for Koala Clash"),
+        )
+    }
+
+    pub fn get_core(&self, id: &str) -> Option<&CoreRecord> {
+        self.cores.iter().find(|core| core.id == id)
+    }
+
+    /// 切换内核，`id` 为 None 时使用内置内核
+    fn switch(&mut self, id: Option<String>) -> Result<()> {
+        if let Some(id) = id.as_ref() {
+            if self.get_core(id).is_none() {
+                bail!("core `{id}` is not installed");
+            }
+        }
+        if self.active != id {
+            self.previous = std::mem::replace(&mut self.active, id);
+        }
+        Ok(())
+    }
+}
+
+/// 托管内核注册表
+///
+/// 内核安装在 `cores/<id>/` 下，可以同时保留多个版本并在它们之间切换
+pub struct CoreRegistry {
+    registry: Mutex<ICoreRegistry>,
+    verified: Mutex<Option<VerifiedCore>>,
+}
+
+/// 校验通过的内核文件，修改时间和大小不变时不再重新计算 SHA-256
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct VerifiedCore {
+    path: PathBuf,
+    sha256: String,
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl CoreRegistry {
+    pub fn global() -> &'static CoreRegistry {
+        static REGISTRY: OnceCell<CoreRegistry> = OnceCell::new();
+        REGISTRY.get_or_init(|| CoreRegistry {
+            registry: Mutex::new(ICoreRegistry::load()),
+            verified: Mutex::new(None),
+        })
+    }
+
+    pub fn get(&self) -> ICoreRegistry {
+        self.registry.lock().clone()
+    }
+
+    /// 正在使用的托管内核路径，使用内置内核时为 None
+    ///
+    /// 内核文件与记录的 SHA-256 不一致时视为被篡改，回退到内置内核
+    pub fn active_core_path(&self) -> Option<PathBuf> {
+        let registry = self.registry.lock();
+        let id = registry.active.as_ref()?;
+        let core = registry.get_core(id)?;
+        let path = PathBuf::from(&core.path);
+        match verify_cached(&self.verified, &path, &core.sha256) {
+            Ok(true) => Some(path),
+            Ok(false) => {
+                logging!(
+                    error,
+                    Type::Core,
+                    true,
+                    "Managed core {} at {} failed checksum verification, using the bundled core",
+                    id,
+                    core.path
+                );
+                None
+            }
+            Err(_) => {
+                logging!(
+                    warn,
+                    Type::Core,
+                    true,
+                    "Managed core {} not found at {}, using the bundled core",
+                    id,
+                    core.path
+                );
+                None
+            }
+        }
+    }
+
+    /// 从本地压缩包或 URL 安装内核，必须提供压缩包的 `sha256` 用于校验
+    ///
+    /// 只做 SHA-256 校验：mihomo 的发布不附带签名，签名校验不在支持范围内
+    pub async fn install(&self, source: String, sha256: String) -> Result<CoreRecord> {
+        let expected = sha256.trim();
+        if expected.len() != 64 || !expected.chars().all(|c| c.is_ascii_hexdigit()) {
+            bail!("a valid SHA-256 checksum is required to install a core");
+        }
+
+        let is_url = source.starts_with("http://") || source.starts_with("https://");
+        let data = if is_url {
+            logging!(info, Type::Core, true, "Downloading core from {}", source);
+            let response = NetworkManager::global()
+                .get_with_interrupt(&source, ProxyType::System, Some(120), None, false, false)
+                .await?;
+            if !response.status().is_success() {
+                bail!("failed to download core: {}", response.status());
+            }
+            response.bytes().await?.to_vec()
+        } else {
+            fs::read(&source).with_context(|| format!("failed to read \"{source}\""))?
+        };
+
+        let actual = sha256_hex(&data);
+        if !actual.eq_ignore_ascii_case(expected) {
+            bail!("checksum mismatch, expected {expected}, got {actual}");
+        }
+
+        let id = help::get_uid("c");
+        let dir = app_cores_dir()?.join(&id);
+        fs::create_dir_all(&dir)?;
+        let bin_path = dir.join(core_file_name());
+
+        let result = async {
+            let binary = extract_binary(&source, data)?;
+            fs::write(&bin_path, &binary)?;
+            set_executable(&bin_path)?;
+            let version = probe_version(bin_path.clone()).await?;
+            Ok::<_, anyhow::Error>(CoreRecord {
+                id: id.clone(),
+                version,
+                path: dirs::path_to_str(&bin_path)?.to_string(),
+                sha256: sha256_hex(&binary),
+                source: if is_url {
+                    CoreSource::Url(source.clone())
+                } else {
+                    CoreSource::Archive(source.clone())
+                },
+                installed: chrono::Local::now().timestamp(),
+            })
+        }
+        .await;
+
+        match result {
+            Ok(record) => {
+                self.add_record(record.clone())?;
+                logging!(
+                    info,
+                    Type::Core,
+                    true,
+                    "Installed core {} ({})",
+                    record.id,
+                    record.version
+                );
+                Ok(record)
+            }
+            Err(err) => {
+                let _ = fs::remove_dir_all(&dir);
+                Err(err)
+            }
+        }
+    }
+
+    /// 添加用户指定路径的内核，通过兼容性检查后才会记录
+    pub async fn add_custom(&self, path: String) -> Result<CoreRecord> {
+        let bin_path = PathBuf::from(&path);
+        if !bin_path.is_file() {
+            bail!("core not found at \"{path}\"");
+        }
+        let version = probe_version(bin_path.clone()).await?;
+        let record = CoreRecord {
+            id: help::get_uid("c"),
+            version,
+            path: path.clone(),
+            sha256: sha256_hex(&fs::read(&bin_path)?),
+            source: CoreSource::Custom(path),
+            installed: chrono::Local::now().timestamp(),
+        };
+        self.add_record(record.clone())?;
+        Ok(record)
+    }
+
+    fn add_record(&self, record: CoreRecord) -> Result<()> {
+        let mut registry = self.registry.lock();
+        registry.cores.push(record);
+        registry.save_file()
+    }
+
+    /// 切换内核，`id` 为 None 时使用内置内核
+    pub fn switch(&self, id: Option<String>) -> Result<()> {
+        let mut registry = self.registry.lock();
+        registry.switch(id)?;
+        registry.save_file()
+    }
+
+    /// 删除内核，不能删除正在使用的内核，用户指定的内核文件不会被删除
+    pub fn remove(&self, id: &str) -> Result<()> {
+        let mut registry = self.registry.lock();
+        if registry.active.as_deref() == Some(id) {
+            bail!("core `{id}` is in use");
+        }
+        let index = registry
+            .cores
+            .iter()
+            .position(|core| core.id == id)
+            .ok_or(anyhow!("core `{id}` is not installed"))?;
+        let core = registry.cores.remove(index);
+        if registry.previous.as_deref() == Some(id) {
+            registry.previous = None;
+        }
+        if !matches!(core.source, CoreSource::Custom(_)) {
+            let _ = fs::remove_dir_all(app_cores_dir()?.join(&core.id));
+        }
+        registry.save_file()
+    }
+}
+
+fn core_file_name() -> &'static str {
+    if cfg!(windows) {
+        "mihomo.exe"
+    } else {
+        "mihomo"
+    }
+}
+
+/// 校验文件的 SHA-256，文件与上次校验通过时一致则直接使用缓存的结果
+fn verify_cached(
+    cache: &Mutex<Option<VerifiedCore>>,
+    path: &Path,
+    sha256: &str,
+) -> io::Result<bool> {
+    let meta = fs::metadata(path)?;
+    let stamp = VerifiedCore {
+        path: path.to_path_buf(),
+        sha256: sha256.to_ascii_lowercase(),
+        modified: meta.modified().ok(),
+        len: meta.len(),
+    };
+    if stamp.modified.is_some() && cache.lock().as_ref() == Some(&stamp) {
+        return Ok(true);
+    }
+    let verified = sha256_hex(&fs::read(path)?).eq_ignore_ascii_case(sha256);
+    *cache.lock() = verified.then_some(stamp);
+    Ok(verified)
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// 压缩包中的内核文件，例如 `mihomo`、`mihomo-windows-amd64.exe`
+fn is_core_entry(name: &str) -> bool {
+    let name = name.rsplit('/').next().unwrap_or_default().to_lowercase();
+    let stem = match name.strip_suffix(".exe") {
+        Some(stem) if cfg!(windows) => stem,
+        None if !cfg!(windows) => name.as_str(),
+        _ => return false,
+    };
+    stem == "mihomo" || stem.starts_with("mihomo-")
+}
+
+/// 从 `.gz` 或 `.zip` 中取出内核，其他文件视为内核本身
+fn extract_binary(source: &str, data: Vec<u8>) -> Result<Vec<u8>> {
+    let source = source
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+
+    if source.ends_with(".zip") {
+        let mut archive = zip::ZipArchive::new(Cursor::new(data))?;
+        let mut entries = (0..archive.len()).filter(|&i| {
+            archive
+                .by_index(i)
+                .is_ok_and(|file| file.is_file() && is_core_entry(file.name()))
+        });
+        let index = match (entries.next(), entries.next()) {
+            (Some(index), None) => index,
+            (None, _) => bail!("no core binary found in the archive"),
+            (Some(_), Some(_)) => bail!("more than one core binary found in the archive"),
+        };
+        let mut binary = vec![];
+        archive.by_index(index)?.read_to_end(&mut binary)?;
+        return Ok(binary);
+    }
+
+    if source.ends_with(".gz") {
+        let mut binary = vec![];
+        GzDecoder::new(data.as_slice()).read_to_end(&mut binary)?;
+        return Ok(binary);
+    }
+
+    Ok(data)
+}
+
+#[cfg(unix)]
+fn set_executable(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+}
+
+#[cfg(not(unix))]
+fn set_executable(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// 兼容性检查，运行 `-v` 并解析版本号
+async fn probe_version(path: PathBuf) -> Result<String> {
+    probe_version_within(path, PROBE_TIMEOUT).await
+}
+
+/// 超时后丢弃的子进程由 `kill_on_drop` 结束，卡住的内核不会阻塞安装
+async fn probe_version_within(path: PathBuf, limit: Duration) -> Result<String> {
+    let output = tokio::process::Command::new(path)
+        .arg("-v")
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    let output = tokio::time::timeout(limit, output)
+        .await
+        .map_err(|_| anyhow!("the core did not answer `-v` within {}s", limit.as_secs()))?
+        .context("failed to run the core")?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    parse_version(&stdout).ok_or(anyhow!(
+        "incompatible core, unexpected `-v` output: {}",
+        stdout.trim()
+    ))
+}
+
+/// 解析 `Mihomo Meta v1.19.0 linux amd64 with go1.22.5 ...` 中的版本号
+fn parse_version(output: &str) -> Option<String> {
+    let line = output.lines().next()?;
+    if !line.contains("Mihomo") && !line.contains("Clash") {
+        return None;
+    }
+    line.split_whitespace()
+        .find(|word| {
+            (word.starts_with('v') && word[1..].starts_with(|c: char| c.is_ascii_digit()))
+                || word.starts_with("alpha")
+        })
+        .map(|word| word.to_string())
+}
+
+#[test]
+fn test_parse_version() {
+    assert_eq!(
+        parse_version("Mihomo Meta v1.19.0 linux amd64 with go1.22.5 Sat Jul 20 2024\n"),
+        Some("v1.19.0".into())
+    );
+    assert_eq!(
+        parse_version("Mihomo Meta alpha-0c5a9a3 darwin arm64 with go1.23.0\n"),
+        Some("alpha-0c5a9a3".into())
+    );
+    assert_eq!(parse_version("Python 3.12.1\n"), None);
+}
+
+#[test]
+fn test_is_core_entry() {
+    if cfg!(windows) {
+        assert!(is_core_entry("mihomo-windows-amd64.exe"));
+        assert!(!is_core_entry("mihomo"));
+    } else {
+        assert!(is_core_entry("mihomo"));
+        assert!(is_core_entry("dist/mihomo-linux-amd64-v1.19.0"));
+        assert!(!is_core_entry("mihomo.exe"));
+    }
+    assert!(!is_core_entry("README-mihomo.md"));
+    assert!(!is_core_entry("clash-helper"));
+    assert!(!is_core_entry("mihomo/"));
+}
+
+#[test]
+fn test_switch_and_rollback() {
+    let record = |id: &str| CoreRecord {
+        id: id.into(),
+        version: "v1.19.0".into(),
+        path: String::new(),
+        sha256: String::new(),
+        source: CoreSource::Custom(String::new()),
+        installed: 0,
+    };
+    let mut registry = ICoreRegistry {
+        cores: vec![record("a"), record("b")],
+        ..Default::default()
+    };
+
+    registry.switch(Some("a".into())).unwrap();
+    registry.switch(Some("b".into())).unwrap();
+    assert_eq!(registry.active.as_deref(), Some("b"));
+    assert_eq!(registry.previous.as_deref(), Some("a"));
+
+    let previous = registry.previous.clone();
+    registry.switch(previous).unwrap();
+    assert_eq!(registry.active.as_deref(), Some("a"));
+    assert_eq!(registry.previous.as_deref(), Some("b"));
+
+    assert!(registry.switch(Some("c".into())).is_err());
+    registry.switch(None).unwrap();
+    assert_eq!(registry.active, None);
+}
+
+#[test]
+fn test_verify_cached() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("mihomo");
+    fs::write(&path, b"core").unwrap();
+    let sha256 = sha256_hex(b"core");
+    let cache = Mutex::new(None);
+
+    assert!(verify_cached(&cache, &path, &sha256).unwrap());
+    assert!(cache.lock().is_some());
+
+    // 文件未变化时使用缓存结果，不再读取内容
+    cache.lock().as_mut().unwrap().sha256 = sha256_hex(b"other").to_ascii_lowercase();
+    assert!(verify_cached(&cache, &path, &sha256_hex(b"other")).unwrap());
+
+    fs::write(&path, b"tampered core").unwrap();
+    assert!(!verify_cached(&cache, &path, &sha256).unwrap());
+    assert!(cache.lock().is_none());
+    assert!(verify_cached(&cache, &dir.path().join("missing"), &sha256).is_err());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_probe_version_timeout() {
+    let dir = tempfile::tempdir().unwrap();
+    let script = |name: &str, body: &str| {
+        let path = dir.path().join(name);
+        fs::write(&path, format!("#!/bin/sh\n{body}\n")).unwrap();
+        set_executable(&path).unwrap();
+        path
+    };
+
+    let core = script("core", "echo 'Mihomo Meta v1.19.0 linux amd64'");
+    assert_eq!(
+        probe_version_within(core, Duration::from_secs(5))
+            .await
+            .unwrap(),
+        "v1.19.0"
+    );
+
+    let hang = script("hang", "sleep 30");
+    let started = std::time::Instant::now();
+    assert!(probe_version_within(hang, Duration::from_millis(200))
+        .await
+        .is_err());
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
@@ -4,6 +4,8 @@ use tokio::sync::{mpsc, oneshot};
 use tokio::time::{sleep, timeout, Duration};
 
 use crate::config::{Config, IVerge};
+#[cfg(target_os = "linux")]
+use crate::core::linux_proxy::{self, ProxySetting};
 use crate::core::{async_proxy_query::AsyncProxyQuery, handle};
 use crate::logging_error;
 use crate::utils::logging::Type;
@@ -34,6 +36,8 @@ pub enum ProxyEvent {
     /// 应用关闭事件
     #[allow(dead_code)]
     AppStopping,
+    /// 网络环境变化
+    NetworkChanged,
 }
 
 #[derive(Debug, Clone)]
@@ -146,6 +150,11 @@ impl EventDrivenProxyManager {
         self.send_event(ProxyEvent::AppStopping);
     }
 
+    /// Notify network changed
+    pub fn notify_network_changed(&self) {
+        self.send_event(ProxyEvent::NetworkChanged);
+    }
+
     /// Enable system proxy
     #[allow(dead_code)]
     pub fn enable_proxy(&self) {
@@ -229,6 +238,15 @@ impl EventDrivenProxyManager {
             ProxyEvent::AppStarted => {
                 Self::initialize_proxy_state(state).await;
             }
+            ProxyEvent::NetworkChanged => {
+                // 切换网络后系统代理可能丢失，不论是否开启守卫都检查一次
+                let config = Self::get_proxy_config();
+                Self::update_state_timestamp(state, |s| {
+                    s.sys_enabled = config.sys_enabled;
+                    s.pac_enabled = config.pac_enabled;
+                });
+                Self::check_and_restore_proxy(state).await;
+            }
             ProxyEvent::AppStopping => {
                 log::info!(target: "app", "Cleaning up proxy state");
                 Self::update_state_timestamp(state, |s| {
@@ -386,7 +404,10 @@ impl EventDrivenProxyManager {
     async fn disable_system_proxy(_state: &Arc<RwLock<ProxyState>>) {
         log::info!(target: "app", "Disabling system proxy");
 
-        #[cfg(not(target_os = "windows"))]
+        #[cfg(target_os = "linux")]
+        logging_error!(Type::System, true, linux_proxy::reset());
+
+        #[cfg(target_os = "macos")]
         {
             let disabled_sys = Sysproxy::default();
             let disabled_auto = Autoproxy::default();
@@ -404,15 +425,22 @@ impl EventDrivenProxyManager {
 
         log::info!(target: "app", "Switching to {} mode", if to_pac { "PAC" } else { "HTTP Proxy" });
 
+        // Linux 后端写入新模式时会覆盖旧模式，无需先关闭
         if to_pac {
-            let disabled_sys = Sysproxy::default();
-            logging_error!(Type::System, true, disabled_sys.set_system_proxy());
+            #[cfg(not(target_os = "linux"))]
+            {
+                let disabled_sys = Sysproxy::default();
+                logging_error!(Type::System, true, disabled_sys.set_system_proxy());
+            }
 
             let expected = Self::get_expected_pac_config();
             Self::restore_pac_proxy(&expected.url).await;
         } else {
-            let disabled_auto = Autoproxy::default();
-            logging_error!(Type::System, true, disabled_auto.set_auto_proxy());
+            #[cfg(not(target_os = "linux"))]
+            {
+                let disabled_auto = Autoproxy::default();
+                logging_error!(Type::System, true, disabled_auto.set_auto_proxy());
+            }
 
             let expected = Self::get_expected_sys_proxy();
             Self::restore_sys_proxy(&expected).await;
@@ -525,7 +553,15 @@ impl EventDrivenProxyManager {
     }
 
     async fn restore_pac_proxy(expected_url: &str) {
-        #[cfg(not(target_os = "windows"))]
+        #[cfg(target_os = "linux")]
+        {
+            let setting = ProxySetting::Auto {
+                url: expected_url.to_string(),
+            };
+            logging_error!(Type::System, true, linux_proxy::apply(&setting));
+        }
+
+        #[cfg(target_os = "macos")]
         {
             let new_autoproxy = Autoproxy {
                 enable: true,
@@ -545,7 +581,17 @@ impl EventDrivenProxyManager {
     }
 
     async fn restore_sys_proxy(expected: &Sysproxy) {
-        #[cfg(not(target_os = "windows"))]
+        #[cfg(target_os = "linux")]
+        {
+            let setting = ProxySetting::Manual {
+                host: expected.host.clone(),
+                port: expected.port,
+                bypass: expected.bypass.clone(),
+            };
+            logging_error!(Type::System, true, linux_proxy::apply(&setting));
+        }
+
+        #[cfg(target_os = "macos")]
         {
             logging_error!(Type::System, true, expected.set_system_proxy());
         }
@@ -23,6 +23,7 @@ enum FrontendEvent {
     StartupCompleted,
     ProfileUpdateStarted { uid: String },
     ProfileUpdateCompleted { uid: String },
+    LatencyProgress { payload: serde_json::Value },
 }
 
 /// 事件发送统计和监控
@@ -145,6 +146,9 @@ impl NotificationSystem {
                                         FrontendEvent::ProfileUpdateCompleted { uid } => {
                                             ("profile-update-completed", Ok(serde_json::json!({ "uid": uid })))
                                         }
+                                        FrontendEvent::LatencyProgress { payload } => {
+                                            ("verge://latency-progress", Ok(payload))
+                                        }
                                     };
 
                                     if let Ok(payload) = payload_result {
@@ -412,6 +416,18 @@ impl Handle {
         }
     }
 
+    pub fn notify_latency_progress(payload: serde_json::Value) {
+        let handle = Self::global();
+        if handle.is_exiting() {
+            return;
+        }
+
+        let system_opt = handle.notification_system.read();
+        if let Some(system) = system_opt.as_ref() {
+            system.send_event(FrontendEvent::LatencyProgress { payload });
+        }
+    }
+
     /// 通知前端显示消息队列
     pub fn notice_message<S: Into<String>, M: Into<String>>(status: S, msg: M) {
         let handle = Self::global();
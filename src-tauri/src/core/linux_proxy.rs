@@ -0,0 +1,551 @@
+//! Linux 系统代理后端：GNOME (gsettings/dconf)、KDE (kioslaverc) 与环境变量
+
+use super::async_proxy_query::{AsyncAutoproxy, AsyncSysproxy};
+use crate::{config::Config, utils::dirs};
+use anyhow::{anyhow, bail, Result};
+use std::{collections::HashMap, path::PathBuf, process::Command};
+
+const GNOME_SCHEMA: &str = "org.gnome.system.proxy";
+const KDE_GROUP: &str = "Proxy Settings";
+const ENV_CONF_NAME: &str = "koala-clash-proxy.conf";
+const ENV_SHELL_NAME: &str = "proxy-env.sh";
+/// 记录上次写入代理的后端，切换或重置时据此撤销
+const LAST_BACKEND_NAME: &str = "linux-proxy-backend";
+
+/// 要写入系统的代理状态
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProxySetting {
+    Manual {
+        host: String,
+        port: u16,
+        bypass: String,
+    },
+    Auto {
+        url: String,
+    },
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyBackend {
+    Gnome,
+    Kde,
+    Env,
+}
+
+impl ProxyBackend {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Gnome => "gnome",
+            Self::Kde => "kde",
+            Self::Env => "env",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "gnome" => Some(Self::Gnome),
+            "kde" => Some(Self::Kde),
+            "env" => Some(Self::Env),
+            _ => None,
+        }
+    }
+
+    /// 根据 XDG_CURRENT_DESKTOP 判断后端，未知桌面只写环境变量
+    pub fn detect_from(desktop: &str) -> Self {
+        let desktops = desktop
+            .split(':')
+            .map(|item| item.trim().to_ascii_lowercase())
+            .collect::<Vec<_>>();
+        if desktops.iter().any(|item| item == "kde") {
+            return Self::Kde;
+        }
+        const GSETTINGS_DESKTOPS: [&str; 8] = [
+            "gnome",
+            "unity",
+            "ubuntu",
+            "x-cinnamon",
+            "cinnamon",
+            "budgie",
+            "pantheon",
+            "mate",
+        ];
+        if desktops
+            .iter()
+            .any(|item| GSETTINGS_DESKTOPS.contains(&item.as_str()))
+        {
+            return Self::Gnome;
+        }
+        Self::Env
+    }
+
+    pub fn detect() -> Self {
+        Self::detect_from(&std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default())
+    }
+
+    /// 优先使用 verge 中的 linux_proxy_backend，auto 或未设置时自动检测
+    pub fn current() -> Self {
+        let name = Config::verge().latest().linux_proxy_backend.clone();
+        name.as_deref()
+            .and_then(Self::from_name)
+            .unwrap_or_else(Self::detect)
+    }
+
+    /// 上次写入代理的后端
+    pub fn last_applied() -> Option<Self> {
+        let content = std::fs::read_to_string(last_backend_path().ok()?).ok()?;
+        Self::from_name(&content)
+    }
+
+    pub fn apply(&self, setting: &ProxySetting) -> Result<()> {
+        log::debug!(target: "app", "apply linux proxy via {self:?}: {setting:?}");
+        match self {
+            Self::Gnome => apply_gnome(setting)?,
+            Self::Kde => apply_kde(setting)?,
+            Self::Env => return apply_env(setting),
+        }
+        // 之前使用环境变量后端时留下的文件不再生效，一并清理
+        remove_env_files()
+    }
+
+    /// 读取当前系统代理，GNOME 返回 None 交由 gsettings 查询
+    pub fn query_system_proxy(&self) -> Option<AsyncSysproxy> {
+        match self {
+            Self::Gnome => None,
+            Self::Kde => {
+                let group = read_kioslaverc().unwrap_or_default();
+                let enable = group.get("ProxyType").map(String::as_str) == Some("1");
+                let (host, port) = group
+                    .get("httpProxy")
+                    .and_then(|value| split_proxy_url(value))
+                    .unwrap_or_default();
+                Some(AsyncSysproxy {
+                    enable: enable && !host.is_empty(),
+                    host,
+                    port,
+                    bypass: group.get("NoProxyFor").cloned().unwrap_or_default(),
+                })
+            }
+            Self::Env => {
+                let vars = read_env_conf().unwrap_or_default();
+                let (host, port) = vars
+                    .get("http_proxy")
+                    .and_then(|value| split_proxy_url(value))
+                    .unwrap_or_default();
+                Some(AsyncSysproxy {
+                    enable: !host.is_empty(),
+                    host,
+                    port,
+                    bypass: vars.get("no_proxy").cloned().unwrap_or_default(),
+                })
+            }
+        }
+    }
+
+    /// 读取当前 PAC 配置，GNOME 返回 None 交由 gsettings 查询
+    pub fn query_auto_proxy(&self) -> Option<AsyncAutoproxy> {
+        let url = match self {
+            Self::Gnome => return None,
+            Self::Kde => {
+                let group = read_kioslaverc().unwrap_or_default();
+                if group.get("ProxyType").map(String::as_str) != Some("2") {
+                    return Some(AsyncAutoproxy::default());
+                }
+                group.get("Proxy Config Script").cloned()
+            }
+            Self::Env => read_env_conf().unwrap_or_default().remove("auto_proxy"),
+        }
+        .unwrap_or_default();
+        Some(AsyncAutoproxy {
+            enable: !url.is_empty(),
+            url,
+        })
+    }
+}
+
+/// 通过当前后端写入代理，后端变化时先关闭上次写入的后端
+pub fn apply(setting: &ProxySetting) -> Result<()> {
+    let backend = ProxyBackend::current();
+    if let Some(last) = ProxyBackend::last_applied().filter(|last| *last != backend) {
+        log::info!(target: "app", "linux proxy backend changed from {last:?} to {backend:?}");
+        if let Err(err) = last.apply(&ProxySetting::None) {
+            log::warn!(target: "app", "failed to reset proxy of {last:?}: {err}");
+        }
+    }
+    backend.apply(setting)?;
+    std::fs::write(last_backend_path()?, backend.name())?;
+    Ok(())
+}
+
+/// 关闭上次写入的后端的代理，没有记录时关闭当前后端
+pub fn reset() -> Result<()> {
+    let backend = ProxyBackend::last_applied().unwrap_or_else(ProxyBackend::current);
+    backend.apply(&ProxySetting::None)?;
+    let path = last_backend_path()?;
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+fn last_backend_path() -> Result<PathBuf> {
+    Ok(dirs::app_home_dir()?.join(LAST_BACKEND_NAME))
+}
+
+fn gvariant_str(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+fn split_bypass(bypass: &str) -> Vec<&str> {
+    bypass
+        .split([',', ';'])
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// 生成 gsettings 键值，值为 GVariant 文本，可同时用于 dconf
+pub fn gnome_settings(setting: &ProxySetting) -> Vec<(String, &'static str, String)> {
+    let schema = |sub: &str| {
+        if sub.is_empty() {
+            GNOME_SCHEMA.to_string()
+        } else {
+            format!("{GNOME_SCHEMA}.{sub}")
+        }
+    };
+    match setting {
+        ProxySetting::Manual { host, port, bypass } => {
+            let mut settings = vec![(schema(""), "mode", gvariant_str("manual"))];
+            for sub in ["http", "https", "socks"] {
+                settings.push((schema(sub), "host", gvariant_str(host)));
+                settings.push((schema(sub), "port", port.to_string()));
+            }
+            let hosts = split_bypass(bypass)
+                .into_iter()
+                .map(gvariant_str)
+                .collect::<Vec<_>>();
+            let hosts = if hosts.is_empty() {
+                "@as []".to_string()
+            } else {
+                format!("[{}]", hosts.join(", "))
+            };
+            settings.push((schema(""), "ignore-hosts", hosts));
+            settings
+        }
+        ProxySetting::Auto { url } => vec![
+            (schema(""), "mode", gvariant_str("auto")),
+            (schema(""), "autoconfig-url", gvariant_str(url)),
+        ],
+        ProxySetting::None => vec![(schema(""), "mode", gvariant_str("none"))],
+    }
+}
+
+/// org.gnome.system.proxy.http + host => /system/proxy/http/host
+fn dconf_key(schema: &str, key: &str) -> String {
+    let path = schema.strip_prefix("org.gnome").unwrap_or(schema);
+    format!("{}/{key}", path.replace('.', "/"))
+}
+
+fn run_command(program: &str, args: &[&str]) -> Result<()> {
+    let output = Command::new(program).args(args).output()?;
+    if !output.status.success() {
+        bail!(
+            "{program} {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+fn apply_gnome(setting: &ProxySetting) -> Result<()> {
+    let settings = gnome_settings(setting);
+    // 没有 gsettings 时退回 dconf
+    let use_dconf = Command::new("gsettings").arg("--version").output().is_err();
+    for (schema, key, value) in settings {
+        if use_dconf {
+            run_command("dconf", &["write", &dconf_key(&schema, key), &value])?;
+        } else {
+            run_command("gsettings", &["set", &schema, key, &value])?;
+        }
+    }
+    Ok(())
+}
+
+/// 生成 kioslaverc 中 [Proxy Settings] 的键值
+pub fn kde_entries(setting: &ProxySetting) -> Vec<(&'static str, String)> {
+    match setting {
+        ProxySetting::Manual { host, port, bypass } => vec![
+            ("ProxyType", "1".into()),
+            ("httpProxy", format!("http://{host} {port}")),
+            ("httpsProxy", format!("http://{host} {port}")),
+            ("socksProxy", format!("socks://{host} {port}")),
+            ("NoProxyFor", split_bypass(bypass).join(",")),
+        ],
+        ProxySetting::Auto { url } => vec![
+            ("ProxyType", "2".into()),
+            ("Proxy Config Script", url.clone()),
+        ],
+        ProxySetting::None => vec![("ProxyType", "0".into())],
+    }
+}
+
+/// 更新 ini 中指定分组的键，保留其他分组和未涉及的键
+pub fn update_ini_group(content: &str, group: &str, entries: &[(&str, String)]) -> String {
+    let header = format!("[{group}]");
+    let mut lines = content.lines().map(String::from).collect::<Vec<_>>();
+    let start = lines.iter().position(|line| line.trim() == header);
+    let Some(start) = start else {
+        while lines.last().is_some_and(|line| line.trim().is_empty()) {
+            lines.pop();
+        }
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        lines.push(header);
+        lines.extend(entries.iter().map(|(key, value)| format!("{key}={value}")));
+        return lines.join("\n") + "\n";
+    };
+
+    let mut end = lines[start + 1..]
+        .iter()
+        .position(|line| line.trim_start().starts_with('['))
+        .map_or(lines.len(), |pos| start + 1 + pos);
+    // 新增的键放在分组末尾的空行之前
+    while end > start + 1 && lines[end - 1].trim().is_empty() {
+        end -= 1;
+    }
+    for (key, value) in entries {
+        let line = format!("{key}={value}");
+        let existing = lines[start + 1..end].iter().position(|item| {
+            item.split_once('=')
+                .is_some_and(|(name, _)| name.trim() == *key)
+        });
+        match existing {
+            Some(pos) => lines[start + 1 + pos] = line,
+            None => {
+                lines.insert(end, line);
+                end += 1;
+            }
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+/// 读取 ini 中指定分组的键值
+pub fn read_ini_group(content: &str, group: &str) -> HashMap<String, String> {
+    let header = format!("[{group}]");
+    content
+        .lines()
+        .skip_while(|line| line.trim() != header)
+        .skip(1)
+        .take_while(|line| !line.trim_start().starts_with('['))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+fn kioslaverc_path() -> Result<PathBuf> {
+    Ok(::dirs::config_dir()
+        .ok_or(anyhow!("failed to get the config dir"))?
+        .join("kioslaverc"))
+}
+
+fn read_kioslaverc() -> Result<HashMap<String, String>> {
+    let content = std::fs::read_to_string(kioslaverc_path()?)?;
+    Ok(read_ini_group(&content, KDE_GROUP))
+}
+
+fn apply_kde(setting: &ProxySetting) -> Result<()> {
+    let path = kioslaverc_path()?;
+    let content = std::fs::read_to_string(&path).unwrap_or_default();
+    let content = update_ini_group(&content, KDE_GROUP, &kde_entries(setting));
+    std::fs::write(&path, content)?;
+
+    // 通知 KIO 重新读取代理配置
+    if let Err(err) = run_command(
+        "dbus-send",
+        &[
+            "--type=signal",
+            "/KIO/Scheduler",
+            "org.kde.KIO.Scheduler.reparseSlaveConfiguration",
+            "string:",
+        ],
+    ) {
+        log::warn!(target: "app", "failed to notify KIO about proxy changes: {err}");
+    }
+    Ok(())
+}
+
+/// 生成代理环境变量，PAC 没有通用变量，只写入 auto_proxy
+pub fn env_vars(setting: &ProxySetting) -> Vec<(String, String)> {
+    let vars = match setting {
+        ProxySetting::Manual { host, port, bypass } => vec![
+            ("http_proxy", format!("http://{host}:{port}")),
+            ("https_proxy", format!("http://{host}:{port}")),
+            ("all_proxy", format!("socks5://{host}:{port}")),
+            ("no_proxy", split_bypass(bypass).join(",")),
+        ],
+        ProxySetting::Auto { url } => vec![("auto_proxy", url.clone())],
+        ProxySetting::None => vec![],
+    };
+    vars.iter()
+        .map(|(key, value)| (key.to_string(), value.clone()))
+        .chain(
+            vars.iter()
+                .map(|(key, value)| (key.to_ascii_uppercase(), value.clone())),
+        )
+        .collect()
+}
+
+/// environment.d 格式，重新登录后生效
+pub fn environment_d_content(vars: &[(String, String)]) -> String {
+    vars.iter()
+        .map(|(key, value)| format!("{key}={value}\n"))
+        .collect()
+}
+
+/// 可被 shell source 的脚本
+pub fn shell_content(vars: &[(String, String)]) -> String {
+    vars.iter()
+        .map(|(key, value)| format!("export {key}='{}'\n", value.replace('\'', "'\\''")))
+        .collect()
+}
+
+fn env_conf_path() -> Result<PathBuf> {
+    Ok(::dirs::config_dir()
+        .ok_or(anyhow!("failed to get the config dir"))?
+        .join("environment.d")
+        .join(ENV_CONF_NAME))
+}
+
+/// 供 shell 使用的代理脚本路径
+pub fn env_shell_path() -> Result<PathBuf> {
+    Ok(dirs::app_home_dir()?.join(ENV_SHELL_NAME))
+}
+
+fn read_env_conf() -> Result<HashMap<String, String>> {
+    let content = std::fs::read_to_string(env_conf_path()?)?;
+    Ok(content
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect())
+}
+
+fn remove_env_files() -> Result<()> {
+    for path in [env_conf_path()?, env_shell_path()?] {
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+fn apply_env(setting: &ProxySetting) -> Result<()> {
+    let vars = env_vars(setting);
+    if vars.is_empty() {
+        return remove_env_files();
+    }
+    let conf = env_conf_path()?;
+    if let Some(parent) = conf.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&conf, environment_d_content(&vars))?;
+    std::fs::write(env_shell_path()?, shell_content(&vars))?;
+    Ok(())
+}
+
+/// 解析 http://host:port 或 KDE 的 http://host port
+fn split_proxy_url(value: &str) -> Option<(String, u16)> {
+    let value = value.trim();
+    let value = value.split_once("://").map_or(value, |(_, rest)| rest);
+    let (host, port) = value.rsplit_once(' ').or_else(|| value.rsplit_once(':'))?;
+    let host = host.trim().trim_end_matches('/');
+    if host.is_empty() {
+        return None;
+    }
+    Some((
+        host.to_string(),
+        port.trim().trim_end_matches('/').parse().ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manual() -> ProxySetting {
+        ProxySetting::Manual {
+            host: "127.0.0.1".into(),
+            port: 7897,
+            bypass: "localhost,127.0.0.1,::1".into(),
+        }
+    }
+
+    #[test]
+    fn test_detect_from() {
+        assert_eq!(ProxyBackend::detect_from("KDE"), ProxyBackend::Kde);
+        assert_eq!(
+            ProxyBackend::detect_from("ubuntu:GNOME"),
+            ProxyBackend::Gnome
+        );
+        assert_eq!(ProxyBackend::detect_from("X-Cinnamon"), ProxyBackend::Gnome);
+        assert_eq!(ProxyBackend::detect_from("sway"), ProxyBackend::Env);
+        assert_eq!(ProxyBackend::detect_from(""), ProxyBackend::Env);
+        assert_eq!(ProxyBackend::from_name("auto"), None);
+        for backend in [ProxyBackend::Gnome, ProxyBackend::Kde, ProxyBackend::Env] {
+            assert_eq!(ProxyBackend::from_name(backend.name()), Some(backend));
+        }
+    }
+
+    #[test]
+    fn test_gnome_settings() {
+        let settings = gnome_settings(&manual());
+        assert!(settings.contains(&("org.gnome.system.proxy.https".into(), "port", "7897".into())));
+        assert!(settings.contains(&(
+            "org.gnome.system.proxy".into(),
+            "ignore-hosts",
+            "['localhost', '127.0.0.1', '::1']".into()
+        )));
+        assert_eq!(
+            dconf_key("org.gnome.system.proxy.http", "host"),
+            "/system/proxy/http/host"
+        );
+        assert_eq!(
+            gnome_settings(&ProxySetting::None),
+            vec![("org.gnome.system.proxy".into(), "mode", "'none'".into())]
+        );
+    }
+
+    #[test]
+    fn test_update_ini_group() {
+        let content = "[Cache]\nCacheEnabled=true\n\n[Proxy Settings]\nProxyType=0\nReversedException=false\n\n[Other]\nKey=1\n";
+        let updated = update_ini_group(content, KDE_GROUP, &kde_entries(&manual()));
+        let group = read_ini_group(&updated, KDE_GROUP);
+        assert_eq!(group["ProxyType"], "1");
+        assert_eq!(group["httpProxy"], "http://127.0.0.1 7897");
+        assert_eq!(group["ReversedException"], "false");
+        assert!(updated.starts_with("[Cache]\nCacheEnabled=true\n\n[Proxy Settings]\n"));
+        assert!(updated.ends_with("\n\n[Other]\nKey=1\n"));
+        assert_eq!(
+            split_proxy_url(&group["httpProxy"]),
+            Some(("127.0.0.1".into(), 7897))
+        );
+
+        let created = update_ini_group("", KDE_GROUP, &kde_entries(&ProxySetting::None));
+        assert_eq!(created, "[Proxy Settings]\nProxyType=0\n");
+    }
+
+    #[test]
+    fn test_env_content() {
+        let vars = env_vars(&manual());
+        let conf = environment_d_content(&vars);
+        assert!(conf.contains("http_proxy=http://127.0.0.1:7897\n"));
+        assert!(conf.contains("NO_PROXY=localhost,127.0.0.1,::1\n"));
+        let shell = shell_content(&vars);
+        assert!(shell.contains("export all_proxy='socks5://127.0.0.1:7897'\n"));
+        assert!(env_vars(&ProxySetting::None).is_empty());
+    }
+}
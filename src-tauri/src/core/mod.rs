@@ -2,14 +2,23 @@ pub mod async_proxy_query;
 pub mod backup;
 #[allow(clippy::module_inception)]
 mod core;
+pub mod core_log;
+pub mod core_registry;
 pub mod event_driven_proxy;
 pub mod handle;
 pub mod hotkey;
+#[cfg(target_os = "linux")]
+pub mod linux_proxy;
+pub mod network_monitor;
+pub mod schedule;
 pub mod service;
 pub mod service_ipc;
 pub mod sysopt;
 pub mod timer;
+#[cfg(target_os = "linux")]
+pub mod tproxy;
 pub mod tray;
+pub mod watchdog;
 pub mod win_uwp;
 
 pub use self::{core::*, event_driven_proxy::EventDrivenProxyManager, timer::Timer};
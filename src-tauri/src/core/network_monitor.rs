@@ -0,0 +1,568 @@
+//! 网络变化监听：网卡、地址、默认路由变化及休眠唤醒后重新应用代理与内核状态
+
+use crate::{
+    config::Config,
+    core::EventDrivenProxyManager,
+    feat, logging, logging_error,
+    module::{health_check, mihomo::MihomoManager, network_rules},
+    utils::logging::Type,
+};
+use network_interface::{NetworkInterface, NetworkInterfaceConfig};
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+#[cfg(not(windows))]
+use std::time::{Instant, SystemTime};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    net::IpAddr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::sync::Notify;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+/// 合并短时间内的连续变化
+const DEBOUNCE: Duration = Duration::from_secs(2);
+/// 墙上时钟比单调时钟多走这么久，视为从休眠中唤醒
+#[cfg(not(windows))]
+const RESUME_THRESHOLD: Duration = Duration::from_secs(30);
+const DEFAULT_ACTIONS: [&str; 3] = ["sysproxy", "flush_dns", "close_connections"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum NetworkChange {
+    InterfaceAdded {
+        name: String,
+    },
+    InterfaceRemoved {
+        name: String,
+    },
+    AddressChanged {
+        name: String,
+    },
+    DefaultRouteChanged {
+        from: Option<String>,
+        to: Option<String>,
+    },
+    Resumed,
+}
+
+/// 某一时刻的网络状态
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NetworkSnapshot {
+    pub interfaces: BTreeMap<String, BTreeSet<IpAddr>>,
+    pub default_route: Option<String>,
+}
+
+impl NetworkSnapshot {
+    pub fn capture() -> Self {
+        let tun_device = Config::clash()
+            .latest()
+            .0
+            .get("tun")
+            .and_then(|tun| tun.get("device"))
+            .and_then(|device| device.as_str())
+            .map(String::from);
+        let interfaces = NetworkInterface::show().unwrap_or_default();
+        Self {
+            interfaces: collect_interfaces(
+                interfaces.into_iter().map(|iface| {
+                    (
+                        iface.name,
+                        iface.addr.iter().map(|addr| addr.ip()).collect(),
+                    )
+                }),
+                tun_device.as_deref(),
+            ),
+            default_route: default_route(),
+        }
+    }
+}
+
+/// 过滤回环、链路本地地址和内核自己的 TUN 网卡，避免开关 TUN 触发自身
+pub fn collect_interfaces(
+    interfaces: impl IntoIterator<Item = (String, Vec<IpAddr>)>,
+    tun_device: Option<&str>,
+) -> BTreeMap<String, BTreeSet<IpAddr>> {
+    let mut result = BTreeMap::<String, BTreeSet<IpAddr>>::new();
+    for (name, addrs) in interfaces {
+        if tun_device == Some(name.as_str()) || addrs.iter().any(is_fake_ip) {
+            continue;
+        }
+        let addrs = addrs
+            .into_iter()
+            .filter(|addr| !addr.is_loopback() && !is_link_local(addr))
+            .collect::<BTreeSet<_>>();
+        if !addrs.is_empty() {
+            result.entry(name).or_default().extend(addrs);
+        }
+    }
+    result
+}
+
+fn is_fake_ip(addr: &IpAddr) -> bool {
+    // 198.18.0.0/15
+    matches!(addr, IpAddr::V4(v4) if v4.octets()[0] == 198 && v4.octets()[1] & 0xfe == 18)
+}
+
+fn is_link_local(addr: &IpAddr) -> bool {
+    match addr {
+        IpAddr::V4(v4) => v4.is_link_local(),
+        IpAddr::V6(v6) => v6.segments()[0] & 0xffc0 == 0xfe80,
+    }
+}
+
+/// 比较两次快照
+pub fn diff(old: &NetworkSnapshot, new: &NetworkSnapshot) -> Vec<NetworkChange> {
+    let mut changes = Vec::new();
+    for (name, addrs) in &new.interfaces {
+        match old.interfaces.get(name) {
+            None => changes.push(NetworkChange::InterfaceAdded { name: name.clone() }),
+            Some(old_addrs) if old_addrs != addrs => {
+                changes.push(NetworkChange::AddressChanged { name: name.clone() })
+            }
+            _ => {}
+        }
+    }
+    for name in old.interfaces.keys() {
+        if !new.interfaces.contains_key(name) {
+            changes.push(NetworkChange::InterfaceRemoved { name: name.clone() });
+        }
+    }
+    if old.default_route != new.default_route {
+        changes.push(NetworkChange::DefaultRouteChanged {
+            from: old.default_route.clone(),
+            to: new.default_route.clone(),
+        });
+    }
+    changes
+}
+
+/// 解析 /proc/net/route 的默认路由，返回网卡和网关
+#[cfg(any(target_os = "linux", test))]
+pub fn parse_proc_route(content: &str) -> Option<(String, std::net::Ipv4Addr)> {
+    content.lines().skip(1).find_map(|line| {
+        let fields = line.split_whitespace().collect::<Vec<_>>();
+        if fields.len() < 8 || fields[1] != "00000000" || fields[7] != "00000000" {
+            return None;
+        }
+        let gateway = u32::from_str_radix(fields[2], 16).ok()?;
+        // 内核按主机字节序输出 `__be32` 的值
+        let gateway = std::net::Ipv4Addr::from(gateway.to_ne_bytes());
+        Some((fields[0].to_string(), gateway))
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn default_route() -> Option<String> {
+    parse_proc_route(&std::fs::read_to_string("/proc/net/route").ok()?)
+        .map(|(iface, gateway)| format!("{iface} via {gateway}"))
+}
+
+/// 其他平台依靠网卡和地址变化判断
+#[cfg(not(target_os = "linux"))]
+fn default_route() -> Option<String> {
+    None
+}
+
+pub struct NetworkMonitor {
+    started: AtomicBool,
+    notify: Arc<Notify>,
+}
+
+impl NetworkMonitor {
+    pub fn global() -> &'static NetworkMonitor {
+        static MONITOR: OnceCell<NetworkMonitor> = OnceCell::new();
+        MONITOR.get_or_init(|| NetworkMonitor {
+            started: AtomicBool::new(false),
+            notify: Arc::new(Notify::new()),
+        })
+    }
+
+    pub fn start(&self) {
+        if self.started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        #[cfg(target_os = "linux")]
+        netlink::spawn(self.notify.clone());
+        #[cfg(windows)]
+        power::spawn(self.notify.clone());
+
+        let notify = self.notify.clone();
+        tokio::spawn(async move {
+            let mut last = NetworkSnapshot::capture();
+            #[cfg(not(windows))]
+            let (mut last_instant, mut last_wall) = (Instant::now(), SystemTime::now());
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                    _ = notify.notified() => tokio::time::sleep(DEBOUNCE).await,
+                }
+
+                // 休眠期间单调时钟不走，墙上时钟会跳变
+                #[cfg(not(windows))]
+                let resumed = {
+                    let (instant, wall) = (Instant::now(), SystemTime::now());
+                    let resumed = wall.duration_since(last_wall).is_ok_and(|elapsed| {
+                        elapsed > instant.duration_since(last_instant) + RESUME_THRESHOLD
+                    });
+                    (last_instant, last_wall) = (instant, wall);
+                    resumed
+                };
+                #[cfg(windows)]
+                let resumed = power::take_resumed();
+
+                let current = NetworkSnapshot::capture();
+                let mut changes = diff(&last, &current);
+                last = current;
+                if resumed {
+                    changes.push(NetworkChange::Resumed);
+                }
+                if changes.is_empty() {
+                    continue;
+                }
+                #[cfg(target_os = "linux")]
+                Self::resync_system(&changes).await;
+                if !Self::is_enabled() {
+                    continue;
+                }
+                logging!(info, Type::Network, true, "Network changed: {:?}", changes);
+                Self::react(&changes).await;
+            }
+        });
+    }
+
+    /// 网络变化后的操作会关闭连接，默认不开启
+    fn is_enabled() -> bool {
+        Config::verge()
+            .latest()
+            .enable_network_monitor
+            .unwrap_or(false)
+    }
+
+    /// 默认路由变化后 DNS 接管的网卡和透明代理规则可能已失效，不受开关影响
+    #[cfg(target_os = "linux")]
+    async fn resync_system(changes: &[NetworkChange]) {
+        let route_changed = changes.iter().any(|change| {
+            matches!(
+                change,
+                NetworkChange::DefaultRouteChanged { .. } | NetworkChange::Resumed
+            )
+        });
+        if !route_changed
+            || crate::core::CoreManager::global().get_running_mode().await
+                == crate::core::RunningMode::NotRunning
+        {
+            return;
+        }
+        logging_error!(
+            Type::Network,
+            true,
+            crate::core::tproxy::TransparentProxy::global().sync().await
+        );
+        logging_error!(Type::Network, true, crate::utils::linux_dns::sync().await);
+    }
+
+    /// 当前配置的操作，未配置时使用默认操作
+    pub fn actions() -> Vec<String> {
+        Config::verge()
+            .latest()
+            .network_change_actions
+            .clone()
+            .unwrap_or_else(|| DEFAULT_ACTIONS.map(String::from).to_vec())
+    }
+
+    async fn react(changes: &[NetworkChange]) {
+        let actions = Self::actions();
+        let has = |action: &str| actions.iter().any(|item| item == action);
+
+        if has("sysproxy") {
+            EventDrivenProxyManager::global().notify_network_changed();
+        }
+        if has("flush_dns") {
+            let mihomo = MihomoManager::global();
+            if let Err(err) = mihomo.flush_fakeip_cache().await {
+                logging!(
+                    warn,
+                    Type::Network,
+                    true,
+                    "Failed to flush fake-ip cache: {}",
+                    err
+                );
+            }
+            if let Err(err) = mihomo.flush_dns_cache().await {
+                logging!(
+                    warn,
+                    Type::Network,
+                    true,
+                    "Failed to flush dns cache: {}",
+                    err
+                );
+            }
+        }
+        if has("close_connections") {
+            if let Err(err) = MihomoManager::global().close_all_connections().await {
+                logging!(
+                    warn,
+                    Type::Network,
+                    true,
+                    "Failed to close connections: {}",
+                    err
+                );
+            }
+        }
+        if has("health_check") {
+            let checks = Config::verge()
+                .latest()
+                .group_health_checks
+                .clone()
+                .unwrap_or_default();
+            for check in checks {
+                if let Err(err) = health_check::check_group(&check).await {
+                    logging!(
+                        warn,
+                        Type::Network,
+                        true,
+                        "Health check failed for group {}: {}",
+                        check.group,
+                        err
+                    );
+                }
+            }
+        }
+        // 网络环境规则优先于固定的切换目标
+        match network_rules::evaluate().await {
+            Ok(Some(_)) => return,
+            Ok(None) => {}
+            Err(err) => {
+                logging!(
+                    warn,
+                    Type::Network,
+                    true,
+                    "Failed to evaluate network rules: {}",
+                    err
+                );
+            }
+        }
+        if has("switch_profile") {
+            let target = Config::verge().latest().network_change_profile.clone();
+            let current = Config::profiles().latest().get_current();
+            if let Some(uid) = target.filter(|uid| current.as_ref() != Some(uid)) {
+                logging!(
+                    info,
+                    Type::Network,
+                    true,
+                    "Switching profile to {} after {} network change(s)",
+                    uid,
+                    changes.len()
+                );
+                feat::toggle_proxy_profile(uid);
+            }
+        }
+    }
+}
+
+/// 通过 rtnetlink 多播组即时感知变化，轮询作为兜底
+#[cfg(target_os = "linux")]
+mod netlink {
+    use std::sync::Arc;
+    use tokio::sync::Notify;
+
+    pub fn spawn(notify: Arc<Notify>) {
+        let result = std::thread::Builder::new()
+            .name("netlink-monitor".into())
+            .spawn(move || {
+                if let Err(err) = listen(&notify) {
+                    log::warn!(target: "app", "netlink monitor stopped: {err}");
+                }
+            });
+        if let Err(err) = result {
+            log::warn!(target: "app", "failed to spawn netlink monitor: {err}");
+        }
+    }
+
+    fn listen(notify: &Notify) -> std::io::Result<()> {
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+                libc::NETLINK_ROUTE,
+            )
+        };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let mut addr: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+        addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        addr.nl_groups = (libc::RTMGRP_LINK
+            | libc::RTMGRP_IPV4_IFADDR
+            | libc::RTMGRP_IPV6_IFADDR
+            | libc::RTMGRP_IPV4_ROUTE
+            | libc::RTMGRP_IPV6_ROUTE) as u32;
+        let ret = unsafe {
+            libc::bind(
+                fd,
+                &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+                std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+        if ret < 0 {
+            let err = std::io::Error::last_os_error();
+            unsafe { libc::close(fd) };
+            return Err(err);
+        }
+
+        let mut buf = [0u8; 8192];
+        loop {
+            let len = unsafe { libc::recv(fd, buf.as_mut_ptr().cast(), buf.len(), 0) };
+            if len < 0 {
+                let err = std::io::Error::last_os_error();
+                if err.kind() == std::io::ErrorKind::Interrupted {
+                    continue;
+                }
+                unsafe { libc::close(fd) };
+                return Err(err);
+            }
+            // 只作为触发信号，具体变化由快照比较得出
+            notify.notify_one();
+        }
+    }
+}
+
+/// Windows 的单调时钟在休眠期间仍会计时，改用系统电源通知感知唤醒
+#[cfg(windows)]
+mod power {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+    use tokio::sync::Notify;
+    use winapi::{
+        shared::{minwindef::ULONG, ntdef::PVOID, winerror::ERROR_SUCCESS},
+        um::{
+            powerbase::PowerRegisterSuspendResumeNotification,
+            winnt::HANDLE,
+            winuser::{
+                DEVICE_NOTIFY_CALLBACK, DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS, HPOWERNOTIFY,
+                PBT_APMRESUMEAUTOMATIC, PBT_APMRESUMESUSPEND,
+            },
+        },
+    };
+
+    static RESUMED: AtomicBool = AtomicBool::new(false);
+
+    pub fn spawn(notify: Arc<Notify>) {
+        // 注册在进程生命周期内有效，参数和通知都不释放
+        let params = Box::leak(Box::new(DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS {
+            Callback: Some(on_power_event),
+            Context: Arc::into_raw(notify) as PVOID,
+        }));
+        let mut handle: HPOWERNOTIFY = std::ptr::null_mut();
+        let ret = unsafe {
+            PowerRegisterSuspendResumeNotification(
+                DEVICE_NOTIFY_CALLBACK,
+                params as *mut DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS as HANDLE,
+                &mut handle,
+            )
+        };
+        if ret != ERROR_SUCCESS {
+            log::warn!(target: "app", "failed to register power notification: {ret}");
+        }
+    }
+
+    unsafe extern "system" fn on_power_event(context: PVOID, kind: ULONG, _: PVOID) -> ULONG {
+        let kind = kind as usize;
+        if kind == PBT_APMRESUMEAUTOMATIC || kind == PBT_APMRESUMESUSPEND {
+            RESUMED.store(true, Ordering::SeqCst);
+            (*(context as *const Notify)).notify_one();
+        }
+        ERROR_SUCCESS
+    }
+
+    /// 上次检查后是否从休眠中唤醒
+    pub fn take_resumed() -> bool {
+        RESUMED.swap(false, Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(interfaces: &[(&str, &[&str])], route: Option<&str>) -> NetworkSnapshot {
+        NetworkSnapshot {
+            interfaces: collect_interfaces(
+                interfaces.iter().map(|(name, addrs)| {
+                    (
+                        name.to_string(),
+                        addrs.iter().map(|addr| addr.parse().unwrap()).collect(),
+                    )
+                }),
+                Some("Meta"),
+            ),
+            default_route: route.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let wifi = snapshot(
+            &[
+                ("lo", &["127.0.0.1"]),
+                ("wlan0", &["192.168.1.20", "fe80::1"]),
+                ("Meta", &["198.18.0.1"]),
+            ],
+            Some("wlan0 via 192.168.1.1"),
+        );
+        assert_eq!(wifi.interfaces.len(), 1);
+        assert!(diff(&wifi, &wifi).is_empty());
+
+        // TUN 网卡出现不算网络变化
+        let with_tun = snapshot(
+            &[("wlan0", &["192.168.1.20"]), ("utun3", &["198.18.0.1"])],
+            Some("wlan0 via 192.168.1.1"),
+        );
+        assert!(diff(&wifi, &with_tun).is_empty());
+
+        let ethernet = snapshot(
+            &[("wlan0", &["10.0.0.5"]), ("eth0", &["192.168.2.3"])],
+            Some("eth0 via 192.168.2.1"),
+        );
+        assert_eq!(
+            diff(&wifi, &ethernet),
+            vec![
+                NetworkChange::InterfaceAdded {
+                    name: "eth0".into()
+                },
+                NetworkChange::AddressChanged {
+                    name: "wlan0".into()
+                },
+                NetworkChange::DefaultRouteChanged {
+                    from: Some("wlan0 via 192.168.1.1".into()),
+                    to: Some("eth0 via 192.168.2.1".into()),
+                },
+            ]
+        );
+        assert_eq!(
+            diff(&ethernet, &NetworkSnapshot::default()).len(),
+            3,
+            "two removed interfaces and a lost default route"
+        );
+    }
+
+    #[test]
+    fn test_parse_proc_route() {
+        let content =
+            "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT\n\
+wlan0\t0001A8C0\t00000000\t0001\t0\t0\t600\t00FFFFFF\t0\t0\t0\n\
+wlan0\t00000000\t0101A8C0\t0003\t0\t0\t600\t00000000\t0\t0\t0\n";
+        assert_eq!(
+            parse_proc_route(content),
+            Some(("wlan0".into(), "192.168.1.1".parse().unwrap()))
+        );
+        assert_eq!(parse_proc_route("Iface\tDestination\n"), None);
+    }
+}
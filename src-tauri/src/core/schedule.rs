@@ -0,0 +1,554 @@
+//! 定时任务：由 Timer 每分钟检查一次，按上次检查时间补执行休眠或退出期间错过的任务
+
+use crate::{
+    config::{Config, IVerge, IVergeSchedule, IVergeScheduleAction},
+    core::handle,
+    feat, logging,
+    module::mihomo::MihomoManager,
+    utils::{dirs, logging::Type},
+};
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Datelike, Duration, Local, LocalResult, NaiveDateTime, TimeZone, Timelike};
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, str::FromStr};
+
+const STATE_FILE: &str = "schedule_state.json";
+/// 最多补执行这么久以前错过的任务
+const MAX_CATCH_UP: Duration = Duration::days(7);
+/// 搜索下一次触发时间的步数上限，避免 2 月 30 日这类永不触发的表达式死循环
+const MAX_STEPS: usize = 100_000;
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// 五段 cron 表达式，每段用位图表示允许的取值
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronExpr {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// 日和周都有限制时满足其一即可，与 vixie cron 一致
+    any_day: bool,
+    any_weekday: bool,
+}
+
+fn parse_value(value: &str, min: u32, names: &[&str], offset: u32) -> Result<u32> {
+    if let Ok(value) = value.parse::<u32>() {
+        return Ok(value);
+    }
+    let lower = value.to_lowercase();
+    names
+        .iter()
+        .position(|name| *name == lower)
+        .map(|index| index as u32 + offset)
+        .filter(|value| *value >= min)
+        .ok_or(anyhow!("invalid value `{value}`"))
+}
+
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str], offset: u32) -> Result<u64> {
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>()?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            bail!("invalid step in `{part}`");
+        }
+        let (start, end) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((start, end)) => (
+                    parse_value(start, min, names, offset)?,
+                    parse_value(end, min, names, offset)?,
+                ),
+                None => {
+                    let start = parse_value(range, min, names, offset)?;
+                    // `5/15` 表示从 5 开始每 15 个单位
+                    (start, if part.contains('/') { max } else { start })
+                }
+            },
+        };
+        if start < min || end > max || start > end {
+            bail!("`{part}` is out of range {min}-{max}");
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+impl FromStr for CronExpr {
+    type Err = anyhow::Error;
+
+    fn from_str(expr: &str) -> Result<Self> {
+        let expr = match expr.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            expr => expr,
+        };
+        let fields = expr.split_whitespace().collect::<Vec<_>>();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            bail!("cron expression `{expr}` must have 5 fields");
+        };
+        let mut weekdays = parse_field(weekday, 0, 7, &WEEKDAYS, 0)?;
+        // 0 和 7 都表示周日
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59, &[], 0)?,
+            hours: parse_field(hour, 0, 23, &[], 0)?,
+            days: parse_field(day, 1, 31, &[], 0)?,
+            months: parse_field(month, 1, 12, &MONTHS, 1)?,
+            weekdays,
+            any_day: day == "*",
+            any_weekday: weekday == "*",
+        })
+    }
+}
+
+impl CronExpr {
+    fn matches_day(&self, time: &NaiveDateTime) -> bool {
+        let day = self.days & (1 << time.day()) != 0;
+        let weekday = self.weekdays & (1 << time.weekday().num_days_from_sunday()) != 0;
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => weekday,
+            (false, true) => day,
+            (false, false) => day || weekday,
+        }
+    }
+
+    /// 严格晚于 `after` 的下一次触发时间，夏令时跳过的时刻不触发
+    pub fn next_after<Tz: TimeZone>(&self, after: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let tz = after.timezone();
+        let mut time = after
+            .naive_local()
+            .with_second(0)?
+            .with_nanosecond(0)?
+            .checked_add_signed(Duration::minutes(1))?;
+        for _ in 0..MAX_STEPS {
+            if self.months & (1 << time.month()) == 0 {
+                let (year, month) = match time.month() {
+                    12 => (time.year() + 1, 1),
+                    month => (time.year(), month + 1),
+                };
+                time = chrono::NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !self.matches_day(&time) {
+                time = time.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if self.hours & (1 << time.hour()) == 0 {
+                time = time.with_minute(0)? + Duration::hours(1);
+                continue;
+            }
+            if self.minutes & (1 << time.minute()) == 0 {
+                time += Duration::minutes(1);
+                continue;
+            }
+            let candidate = match tz.from_local_datetime(&time) {
+                LocalResult::Single(candidate) => Some(candidate),
+                LocalResult::Ambiguous(first, second) => {
+                    Some(if first > *after { first } else { second })
+                }
+                LocalResult::None => None,
+            };
+            match candidate {
+                Some(candidate) if candidate > *after => return Some(candidate),
+                _ => time += Duration::minutes(1),
+            }
+        }
+        None
+    }
+
+    /// 接下来的若干次触发时间
+    pub fn upcoming<Tz: TimeZone>(&self, after: &DateTime<Tz>, count: usize) -> Vec<DateTime<Tz>> {
+        std::iter::successors(self.next_after(after), |time| self.next_after(time))
+            .take(count)
+            .collect()
+    }
+
+    /// (since, until] 区间内最后一次触发时间
+    pub fn last_between<Tz: TimeZone>(
+        &self,
+        since: &DateTime<Tz>,
+        until: &DateTime<Tz>,
+    ) -> Option<DateTime<Tz>> {
+        std::iter::successors(self.next_after(since), |time| self.next_after(time))
+            .take_while(|time| time <= until)
+            .last()
+    }
+}
+
+/// 区间内需要执行的任务，每个任务只取最后一次触发，按触发时间排序
+pub fn due_schedules<'a, Tz: TimeZone>(
+    schedules: &'a [IVergeSchedule],
+    since: &DateTime<Tz>,
+    until: &DateTime<Tz>,
+) -> Vec<(DateTime<Tz>, &'a IVergeSchedule)> {
+    let mut due = schedules
+        .iter()
+        .filter(|schedule| schedule.enable.unwrap_or(true))
+        .filter_map(|schedule| {
+            let cron = schedule.cron.parse::<CronExpr>().ok()?;
+            Some((cron.last_between(since, until)?, schedule))
+        })
+        .collect::<Vec<_>>();
+    due.sort_by(|(a, _), (b, _)| a.cmp(b));
+    due
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ScheduleState {
+    last_checked: i64,
+}
+
+fn state_path() -> Result<PathBuf> {
+    Ok(dirs::app_home_dir()?.join(STATE_FILE))
+}
+
+fn load_state() -> Option<ScheduleState> {
+    let content = std::fs::read_to_string(state_path().ok()?).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn save_state(state: &ScheduleState) -> Result<()> {
+    std::fs::write(state_path()?, serde_json::to_string(state)?)?;
+    Ok(())
+}
+
+/// 修改任务后从当前时间开始计算，避免新任务补执行过去的触发
+pub fn mark_checked() -> Result<()> {
+    save_state(&ScheduleState {
+        last_checked: Local::now().timestamp(),
+    })
+}
+
+pub fn schedules() -> Vec<IVergeSchedule> {
+    Config::verge()
+        .latest()
+        .schedules
+        .clone()
+        .unwrap_or_default()
+}
+
+pub fn has_enabled() -> bool {
+    schedules()
+        .iter()
+        .any(|schedule| schedule.enable.unwrap_or(true))
+}
+
+/// 任务及接下来的触发时间（秒级时间戳）
+#[derive(Debug, Clone, Serialize)]
+pub struct ScheduleInfo {
+    #[serde(flatten)]
+    pub schedule: IVergeSchedule,
+    pub next_firings: Vec<i64>,
+    pub error: Option<String>,
+}
+
+/// 接下来 `count` 次触发时间
+pub fn preview(cron: &str, count: usize) -> Result<Vec<i64>> {
+    let cron = cron.parse::<CronExpr>()?;
+    Ok(cron
+        .upcoming(&Local::now(), count)
+        .iter()
+        .map(DateTime::timestamp)
+        .collect())
+}
+
+pub fn list(count: usize) -> Vec<ScheduleInfo> {
+    schedules()
+        .into_iter()
+        .map(|schedule| {
+            let (next_firings, error) = match preview(&schedule.cron, count) {
+                Ok(firings) => (firings, None),
+                Err(err) => (vec![], Some(err.to_string())),
+            };
+            ScheduleInfo {
+                schedule,
+                next_firings,
+                error,
+            }
+        })
+        .collect()
+}
+
+/// 由 Timer 周期调用，执行上次检查以来到期的任务
+pub async fn tick() {
+    let now = Local::now();
+    let since = load_state()
+        .and_then(|state| Local.timestamp_opt(state.last_checked, 0).single())
+        .unwrap_or(now)
+        .max(now - MAX_CATCH_UP);
+    if let Err(err) = mark_checked() {
+        logging!(
+            warn,
+            Type::Timer,
+            true,
+            "Failed to save schedule state: {}",
+            err
+        );
+    }
+
+    let schedules = schedules();
+    for (time, schedule) in due_schedules(&schedules, &since, &now) {
+        logging!(
+            info,
+            Type::Timer,
+            true,
+            "Running schedule {} due at {}",
+            schedule.name,
+            time.format("%Y-%m-%d %H:%M")
+        );
+        if let Err(err) = run(&schedule.action).await {
+            logging!(
+                warn,
+                Type::Timer,
+                true,
+                "Schedule {} failed: {}",
+                schedule.name,
+                err
+            );
+        }
+    }
+}
+
+/// 执行单个操作，状态已符合时跳过
+pub async fn run(action: &IVergeScheduleAction) -> Result<()> {
+    match action {
+        IVergeScheduleAction::Mode(mode) => {
+            let mode = mode.to_lowercase();
+            if !["rule", "global", "direct"].contains(&mode.as_str()) {
+                bail!("invalid mode `{mode}`");
+            }
+            let current = Config::clash()
+                .latest()
+                .0
+                .get("mode")
+                .and_then(|mode| mode.as_str())
+                .map(String::from);
+            if current.as_deref() != Some(mode.as_str()) {
+                feat::change_clash_mode(mode);
+            }
+        }
+        IVergeScheduleAction::Profile(uid) => {
+            let profiles = Config::profiles().latest().clone();
+            profiles.get_item(uid)?;
+            if profiles.get_current().as_ref() != Some(uid) {
+                feat::toggle_proxy_profile(uid.clone());
+            }
+        }
+        IVergeScheduleAction::Sysproxy(enable) => {
+            let current = Config::verge().latest().enable_system_proxy;
+            if current.unwrap_or(false) != *enable {
+                feat::patch_verge(
+                    IVerge {
+                        enable_system_proxy: Some(*enable),
+                        ..IVerge::default()
+                    },
+                    false,
+                )
+                .await?;
+                handle::Handle::refresh_verge();
+            }
+        }
+        IVergeScheduleAction::Tun(enable) => {
+            let current = Config::verge().latest().enable_tun_mode;
+            if current.unwrap_or(false) != *enable {
+                feat::patch_verge(
+                    IVerge {
+                        enable_tun_mode: Some(*enable),
+                        ..IVerge::default()
+                    },
+                    false,
+                )
+                .await?;
+                handle::Handle::refresh_verge();
+            }
+        }
+        IVergeScheduleAction::CloseConnections => {
+            MihomoManager::global()
+                .close_all_connections()
+                .await
+                .map_err(|err| anyhow!(err))?;
+        }
+        IVergeScheduleAction::UpdateSubscriptions => {
+            let (current, remotes) = {
+                let profiles = Config::profiles().latest().clone();
+                let remotes = profiles
+                    .get_items()
+                    .into_iter()
+                    .flatten()
+                    .filter(|item| item.itype.as_deref() == Some("remote"))
+                    .filter_map(|item| item.uid.clone())
+                    .collect::<Vec<_>>();
+                (profiles.get_current(), remotes)
+            };
+            for uid in remotes {
+                let is_current = current.as_ref() == Some(&uid);
+                if let Err(err) = feat::update_profile(uid.clone(), None, Some(is_current)).await {
+                    logging!(
+                        warn,
+                        Type::Timer,
+                        true,
+                        "Failed to update subscription {}: {}",
+                        uid,
+                        err
+                    );
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, Utc};
+
+    fn at(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn upcoming(expr: &str, after: &str, count: usize) -> Vec<String> {
+        expr.parse::<CronExpr>()
+            .unwrap()
+            .upcoming(&at(after), count)
+            .iter()
+            .map(|time| time.format("%a %Y-%m-%d %H:%M").to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!("* * * * *".parse::<CronExpr>().is_ok());
+        assert!("*/15 9-17 * * mon-fri".parse::<CronExpr>().is_ok());
+        assert!("0 0 1 jan,jul *".parse::<CronExpr>().is_ok());
+        assert!("@daily".parse::<CronExpr>().is_ok());
+        assert!("* * * *".parse::<CronExpr>().is_err());
+        assert!("60 * * * *".parse::<CronExpr>().is_err());
+        assert!("* * 0 * *".parse::<CronExpr>().is_err());
+        assert!("*/0 * * * *".parse::<CronExpr>().is_err());
+        assert!("* * * * foo".parse::<CronExpr>().is_err());
+        assert_eq!(
+            "0 0 * * 7".parse::<CronExpr>().unwrap(),
+            "0 0 * * sun".parse::<CronExpr>().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_upcoming() {
+        // 2025-01-03 是周五
+        assert_eq!(
+            upcoming("0 9 * * 1-5", "2025-01-03T08:59:30Z", 3),
+            [
+                "Fri 2025-01-03 09:00",
+                "Mon 2025-01-06 09:00",
+                "Tue 2025-01-07 09:00"
+            ]
+        );
+        assert_eq!(
+            upcoming("0 9 * * 1-5", "2025-01-03T09:00:00Z", 1),
+            ["Mon 2025-01-06 09:00"]
+        );
+        assert_eq!(
+            upcoming("30 18 * * *", "2025-12-31T20:00:00Z", 2),
+            ["Thu 2026-01-01 18:30", "Fri 2026-01-02 18:30"]
+        );
+        assert_eq!(
+            upcoming("*/20 * * * *", "2025-01-01T10:45:00Z", 3),
+            [
+                "Wed 2025-01-01 11:00",
+                "Wed 2025-01-01 11:20",
+                "Wed 2025-01-01 11:40"
+            ]
+        );
+        // 日和周都有限制时满足其一即可
+        assert_eq!(
+            upcoming("0 0 13 * fri", "2025-06-01T00:00:00Z", 3),
+            [
+                "Fri 2025-06-06 00:00",
+                "Fri 2025-06-13 00:00",
+                "Fri 2025-06-20 00:00"
+            ]
+        );
+        assert_eq!(
+            upcoming("0 0 29 2 *", "2025-01-01T00:00:00Z", 1),
+            ["Tue 2028-02-29 00:00"]
+        );
+        assert!(upcoming("0 0 30 2 *", "2025-01-01T00:00:00Z", 1).is_empty());
+    }
+
+    #[test]
+    fn test_timezone() {
+        let tz = FixedOffset::east_opt(8 * 3600).unwrap();
+        let after = at("2025-01-01T00:00:00Z").with_timezone(&tz);
+        let next = "0 9 * * *".parse::<CronExpr>().unwrap().next_after(&after);
+        assert_eq!(next.unwrap().to_rfc3339(), "2025-01-01T09:00:00+08:00");
+    }
+
+    #[test]
+    fn test_due_schedules() {
+        let schedule = |name: &str, cron: &str| IVergeSchedule {
+            name: name.into(),
+            cron: cron.into(),
+            enable: None,
+            action: IVergeScheduleAction::CloseConnections,
+        };
+        let schedules = vec![
+            schedule("direct", "0 18 * * *"),
+            schedule("proxy", "0 9 * * *"),
+            schedule("invalid", "bad"),
+            IVergeSchedule {
+                enable: Some(false),
+                ..schedule("disabled", "* * * * *")
+            },
+        ];
+
+        // 从前一天晚上休眠到第二天中午，只执行每个任务最后一次错过的触发
+        let due = due_schedules(
+            &schedules,
+            &at("2025-01-01T17:00:00Z"),
+            &at("2025-01-02T12:00:00Z"),
+        );
+        assert_eq!(
+            due.iter()
+                .map(|(time, schedule)| (time.to_rfc3339(), schedule.name.as_str()))
+                .collect::<Vec<_>>(),
+            [
+                ("2025-01-01T18:00:00+00:00".to_string(), "direct"),
+                ("2025-01-02T09:00:00+00:00".to_string(), "proxy"),
+            ]
+        );
+        assert!(due_schedules(
+            &schedules,
+            &at("2025-01-02T09:00:00Z"),
+            &at("2025-01-02T09:00:59Z")
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_action_serde() {
+        let action: IVergeScheduleAction =
+            serde_yaml::from_str("type: mode\nvalue: direct").unwrap();
+        assert_eq!(action, IVergeScheduleAction::Mode("direct".into()));
+        let action: IVergeScheduleAction =
+            serde_yaml::from_str("type: update_subscriptions").unwrap();
+        assert_eq!(action, IVergeScheduleAction::UpdateSubscriptions);
+    }
+}
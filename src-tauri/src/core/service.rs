@@ -797,6 +797,8 @@ pub(super) async fn start_with_existing_service(config_file: &PathBuf) -> Result
 
     let bin_ext = if cfg!(windows) { ".exe" } else { "" };
     let clash_bin = format!("{clash_core}{bin_ext}");
+    // 服务以 root/SYSTEM 权限运行，只能启动安装目录中随应用分发的内核，
+    // 托管内核位于用户可写的目录，不能交给服务运行
     let bin_path = current_exe()?.with_file_name(clash_bin);
     let bin_path = dirs::path_to_str(&bin_path)?;
 
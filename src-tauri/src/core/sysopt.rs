@@ -9,7 +9,7 @@ use crate::{
 use anyhow::Result;
 use once_cell::sync::OnceCell;
 use std::sync::Arc;
-#[cfg(not(target_os = "windows"))]
+#[cfg(target_os = "macos")]
 use sysproxy::{Autoproxy, Sysproxy};
 use tauri::async_runtime::Mutex as TokioMutex;
 use tauri_plugin_autostart::ManagerExt;
@@ -94,7 +94,26 @@ impl Sysopt {
             )
         };
 
-        #[cfg(not(target_os = "windows"))]
+        #[cfg(target_os = "linux")]
+        {
+            use crate::core::linux_proxy::{self, ProxySetting};
+
+            let setting = if !sys_enable {
+                ProxySetting::None
+            } else if pac_enable {
+                ProxySetting::Auto {
+                    url: format!("http://{proxy_host}:{pac_port}/commands/pac"),
+                }
+            } else {
+                ProxySetting::Manual {
+                    host: proxy_host.clone(),
+                    port,
+                    bypass: get_bypass(),
+                }
+            };
+            linux_proxy::apply(&setting)?;
+        }
+        #[cfg(target_os = "macos")]
         {
             let mut sys = Sysproxy {
                 enable: false,
@@ -195,7 +214,10 @@ impl Sysopt {
         }
         let _lock = self.reset_sysproxy.lock().await;
         //直接关闭所有代理
-        #[cfg(not(target_os = "windows"))]
+        #[cfg(target_os = "linux")]
+        crate::core::linux_proxy::reset()?;
+
+        #[cfg(target_os = "macos")]
         {
             let mut sysproxy: Sysproxy = Sysproxy::get_system_proxy()?;
             let mut autoproxy = match Autoproxy::get_auto_proxy() {
@@ -1,4 +1,7 @@
-use crate::{config::Config, feat, logging, logging_error, utils::logging::Type};
+use crate::{
+    config::Config, core::schedule, feat, logging, logging_error, module::health_check,
+    utils::logging::Type,
+};
 use anyhow::{Context, Result};
 use delay_timer::prelude::{DelayTimer, DelayTimerBuilder, TaskBuilder};
 use once_cell::sync::OnceCell;
@@ -7,6 +10,11 @@ use std::{collections::HashMap, sync::Arc};
 
 type TaskID = u64;
 
+/// 代理组健康检查任务的 key 前缀，其余任务的 key 为订阅 uid
+const HEALTH_CHECK_PREFIX: &str = "health_check::";
+/// 每分钟检查一次定时任务
+const SCHEDULE_TASK: &str = "schedule::tick";
+
 #[derive(Debug, Clone)]
 pub struct TimerTask {
     pub task_id: TaskID,
@@ -116,6 +124,13 @@ impl Timer {
             Vec::new()
         };
 
+        // 启动时立即补执行退出期间错过的定时任务
+        if let Some(task) = timer_map.get(SCHEDULE_TASK) {
+            if let Err(e) = self.delay_timer.write().advance_task(task.task_id) {
+                logging!(warn, Type::Timer, "Failed to advance schedule task: {}", e);
+            }
+        }
+
         // Advance tasks outside of locks to minimize lock contention
         if !profiles_to_update.is_empty() {
             logging!(
@@ -253,6 +268,26 @@ impl Timer {
             }
         }
 
+        if let Some(checks) = Config::verge().latest().group_health_checks.as_ref() {
+            for check in checks.iter().filter(|check| check.interval > 0) {
+                logging!(
+                    debug,
+                    Type::Timer,
+                    "Found group health check config: group={}, interval={}min",
+                    check.group,
+                    check.interval
+                );
+                new_map.insert(
+                    format!("{HEALTH_CHECK_PREFIX}{}", check.group),
+                    check.interval,
+                );
+            }
+        }
+
+        if schedule::has_enabled() {
+            new_map.insert(SCHEDULE_TASK.to_string(), 1);
+        }
+
         logging!(
             debug,
             Type::Timer,
@@ -365,7 +400,13 @@ impl Timer {
             .spawn_async_routine(move || {
                 let uid = uid.clone();
                 async move {
-                    Self::async_task(uid).await;
+                    if uid == SCHEDULE_TASK {
+                        return schedule::tick().await;
+                    }
+                    match uid.strip_prefix(HEALTH_CHECK_PREFIX) {
+                        Some(group) => Self::health_check_task(group).await,
+                        None => Self::async_task(uid).await,
+                    }
                 }
             })
             .context("failed to create timer task")?;
@@ -456,6 +497,34 @@ impl Timer {
         }
     }
 
+    /// Run the health check of a proxy group
+    async fn health_check_task(group: &str) {
+        let check = Config::verge()
+            .latest()
+            .group_health_checks
+            .as_ref()
+            .and_then(|checks| checks.iter().find(|check| check.group == group).cloned());
+        let Some(check) = check else {
+            return;
+        };
+
+        logging!(
+            info,
+            Type::Timer,
+            "Running health check for group: {}",
+            group
+        );
+        if let Err(e) = health_check::check_group(&check).await {
+            logging!(
+                warn,
+                Type::Timer,
+                "Health check failed for group {}: {}",
+                group,
+                e
+            );
+        }
+    }
+
     /// Async task with better error handling and logging
     async fn async_task(uid: String) {
         let task_start = std::time::Instant::now();
@@ -0,0 +1,545 @@
+//! Linux 透明代理：为 redir/tproxy 端口生成并应用 nftables（或 iptables）规则
+
+use crate::{
+    config::{Config, IClashTemp},
+    logging,
+    utils::{dirs, help, logging::Type},
+};
+use anyhow::{anyhow, bail, Result};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::{net::IpAddr, path::PathBuf};
+use tokio::{process::Command, sync::Mutex};
+
+const NFT_TABLE: &str = "koala_clash";
+const IPT_CHAIN: &str = "KOALA_CLASH";
+const IPT_OUTPUT_CHAIN: &str = "KOALA_CLASH_OUT";
+/// 内核出站默认 routing-mark
+pub const DEFAULT_ROUTING_MARK: u32 = 6666;
+/// tproxy 流量的 fwmark 与策略路由表
+const TPROXY_FWMARK: u32 = 0x1;
+const TPROXY_ROUTE_TABLE: u32 = 100;
+const STATE_FILE: &str = "transparent_rules.json";
+
+/// 局域网与保留地址，不包含 fake-ip 使用的 198.18.0.0/15
+const RESERVED_V4: [&str; 10] = [
+    "0.0.0.0/8",
+    "10.0.0.0/8",
+    "100.64.0.0/10",
+    "127.0.0.0/8",
+    "169.254.0.0/16",
+    "172.16.0.0/12",
+    "192.0.0.0/24",
+    "192.168.0.0/16",
+    "224.0.0.0/4",
+    "240.0.0.0/4",
+];
+const RESERVED_V6: [&str; 4] = ["::1/128", "fc00::/7", "fe80::/10", "ff00::/8"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FirewallBackend {
+    Nftables,
+    Iptables,
+}
+
+/// 生成规则所需的参数，tproxy 与 redir 同时开启时只使用 tproxy
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RulesetOptions {
+    pub redir_port: Option<u16>,
+    pub tproxy_port: Option<u16>,
+    pub routing_mark: u32,
+    pub bypass_uid: Option<u32>,
+    pub bypass_cidrs: Vec<String>,
+}
+
+impl RulesetOptions {
+    /// 保留地址加上用户网段，按 IPv4/IPv6 分开
+    fn bypass_sets(&self) -> Result<(Vec<String>, Vec<String>)> {
+        let mut v4 = RESERVED_V4.map(String::from).to_vec();
+        let mut v6 = RESERVED_V6.map(String::from).to_vec();
+        for cidr in &self.bypass_cidrs {
+            let cidr = cidr.trim();
+            let (addr, prefix) = cidr.split_once('/').unwrap_or((cidr, ""));
+            let addr: IpAddr = addr
+                .parse()
+                .map_err(|_| anyhow!("invalid bypass cidr: {cidr}"))?;
+            let max = if addr.is_ipv4() { 32 } else { 128 };
+            if !prefix.is_empty() && !prefix.parse::<u8>().is_ok_and(|len| len <= max) {
+                bail!("invalid bypass cidr: {cidr}");
+            }
+            if addr.is_ipv4() {
+                v4.push(cidr.to_string());
+            } else {
+                v6.push(cidr.to_string());
+            }
+        }
+        Ok((v4, v6))
+    }
+}
+
+/// 生成 nftables 规则集，可直接交给 `nft -f`
+pub fn nft_ruleset(opts: &RulesetOptions) -> Result<String> {
+    let (v4, v6) = opts.bypass_sets()?;
+    let mark = opts.routing_mark;
+    let skuid = opts
+        .bypass_uid
+        .map(|uid| format!("\t\tmeta skuid {uid} accept\n"))
+        .unwrap_or_default();
+
+    let mut out = format!("table inet {NFT_TABLE} {{\n");
+    out += &format!(
+        "\tset bypass_v4 {{\n\t\ttype ipv4_addr\n\t\tflags interval\n\t\telements = {{ {} }}\n\t}}\n",
+        v4.join(", ")
+    );
+    out += &format!(
+        "\tset bypass_v6 {{\n\t\ttype ipv6_addr\n\t\tflags interval\n\t\telements = {{ {} }}\n\t}}\n",
+        v6.join(", ")
+    );
+    out += &format!(
+        "\tchain bypass {{\n\t\tmeta mark {mark} accept\n\t\tfib daddr type local accept\n\t\tip daddr @bypass_v4 accept\n\t\tip6 daddr @bypass_v6 accept\n\t}}\n"
+    );
+
+    if let Some(port) = opts.tproxy_port {
+        let fwmark = TPROXY_FWMARK;
+        out += &format!(
+            "\tchain tproxy_prerouting {{\n\t\ttype filter hook prerouting priority mangle; policy accept;\n\t\tjump bypass\n\t\tmeta nfproto ipv4 meta l4proto {{ tcp, udp }} meta mark set {fwmark} tproxy ip to :{port} accept\n\t\tmeta nfproto ipv6 meta l4proto {{ tcp, udp }} meta mark set {fwmark} tproxy ip6 to :{port} accept\n\t}}\n"
+        );
+        out += &format!(
+            "\tchain tproxy_output {{\n\t\ttype route hook output priority mangle; policy accept;\n{skuid}\t\tjump bypass\n\t\tmeta l4proto {{ tcp, udp }} meta mark set {fwmark}\n\t}}\n"
+        );
+    } else if let Some(port) = opts.redir_port {
+        out += &format!(
+            "\tchain redir_prerouting {{\n\t\ttype nat hook prerouting priority dstnat; policy accept;\n\t\tjump bypass\n\t\tmeta l4proto tcp redirect to :{port}\n\t}}\n"
+        );
+        out += &format!(
+            "\tchain redir_output {{\n\t\ttype nat hook output priority dstnat; policy accept;\n{skuid}\t\tjump bypass\n\t\tmeta l4proto tcp redirect to :{port}\n\t}}\n"
+        );
+    }
+    out += "}\n";
+    Ok(out)
+}
+
+/// 生成 iptables 规则命令（仅 IPv4）
+pub fn iptables_rules(opts: &RulesetOptions) -> Result<Vec<String>> {
+    let (v4, _) = opts.bypass_sets()?;
+    let mark = opts.routing_mark;
+    let mut rules = Vec::new();
+    let returns = |table: &str, chain: &str, rules: &mut Vec<String>| {
+        rules.push(format!("iptables -t {table} -N {chain}"));
+        rules.push(format!(
+            "iptables -t {table} -A {chain} -m mark --mark {mark} -j RETURN"
+        ));
+        for cidr in &v4 {
+            rules.push(format!(
+                "iptables -t {table} -A {chain} -d {cidr} -j RETURN"
+            ));
+        }
+    };
+    let owner = opts
+        .bypass_uid
+        .map(|uid| format!(" -m owner ! --uid-owner {uid}"))
+        .unwrap_or_default();
+
+    if let Some(port) = opts.tproxy_port {
+        let fwmark = TPROXY_FWMARK;
+        returns("mangle", IPT_CHAIN, &mut rules);
+        rules.push(local_return("mangle"));
+        for proto in ["tcp", "udp"] {
+            rules.push(format!(
+                "iptables -t mangle -A {IPT_CHAIN} -p {proto} -j TPROXY --on-port {port} --tproxy-mark {fwmark}"
+            ));
+        }
+        rules.push(format!("iptables -t mangle -A PREROUTING -j {IPT_CHAIN}"));
+        returns("mangle", IPT_OUTPUT_CHAIN, &mut rules);
+        for proto in ["tcp", "udp"] {
+            rules.push(format!(
+                "iptables -t mangle -A {IPT_OUTPUT_CHAIN} -p {proto} -j MARK --set-mark {fwmark}"
+            ));
+        }
+        rules.push(format!(
+            "iptables -t mangle -A OUTPUT{owner} -j {IPT_OUTPUT_CHAIN}"
+        ));
+    } else if let Some(port) = opts.redir_port {
+        returns("nat", IPT_CHAIN, &mut rules);
+        rules.push(local_return("nat"));
+        rules.push(format!(
+            "iptables -t nat -A {IPT_CHAIN} -p tcp -j REDIRECT --to-ports {port}"
+        ));
+        rules.push(format!(
+            "iptables -t nat -A PREROUTING -p tcp -j {IPT_CHAIN}"
+        ));
+        rules.push(format!(
+            "iptables -t nat -A OUTPUT -p tcp{owner} -j {IPT_CHAIN}"
+        ));
+    }
+    Ok(rules)
+}
+
+/// 发往本机地址的流量不经过代理，避免访问本机服务时形成回环
+fn local_return(table: &str) -> String {
+    format!("iptables -t {table} -A {IPT_CHAIN} -m addrtype --dst-type LOCAL -j RETURN")
+}
+
+fn policy_route_rules() -> Vec<String> {
+    let (fwmark, table) = (TPROXY_FWMARK, TPROXY_ROUTE_TABLE);
+    vec![
+        format!("ip rule add fwmark {fwmark} table {table}"),
+        format!("ip route add local default dev lo table {table}"),
+        // 未启用 IPv6 的系统上忽略失败
+        format!("ip -6 rule add fwmark {fwmark} table {table} || true"),
+        format!("ip -6 route add local default dev lo table {table} || true"),
+    ]
+}
+
+/// 回滚脚本，每条命令失败都忽略，可重复执行
+pub fn rollback_script(backend: FirewallBackend, opts: &RulesetOptions) -> String {
+    let mut commands = match backend {
+        FirewallBackend::Nftables => vec![format!("nft delete table inet {NFT_TABLE}")],
+        FirewallBackend::Iptables => iptables_rules(opts)
+            .unwrap_or_default()
+            .into_iter()
+            .rev()
+            .flat_map(|rule| {
+                // 倒序处理：先删除跳转规则，再清空并删除链
+                if let Some((head, chain)) = rule.split_once(" -N ") {
+                    return vec![format!("{head} -F {chain}"), format!("{head} -X {chain}")];
+                }
+                match rule.split_once(" -A ") {
+                    Some((head, tail))
+                        if tail.starts_with("PREROUTING ") || tail.starts_with("OUTPUT ") =>
+                    {
+                        vec![format!("{head} -D {tail}")]
+                    }
+                    _ => vec![],
+                }
+            })
+            .collect(),
+    };
+    if opts.tproxy_port.is_some() {
+        let (fwmark, table) = (TPROXY_FWMARK, TPROXY_ROUTE_TABLE);
+        commands.push(format!("ip rule del fwmark {fwmark} table {table}"));
+        commands.push(format!("ip route flush table {table}"));
+        commands.push(format!("ip -6 rule del fwmark {fwmark} table {table}"));
+        commands.push(format!("ip -6 route flush table {table}"));
+    }
+    let mut script = String::from("#!/bin/sh\n");
+    for command in commands {
+        script += &format!("{command} 2>/dev/null || true\n");
+    }
+    script
+}
+
+/// 应用脚本：先回滚旧规则再写入，保证幂等
+pub fn apply_script(backend: FirewallBackend, opts: &RulesetOptions) -> Result<String> {
+    let mut script = rollback_script(backend, opts);
+    script += "set -e\n";
+    match backend {
+        FirewallBackend::Nftables => {
+            script += &format!("nft -f - <<'EOF'\n{}EOF\n", nft_ruleset(opts)?);
+        }
+        FirewallBackend::Iptables => {
+            for rule in iptables_rules(opts)? {
+                script += &format!("{rule}\n");
+            }
+        }
+    }
+    if opts.tproxy_port.is_some() {
+        for rule in policy_route_rules() {
+            script += &format!("{rule}\n");
+        }
+    }
+    Ok(script)
+}
+
+/// 已应用的规则，持久化以便崩溃后回滚
+///
+/// 只保存参数，回滚脚本在使用时重新生成，状态文件被篡改也无法注入命令
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct AppliedRules {
+    backend: FirewallBackend,
+    options: RulesetOptions,
+}
+
+impl AppliedRules {
+    fn rollback(&self) -> String {
+        rollback_script(self.backend, &self.options)
+    }
+}
+
+pub struct TransparentProxy {
+    applied: Mutex<Option<AppliedRules>>,
+}
+
+impl TransparentProxy {
+    pub fn global() -> &'static TransparentProxy {
+        static INSTANCE: OnceCell<TransparentProxy> = OnceCell::new();
+        INSTANCE.get_or_init(|| TransparentProxy {
+            applied: Mutex::new(None),
+        })
+    }
+
+    /// 启动时回滚上次残留的规则（崩溃或重启后），内核启动后再由 `sync` 应用
+    pub async fn init(&self) -> Result<()> {
+        let leftover = std::fs::read_to_string(state_path()?)
+            .ok()
+            .and_then(|content| serde_json::from_str::<AppliedRules>(&content).ok());
+        if let Some(leftover) = leftover {
+            logging!(
+                info,
+                Type::System,
+                true,
+                "Rolling back leftover transparent proxy rules"
+            );
+            run_privileged(&leftover.rollback()).await?;
+            std::fs::remove_file(state_path()?)?;
+        }
+        Ok(())
+    }
+
+    /// 按当前配置应用或移除规则，配置未变化时不重复执行
+    pub async fn sync(&self) -> Result<()> {
+        let Some(opts) = desired_options() else {
+            return self.clear().await;
+        };
+        let backend = detect_backend().await?;
+        let script = apply_script(backend, &opts)?;
+        let rules = AppliedRules {
+            backend,
+            options: opts,
+        };
+
+        let mut applied = self.applied.lock().await;
+        if applied.as_ref() == Some(&rules) {
+            return Ok(());
+        }
+        if let Some(rules) = applied.take() {
+            run_privileged(&rules.rollback()).await?;
+        }
+        logging!(
+            info,
+            Type::System,
+            true,
+            "Applying transparent proxy rules via {:?}",
+            backend
+        );
+        // 先持久化，执行中途崩溃也能在下次启动回滚
+        std::fs::write(state_path()?, serde_json::to_string(&rules)?)?;
+        if let Err(err) = run_privileged(&script).await {
+            try_rollback(&rules.rollback()).await;
+            std::fs::remove_file(state_path()?)?;
+            return Err(err);
+        }
+        *applied = Some(rules);
+        Ok(())
+    }
+
+    /// 移除已应用的规则
+    pub async fn clear(&self) -> Result<()> {
+        let mut applied = self.applied.lock().await;
+        let Some(rules) = applied.take() else {
+            return Ok(());
+        };
+        logging!(info, Type::System, true, "Removing transparent proxy rules");
+        run_privileged(&rules.rollback()).await?;
+        let path = state_path()?;
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+async fn try_rollback(rollback: &str) {
+    if let Err(err) = run_privileged(rollback).await {
+        logging!(
+            warn,
+            Type::System,
+            true,
+            "Failed to roll back transparent proxy rules: {}",
+            err
+        );
+    }
+}
+
+fn state_path() -> Result<PathBuf> {
+    Ok(dirs::app_home_dir()?.join(STATE_FILE))
+}
+
+/// 内核出站的 routing-mark，未启用规则时返回 None
+pub fn core_routing_mark() -> Option<u32> {
+    let verge = Config::verge();
+    let verge = verge.latest();
+    verge.enable_transparent_rules.unwrap_or(false).then(|| {
+        verge
+            .transparent_routing_mark
+            .unwrap_or(DEFAULT_ROUTING_MARK)
+    })
+}
+
+fn desired_options() -> Option<RulesetOptions> {
+    let (enabled, redir, tproxy, routing_mark, bypass_uid, bypass_cidrs) = {
+        let verge = Config::verge();
+        let verge = verge.latest();
+        (
+            verge.enable_transparent_rules.unwrap_or(false),
+            verge.verge_redir_enabled.unwrap_or(false),
+            verge.verge_tproxy_enabled.unwrap_or(false),
+            verge
+                .transparent_routing_mark
+                .unwrap_or(DEFAULT_ROUTING_MARK),
+            verge.transparent_bypass_uid,
+            verge.transparent_bypass_cidrs.clone().unwrap_or_default(),
+        )
+    };
+    if !enabled || !(redir || tproxy) {
+        return None;
+    }
+    let clash = Config::clash().latest().0.clone();
+    Some(RulesetOptions {
+        redir_port: redir.then(|| IClashTemp::guard_redir_port(&clash)),
+        tproxy_port: tproxy.then(|| IClashTemp::guard_tproxy_port(&clash)),
+        routing_mark,
+        bypass_uid,
+        bypass_cidrs,
+    })
+}
+
+async fn command_exists(name: &str) -> bool {
+    ["/usr/sbin", "/sbin", "/usr/bin", "/bin"]
+        .iter()
+        .any(|dir| PathBuf::from(dir).join(name).exists())
+        || Command::new("which")
+            .arg(name)
+            .output()
+            .await
+            .is_ok_and(|output| output.status.success())
+}
+
+async fn detect_backend() -> Result<FirewallBackend> {
+    if command_exists("nft").await {
+        Ok(FirewallBackend::Nftables)
+    } else if command_exists("iptables").await {
+        Ok(FirewallBackend::Iptables)
+    } else {
+        bail!("neither nft nor iptables is available")
+    }
+}
+
+/// 服务 IPC 目前只支持启停内核，因此通过提权脚本执行
+async fn run_privileged(script: &str) -> Result<()> {
+    help::run_elevated_script("transparent_rules.sh", script)
+        .await
+        .map_err(|err| anyhow!("failed to apply firewall rules: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> RulesetOptions {
+        RulesetOptions {
+            redir_port: Some(7895),
+            tproxy_port: None,
+            routing_mark: DEFAULT_ROUTING_MARK,
+            bypass_uid: Some(977),
+            bypass_cidrs: vec!["203.0.113.0/24".into(), "2001:db8::/32".into()],
+        }
+    }
+
+    #[test]
+    fn test_nft_ruleset() {
+        let redir = nft_ruleset(&options()).unwrap();
+        assert!(redir.contains("203.0.113.0/24"));
+        assert!(redir.contains("2001:db8::/32"));
+        assert!(!redir.contains("198.18.0.0"));
+        assert!(redir.contains("meta mark 6666 accept"));
+        assert!(redir.contains("\t\tfib daddr type local accept\n"));
+        assert!(redir.contains("meta skuid 977 accept"));
+        assert!(redir.contains("meta l4proto tcp redirect to :7895"));
+        assert!(!redir.contains("tproxy"));
+
+        let tproxy = nft_ruleset(&RulesetOptions {
+            tproxy_port: Some(7896),
+            ..options()
+        })
+        .unwrap();
+        assert!(tproxy.contains("tproxy ip to :7896"));
+        assert!(tproxy.contains("tproxy ip6 to :7896"));
+        assert!(!tproxy.contains("redirect"));
+
+        let invalid = RulesetOptions {
+            bypass_cidrs: vec!["10.0.0.0/33".into()],
+            ..options()
+        };
+        assert!(nft_ruleset(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_scripts() {
+        let opts = RulesetOptions {
+            tproxy_port: Some(7896),
+            ..options()
+        };
+        let script = apply_script(FirewallBackend::Nftables, &opts).unwrap();
+        let rollback = rollback_script(FirewallBackend::Nftables, &opts);
+        // 应用前先回滚，重复执行结果一致
+        assert!(script.starts_with(&rollback));
+        assert!(script.contains("ip route add local default dev lo table 100\n"));
+        assert!(script.contains("ip -6 route add local default dev lo table 100 || true\n"));
+
+        let rules = iptables_rules(&opts).unwrap();
+        let local = rules
+            .iter()
+            .position(|rule| {
+                rule == "iptables -t mangle -A KOALA_CLASH -m addrtype --dst-type LOCAL -j RETURN"
+            })
+            .unwrap();
+        let tproxy = rules
+            .iter()
+            .position(|rule| rule.contains("TPROXY"))
+            .unwrap();
+        assert!(local < tproxy);
+        let redir = iptables_rules(&options()).unwrap();
+        assert!(redir.contains(
+            &"iptables -t nat -A KOALA_CLASH -m addrtype --dst-type LOCAL -j RETURN".into()
+        ));
+        assert!(rules.contains(
+            &"iptables -t mangle -A OUTPUT -m owner ! --uid-owner 977 -j KOALA_CLASH_OUT".into()
+        ));
+        let rollback = rollback_script(FirewallBackend::Iptables, &opts);
+        assert!(rollback.contains(
+            "iptables -t mangle -D OUTPUT -m owner ! --uid-owner 977 -j KOALA_CLASH_OUT 2>/dev/null || true\n"
+        ));
+        assert!(rollback.contains("iptables -t mangle -X KOALA_CLASH 2>/dev/null || true\n"));
+        let delete = rollback.find("-D PREROUTING").unwrap();
+        let flush = rollback.find("-F KOALA_CLASH ").unwrap();
+        assert!(delete < flush);
+        assert!(!rollback.contains(" -A "));
+    }
+
+    #[test]
+    fn test_applied_rules_state() {
+        let rules = AppliedRules {
+            backend: FirewallBackend::Iptables,
+            options: RulesetOptions {
+                tproxy_port: Some(7896),
+                ..options()
+            },
+        };
+        let state = serde_json::to_string(&rules).unwrap();
+        assert!(!state.contains("iptables -t"));
+
+        let loaded = serde_json::from_str::<AppliedRules>(&state).unwrap();
+        assert_eq!(loaded, rules);
+        assert_eq!(
+            loaded.rollback(),
+            rollback_script(FirewallBackend::Iptables, &rules.options)
+        );
+
+        // 非法参数不会生成任何 iptables 命令
+        let tampered = state.replace("203.0.113.0/24", "1.1.1.1; reboot");
+        let tampered = serde_json::from_str::<AppliedRules>(&tampered).unwrap();
+        assert!(!tampered.rollback().contains("reboot"));
+    }
+}
@@ -6,7 +6,7 @@ use crate::{
     config::Config,
     feat, logging,
     module::{lightweight::is_in_lightweight_mode, mihomo::Rate},
-    utils::{dirs::find_target_icons, i18n::t, resolve::VERSION},
+    utils::{dirs::find_target_icons, headless, i18n::t, resolve::VERSION},
     Type,
 };
 
@@ -197,6 +197,9 @@ impl Tray {
             log::debug!(target: "app", "Application is exiting, skip tray click behavior update");
             return Ok(());
         }
+        if headless::is_headless() {
+            return Ok(());
+        }
         let app_handle = handle::Handle::global().app_handle().unwrap();
         let tray_event = { Config::verge().latest().tray_event.clone() };
         let tray_event: String = tray_event.unwrap_or("main_window".into());
@@ -214,6 +217,9 @@ impl Tray {
             log::debug!(target: "app", "Application is exiting, skip tray menu update");
             return Ok(());
         }
+        if headless::is_headless() {
+            return Ok(());
+        }
         // 调整最小更新间隔，确保状态及时刷新
         const MIN_UPDATE_INTERVAL: Duration = Duration::from_millis(100);
 
@@ -307,6 +313,9 @@ impl Tray {
             log::debug!(target: "app", "Application is exiting, skip tray icon update");
             return Ok(());
         }
+        if headless::is_headless() {
+            return Ok(());
+        }
         let app_handle = match handle::Handle::global().app_handle() {
             Some(handle) => handle,
             None => {
@@ -348,6 +357,9 @@ impl Tray {
             log::debug!(target: "app", "Application is exiting, skip tray icon update");
             return Ok(());
         }
+        if headless::is_headless() {
+            return Ok(());
+        }
         let app_handle = match handle::Handle::global().app_handle() {
             Some(handle) => handle,
             None => {
@@ -385,6 +397,9 @@ impl Tray {
             log::debug!(target: "app", "Application is exiting, skip tray display update");
             return Ok(());
         }
+        if headless::is_headless() {
+            return Ok(());
+        }
         let app_handle = handle::Handle::global().app_handle().unwrap();
         let _tray = app_handle.tray_by_id("main").unwrap();
 
@@ -400,6 +415,9 @@ impl Tray {
             log::debug!(target: "app", "Application is exiting, skip tray tooltip update");
             return Ok(());
         }
+        if headless::is_headless() {
+            return Ok(());
+        }
         let app_handle = match handle::Handle::global().app_handle() {
             Some(handle) => handle,
             None => {
@@ -478,6 +496,9 @@ impl Tray {
             log::debug!(target: "app", "Application is exiting, skip tray creation");
             return Ok(());
         }
+        if headless::is_headless() {
+            return Ok(());
+        }
         log::info!(target: "app", "Creating system tray from AppHandle");
 
         // 获取图标
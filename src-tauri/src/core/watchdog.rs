@@ -0,0 +1,359 @@
+use crate::{
+    config::{Config, Fallback, IRuntime},
+    core::{handle, sysopt, CoreManager, RunningMode},
+    logging, logging_error,
+    process::AsyncHandler,
+    state::degraded::DegradedState,
+    utils::logging::Type,
+};
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tokio::time::sleep;
+
+/// 健康检查间隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+/// 连续失败多少次才认为内核已崩溃，避免和正常的重启流程冲突
+const FAILURE_THRESHOLD: u32 = 2;
+/// 连续健康多少次后才保存为最近可用的运行时配置
+const STABLE_CHECKS: u32 = 3;
+/// 在 `CRASH_LOOP_WINDOW` 内崩溃 `CRASH_LOOP_COUNT` 次视为崩溃循环
+const CRASH_LOOP_COUNT: usize = 3;
+const CRASH_LOOP_WINDOW: Duration = Duration::from_secs(5 * 60);
+/// 重启退避时间
+const BACKOFF_BASE: Duration = Duration::from_secs(1);
+const BACKOFF_MAX: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Default)]
+struct WatchdogState {
+    /// 连续失败的健康检查次数
+    failures: u32,
+    /// 连续成功的健康检查次数
+    healthy_checks: u32,
+    /// 最近的崩溃时间
+    crashes: VecDeque<Instant>,
+    /// 当前的重启次数，用于计算退避时间
+    restart_attempt: u32,
+    /// 是否因为内核不可用而临时关闭了系统代理
+    sysproxy_suspended: bool,
+}
+
+impl WatchdogState {
+    /// 丢弃崩溃窗口之外的崩溃记录
+    fn prune_crashes(&mut self, now: Instant) {
+        self.crashes
+            .retain(|time| now.duration_since(*time) < CRASH_LOOP_WINDOW);
+    }
+
+    /// 记录一次崩溃，返回是否进入崩溃循环以及重启前的等待时间
+    fn record_crash(&mut self, now: Instant) -> (bool, Duration) {
+        self.failures = 0;
+        self.crashes.push_back(now);
+        self.prune_crashes(now);
+
+        let delay = backoff(self.restart_attempt);
+        self.restart_attempt = self.restart_attempt.saturating_add(1);
+        (self.crashes.len() >= CRASH_LOOP_COUNT, delay)
+    }
+}
+
+/// 内核进程看门狗
+///
+/// 定期检查内核进程和 `/version` 接口，崩溃后按指数退避重启；
+/// 出现崩溃循环时回退到最近可用的配置，仍然失败则临时关闭系统代理
+pub struct CoreWatchdog {
+    state: Arc<Mutex<WatchdogState>>,
+    started: AtomicBool,
+}
+
+impl CoreWatchdog {
+    pub fn global() -> &'static CoreWatchdog {
+        static WATCHDOG: OnceCell<CoreWatchdog> = OnceCell::new();
+        WATCHDOG.get_or_init(|| CoreWatchdog {
+            state: Arc::new(Mutex::new(WatchdogState::default())),
+            started: AtomicBool::new(false),
+        })
+    }
+
+    /// 启动看门狗，重复调用无效
+    pub fn start(&'static self) {
+        if self.started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        logging!(info, Type::Core, true, "Core watchdog started");
+        AsyncHandler::spawn(move || async move {
+            loop {
+                sleep(CHECK_INTERVAL).await;
+
+                if handle::Handle::global().is_exiting() {
+                    logging!(
+                        info,
+                        Type::Core,
+                        true,
+                        "App is exiting, core watchdog stopped"
+                    );
+                    break;
+                }
+
+                let enabled = Config::verge()
+                    .latest()
+                    .enable_core_watchdog
+                    .unwrap_or(true);
+                if enabled {
+                    self.tick().await;
+                }
+            }
+        });
+    }
+
+    async fn tick(&self) {
+        if CoreManager::global().get_running_mode().await == RunningMode::NotRunning {
+            // 内核被主动停止，不需要处理
+            let mut state = self.state.lock();
+            state.failures = 0;
+            state.healthy_checks = 0;
+            return;
+        }
+
+        match CoreManager::global().check_core_health().await {
+            Ok(_) => self.on_healthy().await,
+            Err(err) => {
+                let failures = {
+                    let mut state = self.state.lock();
+                    state.failures += 1;
+                    state.healthy_checks = 0;
+                    state.failures
+                };
+                logging!(
+                    warn,
+                    Type::Core,
+                    true,
+                    "Core health check failed ({}/{}): {}",
+                    failures,
+                    FAILURE_THRESHOLD,
+                    err
+                );
+                if failures >= FAILURE_THRESHOLD {
+                    self.on_crash().await;
+                }
+            }
+        }
+    }
+
+    async fn on_healthy(&self) {
+        let (stable, resume_sysproxy) = {
+            let mut state = self.state.lock();
+            state.failures = 0;
+            state.healthy_checks = state.healthy_checks.saturating_add(1);
+
+            // 稳定运行超过崩溃窗口后重置退避
+            state.prune_crashes(Instant::now());
+            if state.crashes.is_empty() {
+                state.restart_attempt = 0;
+            }
+
+            (
+                state.healthy_checks == STABLE_CHECKS,
+                std::mem::take(&mut state.sysproxy_suspended),
+            )
+        };
+
+        if stable {
+            CoreManager::global().save_last_good_config();
+        }
+
+        if resume_sysproxy {
+            logging!(
+                info,
+                Type::Core,
+                true,
+                "Core recovered, restoring system proxy"
+            );
+            logging_error!(
+                Type::Core,
+                true,
+                sysopt::Sysopt::global().update_sysproxy().await
+            );
+            handle::Handle::notice_message("core_watchdog::recovered", "");
+        }
+    }
+
+    async fn on_crash(&self) {
+        let (in_crash_loop, delay) = self.state.lock().record_crash(Instant::now());
+
+        if in_crash_loop {
+            self.recover_from_crash_loop().await;
+            return;
+        }
+
+        logging!(
+            warn,
+            Type::Core,
+            true,
+            "Core crashed, restarting in {:?}",
+            delay
+        );
+        // 内核重启成功后 start_core 会重新应用规则与 DNS 接管
+        Self::release_system_redirects().await;
+        handle::Handle::notice_message(
+            "core_watchdog::restarting",
+            format!("{}s", delay.as_secs()),
+        );
+        sleep(delay).await;
+
+        if handle::Handle::global().is_exiting() {
+            return;
+        }
+        logging_error!(Type::Core, true, CoreManager::global().restart_core().await);
+    }
+
+    async fn recover_from_crash_loop(&self) {
+        self.state.lock().crashes.clear();
+
+        logging!(
+            error,
+            Type::Core,
+            true,
+            "Core crash loop detected ({} crashes in {:?})",
+            CRASH_LOOP_COUNT,
+            CRASH_LOOP_WINDOW
+        );
+
+        if let Fallback::LastGood(snapshot) = Fallback::load() {
+            logging!(
+                warn,
+                Type::Core,
+                true,
+                "Falling back to the last known good runtime config"
+            );
+            handle::Handle::notice_message("core_watchdog::crash_loop", "");
+            DegradedState::enter(
+                "core crash loop".to_string(),
+                snapshot.uid.clone(),
+                Some(snapshot.updated),
+            );
+
+            **Config::runtime().draft() = IRuntime {
+                config: Some(snapshot.config),
+                ..IRuntime::new()
+            };
+            Config::runtime().apply();
+
+            if CoreManager::global().restart_core().await.is_ok() {
+                sleep(CHECK_INTERVAL).await;
+                if CoreManager::global().check_core_health().await.is_ok() {
+                    logging!(
+                        info,
+                        Type::Core,
+                        true,
+                        "Core recovered with the last known good runtime config"
+                    );
+                    return;
+                }
+            }
+        }
+
+        Self::release_system_redirects().await;
+        self.suspend_sysproxy().await;
+    }
+
+    /// 内核不可用时移除透明代理规则并恢复系统 DNS，避免流量与解析指向已关闭的端口
+    async fn release_system_redirects() {
+        #[cfg(target_os = "linux")]
+        {
+            logging_error!(
+                Type::System,
+                true,
+                crate::core::tproxy::TransparentProxy::global()
+                    .clear()
+                    .await
+            );
+            logging_error!(Type::System, true, crate::utils::linux_dns::restore().await);
+        }
+    }
+
+    /// 内核无法恢复时临时关闭系统代理，避免系统代理指向失效的端口
+    async fn suspend_sysproxy(&self) {
+        let enabled = Config::verge()
+            .latest()
+            .enable_system_proxy
+            .unwrap_or(false);
+        if !enabled || self.state.lock().sysproxy_suspended {
+            return;
+        }
+
+        logging!(
+            error,
+            Type::Core,
+            true,
+            "Core is unavailable, temporarily disabling system proxy"
+        );
+        match sysopt::Sysopt::global().reset_sysproxy().await {
+            Ok(_) => {
+                self.state.lock().sysproxy_suspended = true;
+                handle::Handle::notice_message("core_watchdog::sysproxy_disabled", "");
+            }
+            Err(err) => {
+                logging!(
+                    error,
+                    Type::Core,
+                    true,
+                    "Failed to disable system proxy: {}",
+                    err
+                );
+            }
+        }
+    }
+}
+
+/// 第 `attempt` 次重启前的等待时间
+fn backoff(attempt: u32) -> Duration {
+    BACKOFF_BASE
+        .checked_mul(1u32.checked_shl(attempt).unwrap_or(u32::MAX))
+        .map_or(BACKOFF_MAX, |delay| delay.min(BACKOFF_MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(0), Duration::from_secs(1));
+        assert_eq!(backoff(1), Duration::from_secs(2));
+        assert_eq!(backoff(5), Duration::from_secs(32));
+        assert_eq!(backoff(6), BACKOFF_MAX);
+        assert_eq!(backoff(31), BACKOFF_MAX);
+        assert_eq!(backoff(u32::MAX), BACKOFF_MAX);
+    }
+
+    #[test]
+    fn test_crash_loop_window() {
+        let start = Instant::now();
+        let mut state = WatchdogState::default();
+
+        assert_eq!(state.record_crash(start), (false, Duration::from_secs(1)));
+        assert_eq!(
+            state.record_crash(start + Duration::from_secs(10)),
+            (false, Duration::from_secs(2))
+        );
+        // 第一次崩溃已经移出窗口，不算崩溃循环
+        let later = start + CRASH_LOOP_WINDOW + Duration::from_secs(1);
+        assert_eq!(state.record_crash(later), (false, Duration::from_secs(4)));
+        assert_eq!(state.crashes.len(), 2);
+
+        let (in_crash_loop, _) = state.record_crash(later + Duration::from_secs(1));
+        assert!(in_crash_loop);
+
+        state.prune_crashes(later + CRASH_LOOP_WINDOW * 2);
+        assert!(state.crashes.is_empty());
+    }
+}
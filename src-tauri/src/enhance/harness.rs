@@ -0,0 +1,378 @@
+//! Script test harness
+//!
+//! 在指定的配置上执行脚本，返回输出、日志、耗时和差异，
+//! 并执行脚本中 `export const tests = [...]` 声明的测试用例
+
+use super::{
+    script::{create_context, escape_js_string_for_single_quote, strip_exports},
+    use_lowercase, use_script,
+};
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use serde_yaml::Mapping;
+use std::time::Instant;
+
+/// 配置差异的类型
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// 单个字段的差异
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffEntry {
+    pub path: String,
+    pub kind: DiffKind,
+    pub before: Option<JsonValue>,
+    pub after: Option<JsonValue>,
+}
+
+/// 单个测试用例的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptCaseResult {
+    pub name: String,
+    pub passed: bool,
+    pub failures: Vec<String>,
+}
+
+/// 脚本执行报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptReport {
+    pub output: Option<Mapping>,
+    pub logs: Vec<(String, String)>,
+    pub elapsed_ms: u128,
+    pub diff: Vec<DiffEntry>,
+    pub tests: Vec<ScriptCaseResult>,
+}
+
+impl ScriptReport {
+    pub fn passed(&self) -> bool {
+        self.tests.iter().all(|case| case.passed)
+    }
+
+    /// 类似 `cargo test` 的文字摘要
+    pub fn summary(&self) -> String {
+        let mut lines = vec![format!("running {} tests", self.tests.len())];
+        for case in &self.tests {
+            let status = if case.passed { "ok" } else { "FAILED" };
+            lines.push(format!("test {} ... {status}", case.name));
+            for failure in &case.failures {
+                lines.push(format!("    {failure}"));
+            }
+        }
+        let passed = self.tests.iter().filter(|case| case.passed).count();
+        let failed = self.tests.len() - passed;
+        let status = if failed == 0 { "ok" } else { "FAILED" };
+        lines.push(format!(
+            "test result: {status}. {passed} passed; {failed} failed; finished in {}ms",
+            self.elapsed_ms
+        ));
+        lines.join("\n")
+    }
+}
+
+/// 在 fixture 配置上执行脚本并运行其中声明的测试用例
+pub fn run_script(script: String, fixture: Mapping, name: String) -> Result<ScriptReport> {
+    let before = serde_json::to_value(use_lowercase(fixture.clone()))?;
+
+    let start = Instant::now();
+    let result = use_script(script.clone(), fixture, name.clone());
+    let elapsed_ms = start.elapsed().as_millis();
+
+    let (output, mut logs) = match result {
+        Ok((output, logs)) => (Some(output), logs),
+        Err(err) => (None, vec![("exception".into(), err.to_string())]),
+    };
+
+    let diff = match &output {
+        Some(output) => diff_value("", &before, &serde_json::to_value(output)?),
+        None => vec![],
+    };
+
+    let tests = match run_cases(&script, &name) {
+        Ok(tests) => tests,
+        Err(err) => {
+            logs.push(("exception".into(), err.to_string()));
+            vec![]
+        }
+    };
+
+    Ok(ScriptReport {
+        output,
+        logs,
+        elapsed_ms,
+        diff,
+        tests,
+    })
+}
+
+/// 执行脚本声明的 `tests`，每个用例形如
+/// `{ name, config, profileName, expect: { "dns.enable": true }, assert(config) {} }`
+fn run_cases(script: &str, name: &str) -> Result<Vec<ScriptCaseResult>> {
+    use boa_engine::Source;
+    let (mut context, _) = create_context();
+    let script = strip_exports(script);
+    let safe_name = escape_js_string_for_single_quote(name);
+
+    let code = format!(
+        r#"try{{
+        {script};
+        JSON.stringify((typeof tests === 'undefined' ? [] : tests).map((t, i) => {{
+          const res = {{ name: t.name || `case ${{i + 1}}`, expect: t.expect || {{}}, output: null, error: null }};
+          try {{
+            const input = JSON.parse(JSON.stringify(t.config || {{}}));
+            res.output = main(input, t.profileName || '{safe_name}') || null;
+            if (typeof t.assert === 'function' && t.assert(res.output) === false) {{
+              res.error = 'assert returned false';
+            }}
+          }} catch (err) {{
+            res.error = err.toString();
+          }}
+          return res;
+        }}))
+      }} catch(err) {{
+        `__error_flag__ ${{err.toString()}}`
+      }}"#
+    );
+
+    let result = match context.eval(Source::from_bytes(code.as_str())) {
+        Ok(result) => result,
+        Err(err) => bail!("failed to evaluate tests: {err}"),
+    };
+    let result = match result.to_string(&mut context) {
+        Ok(result) => result.to_std_string_escaped(),
+        Err(err) => bail!("failed to evaluate tests: {err}"),
+    };
+    if let Some(err) = result.strip_prefix("__error_flag__") {
+        bail!("failed to evaluate tests: {}", err.trim());
+    }
+
+    let cases: Vec<JsonValue> = serde_json::from_str(&result)?;
+    Ok(cases.iter().map(check_case).collect())
+}
+
+fn check_case(case: &JsonValue) -> ScriptCaseResult {
+    let name = case["name"].as_str().unwrap_or_default().to_string();
+    let mut failures = vec![];
+
+    if let Some(err) = case["error"].as_str() {
+        failures.push(err.to_string());
+    }
+
+    if let Some(expect) = case["expect"].as_object() {
+        for (path, expected) in expect {
+            let actual = lookup(&case["output"], path);
+            if let Err(msg) = check_expect(actual, expected) {
+                failures.push(format!("`{path}`: {msg}"));
+            }
+        }
+    }
+
+    ScriptCaseResult {
+        name,
+        passed: failures.is_empty(),
+        failures,
+    }
+}
+
+/// 按 `a.b.0.c` 形式的路径查找值
+fn lookup<'a>(value: &'a JsonValue, path: &str) -> Option<&'a JsonValue> {
+    path.split('.')
+        .filter(|part| !part.is_empty())
+        .try_fold(value, |value, part| match value {
+            JsonValue::Object(map) => map.get(part),
+            JsonValue::Array(list) => part.parse::<usize>().ok().and_then(|i| list.get(i)),
+            _ => None,
+        })
+}
+
+/// 校验单个期望值，支持 `$exists`、`$contains`、`$length`、`$matches` 断言，
+/// 其余值按相等比较
+fn check_expect(actual: Option<&JsonValue>, expected: &JsonValue) -> Result<(), String> {
+    let matcher = expected
+        .as_object()
+        .filter(|map| map.len() == 1 && map.keys().all(|key| key.starts_with('$')));
+
+    let Some((op, arg)) = matcher.and_then(|map| map.iter().next()) else {
+        return match actual {
+            Some(actual) if actual == expected => Ok(()),
+            _ => Err(format!("expected {expected}, got {}", display(actual))),
+        };
+    };
+
+    match op.as_str() {
+        "$exists" => {
+            let want = arg.as_bool().unwrap_or(true);
+            if actual.is_some() == want {
+                Ok(())
+            } else {
+                Err(format!("expected exists = {want}, got {}", display(actual)))
+            }
+        }
+        "$contains" => {
+            let found = match actual {
+                Some(JsonValue::Array(list)) => list.contains(arg),
+                Some(JsonValue::String(s)) => arg.as_str().is_some_and(|arg| s.contains(arg)),
+                Some(JsonValue::Object(map)) => {
+                    arg.as_str().is_some_and(|arg| map.contains_key(arg))
+                }
+                _ => false,
+            };
+            if found {
+                Ok(())
+            } else {
+                Err(format!(
+                    "expected to contain {arg}, got {}",
+                    display(actual)
+                ))
+            }
+        }
+        "$length" => {
+            let len = match actual {
+                Some(JsonValue::Array(list)) => Some(list.len()),
+                Some(JsonValue::Object(map)) => Some(map.len()),
+                Some(JsonValue::String(s)) => Some(s.chars().count()),
+                _ => None,
+            };
+            if len.is_some() && len.map(|len| len as u64) == arg.as_u64() {
+                Ok(())
+            } else {
+                Err(format!("expected length {arg}, got {}", display(actual)))
+            }
+        }
+        "$matches" => {
+            let pattern = arg.as_str().unwrap_or_default();
+            let re = regex::Regex::new(pattern).map_err(|err| err.to_string())?;
+            match actual.and_then(JsonValue::as_str) {
+                Some(s) if re.is_match(s) => Ok(()),
+                _ => Err(format!(
+                    "expected to match /{pattern}/, got {}",
+                    display(actual)
+                )),
+            }
+        }
+        _ => Err(format!("unknown assertion `{op}`")),
+    }
+}
+
+fn display(value: Option<&JsonValue>) -> String {
+    value.map_or("nothing".into(), |value| value.to_string())
+}
+
+/// 递归比较两个配置，mapping 逐键比较，其余值整体比较
+fn diff_value(path: &str, before: &JsonValue, after: &JsonValue) -> Vec<DiffEntry> {
+    let join = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{path}.{key}")
+        }
+    };
+
+    match (before, after) {
+        (JsonValue::Object(before), JsonValue::Object(after)) => {
+            let mut diff = vec![];
+            for (key, value) in before {
+                match after.get(key) {
+                    Some(next) => diff.extend(diff_value(&join(key), value, next)),
+                    None => diff.push(DiffEntry {
+                        path: join(key),
+                        kind: DiffKind::Removed,
+                        before: Some(value.clone()),
+                        after: None,
+                    }),
+                }
+            }
+            for (key, value) in after {
+                if !before.contains_key(key) {
+                    diff.push(DiffEntry {
+                        path: join(key),
+                        kind: DiffKind::Added,
+                        before: None,
+                        after: Some(value.clone()),
+                    });
+                }
+            }
+            diff
+        }
+        (before, after) if before == after => vec![],
+        (before, after) => vec![DiffEntry {
+            path: path.to_string(),
+            kind: DiffKind::Changed,
+            before: Some(before.clone()),
+            after: Some(after.clone()),
+        }],
+    }
+}
+
+#[test]
+fn test_run_script_with_cases() -> anyhow::Result<()> {
+    let script = r#"
+    export const tests = [
+      {
+        name: "appends rule",
+        config: { rules: ["MATCH,DIRECT"] },
+        expect: { "rules.1": "MATCH,PROXY", "rules": { "$length": 2 }, "mode": "rule" },
+      },
+      {
+        name: "fails on purpose",
+        config: {},
+        expect: { "dns": { "$exists": true } },
+      },
+    ];
+
+    function main(config) {
+      config.rules = [...(config.rules || []), "MATCH,PROXY"];
+      config.mode = "rule";
+      console.log("done");
+      return config;
+    }
+  "#;
+
+    let fixture = serde_yaml::from_str::<Mapping>("rules:\n  - MATCH,DIRECT\nmode: global\n")?;
+    let report = run_script(script.into(), fixture, "".into())?;
+
+    assert!(report.output.is_some());
+    assert_eq!(report.logs.len(), 1);
+    assert!(report
+        .diff
+        .iter()
+        .any(|entry| entry.path == "mode" && entry.kind == DiffKind::Changed));
+
+    assert_eq!(report.tests.len(), 2);
+    assert!(report.tests[0].passed, "{:?}", report.tests[0].failures);
+    assert!(!report.tests[1].passed);
+    assert!(!report.passed());
+
+    Ok(())
+}
+
+/// 以 `cargo test` 方式运行目录中的脚本测试：
+/// `SCRIPT_TEST_DIR=/path/to/scripts cargo test script_fixtures -- --nocapture`
+#[test]
+fn script_fixtures() -> anyhow::Result<()> {
+    let Ok(dir) = std::env::var("SCRIPT_TEST_DIR") else {
+        return Ok(());
+    };
+
+    let mut failed = vec![];
+    for entry in std::fs::read_dir(&dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("js") {
+            continue;
+        }
+        let script = std::fs::read_to_string(&path)?;
+        let report = run_script(script, Mapping::new(), "".into())?;
+        println!("{}\n{}\n", path.display(), report.summary());
+        if !report.passed() {
+            failed.push(path.display().to_string());
+        }
+    }
+
+    assert!(failed.is_empty(), "script tests failed: {failed:?}");
+    Ok(())
+}
@@ -1,6 +1,109 @@
 use super::use_lowercase;
 use serde_yaml::{self, Mapping, Value};
 
+/// 声明 Merge 文件使用合并指令的版本标记
+///
+/// 只有声明了该标记时才会解析指令，否则保持旧的合并行为
+pub const MERGE_VERSION_KEY: &str = "merge-version";
+pub const MERGE_VERSION: u64 = 2;
+
+/// Merge 文件中 key 上携带的合并指令
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Directive {
+    /// 默认：mapping 递归合并，其余值直接替换
+    Default,
+    /// `key!` 强制整体替换
+    Replace,
+    /// `+key` 插入到序列开头
+    Prepend,
+    /// `key+` 追加到序列末尾
+    Append,
+    /// `<key>` 显式深度合并 mapping
+    DeepMerge,
+}
+
+/// 以域名等用户内容为 key 的 mapping，`+.example.com` 这类 key 不能当作指令
+const LITERAL_KEYED: [&str; 3] = ["hosts", "nameserver-policy", "fallback-filter"];
+
+fn parse_directive(key: &str) -> (&str, Directive) {
+    if key.len() > 2 && key.starts_with('<') && key.ends_with('>') {
+        return (&key[1..key.len() - 1], Directive::DeepMerge);
+    }
+    if key.len() > 1 {
+        if let Some(key) = key.strip_suffix('!') {
+            return (key, Directive::Replace);
+        }
+        if let Some(key) = key.strip_prefix('+') {
+            return (key, Directive::Prepend);
+        }
+        if let Some(key) = key.strip_suffix('+') {
+            return (key, Directive::Append);
+        }
+    }
+    (key, Directive::Default)
+}
+
+/// 按指令合并两个 mapping
+///
+/// 嵌套的 mapping 同样解析指令，只有 `LITERAL_KEYED` 中的 mapping 按原样深度合并，
+/// 避免 hosts、nameserver-policy 中 `+.example.com` 这类 key 被误当成指令
+fn merge_mapping(target: &mut Mapping, patch: &Mapping) {
+    for (key, value) in patch {
+        let (key, directive) = match key.as_str() {
+            Some(key_str) => {
+                let (name, directive) = parse_directive(key_str);
+                (Value::from(name), directive)
+            }
+            None => (key.clone(), Directive::Default),
+        };
+
+        match directive {
+            Directive::Replace => {
+                target.insert(key, value.clone());
+            }
+            Directive::Prepend | Directive::Append => {
+                let Value::Sequence(items) = value else {
+                    log::warn!(target: "app", "merge directive on `{key:?}` expects a sequence, replace instead");
+                    target.insert(key, value.clone());
+                    continue;
+                };
+                let entry = target.entry(key).or_insert(Value::Null);
+                let mut origin = match std::mem::take(entry) {
+                    Value::Sequence(origin) => origin,
+                    _ => vec![],
+                };
+                if directive == Directive::Prepend {
+                    let mut seq = items.clone();
+                    seq.append(&mut origin);
+                    *entry = Value::Sequence(seq);
+                } else {
+                    origin.extend(items.iter().cloned());
+                    *entry = Value::Sequence(origin);
+                }
+            }
+            Directive::DeepMerge | Directive::Default => {
+                if directive == Directive::DeepMerge && !value.is_mapping() {
+                    log::warn!(target: "app", "merge directive `<key>` expects a mapping, replace instead");
+                }
+                let literal = key.as_str().is_some_and(|key| LITERAL_KEYED.contains(&key));
+                let entry = target.entry(key).or_insert(Value::Null);
+                match value {
+                    Value::Mapping(patch) if !literal => {
+                        if !entry.is_mapping() {
+                            *entry = Value::Mapping(Mapping::new());
+                        }
+                        if let Value::Mapping(entry) = entry {
+                            merge_mapping(entry, patch);
+                        }
+                    }
+                    _ => deep_merge(entry, value),
+                }
+            }
+        }
+    }
+}
+
+/// 旧版本的合并行为，不解析任何指令
 fn deep_merge(a: &mut Value, b: &Value) {
     match (a, b) {
         (&mut Value::Mapping(ref mut a), Value::Mapping(b)) => {
@@ -12,15 +115,33 @@ fn deep_merge(a: &mut Value, b: &Value) {
     }
 }
 
+/// 是否声明了支持合并指令的版本标记
+fn is_versioned(merge: &Mapping) -> bool {
+    merge
+        .get(MERGE_VERSION_KEY)
+        .and_then(Value::as_u64)
+        .is_some_and(|version| version >= MERGE_VERSION)
+}
+
+/// 合并 Merge 文件
+///
+/// 声明了 `merge-version: 2` 时支持任意层级的 `key!`、`+key`、`key+`、`<key>` 指令，
+/// 否则保持旧的合并行为
 pub fn use_merge(merge: Mapping, config: Mapping) -> Mapping {
+    let mut merge = use_lowercase(merge);
+
+    if is_versioned(&merge) {
+        let mut config = config;
+        merge.remove(MERGE_VERSION_KEY);
+        merge_mapping(&mut config, &merge);
+        return config;
+    }
+
     let mut config = Value::from(config);
-    let merge = use_lowercase(merge.clone());
 
     deep_merge(&mut config, &Value::from(merge));
 
-    let config = config.as_mapping().unwrap().clone();
-
-    config
+    config.as_mapping().cloned().unwrap_or_default()
 }
 
 #[test]
@@ -58,3 +179,160 @@ fn test_merge() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_merge_directives() -> anyhow::Result<()> {
+    let merge = r"
+    merge-version: 2
+    +rules:
+      - DOMAIN,prepend.com,DIRECT
+    proxies+:
+      - name: appended
+    dns:
+      nameserver-policy:
+        geosite:cn: 223.5.5.5
+        +.example.com: 1.1.1.1
+      nameserver+:
+        - 1.1.1.1
+    hosts:
+      +.example.com: 127.0.0.1
+    tun!:
+      enable: true
+    <sniffer>:
+      sniff:
+        TLS:
+          ports: [443]
+  ";
+
+    let config = r"
+    rules:
+      - MATCH,PROXY
+    proxies:
+      - name: origin
+    dns:
+      enable: true
+      nameserver:
+        - 8.8.8.8
+      nameserver-policy:
+        geosite:private: system
+    tun:
+      enable: false
+      stack: gvisor
+    sniffer:
+      enable: true
+  ";
+
+    let merge = serde_yaml::from_str::<Mapping>(merge)?;
+    let config = serde_yaml::from_str::<Mapping>(config)?;
+    let config = use_merge(merge, config);
+
+    let rules = config["rules"].as_sequence().unwrap();
+    assert_eq!(rules[0].as_str(), Some("DOMAIN,prepend.com,DIRECT"));
+    assert_eq!(rules[1].as_str(), Some("MATCH,PROXY"));
+
+    let proxies = config["proxies"].as_sequence().unwrap();
+    assert_eq!(proxies.len(), 2);
+    assert_eq!(proxies[1]["name"].as_str(), Some("appended"));
+
+    let dns = config["dns"].as_mapping().unwrap();
+    assert_eq!(dns["enable"].as_bool(), Some(true));
+    assert_eq!(dns["nameserver"][0].as_str(), Some("8.8.8.8"));
+    assert_eq!(dns["nameserver"][1].as_str(), Some("1.1.1.1"));
+    assert!(!dns.contains_key("nameserver+"));
+    // 域名为 key 的 mapping 不解析指令，key 原样保留
+    let policy = dns["nameserver-policy"].as_mapping().unwrap();
+    assert_eq!(policy.len(), 3);
+    assert_eq!(policy["+.example.com"].as_str(), Some("1.1.1.1"));
+    assert_eq!(config["hosts"]["+.example.com"].as_str(), Some("127.0.0.1"));
+
+    let tun = config["tun"].as_mapping().unwrap();
+    assert_eq!(tun.len(), 1);
+    assert_eq!(tun["enable"].as_bool(), Some(true));
+
+    let sniffer = config["sniffer"].as_mapping().unwrap();
+    assert_eq!(sniffer["enable"].as_bool(), Some(true));
+    assert!(sniffer.contains_key("sniff"));
+
+    assert!(!config.contains_key("+rules"));
+    assert!(!config.contains_key("tun!"));
+
+    Ok(())
+}
+
+#[test]
+fn test_merge_nested_directives() -> anyhow::Result<()> {
+    let merge = r"
+    merge-version: 2
+    dns:
+      nameserver-policy!:
+        +.corp.example.com: 10.0.0.53
+      +nameserver:
+        - 223.5.5.5
+      fallback-filter:
+        geoip: false
+    sniffer:
+      sniff:
+        TLS:
+          ports!: [443, 8443]
+  ";
+
+    let config = r"
+    dns:
+      nameserver:
+        - 8.8.8.8
+      nameserver-policy:
+        geosite:cn: 223.5.5.5
+      fallback-filter:
+        geoip: true
+        ipcidr: [240.0.0.0/4]
+    sniffer:
+      sniff:
+        TLS:
+          ports: [443]
+          override-destination: true
+  ";
+
+    let merge = serde_yaml::from_str::<Mapping>(merge)?;
+    let config = serde_yaml::from_str::<Mapping>(config)?;
+    let config = use_merge(merge, config);
+
+    let dns = config["dns"].as_mapping().unwrap();
+    let policy = dns["nameserver-policy"].as_mapping().unwrap();
+    assert_eq!(policy.len(), 1);
+    assert_eq!(policy["+.corp.example.com"].as_str(), Some("10.0.0.53"));
+    assert!(!dns.contains_key("nameserver-policy!"));
+
+    let nameserver = dns["nameserver"].as_sequence().unwrap();
+    assert_eq!(nameserver[0].as_str(), Some("223.5.5.5"));
+    assert_eq!(nameserver[1].as_str(), Some("8.8.8.8"));
+
+    let filter = dns["fallback-filter"].as_mapping().unwrap();
+    assert_eq!(filter["geoip"].as_bool(), Some(false));
+    assert_eq!(filter["ipcidr"][0].as_str(), Some("240.0.0.0/4"));
+
+    let tls = config["sniffer"]["sniff"]["TLS"].as_mapping().unwrap();
+    assert_eq!(tls["ports"].as_sequence().unwrap().len(), 2);
+    assert_eq!(tls["override-destination"].as_bool(), Some(true));
+    assert!(!tls.contains_key("ports!"));
+
+    Ok(())
+}
+
+#[test]
+fn test_merge_compat() -> anyhow::Result<()> {
+    let config = serde_yaml::from_str::<Mapping>("rules:\n  - MATCH,DIRECT\n")?;
+
+    let legacy = serde_yaml::from_str::<Mapping>("+rules:\n  - DOMAIN,a.com,DIRECT\n")?;
+    let merged = use_merge(legacy, config.clone());
+    assert!(merged.contains_key("+rules"));
+    assert_eq!(merged["rules"].as_sequence().unwrap().len(), 1);
+
+    let versioned =
+        serde_yaml::from_str::<Mapping>("merge-version: 2\n+rules:\n  - DOMAIN,a.com,DIRECT\n")?;
+    let merged = use_merge(versioned, config);
+    assert!(!merged.contains_key("+rules"));
+    assert!(!merged.contains_key(MERGE_VERSION_KEY));
+    assert_eq!(merged["rules"].as_sequence().unwrap().len(), 2);
+
+    Ok(())
+}
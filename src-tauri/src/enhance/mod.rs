@@ -1,10 +1,13 @@
 mod chain;
 pub mod field;
+pub mod harness;
 mod merge;
+pub mod pac;
 mod script;
 pub mod seq;
 mod tun;
 
+pub use self::script::strip_exports;
 use self::{chain::*, field::*, merge::*, script::*, seq::*, tun::*};
 use crate::{config::Config, utils::tmpl};
 use serde_yaml::Mapping;
@@ -234,6 +237,12 @@ pub async fn enhance() -> (Mapping, Vec<String>, HashMap<String, ResultLog>) {
         }
     }
 
+    // 透明代理规则依赖 routing-mark 放行内核自身的出站流量，必须与规则中的值一致
+    #[cfg(target_os = "linux")]
+    if let Some(mark) = crate::core::tproxy::core_routing_mark() {
+        config.insert("routing-mark".into(), mark.into());
+    }
+
     // 内建脚本最后跑
     if enable_builtin {
         ChainItem::builtin()
@@ -292,6 +301,11 @@ pub async fn enhance() -> (Mapping, Vec<String>, HashMap<String, ResultLog>) {
         }
     }
 
+    // 根据最终的规则重新生成 PAC
+    if Config::verge().latest().pac_generated.unwrap_or(false) {
+        pac::refresh_pac(&config);
+    }
+
     let mut exists_set = HashSet::new();
     exists_set.extend(exists_keys);
     exists_keys = exists_set.into_iter().collect();
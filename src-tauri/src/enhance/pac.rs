@@ -0,0 +1,466 @@
+use crate::{logging, utils::dirs, utils::logging::Type};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde_yaml::{Mapping, Value};
+use std::{collections::HashMap, fmt::Write, fs, net::Ipv4Addr, path::Path};
+
+/// 规则动作，与生成脚本中的 ACTIONS 顺序一致
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Direct = 0,
+    Proxy = 1,
+    Reject = 2,
+}
+
+/// 最近一次 enhance 生成的 PAC 脚本
+static GENERATED: Lazy<RwLock<Option<String>>> = Lazy::new(|| RwLock::new(None));
+
+/// 编译后的规则，值为 `规则序号 * 4 + 动作`，序号越小优先级越高
+#[derive(Debug, Default)]
+struct PacRules {
+    domains: HashMap<String, u32>,
+    suffixes: HashMap<String, u32>,
+    keywords: Vec<(String, u32)>,
+    /// 网络、掩码、值、no-resolve
+    cidrs: Vec<(Ipv4Addr, Ipv4Addr, u32, bool)>,
+    final_value: u32,
+    /// 无法在 PAC 中判断的规则
+    skipped: Vec<String>,
+}
+
+impl PacRules {
+    fn compile(config: &Mapping, providers_dir: Option<&Path>) -> Self {
+        let mut rules = PacRules::default();
+        let mut final_rule = (u32::MAX >> 2, Action::Direct);
+        let providers = config.get("rule-providers").and_then(Value::as_mapping);
+        let lines = config
+            .get("rules")
+            .and_then(Value::as_sequence)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str);
+
+        for (index, line) in lines.enumerate() {
+            let parts = line.split(',').map(str::trim).collect::<Vec<_>>();
+            let index = index as u32;
+            match parts.as_slice() {
+                ["MATCH" | "FINAL", target, ..] => {
+                    final_rule = (index, action(target));
+                    break;
+                }
+                ["RULE-SET", name, target, ..] => {
+                    let payload = providers
+                        .and_then(|providers| providers.get(*name))
+                        .map(|provider| load_provider(provider, providers_dir))
+                        .unwrap_or_default();
+                    if payload.is_empty() {
+                        rules.skipped.push(line.to_string());
+                        continue;
+                    }
+                    let value = encode(index, action(target));
+                    let unsupported = payload
+                        .iter()
+                        .filter(|(rule_type, payload)| {
+                            !rules.insert(rule_type, payload, value, false)
+                        })
+                        .count();
+                    if unsupported > 0 {
+                        rules
+                            .skipped
+                            .push(format!("{line} ({unsupported} unsupported entries)"));
+                    }
+                }
+                [rule_type, payload, target, rest @ ..] => {
+                    let no_resolve = rest.contains(&"no-resolve");
+                    if !rules.insert(
+                        rule_type,
+                        payload,
+                        encode(index, action(target)),
+                        no_resolve,
+                    ) {
+                        rules.skipped.push(line.to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // 跳过的规则可能需要代理，未匹配的请求交给内核判断
+        if !rules.skipped.is_empty() {
+            final_rule.1 = Action::Proxy;
+            logging!(
+                info,
+                Type::Config,
+                true,
+                "PAC skipped {} unsupported rules, unmatched requests go through the proxy: {}",
+                rules.skipped.len(),
+                rules.skipped.join("; ")
+            );
+        }
+        rules.final_value = encode(final_rule.0, final_rule.1);
+        rules
+    }
+
+    /// 不支持的规则类型（GEOIP、PROCESS-NAME、IP-CIDR6 等）返回 false
+    fn insert(&mut self, rule_type: &str, payload: &str, value: u32, no_resolve: bool) -> bool {
+        let payload = payload.to_lowercase();
+        match rule_type {
+            "DOMAIN" => {
+                self.domains.entry(payload).or_insert(value);
+            }
+            "DOMAIN-SUFFIX" => {
+                self.suffixes.entry(payload).or_insert(value);
+            }
+            "DOMAIN-KEYWORD" => self.keywords.push((payload, value)),
+            "IP-CIDR" => match parse_cidr(&payload) {
+                Some((net, mask)) => self.cidrs.push((net, mask, value, no_resolve)),
+                None => return false,
+            },
+            _ => return false,
+        }
+        true
+    }
+
+    fn to_script(&self) -> String {
+        let mut keywords = self.keywords.clone();
+        keywords.sort_by_key(|(_, value)| *value);
+        let mut cidrs = self.cidrs.clone();
+        cidrs.sort_by_key(|(_, _, value, _)| *value);
+
+        let mut script = String::from(
+            "// Generated by Koala Clash from the rules of the runtime config\n\
+             var ACTIONS = [\"DIRECT\", \"PROXY 127.0.0.1:%mixed-port%; SOCKS5 127.0.0.1:%mixed-port%; DIRECT\", \"PROXY 127.0.0.1:1\"];\n",
+        );
+        let _ = writeln!(script, "var DOMAINS = {};", to_object(&self.domains));
+        let _ = writeln!(script, "var SUFFIXES = {};", to_object(&self.suffixes));
+        let keywords = keywords
+            .iter()
+            .map(|(keyword, value)| format!("[{},{value}]", quote(keyword)))
+            .collect::<Vec<_>>();
+        let _ = writeln!(script, "var KEYWORDS = [{}];", keywords.join(","));
+        let cidrs = cidrs
+            .iter()
+            .map(|(net, mask, value, no_resolve)| {
+                format!("[\"{net}\",\"{mask}\",{value},{}]", *no_resolve as u8)
+            })
+            .collect::<Vec<_>>();
+        let _ = writeln!(script, "var CIDRS = [{}];", cidrs.join(","));
+        let _ = writeln!(script, "var FINAL = {};", self.final_value);
+        script.push_str(PAC_FUNCTIONS);
+        script
+    }
+}
+
+const PAC_FUNCTIONS: &str = r#"
+function lookup(table, key) {
+  return Object.prototype.hasOwnProperty.call(table, key) ? table[key] : -1;
+}
+
+function pick(best, value) {
+  return value >= 0 && (best < 0 || value < best) ? value : best;
+}
+
+function FindProxyForURL(url, host) {
+  host = host.toLowerCase();
+  var best = lookup(DOMAINS, host);
+  for (var pos = -1, suffix = host; ; suffix = host.substring(pos + 1)) {
+    best = pick(best, lookup(SUFFIXES, suffix));
+    pos = host.indexOf(".", pos + 1);
+    if (pos < 0) break;
+  }
+  for (var i = 0; i < KEYWORDS.length; i++) {
+    if (best >= 0 && KEYWORDS[i][1] > best) break;
+    if (host.indexOf(KEYWORDS[i][0]) >= 0) {
+      best = pick(best, KEYWORDS[i][1]);
+      break;
+    }
+  }
+  var literal = /^\d+\.\d+\.\d+\.\d+$/.test(host);
+  var ip = literal ? host : null;
+  for (var j = 0; j < CIDRS.length; j++) {
+    var cidr = CIDRS[j];
+    if (best >= 0 && cidr[2] > best) break;
+    if (!literal && cidr[3]) continue;
+    if (ip === null) ip = dnsResolve(host) || "";
+    if (ip && isInNet(ip, cidr[0], cidr[1])) {
+      best = pick(best, cidr[2]);
+      break;
+    }
+  }
+  return ACTIONS[(best < 0 ? FINAL : best) % 4];
+}
+"#;
+
+fn encode(index: u32, action: Action) -> u32 {
+    index * 4 + action as u32
+}
+
+fn action(target: &str) -> Action {
+    match target {
+        "DIRECT" => Action::Direct,
+        "REJECT" | "REJECT-DROP" => Action::Reject,
+        _ => Action::Proxy,
+    }
+}
+
+fn parse_cidr(cidr: &str) -> Option<(Ipv4Addr, Ipv4Addr)> {
+    let (net, prefix) = cidr.split_once('/')?;
+    let net = net.parse::<Ipv4Addr>().ok()?;
+    let prefix = prefix.parse::<u32>().ok().filter(|prefix| *prefix <= 32)?;
+    let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+    Some((Ipv4Addr::from(u32::from(net) & mask), Ipv4Addr::from(mask)))
+}
+
+/// 读取本地规则集，返回 (规则类型, 内容)
+fn load_provider(provider: &Value, providers_dir: Option<&Path>) -> Vec<(String, String)> {
+    let Some(path) = provider.get("path").and_then(Value::as_str) else {
+        return Vec::new();
+    };
+    let path = match providers_dir {
+        Some(dir) if Path::new(path).is_relative() => dir.join(path),
+        _ => Path::new(path).to_path_buf(),
+    };
+    let Ok(content) = fs::read_to_string(&path) else {
+        return Vec::new();
+    };
+    let behavior = provider
+        .get("behavior")
+        .and_then(Value::as_str)
+        .unwrap_or("classical");
+    let format = provider
+        .get("format")
+        .and_then(Value::as_str)
+        .unwrap_or("yaml");
+    let payload = match format {
+        "text" => content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect::<Vec<_>>(),
+        "yaml" => serde_yaml::from_str::<Mapping>(&content)
+            .ok()
+            .and_then(|map| map.get("payload").and_then(Value::as_sequence).cloned())
+            .into_iter()
+            .flatten()
+            .filter_map(|item| item.as_str().map(str::to_string))
+            .collect(),
+        // mrs 等二进制格式无法解析
+        _ => Vec::new(),
+    };
+
+    payload
+        .into_iter()
+        .filter_map(|item| match behavior {
+            "domain" => provider_domain(&item),
+            "ipcidr" => Some(("IP-CIDR".into(), item)),
+            _ => {
+                let mut parts = item.splitn(3, ',').map(str::trim);
+                Some((parts.next()?.to_string(), parts.next()?.to_string()))
+            }
+        })
+        .collect()
+}
+
+/// `+.example.com` 与 `.example.com` 按后缀匹配，其余通配写法不支持
+fn provider_domain(item: &str) -> Option<(String, String)> {
+    if let Some(suffix) = item.strip_prefix("+.").or(item.strip_prefix('.')) {
+        Some(("DOMAIN-SUFFIX".into(), suffix.into()))
+    } else if item.contains('*') {
+        Some(("DOMAIN-WILDCARD".into(), item.into()))
+    } else {
+        Some(("DOMAIN".into(), item.into()))
+    }
+}
+
+fn quote(text: &str) -> String {
+    serde_json::to_string(text).unwrap_or_default()
+}
+
+fn to_object(table: &HashMap<String, u32>) -> String {
+    let mut entries = table.iter().collect::<Vec<_>>();
+    entries.sort();
+    let entries = entries
+        .into_iter()
+        .map(|(key, value)| format!("{}:{value}", quote(key)))
+        .collect::<Vec<_>>();
+    format!("{{{}}}", entries.join(","))
+}
+
+/// 根据配置的规则生成 PAC 脚本，端口保留 `%mixed-port%` 占位
+pub fn generate_pac(config: &Mapping) -> String {
+    let providers_dir = dirs::app_home_dir().ok();
+    PacRules::compile(config, providers_dir.as_deref()).to_script()
+}
+
+/// 每次 enhance 后重新生成
+pub fn refresh_pac(config: &Mapping) {
+    let script = generate_pac(config);
+    logging!(
+        debug,
+        Type::Config,
+        true,
+        "Generated pac script ({} bytes)",
+        script.len()
+    );
+    *GENERATED.write() = Some(script);
+}
+
+/// 最近生成的 PAC 脚本
+pub fn generated_pac() -> Option<String> {
+    GENERATED.read().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compile(yaml: &str, dir: Option<&Path>) -> PacRules {
+        PacRules::compile(&serde_yaml::from_str(yaml).unwrap(), dir)
+    }
+
+    #[test]
+    fn test_compile_rules() {
+        let rules = compile(
+            r#"
+rules:
+  - DOMAIN,Example.com,DIRECT
+  - DOMAIN-SUFFIX,google.com,Proxy
+  - DOMAIN-SUFFIX,example.com,REJECT
+  - GEOIP,CN,DIRECT
+  - DOMAIN-KEYWORD,ads,REJECT
+  - IP-CIDR,10.1.2.3/8,DIRECT,no-resolve
+  - IP-CIDR6,::1/128,DIRECT
+  - MATCH,Proxy
+  - DOMAIN,after-match.com,DIRECT
+"#,
+            None,
+        );
+        assert_eq!(
+            rules.domains.get("example.com"),
+            Some(&encode(0, Action::Direct))
+        );
+        assert_eq!(
+            rules.suffixes.get("google.com"),
+            Some(&encode(1, Action::Proxy))
+        );
+        assert_eq!(
+            rules.suffixes.get("example.com"),
+            Some(&encode(2, Action::Reject))
+        );
+        assert_eq!(
+            rules.keywords,
+            vec![("ads".into(), encode(4, Action::Reject))]
+        );
+        assert_eq!(
+            rules.cidrs,
+            vec![(
+                Ipv4Addr::new(10, 0, 0, 0),
+                Ipv4Addr::new(255, 0, 0, 0),
+                encode(5, Action::Direct),
+                true
+            )]
+        );
+        assert_eq!(rules.final_value, encode(7, Action::Proxy));
+        assert_eq!(
+            rules.skipped,
+            vec!["GEOIP,CN,DIRECT", "IP-CIDR6,::1/128,DIRECT"]
+        );
+        assert!(!rules.domains.contains_key("after-match.com"));
+
+        let script = rules.to_script();
+        assert!(script.contains("var DOMAINS = {\"example.com\":0};"));
+        assert!(script.contains("function FindProxyForURL(url, host)"));
+        assert!(script.contains("%mixed-port%"));
+    }
+
+    #[test]
+    fn test_rule_providers() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("domains.yaml"),
+            "payload:\n  - '+.netflix.com'\n  - 'nflxvideo.net'\n  - '*.wild.com'\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("classical.txt"),
+            "# comment\nDOMAIN-KEYWORD,tracker\nIP-CIDR,192.168.0.0/16,no-resolve\n",
+        )
+        .unwrap();
+        let rules = compile(
+            r#"
+rule-providers:
+  streaming:
+    type: file
+    behavior: domain
+    path: ./domains.yaml
+  classical:
+    type: file
+    behavior: classical
+    format: text
+    path: ./classical.txt
+  missing:
+    type: file
+    behavior: domain
+    path: ./missing.yaml
+rules:
+  - RULE-SET,missing,DIRECT
+  - RULE-SET,streaming,Media
+  - RULE-SET,classical,REJECT
+  - MATCH,DIRECT
+"#,
+            Some(dir.path()),
+        );
+        assert_eq!(
+            rules.suffixes.get("netflix.com"),
+            Some(&encode(1, Action::Proxy))
+        );
+        assert_eq!(
+            rules.domains.get("nflxvideo.net"),
+            Some(&encode(1, Action::Proxy))
+        );
+        assert_eq!(rules.domains.len() + rules.suffixes.len(), 2);
+        assert_eq!(
+            rules.keywords,
+            vec![("tracker".into(), encode(2, Action::Reject))]
+        );
+        assert_eq!(rules.cidrs.len(), 1);
+        // 缺失的规则集和通配域名无法判断，未匹配的请求走代理
+        assert_eq!(
+            rules.skipped,
+            vec![
+                "RULE-SET,missing,DIRECT",
+                "RULE-SET,streaming,Media (1 unsupported entries)"
+            ]
+        );
+        assert_eq!(rules.final_value, encode(3, Action::Proxy));
+    }
+
+    #[test]
+    fn test_final_with_skipped_rules() {
+        let rules = compile(
+            r#"
+rules:
+  - DOMAIN-SUFFIX,cn,DIRECT
+  - MATCH,DIRECT
+"#,
+            None,
+        );
+        assert!(rules.skipped.is_empty());
+        assert_eq!(rules.final_value, encode(1, Action::Direct));
+
+        let rules = compile(
+            r#"
+rules:
+  - DOMAIN-SUFFIX,cn,DIRECT
+  - GEOSITE,gfw,Proxy
+  - DOMAIN-REGEX,^ads\.,REJECT
+  - MATCH,DIRECT
+"#,
+            None,
+        );
+        assert_eq!(rules.skipped.len(), 2);
+        assert_eq!(rules.final_value, encode(3, Action::Proxy));
+        assert!(rules.to_script().contains("var FINAL = 13;"));
+    }
+}
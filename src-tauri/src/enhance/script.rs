@@ -1,12 +1,16 @@
 use super::use_lowercase;
 use anyhow::{Error, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
 use serde_yaml::Mapping;
 
-pub fn use_script(
-    script: String,
-    config: Mapping,
-    name: String,
-) -> Result<(Mapping, Vec<(String, String)>)> {
+static EXPORT_TESTS_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?m)^(\s*)export\s+(const|let|var)\s+tests\b").unwrap());
+
+pub(super) type ScriptLogs = std::sync::Arc<std::sync::Mutex<Vec<(String, String)>>>;
+
+/// 创建脚本执行上下文，注册 console 并收集输出
+pub(super) fn create_context() -> (boa_engine::Context, ScriptLogs) {
     use boa_engine::{native_function::NativeFunction, Context, JsValue, Source};
     use std::sync::{Arc, Mutex};
     let mut context = Context::default();
@@ -42,6 +46,25 @@ pub fn use_script(
       });"#,
     ));
 
+    (context, outputs)
+}
+
+/// 脚本不是以模块方式执行的，去掉测试用例声明前的 `export`
+pub fn strip_exports(script: &str) -> String {
+    EXPORT_TESTS_RE
+        .replace_all(script, "$1$2 tests")
+        .into_owned()
+}
+
+pub fn use_script(
+    script: String,
+    config: Mapping,
+    name: String,
+) -> Result<(Mapping, Vec<(String, String)>)> {
+    use boa_engine::Source;
+    let (mut context, outputs) = create_context();
+    let script = strip_exports(&script);
+
     let config = use_lowercase(config.clone());
     let config_str = serde_json::to_string(&config)?;
 
@@ -80,7 +103,7 @@ pub fn use_script(
     }
 }
 
-fn parse_json_safely(json_str: &str) -> Result<Mapping, Error> {
+pub(super) fn parse_json_safely(json_str: &str) -> Result<Mapping, Error> {
     let json_str = strip_outer_quotes(json_str);
 
     Ok(serde_json::from_str::<Mapping>(json_str)?)
@@ -97,7 +120,7 @@ fn strip_outer_quotes(s: &str) -> &str {
 }
 
 // 转义单引号和反斜杠，用于单引号包裹的JavaScript字符串
-fn escape_js_string_for_single_quote(s: &str) -> String {
+pub(super) fn escape_js_string_for_single_quote(s: &str) -> String {
     s.replace('\\', "\\\\").replace('\'', "\\'")
 }
 
@@ -62,6 +62,7 @@ pub async fn use_tun(mut config: Mapping, enable: bool) -> Mapping {
                 crate::utils::resolve::restore_public_dns().await;
                 crate::utils::resolve::set_public_dns("8.8.8.8".to_string()).await;
             }
+            // Linux 在内核启动或配置生效后按运行时配置接管，见 linux_dns::sync
         }
 
         // 当TUN启用时，将修改后的DNS配置写回
@@ -1,68 +1,72 @@
 use crate::{
     config::{Config, IVerge},
-    core::backup,
+    core::backup::{self, BackupFile},
     logging_error,
     utils::{dirs::app_home_dir, logging::Type},
 };
 use anyhow::Result;
-use reqwest_dav::list_cmd::ListFile;
 use std::fs;
 
-/// Create a backup and upload to WebDAV
-pub async fn create_backup_and_upload_webdav() -> Result<()> {
+/// Create a backup and upload it to the selected storage
+pub async fn create_backup_and_upload() -> Result<()> {
     let (file_name, temp_file_path) = backup::create_backup().map_err(|err| {
         log::error!(target: "app", "Failed to create backup: {err:#?}");
         err
     })?;
 
-    if let Err(err) = backup::WebDavClient::global()
-        .upload(temp_file_path.clone(), file_name)
-        .await
-    {
-        log::error!(target: "app", "Failed to upload to WebDAV: {err:#?}");
-        return Err(err);
-    }
-
+    let result = match backup::storage() {
+        Ok(storage) => storage.upload(&temp_file_path, &file_name).await,
+        Err(err) => Err(err),
+    };
     if let Err(err) = std::fs::remove_file(&temp_file_path) {
         log::warn!(target: "app", "Failed to remove temp file: {err:#?}");
     }
-
-    Ok(())
+    result.map_err(|err| {
+        log::error!(target: "app", "Failed to upload backup: {err:#?}");
+        err
+    })
 }
 
-/// List WebDAV backups
-pub async fn list_wevdav_backup() -> Result<Vec<ListFile>> {
-    backup::WebDavClient::global().list().await.map_err(|err| {
-        log::error!(target: "app", "Failed to list WebDAV backup files: {err:#?}");
+/// List backups in the selected storage
+pub async fn list_backup() -> Result<Vec<BackupFile>> {
+    backup::storage()?.list().await.map_err(|err| {
+        log::error!(target: "app", "Failed to list backup files: {err:#?}");
         err
     })
 }
 
-/// Delete WebDAV backup
-pub async fn delete_webdav_backup(filename: String) -> Result<()> {
-    backup::WebDavClient::global()
-        .delete(filename)
-        .await
-        .map_err(|err| {
-            log::error!(target: "app", "Failed to delete WebDAV backup file: {err:#?}");
-            err
-        })
+/// Delete a backup from the selected storage
+pub async fn delete_backup(filename: String) -> Result<()> {
+    backup::storage()?.delete(&filename).await.map_err(|err| {
+        log::error!(target: "app", "Failed to delete backup file: {err:#?}");
+        err
+    })
 }
 
-/// Restore WebDAV backup
-pub async fn restore_webdav_backup(filename: String) -> Result<()> {
+/// Restore a backup from the selected storage
+pub async fn restore_backup(filename: String) -> Result<()> {
     let verge = Config::verge();
     let verge_data = verge.data().clone();
-    let webdav_url = verge_data.webdav_url.clone();
-    let webdav_username = verge_data.webdav_username.clone();
-    let webdav_password = verge_data.webdav_password.clone();
+    // 备份中不含存储配置，恢复后需要写回当前的配置
+    let storage_patch = IVerge {
+        webdav_url: verge_data.webdav_url.clone(),
+        webdav_username: verge_data.webdav_username.clone(),
+        webdav_password: verge_data.webdav_password.clone(),
+        backup_storage: verge_data.backup_storage.clone(),
+        backup_local_dir: verge_data.backup_local_dir.clone(),
+        backup_s3: verge_data.backup_s3.clone(),
+        backup_sftp: verge_data.backup_sftp.clone(),
+        local_api_token: verge_data.local_api_token.clone(),
+        ..IVerge::default()
+    };
 
-    let backup_storage_path = app_home_dir().unwrap().join(&filename);
-    backup::WebDavClient::global()
-        .download(filename, backup_storage_path.clone())
+    backup::check_file_name(&filename)?;
+    let backup_storage_path = app_home_dir()?.join(&filename);
+    backup::storage()?
+        .download(&filename, &backup_storage_path)
         .await
         .map_err(|err| {
-            log::error!(target: "app", "Failed to download WebDAV backup file: {err:#?}");
+            log::error!(target: "app", "Failed to download backup file: {err:#?}");
             err
         })?;
 
@@ -72,16 +76,7 @@ pub async fn restore_webdav_backup(filename: String) -> Result<()> {
     logging_error!(
         Type::Backup,
         true,
-        super::patch_verge(
-            IVerge {
-                webdav_url,
-                webdav_username,
-                webdav_password,
-                ..IVerge::default()
-            },
-            false
-        )
-        .await
+        super::patch_verge(storage_patch, false).await
     );
     // 最后删除临时文件
     fs::remove_file(backup_storage_path)?;
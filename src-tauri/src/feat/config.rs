@@ -1,6 +1,6 @@
 use crate::{
     config::{Config, IVerge},
-    core::{handle, hotkey, sysopt, tray, CoreManager},
+    core::{handle, hotkey, sysopt, tray, CoreManager, Timer},
     logging_error,
     module::lightweight,
     utils::logging::Type,
@@ -23,7 +23,7 @@ pub async fn patch_clash(patch: Mapping) -> Result<()> {
                 logging_error!(Type::Tray, true, tray::Tray::global().update_icon(None));
             }
             Config::runtime().latest().patch_config(patch);
-            CoreManager::global().update_config().await?;
+            CoreManager::global().update_config_patch().await?;
         }
         handle::Handle::refresh_clash();
         <Result<()>>::Ok(())
@@ -56,6 +56,9 @@ enum UpdateFlags {
     SystrayTooltip = 1 << 8,
     SystrayClickBehavior = 1 << 9,
     LighteWeight = 1 << 10,
+    Timer = 1 << 11,
+    #[cfg(target_os = "linux")]
+    TransparentRules = 1 << 12,
 }
 
 /// Patch Verge configuration
@@ -115,6 +118,21 @@ pub async fn patch_verge(patch: IVerge, not_save_file: bool) -> Result<()> {
         #[cfg(target_os = "linux")]
         if tproxy_enabled.is_some() || tproxy_port.is_some() {
             update_flags |= UpdateFlags::RestartCore as i32;
+            update_flags |= UpdateFlags::TransparentRules as i32;
+        }
+        #[cfg(target_os = "linux")]
+        if redir_enabled.is_some() || redir_port.is_some() {
+            update_flags |= UpdateFlags::TransparentRules as i32;
+        }
+        #[cfg(target_os = "linux")]
+        if patch.enable_transparent_rules.is_some() || patch.transparent_routing_mark.is_some() {
+            // routing-mark 写入运行时配置，需要重启内核
+            update_flags |= UpdateFlags::RestartCore as i32;
+            update_flags |= UpdateFlags::TransparentRules as i32;
+        }
+        #[cfg(target_os = "linux")]
+        if patch.transparent_bypass_uid.is_some() || patch.transparent_bypass_cidrs.is_some() {
+            update_flags |= UpdateFlags::TransparentRules as i32;
         }
         if socks_enabled.is_some()
             || http_enabled.is_some()
@@ -165,13 +183,27 @@ pub async fn patch_verge(patch: IVerge, not_save_file: bool) -> Result<()> {
             update_flags |= UpdateFlags::LighteWeight as i32;
         }
 
+        if patch.schedules.is_some() {
+            crate::core::schedule::mark_checked()?;
+        }
+
+        if patch.group_health_checks.is_some() || patch.schedules.is_some() {
+            update_flags |= UpdateFlags::Timer as i32;
+        }
+
         // Process updates based on flags
         if (update_flags & (UpdateFlags::RestartCore as i32)) != 0 {
             Config::generate().await?;
             CoreManager::global().restart_core().await?;
         }
+        #[cfg(target_os = "linux")]
+        if (update_flags & (UpdateFlags::TransparentRules as i32)) != 0 {
+            crate::core::tproxy::TransparentProxy::global()
+                .sync()
+                .await?;
+        }
         if (update_flags & (UpdateFlags::ClashConfig as i32)) != 0 {
-            CoreManager::global().update_config().await?;
+            CoreManager::global().update_config_patch().await?;
             handle::Handle::refresh_clash();
         }
         if (update_flags & (UpdateFlags::VergeConfig as i32)) != 0 {
@@ -207,6 +239,10 @@ pub async fn patch_verge(patch: IVerge, not_save_file: bool) -> Result<()> {
             }
         }
 
+        if (update_flags & (UpdateFlags::Timer as i32)) != 0 {
+            Timer::global().refresh()?;
+        }
+
         <Result<()>>::Ok(())
     };
     match res {
@@ -1,12 +1,14 @@
 use crate::{
     cmd,
-    config::{Config, PrfItem, PrfOption},
+    config::{Config, IProfiles, PrfItem, PrfOption},
     core::{handle, CoreManager, *},
     logging,
+    module::{latency::LatencyHistory, mihomo::MihomoManager},
     process::AsyncHandler,
-    utils::logging::Type,
+    utils::{dirs, help, logging::Type},
 };
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Result};
+use std::collections::HashSet;
 
 /// Toggle proxy profile
 pub fn toggle_proxy_profile(profile_index: String) {
@@ -23,6 +25,58 @@ pub fn toggle_proxy_profile(profile_index: String) {
     });
 }
 
+/// Switch the current profile, returns false if a newer switch request took over
+pub async fn switch_profile(uid: String) -> Result<bool> {
+    cmd::patch_profiles_config(IProfiles {
+        current: Some(uid),
+        items: None,
+    })
+    .await
+    .map_err(|err| anyhow!(err))
+}
+
+/// Import a profile from url
+/// If the url was imported before, update that profile instead
+pub async fn import_profile(url: String, option: Option<PrfOption>) -> Result<()> {
+    let existing_uid = {
+        let profiles = Config::profiles();
+        let profiles = profiles.latest();
+
+        profiles
+            .items
+            .as_ref()
+            .and_then(|items| items.iter().find(|item| item.url.as_deref() == Some(&url)))
+            .and_then(|item| item.uid.clone())
+    };
+
+    if let Some(uid) = existing_uid {
+        logging!(
+            info,
+            Type::Config,
+            true,
+            "The profile with URL {} already exists (UID: {}). Running the update...",
+            url,
+            uid
+        );
+        return update_profile(uid, option, Some(true)).await;
+    }
+
+    logging!(
+        info,
+        Type::Config,
+        true,
+        "Profile with URL {} not found. Create a new one...",
+        url
+    );
+    let item = PrfItem::from_url(&url, None, None, option).await?;
+    let new_uid = item.uid.clone().unwrap_or_default();
+    Config::profiles().data().append_item(item)?;
+    if !new_uid.is_empty() {
+        switch_profile(new_uid).await?;
+    }
+    Ok(())
+}
+
 /// Update a profile
 /// If updating current profile, activate it
 /// auto_refresh: 是否自动更新配置和刷新前端
@@ -62,6 +116,7 @@ pub async fn update_profile(
         }
     };
 
+    let is_remote = url_opt.is_some();
     let should_update = match url_opt {
         Some((url, opt)) => {
             log::info!(target: "app", "[Subscription Update] Start downloading new subscription content");
@@ -167,9 +222,69 @@ pub async fn update_profile(
         }
     }
 
+    if is_remote {
+        prune_latency_history(&uid).await;
+    }
+
     Ok(())
 }
 
+/// 删除订阅中已不存在的节点的延迟记录
+async fn prune_latency_history(uid: &str) {
+    // 当前订阅使用运行时配置，包含 Merge 和 Script 添加的节点
+    let is_current = Config::profiles().latest().get_current().as_deref() == Some(uid);
+    let config = if is_current {
+        Config::runtime().latest().config.clone()
+    } else {
+        let profiles = Config::profiles();
+        let profiles = profiles.latest();
+        profiles
+            .get_item(&uid.to_string())
+            .ok()
+            .and_then(|item| item.file.clone())
+            .and_then(|file| {
+                let path = dirs::app_profiles_dir().ok()?.join(file);
+                help::read_mapping(&path).ok()
+            })
+    };
+    let Some(config) = config else {
+        return;
+    };
+    let proxies = config
+        .get("proxies")
+        .and_then(|proxies| proxies.as_sequence())
+        .map(|proxies| {
+            proxies
+                .iter()
+                .filter_map(|proxy| proxy.as_mapping().cloned())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let mut names = proxies
+        .iter()
+        .filter_map(|proxy| proxy.get("name").and_then(|name| name.as_str()))
+        .map(str::to_string)
+        .collect::<HashSet<_>>();
+    // 代理集合中的节点只能从内核获取，获取不到时保留使用名称标识的记录
+    let names = if !config.contains_key("proxy-providers") {
+        Some(names)
+    } else if is_current {
+        MihomoManager::global()
+            .get_refresh_proxies()
+            .await
+            .ok()
+            .and_then(|value| value.get("proxies")?.as_object().cloned())
+            .map(|proxies| {
+                names.extend(proxies.keys().cloned());
+                names
+            })
+    } else {
+        None
+    };
+    LatencyHistory::global().prune(uid, &proxies, names.as_ref());
+}
+
 /// 增强配置
 pub async fn enhance_profiles() -> Result<()> {
     crate::core::CoreManager::global()
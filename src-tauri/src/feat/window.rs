@@ -169,11 +169,36 @@ async fn clean_async() -> bool {
         }
     };
 
-    // 4. DNS恢复（仅macOS）
-    #[cfg(target_os = "macos")]
+    // 透明代理规则需在内核停止前移除，避免流量被转发到已关闭的端口
+    #[cfg(target_os = "linux")]
+    let rules_success = match timeout(
+        Duration::from_secs(10),
+        crate::core::tproxy::TransparentProxy::global().clear(),
+    )
+    .await
+    {
+        Ok(Ok(_)) => true,
+        Ok(Err(err)) => {
+            log::warn!(target: "app", "Failed to remove transparent proxy rules: {err}");
+            false
+        }
+        Err(_) => {
+            log::warn!(target: "app", "Timeout removing transparent proxy rules");
+            false
+        }
+    };
+    #[cfg(not(target_os = "linux"))]
+    let rules_success = true;
+
+    // 4. DNS恢复（macOS 与 Linux），Linux 可能需要等待提权
+    #[cfg(any(target_os = "macos", target_os = "linux"))]
     let dns_task = async {
         match timeout(
-            Duration::from_millis(1000),
+            Duration::from_millis(if cfg!(target_os = "linux") {
+                10000
+            } else {
+                1000
+            }),
             crate::utils::resolve::restore_public_dns(),
         )
         .await
@@ -192,12 +217,12 @@ async fn clean_async() -> bool {
     // 并行执行所有清理任务
     let (tun_success, proxy_success, core_success) = tokio::join!(tun_task, proxy_task, core_task);
 
-    #[cfg(target_os = "macos")]
+    #[cfg(any(target_os = "macos", target_os = "linux"))]
     let dns_success = dns_task.await;
-    #[cfg(not(target_os = "macos"))]
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
     let dns_success = true;
 
-    let all_success = tun_success && proxy_success && core_success && dns_success;
+    let all_success = tun_success && proxy_success && core_success && dns_success && rules_success;
 
     logging!(
         info,
@@ -1,3 +1,4 @@
+pub mod cli;
 mod cmd;
 mod config;
 mod core;
@@ -93,6 +94,15 @@ pub fn run() {
 
     let _ = utils::dirs::init_portable_flag();
 
+    if utils::headless::handle_unit_args() {
+        return;
+    }
+    utils::headless::init_headless_flag();
+    if utils::headless::is_headless() {
+        utils::headless::run();
+        return;
+    }
+
     #[cfg(target_os = "linux")]
     std::env::set_var("WEBKIT_DISABLE_DMABUF_RENDERER", "1");
 
@@ -328,10 +338,17 @@ pub fn run() {
             cmd::patch_clash_config,
             cmd::patch_clash_mode,
             cmd::change_clash_core,
+            cmd::get_core_registry,
+            cmd::install_core,
+            cmd::add_custom_core,
+            cmd::switch_core,
+            cmd::rollback_core,
+            cmd::remove_core,
             cmd::get_runtime_config,
             cmd::get_runtime_yaml,
             cmd::get_runtime_exists,
             cmd::get_runtime_logs,
+            cmd::get_core_logs,
             cmd::invoke_uwp_tool,
             cmd::copy_clash_env,
             cmd::get_proxies,
@@ -345,13 +362,22 @@ pub fn run() {
             // verge
             cmd::get_verge_config,
             cmd::patch_verge_config,
+            cmd::reset_local_api_token,
             cmd::test_delay,
             cmd::get_app_dir,
+            cmd::get_degraded_state,
             cmd::copy_icon_file,
             cmd::download_icon_cache,
             cmd::open_devtools,
             cmd::exit_app,
             cmd::get_network_interfaces_info,
+            cmd::get_network_rules,
+            cmd::save_network_rules,
+            cmd::set_network_rules_paused,
+            cmd::evaluate_network_rules,
+            cmd::get_schedules,
+            cmd::preview_schedule,
+            cmd::run_schedule,
             // profile
             cmd::get_profiles,
             cmd::enhance_profiles,
@@ -371,21 +397,30 @@ pub fn run() {
             // script validation
             cmd::script_validate_notice,
             cmd::validate_script_file,
+            cmd::test_profile_script,
             // clash api
             cmd::clash_api_get_proxy_delay,
+            cmd::get_latency_stats,
+            cmd::batch_test_delay,
+            cmd::cancel_batch_test_delay,
             // backup
-            cmd::create_webdav_backup,
+            cmd::create_backup,
             cmd::save_webdav_config,
-            cmd::list_webdav_backup,
-            cmd::delete_webdav_backup,
-            cmd::restore_webdav_backup,
+            cmd::save_backup_storage,
+            cmd::list_backup,
+            cmd::delete_backup,
+            cmd::restore_backup,
             // export diagnostic info for issue reporting
             cmd::export_diagnostic_info,
+            cmd::run_diagnostics,
             // get system info for display
             cmd::get_system_info,
             // media unlock checker
             cmd::get_unlock_items,
             cmd::check_media_unlock,
+            cmd::check_unlock_matrix,
+            cmd::get_unlock_matrix,
+            cmd::find_unlock_nodes,
             // light-weight model
             cmd::entry_lightweight_mode,
         ]);
@@ -0,0 +1,193 @@
+//! 诊断包：系统信息、日志、配置和诊断结果打包为 zip，写入前统一脱敏
+
+use super::{probes::Probes, DiagnosticSettings};
+use crate::{
+    config::Config,
+    core::{core_log, handle},
+    module::{diagnostics, sysinfo::PlatformSpecification},
+    utils::{
+        dirs,
+        redact::{self, RedactOptions},
+    },
+};
+use anyhow::Result;
+use serde_yaml::Value;
+use std::{
+    fs,
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use zip::write::SimpleFileOptions;
+
+/// 默认收集的日志总量
+pub const DEFAULT_LOG_LIMIT_MB: u64 = 10;
+
+#[derive(Debug, Clone)]
+pub struct BundleOptions {
+    /// 同时隐藏代理服务器地址
+    pub redact_servers: bool,
+    /// 每类日志最多收集的字节数
+    pub log_limit: u64,
+}
+
+impl Default for BundleOptions {
+    fn default() -> Self {
+        Self {
+            redact_servers: false,
+            log_limit: DEFAULT_LOG_LIMIT_MB * 1024 * 1024,
+        }
+    }
+}
+
+/// 按修改时间从新到旧选取日志，超出预算的文件只保留末尾部分
+fn collect_logs(dir: &Path, budget: u64) -> Vec<(String, String)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+    let mut files = entries
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|ty| ty.is_file()))
+        .filter_map(|entry| {
+            let meta = entry.metadata().ok()?;
+            let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            Some((modified, meta.len(), entry.path()))
+        })
+        .collect::<Vec<_>>();
+    files.sort_by(|a, b| b.0.cmp(&a.0));
+
+    let mut remaining = budget;
+    let mut logs = vec![];
+    for (_, len, path) in files {
+        if remaining == 0 {
+            break;
+        }
+        let take = len.min(remaining);
+        let Ok(content) = read_tail(&path, take) else {
+            continue;
+        };
+        remaining -= take;
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        logs.push((name, content));
+    }
+    logs
+}
+
+fn read_tail(path: &Path, len: u64) -> Result<String> {
+    let mut file = fs::File::open(path)?;
+    let size = file.metadata()?.len();
+    file.seek(SeekFrom::Start(size.saturating_sub(len)))?;
+    let mut buf = Vec::with_capacity(len as usize);
+    file.take(len).read_to_end(&mut buf)?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+fn read_yaml(path: &Path) -> Option<Value> {
+    serde_yaml::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+/// 生成诊断包，返回文件路径
+pub async fn export(probes: &dyn Probes, options: &BundleOptions) -> Result<PathBuf> {
+    let runtime = Config::runtime().latest().clone();
+    let runtime_config = runtime.config.clone().map(Value::Mapping);
+
+    // 日志中的服务器地址只能通过运行配置里的代理列表识别
+    let mut redact_options = RedactOptions {
+        servers: options.redact_servers,
+        ..Default::default()
+    };
+    if let Some(config) = &runtime_config {
+        redact_options = redact_options.with_servers_from(config);
+    }
+    let to_yaml = |mut value: Value| -> Result<String> {
+        redact::redact_yaml(&mut value, &redact_options);
+        Ok(serde_yaml::to_string(&value)?)
+    };
+
+    let sysinfo = PlatformSpecification::new_async().await;
+    let app_version = handle::Handle::global()
+        .app_handle()
+        .map(|app| app.package_info().version.to_string())
+        .unwrap_or_default();
+    let versions = serde_json::json!({
+        "app": app_version,
+        "core": probes.core_version().await.unwrap_or_else(|err| format!("error: {err}")),
+        "service": probes.service_version().await.unwrap_or_else(|err| format!("error: {err}")),
+        "running_mode": probes.running_mode().await,
+    });
+    let mut report = match diagnostics::last_report() {
+        Some(report) => report,
+        None => diagnostics::run(probes, &DiagnosticSettings::from_config()).await,
+    };
+
+    // 检查详情可能带有 PAC 地址、代理地址等信息
+    for result in report.results.iter_mut() {
+        result.detail = redact::redact_text(&result.detail, &redact_options);
+        if let Some(hint) = result.hint.as_mut() {
+            *hint = redact::redact_text(hint, &redact_options);
+        }
+    }
+
+    let mut chain_logs = runtime.chain_logs;
+    for logs in chain_logs.values_mut() {
+        for (_, message) in logs.iter_mut() {
+            *message = redact::redact_text(message, &redact_options);
+        }
+    }
+
+    let now = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    let dir = ::dirs::download_dir()
+        .filter(|dir| dir.is_dir())
+        .map_or_else(dirs::app_home_dir, Ok)?;
+    let path = dir.join(format!("koala-clash-diagnostics-{now}.zip"));
+
+    let mut zip = zip::ZipWriter::new(fs::File::create(&path)?);
+    let options_zip = SimpleFileOptions::default();
+
+    let mut add = |name: &str, content: &str| -> Result<()> {
+        zip.start_file(name, options_zip)?;
+        zip.write_all(content.as_bytes())?;
+        Ok(())
+    };
+    add(
+        "system_info.txt",
+        &redact::redact_text(&format!("{sysinfo:?}"), &redact_options),
+    )?;
+    add("versions.json", &serde_json::to_string_pretty(&versions)?)?;
+    add("diagnostics.json", &serde_json::to_string_pretty(&report)?)?;
+    add(
+        "chain_logs.json",
+        &serde_json::to_string_pretty(&chain_logs)?,
+    )?;
+
+    for (name, path) in [
+        ("config/verge.yaml", dirs::verge_path()?),
+        ("config/profiles.yaml", dirs::profiles_path()?),
+    ] {
+        if let Some(value) = read_yaml(&path) {
+            add(name, &to_yaml(value)?)?;
+        }
+    }
+    if let Some(config) = runtime_config {
+        add("config/runtime.yaml", &to_yaml(config)?)?;
+    }
+
+    let logs_dir = dirs::app_logs_dir()?;
+    for (prefix, dir) in [
+        ("logs/app", logs_dir.clone()),
+        ("logs/core", core_log::core_logs_dir()?),
+        ("logs/service", logs_dir.join("service")),
+    ] {
+        for (name, content) in collect_logs(&dir, options.log_limit) {
+            add(
+                &format!("{prefix}/{name}"),
+                &redact::redact_text(&content, &redact_options),
+            )?;
+        }
+    }
+    zip.finish()?;
+    Ok(path)
+}
@@ -0,0 +1,745 @@
+//! 连接诊断：逐项检查内核、端口、DNS、直连与代理访问、TLS、系统代理、TUN 和服务，给出处理建议
+
+pub mod bundle;
+pub mod probes;
+
+use crate::config::{Config, IVerge};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use probes::Probes;
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::{future::Future, time::Instant};
+
+const DEFAULT_TARGETS: [&str; 2] = [
+    "https://www.gstatic.com/generate_204",
+    "https://cp.cloudflare.com/generate_204",
+];
+/// TLS 握手超过这个时间视为过慢
+const SLOW_TLS_MS: u64 = 1500;
+
+static LAST_REPORT: Lazy<Mutex<Option<DiagnosticReport>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Skip,
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckResult {
+    /// 检查项标识，例如 `port:mixed-port`、`fetch:https://...`
+    pub id: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// 处理建议
+    pub hint: Option<String>,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnosticReport {
+    pub created_at: i64,
+    /// 所有检查项中最差的状态
+    pub status: CheckStatus,
+    pub results: Vec<CheckResult>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FetchResult {
+    pub status: u16,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TlsTiming {
+    pub connect_ms: u64,
+    pub handshake_ms: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SystemProxyState {
+    pub enable: bool,
+    pub host: String,
+    pub port: u16,
+    pub pac_enable: bool,
+    pub pac_url: String,
+}
+
+/// 诊断时对照的配置
+#[derive(Debug, Clone, Default)]
+pub struct DiagnosticSettings {
+    pub targets: Vec<String>,
+    /// 运行配置中开启的端口
+    pub ports: Vec<(String, u16)>,
+    /// 代理访问使用的地址，例如 `http://127.0.0.1:7897`
+    pub proxy: Option<String>,
+    /// 期望的系统代理状态，未开启系统代理时为空
+    pub expected_proxy: Option<SystemProxyState>,
+    pub tun_enabled: bool,
+    pub tun_device: Option<String>,
+}
+
+fn port_of(config: &Mapping, key: &str) -> Option<u16> {
+    match config.get(key)? {
+        Value::Number(port) => port.as_u64().and_then(|port| u16::try_from(port).ok()),
+        Value::String(port) => port.parse().ok(),
+        _ => None,
+    }
+    .filter(|port| *port > 0)
+}
+
+/// 运行配置中的监听端口，包括控制器端口
+pub fn listening_ports(config: &Mapping) -> Vec<(String, u16)> {
+    let mut ports = [
+        "mixed-port",
+        "port",
+        "socks-port",
+        "redir-port",
+        "tproxy-port",
+    ]
+    .iter()
+    .filter_map(|key| Some((key.to_string(), port_of(config, key)?)))
+    .collect::<Vec<_>>();
+    let controller = config
+        .get("external-controller")
+        .and_then(Value::as_str)
+        .and_then(|addr| addr.rsplit_once(':')?.1.parse::<u16>().ok())
+        .filter(|port| *port > 0);
+    if let Some(port) = controller {
+        ports.push(("external-controller".into(), port));
+    }
+    ports
+}
+
+impl DiagnosticSettings {
+    pub fn from_config() -> Self {
+        let runtime = Config::runtime()
+            .latest()
+            .config
+            .clone()
+            .unwrap_or_default();
+        let verge = Config::verge().latest().clone();
+        let mixed_port = port_of(&runtime, "mixed-port");
+        let host = verge
+            .proxy_host
+            .clone()
+            .unwrap_or_else(|| "127.0.0.1".into());
+
+        let expected_proxy = verge.enable_system_proxy.unwrap_or(false).then(|| {
+            let port = verge
+                .verge_mixed_port
+                .or(mixed_port)
+                .unwrap_or_else(|| Config::clash().latest().get_mixed_port());
+            let pac_enable = verge.proxy_auto_config.unwrap_or(false);
+            SystemProxyState {
+                enable: !pac_enable,
+                host: host.clone(),
+                port,
+                pac_enable,
+                pac_url: format!(
+                    "http://{host}:{}/commands/pac",
+                    IVerge::get_singleton_port()
+                ),
+            }
+        });
+
+        let tun = runtime.get("tun").and_then(Value::as_mapping);
+        Self {
+            targets: verge
+                .diagnostic_targets
+                .clone()
+                .filter(|targets| !targets.is_empty())
+                .unwrap_or_else(|| DEFAULT_TARGETS.map(String::from).to_vec()),
+            ports: listening_ports(&runtime),
+            proxy: mixed_port.map(|port| format!("http://127.0.0.1:{port}")),
+            expected_proxy,
+            tun_enabled: verge.enable_tun_mode.unwrap_or(false),
+            tun_device: tun
+                .and_then(|tun| tun.get("device"))
+                .and_then(Value::as_str)
+                .map(String::from),
+        }
+    }
+}
+
+struct Runner {
+    results: Vec<CheckResult>,
+}
+
+impl Runner {
+    async fn check<F>(&mut self, id: impl Into<String>, check: F) -> CheckStatus
+    where
+        F: Future<Output = (CheckStatus, String, Option<&'static str>)>,
+    {
+        let start = Instant::now();
+        let (status, detail, hint) = check.await;
+        self.results.push(CheckResult {
+            id: id.into(),
+            status,
+            detail,
+            hint: hint.map(String::from),
+            elapsed_ms: start.elapsed().as_millis() as u64,
+        });
+        status
+    }
+
+    fn skip(&mut self, id: impl Into<String>, reason: &str) {
+        self.results.push(CheckResult {
+            id: id.into(),
+            status: CheckStatus::Skip,
+            detail: reason.into(),
+            hint: None,
+            elapsed_ms: 0,
+        });
+    }
+}
+
+fn host_of(target: &str) -> Option<String> {
+    url::Url::parse(target).ok()?.host_str().map(String::from)
+}
+
+fn format_ips(ips: &[std::net::IpAddr]) -> String {
+    ips.iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// 按顺序执行所有检查，内核不可用时跳过依赖内核的检查
+pub async fn run(probes: &dyn Probes, settings: &DiagnosticSettings) -> DiagnosticReport {
+    let mut runner = Runner { results: vec![] };
+
+    let core_ok = runner
+        .check("core", async {
+            match probes.core_version().await {
+                Ok(version) => (CheckStatus::Pass, format!("core {version}"), None),
+                Err(err) => (
+                    CheckStatus::Fail,
+                    format!("core is unreachable: {err}"),
+                    Some("Restart the core from settings; if it keeps failing, check the core log and the external-controller address"),
+                ),
+            }
+        })
+        .await
+        == CheckStatus::Pass;
+
+    for (name, port) in &settings.ports {
+        runner
+            .check(format!("port:{name}"), async {
+                if probes.port_open(*port).await {
+                    (CheckStatus::Pass, format!("{name} {port} is listening"), None)
+                } else {
+                    (
+                        CheckStatus::Fail,
+                        format!("{name} {port} is not listening"),
+                        Some("The port may be taken by another program; pick a different port in settings and restart the core"),
+                    )
+                }
+            })
+            .await;
+    }
+
+    for host in settings.targets.iter().filter_map(|target| host_of(target)) {
+        let id = format!("dns:{host}");
+        if !core_ok {
+            runner.skip(id, "core is unreachable");
+            continue;
+        }
+        runner
+            .check(id, async {
+                let core = probes.resolve_via_core(&host).await;
+                let system = probes.resolve_via_system(&host).await;
+                match (core, system) {
+                    (Ok(core), Ok(system)) if !core.is_empty() => (
+                        CheckStatus::Pass,
+                        format!("core: {}; system: {}", format_ips(&core), format_ips(&system)),
+                        None,
+                    ),
+                    (Ok(_), Ok(system)) => (
+                        CheckStatus::Fail,
+                        format!("core returned no records; system: {}", format_ips(&system)),
+                        Some("Check the nameservers in the DNS settings; the upstream servers may be blocked"),
+                    ),
+                    (Ok(core), Err(err)) => (
+                        CheckStatus::Warn,
+                        format!("core: {}; system resolver failed: {err}", format_ips(&core)),
+                        Some("The system resolver is broken; enable TUN with DNS hijacking or fix the system DNS servers"),
+                    ),
+                    (Err(err), Ok(system)) => (
+                        CheckStatus::Fail,
+                        format!("core resolver failed: {err}; system: {}", format_ips(&system)),
+                        Some("Check the nameservers in the DNS settings; the upstream servers may be blocked"),
+                    ),
+                    (Err(core), Err(system)) => (
+                        CheckStatus::Fail,
+                        format!("core resolver failed: {core}; system resolver failed: {system}"),
+                        Some("Name resolution fails everywhere; check that the network is connected"),
+                    ),
+                }
+            })
+            .await;
+    }
+
+    for target in &settings.targets {
+        runner
+            .check(format!("fetch:{target}"), async {
+                let direct = probes.fetch(target, None).await;
+                let proxied = match (&settings.proxy, core_ok) {
+                    (Some(proxy), true) => Some(probes.fetch(target, Some(proxy)).await),
+                    _ => None,
+                };
+                let describe = |result: &anyhow::Result<FetchResult>| match result {
+                    Ok(fetch) => format!("HTTP {} in {}ms", fetch.status, fetch.elapsed_ms),
+                    Err(err) => format!("failed: {err}"),
+                };
+                let detail = match &proxied {
+                    Some(proxied) => {
+                        format!("direct {}; proxied {}", describe(&direct), describe(proxied))
+                    }
+                    None => format!("direct {}; proxied skipped", describe(&direct)),
+                };
+                match (direct.is_ok(), proxied.as_ref().map(|result| result.is_ok())) {
+                    (_, Some(true)) => (CheckStatus::Pass, detail, None),
+                    (true, Some(false)) => (
+                        CheckStatus::Fail,
+                        detail,
+                        Some("The selected node does not work; switch to another node or update the subscription"),
+                    ),
+                    (false, Some(false)) => (
+                        CheckStatus::Fail,
+                        detail,
+                        Some("Neither direct nor proxied access works; check the network connection"),
+                    ),
+                    (true, None) => (
+                        CheckStatus::Warn,
+                        detail,
+                        Some("Proxied access could not be tested because the core or mixed port is unavailable"),
+                    ),
+                    (false, None) => (
+                        CheckStatus::Fail,
+                        detail,
+                        Some("Direct access fails and the core is unavailable; check the network connection"),
+                    ),
+                }
+            })
+            .await;
+    }
+
+    for target in settings
+        .targets
+        .iter()
+        .filter(|target| target.starts_with("https://"))
+    {
+        runner
+            .check(format!("tls:{target}"), async {
+                match probes.tls_timing(target).await {
+                    Ok(timing) if timing.handshake_ms > SLOW_TLS_MS => (
+                        CheckStatus::Warn,
+                        format!("connect {}ms, handshake {}ms", timing.connect_ms, timing.handshake_ms),
+                        Some("TLS is slow on the direct path; the site may be throttled, route it through the proxy"),
+                    ),
+                    Ok(timing) => (
+                        CheckStatus::Pass,
+                        format!("connect {}ms, handshake {}ms", timing.connect_ms, timing.handshake_ms),
+                        None,
+                    ),
+                    Err(err) => (
+                        CheckStatus::Warn,
+                        format!("direct TLS failed: {err}"),
+                        Some("The site is unreachable without the proxy; make sure it matches a proxy rule"),
+                    ),
+                }
+            })
+            .await;
+    }
+
+    runner
+        .check("sysproxy", async {
+            let current = probes.system_proxy().await;
+            match &settings.expected_proxy {
+                Some(expected) if expected.pac_enable => {
+                    if current.pac_enable && current.pac_url == expected.pac_url {
+                        (CheckStatus::Pass, format!("PAC {}", current.pac_url), None)
+                    } else {
+                        (
+                            CheckStatus::Fail,
+                            format!("expected PAC {}, system has {}", expected.pac_url, describe_proxy(&current)),
+                            Some("Another program changed the system proxy; toggle the system proxy again or enable proxy guard"),
+                        )
+                    }
+                }
+                Some(expected) => {
+                    if current.enable && current.host == expected.host && current.port == expected.port {
+                        (CheckStatus::Pass, format!("proxy {}:{}", current.host, current.port), None)
+                    } else {
+                        (
+                            CheckStatus::Fail,
+                            format!("expected proxy {}:{}, system has {}", expected.host, expected.port, describe_proxy(&current)),
+                            Some("Another program changed the system proxy; toggle the system proxy again or enable proxy guard"),
+                        )
+                    }
+                }
+                None if current.enable || current.pac_enable => (
+                    CheckStatus::Warn,
+                    format!("system proxy is disabled in settings, system has {}", describe_proxy(&current)),
+                    Some("A leftover system proxy may break connectivity when the app is closed; clear it in the OS network settings"),
+                ),
+                None => (CheckStatus::Pass, "system proxy is disabled".into(), None),
+            }
+        })
+        .await;
+
+    if settings.tun_enabled {
+        runner
+            .check("tun", async {
+                if probes.tun_present(settings.tun_device.as_deref()).await {
+                    (CheckStatus::Pass, "TUN interface is up".into(), None)
+                } else {
+                    (
+                        CheckStatus::Fail,
+                        "TUN is enabled but no TUN interface was found".into(),
+                        Some("Install the service or grant the core administrator privileges, then toggle TUN again"),
+                    )
+                }
+            })
+            .await;
+    } else {
+        runner.skip("tun", "TUN is disabled");
+    }
+
+    runner
+        .check("service", async {
+            let mode = probes.running_mode().await;
+            match probes.service_version().await {
+                Ok(version) => (
+                    CheckStatus::Pass,
+                    format!("service {version}, core running as {mode}"),
+                    None,
+                ),
+                Err(err) if settings.tun_enabled => (
+                    CheckStatus::Warn,
+                    format!("service unavailable: {err}; core running as {mode}"),
+                    Some("TUN needs the service or administrator privileges; install or reinstall the service"),
+                ),
+                Err(err) => (
+                    CheckStatus::Pass,
+                    format!("service unavailable: {err}; core running as {mode}"),
+                    None,
+                ),
+            }
+        })
+        .await;
+
+    let status = runner
+        .results
+        .iter()
+        .map(|result| result.status)
+        .max()
+        .unwrap_or(CheckStatus::Pass);
+    let report = DiagnosticReport {
+        created_at: chrono::Local::now().timestamp(),
+        status,
+        results: runner.results,
+    };
+    *LAST_REPORT.lock() = Some(report.clone());
+    report
+}
+
+fn describe_proxy(state: &SystemProxyState) -> String {
+    match (state.enable, state.pac_enable) {
+        (true, _) => format!("proxy {}:{}", state.host, state.port),
+        (false, true) => format!("PAC {}", state.pac_url),
+        (false, false) => "no proxy".into(),
+    }
+}
+
+/// 最近一次诊断结果
+pub fn last_report() -> Option<DiagnosticReport> {
+    LAST_REPORT.lock().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{probes::Probes, *};
+    use anyhow::{anyhow, Result};
+    use async_trait::async_trait;
+    use std::net::IpAddr;
+
+    #[derive(Default)]
+    struct MockProbes {
+        core_down: bool,
+        closed_port: Option<u16>,
+        system_dns_down: bool,
+        direct_blocked: bool,
+        node_down: bool,
+        slow_tls: bool,
+        system_proxy: SystemProxyState,
+        tun_present: bool,
+    }
+
+    #[async_trait]
+    impl Probes for MockProbes {
+        async fn core_version(&self) -> Result<String> {
+            match self.core_down {
+                true => Err(anyhow!("connection refused")),
+                false => Ok("v1.19.0".into()),
+            }
+        }
+
+        async fn port_open(&self, port: u16) -> bool {
+            self.closed_port != Some(port)
+        }
+
+        async fn resolve_via_core(&self, _host: &str) -> Result<Vec<IpAddr>> {
+            Ok(vec!["198.18.0.5".parse()?])
+        }
+
+        async fn resolve_via_system(&self, _host: &str) -> Result<Vec<IpAddr>> {
+            match self.system_dns_down {
+                true => Err(anyhow!("timed out")),
+                false => Ok(vec!["142.250.1.1".parse()?]),
+            }
+        }
+
+        async fn fetch(&self, _url: &str, proxy: Option<&str>) -> Result<FetchResult> {
+            let down = match proxy {
+                Some(_) => self.node_down,
+                None => self.direct_blocked,
+            };
+            match down {
+                true => Err(anyhow!("connection reset")),
+                false => Ok(FetchResult {
+                    status: 204,
+                    elapsed_ms: 80,
+                }),
+            }
+        }
+
+        async fn tls_timing(&self, _url: &str) -> Result<TlsTiming> {
+            Ok(TlsTiming {
+                connect_ms: 20,
+                handshake_ms: if self.slow_tls { 3000 } else { 60 },
+            })
+        }
+
+        async fn system_proxy(&self) -> SystemProxyState {
+            self.system_proxy.clone()
+        }
+
+        async fn tun_present(&self, _device: Option<&str>) -> bool {
+            self.tun_present
+        }
+
+        async fn service_version(&self) -> Result<String> {
+            Err(anyhow!("not installed"))
+        }
+
+        async fn running_mode(&self) -> String {
+            "Sidecar".into()
+        }
+    }
+
+    fn proxy_state(host: &str, port: u16) -> SystemProxyState {
+        SystemProxyState {
+            enable: true,
+            host: host.into(),
+            port,
+            ..Default::default()
+        }
+    }
+
+    fn settings() -> DiagnosticSettings {
+        DiagnosticSettings {
+            targets: vec!["https://example.com/generate_204".into()],
+            ports: vec![
+                ("mixed-port".into(), 7897),
+                ("external-controller".into(), 9097),
+            ],
+            proxy: Some("http://127.0.0.1:7897".into()),
+            expected_proxy: Some(proxy_state("127.0.0.1", 7897)),
+            tun_enabled: false,
+            tun_device: None,
+        }
+    }
+
+    async fn status_of(
+        probes: MockProbes,
+        settings: DiagnosticSettings,
+    ) -> Vec<(String, CheckStatus)> {
+        run(&probes, &settings)
+            .await
+            .results
+            .into_iter()
+            .map(|result| (result.id, result.status))
+            .collect()
+    }
+
+    fn find(results: &[(String, CheckStatus)], id: &str) -> CheckStatus {
+        results.iter().find(|(item, _)| item == id).unwrap().1
+    }
+
+    #[tokio::test]
+    async fn test_healthy() {
+        let probes = MockProbes {
+            system_proxy: proxy_state("127.0.0.1", 7897),
+            ..Default::default()
+        };
+        let report = run(&probes, &settings()).await;
+        assert_eq!(report.status, CheckStatus::Pass);
+        assert_eq!(
+            report
+                .results
+                .iter()
+                .map(|result| result.id.as_str())
+                .collect::<Vec<_>>(),
+            [
+                "core",
+                "port:mixed-port",
+                "port:external-controller",
+                "dns:example.com",
+                "fetch:https://example.com/generate_204",
+                "tls:https://example.com/generate_204",
+                "sysproxy",
+                "tun",
+                "service"
+            ]
+        );
+        assert_eq!(report.results[7].status, CheckStatus::Skip);
+        assert!(last_report().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_failures_have_hints() {
+        let probes = MockProbes {
+            core_down: true,
+            closed_port: Some(7897),
+            ..Default::default()
+        };
+        let report = run(&probes, &settings()).await;
+        assert_eq!(report.status, CheckStatus::Fail);
+        let get = |id: &str| {
+            report
+                .results
+                .iter()
+                .find(|result| result.id == id)
+                .unwrap()
+        };
+        assert_eq!(get("core").status, CheckStatus::Fail);
+        assert_eq!(get("port:mixed-port").status, CheckStatus::Fail);
+        assert_eq!(get("port:external-controller").status, CheckStatus::Pass);
+        assert_eq!(get("dns:example.com").status, CheckStatus::Skip);
+        // 内核不可用时无法测试代理访问
+        assert_eq!(
+            get("fetch:https://example.com/generate_204").status,
+            CheckStatus::Warn
+        );
+        assert_eq!(get("sysproxy").status, CheckStatus::Fail);
+        assert!(report
+            .results
+            .iter()
+            .filter(|result| result.status >= CheckStatus::Warn)
+            .all(|result| result.hint.is_some()));
+    }
+
+    #[tokio::test]
+    async fn test_network_paths() {
+        let results = status_of(
+            MockProbes {
+                direct_blocked: true,
+                system_dns_down: true,
+                slow_tls: true,
+                system_proxy: proxy_state("127.0.0.1", 7897),
+                ..Default::default()
+            },
+            settings(),
+        )
+        .await;
+        assert_eq!(find(&results, "dns:example.com"), CheckStatus::Warn);
+        // 直连被阻断但代理可用是正常情况
+        assert_eq!(
+            find(&results, "fetch:https://example.com/generate_204"),
+            CheckStatus::Pass
+        );
+        assert_eq!(
+            find(&results, "tls:https://example.com/generate_204"),
+            CheckStatus::Warn
+        );
+
+        let results = status_of(
+            MockProbes {
+                node_down: true,
+                ..Default::default()
+            },
+            settings(),
+        )
+        .await;
+        assert_eq!(
+            find(&results, "fetch:https://example.com/generate_204"),
+            CheckStatus::Fail
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sysproxy_and_tun() {
+        let pac = DiagnosticSettings {
+            expected_proxy: Some(SystemProxyState {
+                pac_enable: true,
+                pac_url: "http://127.0.0.1:33331/commands/pac".into(),
+                ..Default::default()
+            }),
+            tun_enabled: true,
+            ..settings()
+        };
+        let results = status_of(
+            MockProbes {
+                system_proxy: SystemProxyState {
+                    pac_enable: true,
+                    pac_url: "http://127.0.0.1:33331/commands/pac".into(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            pac.clone(),
+        )
+        .await;
+        assert_eq!(find(&results, "sysproxy"), CheckStatus::Pass);
+        assert_eq!(find(&results, "tun"), CheckStatus::Fail);
+        assert_eq!(find(&results, "service"), CheckStatus::Warn);
+
+        let disabled = DiagnosticSettings {
+            expected_proxy: None,
+            ..settings()
+        };
+        let results = status_of(
+            MockProbes {
+                system_proxy: proxy_state("127.0.0.1", 7897),
+                tun_present: true,
+                ..Default::default()
+            },
+            disabled,
+        )
+        .await;
+        assert_eq!(find(&results, "sysproxy"), CheckStatus::Warn);
+        assert_eq!(find(&results, "service"), CheckStatus::Pass);
+    }
+
+    #[test]
+    fn test_listening_ports() {
+        let config: Mapping = serde_yaml::from_str(
+            "mixed-port: 7897\nport: 0\nsocks-port: '7898'\nexternal-controller: 127.0.0.1:9097\n",
+        )
+        .unwrap();
+        assert_eq!(
+            listening_ports(&config),
+            [
+                ("mixed-port".to_string(), 7897),
+                ("socks-port".to_string(), 7898),
+                ("external-controller".to_string(), 9097)
+            ]
+        );
+    }
+}
@@ -0,0 +1,289 @@
+use super::{FetchResult, SystemProxyState, TlsTiming};
+use crate::{
+    config::Config,
+    core::{async_proxy_query::AsyncProxyQuery, service, CoreManager},
+};
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use network_interface::{NetworkInterface, NetworkInterfaceConfig};
+use reqwest::{Client, Proxy};
+use serde_json::Value;
+use std::{
+    net::IpAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio_rustls::{
+    rustls::{crypto::ring, pki_types::ServerName, ClientConfig, RootCertStore},
+    TlsConnector,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(8);
+
+/// 诊断用到的所有探测，测试时可以替换为本地替身
+#[async_trait]
+pub trait Probes: Send + Sync {
+    /// 内核 `/version`
+    async fn core_version(&self) -> Result<String>;
+
+    /// 本机端口是否在监听
+    async fn port_open(&self, port: u16) -> bool;
+
+    async fn resolve_via_core(&self, host: &str) -> Result<Vec<IpAddr>>;
+
+    async fn resolve_via_system(&self, host: &str) -> Result<Vec<IpAddr>>;
+
+    /// `proxy` 为空时直连
+    async fn fetch(&self, url: &str, proxy: Option<&str>) -> Result<FetchResult>;
+
+    async fn tls_timing(&self, url: &str) -> Result<TlsTiming>;
+
+    async fn system_proxy(&self) -> SystemProxyState;
+
+    /// TUN 网卡是否存在，`device` 为空时按 fake-ip 地址查找
+    async fn tun_present(&self, device: Option<&str>) -> bool;
+
+    /// 服务版本
+    async fn service_version(&self) -> Result<String>;
+
+    async fn running_mode(&self) -> String;
+}
+
+/// 真实环境的探测
+pub struct LiveProbes {
+    /// 内核控制器地址，例如 `http://127.0.0.1:9097`
+    pub controller: String,
+    pub secret: Option<String>,
+}
+
+impl LiveProbes {
+    pub fn from_config() -> Self {
+        let info = Config::clash().latest().get_client_info();
+        Self {
+            controller: format!("http://{}", info.server),
+            secret: info.secret.filter(|secret| !secret.is_empty()),
+        }
+    }
+
+    async fn controller_get(&self, path: &str) -> Result<Value> {
+        let mut request = Client::builder()
+            .no_proxy()
+            .timeout(TIMEOUT)
+            .build()?
+            .get(format!("{}{path}", self.controller));
+        if let Some(secret) = &self.secret {
+            request = request.bearer_auth(secret);
+        }
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            bail!("controller returned {}", response.status());
+        }
+        Ok(response.json().await?)
+    }
+}
+
+fn client(proxy: Option<&str>) -> Result<Client> {
+    let builder = Client::builder()
+        .timeout(TIMEOUT)
+        .pool_max_idle_per_host(0)
+        .redirect(reqwest::redirect::Policy::none());
+    let builder = match proxy {
+        Some(proxy) => builder.proxy(Proxy::all(proxy)?),
+        None => builder.no_proxy(),
+    };
+    Ok(builder.build()?)
+}
+
+fn tls_connector() -> Result<TlsConnector> {
+    let roots = RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(TlsConnector::from(Arc::new(config)))
+}
+
+#[async_trait]
+impl Probes for LiveProbes {
+    async fn core_version(&self) -> Result<String> {
+        let body = self.controller_get("/version").await?;
+        body.get("version")
+            .and_then(Value::as_str)
+            .map(String::from)
+            .ok_or(anyhow!("unexpected /version response"))
+    }
+
+    async fn port_open(&self, port: u16) -> bool {
+        matches!(
+            tokio::time::timeout(
+                Duration::from_secs(2),
+                tokio::net::TcpStream::connect(("127.0.0.1", port))
+            )
+            .await,
+            Ok(Ok(_))
+        )
+    }
+
+    async fn resolve_via_core(&self, host: &str) -> Result<Vec<IpAddr>> {
+        let host = url::form_urlencoded::byte_serialize(host.as_bytes()).collect::<String>();
+        let body = self
+            .controller_get(&format!("/dns/query?name={host}&type=A"))
+            .await?;
+        Ok(body
+            .get("Answer")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|answer| answer.get("data")?.as_str()?.parse().ok())
+            .collect())
+    }
+
+    async fn resolve_via_system(&self, host: &str) -> Result<Vec<IpAddr>> {
+        let addrs = tokio::time::timeout(TIMEOUT, tokio::net::lookup_host((host, 0)))
+            .await
+            .context("timed out")??;
+        Ok(addrs.map(|addr| addr.ip()).collect())
+    }
+
+    async fn fetch(&self, url: &str, proxy: Option<&str>) -> Result<FetchResult> {
+        let start = Instant::now();
+        let response = client(proxy)?.get(url).send().await?;
+        if response.status().is_server_error() {
+            bail!("server returned {}", response.status());
+        }
+        Ok(FetchResult {
+            status: response.status().as_u16(),
+            elapsed_ms: start.elapsed().as_millis() as u64,
+        })
+    }
+
+    /// 直连目标，分别计时 TCP 建连和在该连接上的 TLS 握手
+    async fn tls_timing(&self, url: &str) -> Result<TlsTiming> {
+        let parsed = url::Url::parse(url)?;
+        let host = parsed.host_str().ok_or(anyhow!("missing host"))?;
+        let port = parsed.port_or_known_default().unwrap_or(443);
+
+        let server_name = ServerName::try_from(host.to_string())?;
+        let connector = tls_connector()?;
+
+        let start = Instant::now();
+        let stream = tokio::time::timeout(TIMEOUT, tokio::net::TcpStream::connect((host, port)))
+            .await
+            .context("tcp connect timed out")??;
+        let connect_ms = start.elapsed().as_millis() as u64;
+
+        let start = Instant::now();
+        tokio::time::timeout(TIMEOUT, connector.connect(server_name, stream))
+            .await
+            .context("tls handshake timed out")??;
+        Ok(TlsTiming {
+            connect_ms,
+            handshake_ms: start.elapsed().as_millis() as u64,
+        })
+    }
+
+    async fn system_proxy(&self) -> SystemProxyState {
+        let sys = AsyncProxyQuery::get_system_proxy().await;
+        let auto = AsyncProxyQuery::get_auto_proxy().await;
+        SystemProxyState {
+            enable: sys.enable,
+            host: sys.host,
+            port: sys.port,
+            pac_enable: auto.enable,
+            pac_url: auto.url,
+        }
+    }
+
+    async fn tun_present(&self, device: Option<&str>) -> bool {
+        NetworkInterface::show().unwrap_or_default().iter().any(|iface| {
+            device == Some(iface.name.as_str())
+                || iface.addr.iter().any(|addr| {
+                    matches!(addr.ip(), IpAddr::V4(v4) if v4.octets()[0] == 198 && v4.octets()[1] & 0xfe == 18)
+                })
+        })
+    }
+
+    async fn service_version(&self) -> Result<String> {
+        service::check_service_version().await
+    }
+
+    async fn running_mode(&self) -> String {
+        CoreManager::global().get_running_mode().await.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_server;
+    use std::net::SocketAddr;
+    use warp::{http::StatusCode, Filter};
+
+    /// 同时充当内核控制器、访问目标和 HTTP 代理的本地替身
+    fn stand_in() -> SocketAddr {
+        let version = warp::path("version")
+            .and(warp::header::<String>("authorization"))
+            .map(|auth: String| {
+                if auth == "Bearer secret" {
+                    warp::reply::with_status(
+                        warp::reply::json(&serde_json::json!({"version": "v1.19.0"})),
+                        StatusCode::OK,
+                    )
+                } else {
+                    warp::reply::with_status(
+                        warp::reply::json(&serde_json::json!({"message": "Unauthorized"})),
+                        StatusCode::UNAUTHORIZED,
+                    )
+                }
+            });
+        let dns = warp::path!("dns" / "query").map(|| {
+            warp::reply::json(&serde_json::json!({
+                "Status": 0,
+                "Answer": [{"name": "example.com.", "type": 1, "data": "198.18.0.5"}]
+            }))
+        });
+        let target = warp::path("generate_204")
+            .map(|| warp::reply::with_status(warp::reply(), StatusCode::NO_CONTENT));
+        let unavailable = warp::path("unavailable")
+            .map(|| warp::reply::with_status(warp::reply(), StatusCode::SERVICE_UNAVAILABLE));
+        test_server::serve(version.or(dns).or(target).or(unavailable))
+    }
+
+    #[tokio::test]
+    async fn test_live_probes_against_stand_in() {
+        let addr = stand_in();
+        let probes = LiveProbes {
+            controller: format!("http://{addr}"),
+            secret: Some("secret".into()),
+        };
+        assert_eq!(probes.core_version().await.unwrap(), "v1.19.0");
+        assert_eq!(
+            probes.resolve_via_core("example.com").await.unwrap(),
+            vec!["198.18.0.5".parse::<IpAddr>().unwrap()]
+        );
+        assert!(probes.port_open(addr.port()).await);
+
+        let url = format!("http://{addr}/generate_204");
+        assert_eq!(probes.fetch(&url, None).await.unwrap().status, 204);
+        // 替身按路径响应，也能充当普通 HTTP 代理
+        let proxy = format!("http://{addr}");
+        let proxied = probes
+            .fetch("http://example.invalid/generate_204", Some(&proxy))
+            .await
+            .unwrap();
+        assert_eq!(proxied.status, 204);
+        let unavailable = format!("http://{addr}/unavailable");
+        assert!(probes.fetch(&unavailable, None).await.is_err());
+        // 替身只说明文 HTTP，握手应当失败而不是算出一个时间
+        let https = format!("https://localhost:{}/", addr.port());
+        assert!(probes.tls_timing(&https).await.is_err());
+
+        let unauthorized = LiveProbes {
+            controller: format!("http://{addr}"),
+            secret: Some("wrong".into()),
+        };
+        assert!(unauthorized.core_version().await.is_err());
+    }
+}
@@ -0,0 +1,213 @@
+use crate::{
+    config::IVergeHealthCheck,
+    core::handle,
+    logging,
+    module::{
+        latency::{self, LatencyHistory},
+        mihomo::MihomoManager,
+    },
+    utils::logging::Type,
+};
+use anyhow::{anyhow, Result};
+use futures::{stream, StreamExt};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde_json::Value;
+use std::collections::HashMap;
+
+const DEFAULT_TIMEOUT: i32 = 5000;
+const DEFAULT_CONCURRENCY: usize = 8;
+const DEFAULT_FAILURE_THRESHOLD: u32 = 3;
+
+/// 代理组当前节点的连续失败次数，节点变化后重新计数
+static FAILURES: Lazy<Mutex<HashMap<String, (String, u32)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 对代理组进行一次健康检查，结果写入延迟历史，开启故障转移时切换节点
+pub async fn check_group(check: &IVergeHealthCheck) -> Result<()> {
+    let proxies = MihomoManager::global()
+        .get_refresh_proxies()
+        .await
+        .map_err(|err| anyhow!(err))?;
+    let group = proxies
+        .get("proxies")
+        .and_then(|proxies| proxies.get(&check.group))
+        .ok_or(anyhow!("group `{}` not found", check.group))?;
+    let members = group
+        .get("all")
+        .and_then(Value::as_array)
+        .map(|all| {
+            all.iter()
+                .filter_map(|name| name.as_str().map(str::to_string))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let now = group.get("now").and_then(Value::as_str).map(str::to_string);
+    let is_selector = group.get("type").and_then(Value::as_str) == Some("Selector");
+
+    let delays = if check.use_group_delay.unwrap_or(true) {
+        test_by_group(check, &members).await
+    } else {
+        test_by_nodes(check, &members).await
+    };
+
+    let Some(now) = now else {
+        return Ok(());
+    };
+    let healthy = delays.get(&now).is_some_and(Option::is_some);
+    let failures = {
+        let mut failures = FAILURES.lock();
+        let entry = failures
+            .entry(check.group.clone())
+            .or_insert_with(|| (now.clone(), 0));
+        if entry.0 != now || healthy {
+            *entry = (now.clone(), 0);
+        }
+        if !healthy {
+            entry.1 += 1;
+        }
+        entry.1
+    };
+    if healthy {
+        return Ok(());
+    }
+
+    let threshold = check.failure_threshold.unwrap_or(DEFAULT_FAILURE_THRESHOLD);
+    logging!(
+        warn,
+        Type::Core,
+        true,
+        "Health check: {} of group {} failed ({}/{})",
+        now,
+        check.group,
+        failures,
+        threshold
+    );
+    // 未开启故障转移时保留用户的手动选择
+    if !check.failover.unwrap_or(false) || !is_selector || failures < threshold {
+        return Ok(());
+    }
+
+    let Some(best) = best_member(&delays, &now) else {
+        logging!(
+            warn,
+            Type::Core,
+            true,
+            "Health check: no healthy member in group {}",
+            check.group
+        );
+        return Ok(());
+    };
+    MihomoManager::global()
+        .select_proxy(&check.group, &best)
+        .await
+        .map_err(|err| anyhow!(err))?;
+    FAILURES.lock().remove(&check.group);
+
+    logging!(
+        info,
+        Type::Core,
+        true,
+        "Health check: switched group {} from {} to {}",
+        check.group,
+        now,
+        best
+    );
+    handle::Handle::notice_message(
+        "health_check::failover",
+        format!("{}: {} -> {}", check.group, now, best),
+    );
+    handle::Handle::refresh_clash();
+    Ok(())
+}
+
+/// 使用代理组测速接口，结果中不存在的节点视为失败
+async fn test_by_group(
+    check: &IVergeHealthCheck,
+    members: &[String],
+) -> HashMap<String, Option<u32>> {
+    // 全部节点超时时接口会返回错误
+    let result = MihomoManager::global()
+        .test_group_delay(
+            &check.group,
+            check.url.clone(),
+            check.timeout.unwrap_or(DEFAULT_TIMEOUT),
+        )
+        .await
+        .unwrap_or_else(|err| {
+            logging!(
+                warn,
+                Type::Core,
+                true,
+                "Health check: group delay test of {} failed: {}",
+                check.group,
+                err
+            );
+            Value::Null
+        });
+
+    let history = LatencyHistory::global();
+    members
+        .iter()
+        .map(|name| {
+            let delay = result
+                .get(name)
+                .and_then(Value::as_u64)
+                .filter(|delay| *delay > 0)
+                .map(|delay| delay as u32);
+            history.record(name, delay);
+            (name.clone(), delay)
+        })
+        .collect()
+}
+
+/// 逐个节点测速，限制并发数
+async fn test_by_nodes(
+    check: &IVergeHealthCheck,
+    members: &[String],
+) -> HashMap<String, Option<u32>> {
+    let timeout = check.timeout.unwrap_or(DEFAULT_TIMEOUT);
+    let concurrency = check.concurrency.unwrap_or(DEFAULT_CONCURRENCY).max(1);
+
+    stream::iter(members.iter().cloned())
+        .map(|name| {
+            let url = check.url.clone();
+            async move {
+                let delay = latency::test_proxy_delay(&name, url, timeout)
+                    .await
+                    .ok()
+                    .and_then(|value| value.get("delay").and_then(Value::as_u64))
+                    .filter(|delay| *delay > 0)
+                    .map(|delay| delay as u32);
+                (name, delay)
+            }
+        })
+        .buffer_unordered(concurrency)
+        .collect()
+        .await
+}
+
+/// 延迟最低的可用节点
+fn best_member(delays: &HashMap<String, Option<u32>>, current: &str) -> Option<String> {
+    delays
+        .iter()
+        .filter(|(name, _)| name.as_str() != current)
+        .filter_map(|(name, delay)| delay.map(|delay| (name, delay)))
+        .min_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(b.0)))
+        .map(|(name, _)| name.clone())
+}
+
+#[test]
+fn test_best_member() {
+    let delays = HashMap::from([
+        ("a".to_string(), None),
+        ("b".to_string(), Some(300)),
+        ("c".to_string(), Some(120)),
+        ("d".to_string(), Some(50)),
+    ]);
+    assert_eq!(best_member(&delays, "d").as_deref(), Some("c"));
+    assert_eq!(best_member(&delays, "a").as_deref(), Some("d"));
+
+    let delays = HashMap::from([("a".to_string(), None), ("b".to_string(), None)]);
+    assert_eq!(best_member(&delays, "a"), None);
+}
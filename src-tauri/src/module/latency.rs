@@ -0,0 +1,404 @@
+use crate::{
+    config::Config,
+    logging,
+    module::mihomo::MihomoManager,
+    process::AsyncHandler,
+    utils::{dirs, logging::Type},
+};
+use anyhow::Result;
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Mapping;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fs,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+const HISTORY_FILE: &str = "latency_history.json";
+/// 每个节点最多保留的记录数
+const MAX_SAMPLES: usize = 500;
+/// 记录最长保留时间
+const MAX_AGE_SECS: i64 = 30 * 24 * 60 * 60;
+/// 写入文件前的合并等待时间
+const FLUSH_DELAY: Duration = Duration::from_secs(2);
+/// 找不到节点信息时使用名称作为标识
+const NAME_KEY_PREFIX: &str = "name:";
+
+/// 一次延迟测试的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LatencySample {
+    /// 秒级时间戳
+    #[serde(rename = "t")]
+    pub time: i64,
+    /// 延迟毫秒数，失败时为 None
+    #[serde(rename = "d")]
+    pub delay: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+struct NodeHistory {
+    name: String,
+    samples: VecDeque<LatencySample>,
+}
+
+/// 节点在时间窗口内的延迟统计
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LatencyStats {
+    pub key: String,
+    pub name: String,
+    pub total: usize,
+    pub min: Option<u32>,
+    pub avg: Option<u32>,
+    pub p50: Option<u32>,
+    pub p95: Option<u32>,
+    pub failure_rate: f64,
+    /// 按时间平均分桶的延迟，用于绘制迷你折线图，桶内全部失败或没有记录时为 None
+    pub series: Vec<Option<u32>>,
+}
+
+/// 订阅 uid -> 节点标识 -> 历史记录
+type HistoryMap = HashMap<String, HashMap<String, NodeHistory>>;
+
+/// 节点延迟历史
+pub struct LatencyHistory {
+    history: Mutex<HistoryMap>,
+    flush_scheduled: AtomicBool,
+}
+
+impl LatencyHistory {
+    pub fn global() -> &'static LatencyHistory {
+        static HISTORY: OnceCell<LatencyHistory> = OnceCell::new();
+        HISTORY.get_or_init(|| LatencyHistory {
+            history: Mutex::new(Self::load().unwrap_or_else(|err| {
+                logging!(
+                    warn,
+                    Type::Core,
+                    true,
+                    "Failed to load latency history: {}",
+                    err
+                );
+                HistoryMap::new()
+            })),
+            flush_scheduled: AtomicBool::new(false),
+        })
+    }
+
+    fn load() -> Result<HistoryMap> {
+        let path = dirs::app_home_dir()?.join(HISTORY_FILE);
+        if !path.exists() {
+            return Ok(HistoryMap::new());
+        }
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    fn save_file(&self) -> Result<()> {
+        let data = serde_json::to_vec(&*self.history.lock())?;
+        fs::write(dirs::app_home_dir()?.join(HISTORY_FILE), data)?;
+        Ok(())
+    }
+
+    /// 延迟合并写入，避免批量测试时频繁写文件
+    fn schedule_flush(&'static self) {
+        if self.flush_scheduled.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        AsyncHandler::spawn(move || async move {
+            tokio::time::sleep(FLUSH_DELAY).await;
+            self.flush_scheduled.store(false, Ordering::SeqCst);
+            if let Err(err) = self.save_file() {
+                logging!(
+                    error,
+                    Type::Core,
+                    true,
+                    "Failed to save latency history: {}",
+                    err
+                );
+            }
+        });
+    }
+
+    /// 记录当前订阅中节点的延迟测试结果
+    pub fn record(&'static self, name: &str, delay: Option<u32>) {
+        let uid = Config::profiles()
+            .latest()
+            .get_current()
+            .unwrap_or_default();
+        let key = current_node_key(name);
+        let sample = LatencySample {
+            time: chrono::Local::now().timestamp(),
+            delay,
+        };
+        insert_sample(&mut self.history.lock(), &uid, &key, name, sample);
+        self.schedule_flush();
+    }
+
+    /// 当前订阅中节点的延迟统计，`name` 为 None 时返回全部节点
+    pub fn stats(&self, name: Option<&str>, window_secs: i64, buckets: usize) -> Vec<LatencyStats> {
+        let uid = Config::profiles()
+            .latest()
+            .get_current()
+            .unwrap_or_default();
+        let key = name.map(current_node_key);
+        let now = chrono::Local::now().timestamp();
+
+        let history = self.history.lock();
+        let Some(nodes) = history.get(&uid) else {
+            return vec![];
+        };
+        nodes
+            .iter()
+            .filter(|(node_key, _)| key.as_ref().is_none_or(|key| key == *node_key))
+            .map(|(node_key, node)| compute_stats(node_key, node, now - window_secs, now, buckets))
+            .collect()
+    }
+
+    /// 删除订阅中已不存在的节点，`names` 为当前全部节点名称，为 None 时保留使用名称标识的节点
+    pub fn prune(&'static self, uid: &str, proxies: &[Mapping], names: Option<&HashSet<String>>) {
+        let valid = proxies.iter().filter_map(node_key).collect::<HashSet<_>>();
+        let removed = prune_nodes(&mut self.history.lock(), uid, &valid, names);
+        if removed > 0 {
+            logging!(
+                info,
+                Type::Core,
+                true,
+                "Pruned latency history of {} removed nodes in {}",
+                removed,
+                uid
+            );
+            self.schedule_flush();
+        }
+    }
+}
+
+/// 测试节点延迟并记录结果
+pub async fn test_proxy_delay(
+    name: &str,
+    test_url: Option<String>,
+    timeout: i32,
+) -> Result<serde_json::Value, String> {
+    let result = MihomoManager::global()
+        .test_proxy_delay(name, test_url, timeout)
+        .await;
+    let delay = result
+        .as_ref()
+        .ok()
+        .and_then(|value| value.get("delay"))
+        .and_then(|delay| delay.as_u64())
+        .filter(|delay| *delay > 0)
+        .map(|delay| delay as u32);
+    LatencyHistory::global().record(name, delay);
+    result
+}
+
+/// 节点标识 `server:port:type`，同名节点换了服务器后视为新节点
+pub fn node_key(proxy: &Mapping) -> Option<String> {
+    let server = proxy.get("server")?.as_str()?;
+    let port = match proxy.get("port")? {
+        serde_yaml::Value::Number(port) => port.to_string(),
+        serde_yaml::Value::String(port) => port.clone(),
+        _ => return None,
+    };
+    let kind = proxy.get("type")?.as_str()?;
+    Some(format!("{server}:{port}:{kind}"))
+}
+
+/// 在运行时配置中查找节点标识，订阅集合中的节点使用名称
+fn current_node_key(name: &str) -> String {
+    let runtime = Config::runtime();
+    let runtime = runtime.latest();
+    runtime
+        .config
+        .as_ref()
+        .and_then(|config| config.get("proxies"))
+        .and_then(|proxies| proxies.as_sequence())
+        .and_then(|proxies| {
+            proxies
+                .iter()
+                .filter_map(|proxy| proxy.as_mapping())
+                .find(|proxy| proxy.get("name").and_then(|n| n.as_str()) == Some(name))
+        })
+        .and_then(node_key)
+        .unwrap_or_else(|| format!("{NAME_KEY_PREFIX}{name}"))
+}
+
+fn insert_sample(
+    history: &mut HistoryMap,
+    uid: &str,
+    key: &str,
+    name: &str,
+    sample: LatencySample,
+) {
+    let node = history
+        .entry(uid.to_string())
+        .or_default()
+        .entry(key.to_string())
+        .or_default();
+    node.name = name.to_string();
+    node.samples.push_back(sample);
+    while node.samples.len() > MAX_SAMPLES
+        || node
+            .samples
+            .front()
+            .is_some_and(|first| sample.time - first.time > MAX_AGE_SECS)
+    {
+        node.samples.pop_front();
+    }
+}
+
+/// 返回删除的节点数，使用名称标识的节点按 `names` 判断
+fn prune_nodes(
+    history: &mut HistoryMap,
+    uid: &str,
+    valid: &HashSet<String>,
+    names: Option<&HashSet<String>>,
+) -> usize {
+    let Some(nodes) = history.get_mut(uid) else {
+        return 0;
+    };
+    let before = nodes.len();
+    nodes.retain(|key, _| match key.strip_prefix(NAME_KEY_PREFIX) {
+        Some(name) => names.is_none_or(|names| names.contains(name)),
+        None => valid.contains(key),
+    });
+    before - nodes.len()
+}
+
+fn percentile(sorted: &[u32], p: f64) -> Option<u32> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (p * (sorted.len() - 1) as f64).round() as usize;
+    sorted.get(rank).copied()
+}
+
+fn compute_stats(
+    key: &str,
+    node: &NodeHistory,
+    from: i64,
+    to: i64,
+    buckets: usize,
+) -> LatencyStats {
+    let samples = node
+        .samples
+        .iter()
+        .filter(|sample| sample.time >= from && sample.time <= to)
+        .collect::<Vec<_>>();
+    let mut delays = samples
+        .iter()
+        .filter_map(|sample| sample.delay)
+        .collect::<Vec<_>>();
+    delays.sort_unstable();
+
+    let total = samples.len();
+    let failures = total - delays.len();
+    let avg = (!delays.is_empty())
+        .then(|| (delays.iter().map(|d| *d as u64).sum::<u64>() / delays.len() as u64) as u32);
+
+    let mut series = vec![None; buckets];
+    if buckets > 0 && to > from {
+        let mut sums = vec![(0u64, 0u64); buckets];
+        for sample in &samples {
+            let Some(delay) = sample.delay else {
+                continue;
+            };
+            let index =
+                ((sample.time - from) as u128 * buckets as u128 / (to - from + 1) as u128) as usize;
+            let bucket = &mut sums[index.min(buckets - 1)];
+            bucket.0 += delay as u64;
+            bucket.1 += 1;
+        }
+        for (point, (sum, count)) in series.iter_mut().zip(sums) {
+            if count > 0 {
+                *point = Some((sum / count) as u32);
+            }
+        }
+    }
+
+    LatencyStats {
+        key: key.to_string(),
+        name: node.name.clone(),
+        total,
+        min: delays.first().copied(),
+        avg,
+        p50: percentile(&delays, 0.5),
+        p95: percentile(&delays, 0.95),
+        failure_rate: if total == 0 {
+            0.0
+        } else {
+            failures as f64 / total as f64
+        },
+        series,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(time: i64, delay: Option<u32>) -> LatencySample {
+        LatencySample { time, delay }
+    }
+
+    #[test]
+    fn test_node_key() {
+        let proxy: Mapping =
+            serde_yaml::from_str("name: hk\ntype: ss\nserver: 1.2.3.4\nport: 443\n").unwrap();
+        assert_eq!(node_key(&proxy).as_deref(), Some("1.2.3.4:443:ss"));
+
+        let proxy: Mapping = serde_yaml::from_str("name: hk\ntype: ss\n").unwrap();
+        assert_eq!(node_key(&proxy), None);
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut history = HistoryMap::new();
+        for (time, delay) in [
+            (0, Some(100)),
+            (10, Some(200)),
+            (20, None),
+            (30, Some(300)),
+            (90, Some(400)),
+        ] {
+            insert_sample(&mut history, "p", "k", "hk", sample(time, delay));
+        }
+
+        let node = &history["p"]["k"];
+        let stats = compute_stats("k", node, 0, 99, 2);
+        assert_eq!(stats.total, 5);
+        assert_eq!(stats.min, Some(100));
+        assert_eq!(stats.avg, Some(250));
+        assert_eq!(stats.p50, Some(300));
+        assert_eq!(stats.p95, Some(400));
+        assert_eq!(stats.failure_rate, 0.2);
+        assert_eq!(stats.series, vec![Some(200), Some(400)]);
+
+        let stats = compute_stats("k", node, 50, 99, 2);
+        assert_eq!(stats.total, 1);
+        assert_eq!(stats.series, vec![None, Some(400)]);
+    }
+
+    #[test]
+    fn test_prune() {
+        let mut history = HistoryMap::new();
+        insert_sample(&mut history, "p", "1.1.1.1:443:ss", "a", sample(0, Some(1)));
+        insert_sample(&mut history, "p", "2.2.2.2:443:ss", "b", sample(0, Some(1)));
+        insert_sample(&mut history, "p", "name:c", "c", sample(0, Some(1)));
+        insert_sample(&mut history, "p", "name:d", "d", sample(0, Some(1)));
+
+        let valid = HashSet::from(["1.1.1.1:443:ss".to_string()]);
+        assert_eq!(prune_nodes(&mut history, "p", &valid, None), 1);
+        assert_eq!(history["p"].len(), 3);
+
+        let names = HashSet::from(["a".to_string(), "c".to_string()]);
+        assert_eq!(prune_nodes(&mut history, "p", &valid, Some(&names)), 1);
+        assert!(history["p"].contains_key("name:c"));
+        assert!(!history["p"].contains_key("name:d"));
+        assert_eq!(
+            prune_nodes(&mut history, "missing", &valid, Some(&names)),
+            0
+        );
+    }
+}
@@ -0,0 +1,267 @@
+use crate::{
+    config::Config,
+    core::handle,
+    logging,
+    module::{latency, mihomo::MihomoManager},
+    utils::logging::Type,
+};
+use anyhow::{anyhow, bail, Result};
+use futures::{stream, StreamExt};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{
+    cmp::Ordering,
+    collections::{hash_map::Entry, HashMap, HashSet},
+};
+use tokio::sync::watch;
+
+const DEFAULT_TIMEOUT: i32 = 10000;
+const DEFAULT_CONCURRENCY: usize = 16;
+const MAX_CONCURRENCY: usize = 64;
+
+/// 正在运行的批量测试，用于取消
+static RUNNING: Lazy<Mutex<HashMap<String, watch::Sender<bool>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BatchDelayResult {
+    pub name: String,
+    /// 延迟毫秒数，失败时为 None
+    pub delay: Option<u32>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchDelaySummary {
+    pub batch_id: String,
+    pub total: usize,
+    pub completed: usize,
+    pub cancelled: bool,
+    /// 可用节点按延迟升序，失败的节点排在最后
+    pub results: Vec<BatchDelayResult>,
+}
+
+/// 批量测试节点延迟，`groups` 中的节点会被展开，重复的节点只测试一次
+pub async fn run(
+    batch_id: String,
+    nodes: Vec<String>,
+    groups: Vec<String>,
+    test_url: Option<String>,
+    concurrency: Option<usize>,
+) -> Result<BatchDelaySummary> {
+    let names = expand_nodes(nodes, &groups).await?;
+    let total = names.len();
+    let timeout = Config::verge()
+        .latest()
+        .default_latency_timeout
+        .filter(|timeout| *timeout > 0)
+        .unwrap_or(DEFAULT_TIMEOUT);
+    let concurrency = concurrency
+        .unwrap_or(DEFAULT_CONCURRENCY)
+        .clamp(1, MAX_CONCURRENCY);
+
+    let (cancel_rx, _guard) = register(&batch_id)?;
+    logging!(
+        info,
+        Type::Core,
+        true,
+        "Batch delay test {}: {} nodes, concurrency {}",
+        batch_id,
+        total,
+        concurrency
+    );
+
+    let mut completed = 0;
+    let mut results = Vec::with_capacity(total);
+    let mut tests = stream::iter(names)
+        .map(|name| {
+            let test_url = test_url.clone();
+            let mut cancel_rx = cancel_rx.clone();
+            async move {
+                if *cancel_rx.borrow() {
+                    return None;
+                }
+                tokio::select! {
+                    result = latency::test_proxy_delay(&name, test_url, timeout) => {
+                        Some(to_result(name, result))
+                    }
+                    _ = cancel_rx.wait_for(|cancelled| *cancelled) => None,
+                }
+            }
+        })
+        .buffer_unordered(concurrency);
+
+    while let Some(result) = tests.next().await {
+        let Some(result) = result else {
+            continue;
+        };
+        completed += 1;
+        handle::Handle::notify_latency_progress(json!({
+            "batch_id": batch_id,
+            "completed": completed,
+            "total": total,
+            "name": result.name,
+            "delay": result.delay,
+        }));
+        results.push(result);
+    }
+
+    let cancelled = *cancel_rx.borrow();
+    sort_results(&mut results);
+
+    logging!(
+        info,
+        Type::Core,
+        true,
+        "Batch delay test {} finished: {}/{} tested, cancelled: {}",
+        batch_id,
+        completed,
+        total,
+        cancelled
+    );
+    Ok(BatchDelaySummary {
+        batch_id,
+        total,
+        completed,
+        cancelled,
+        results,
+    })
+}
+
+/// 运行结束或被丢弃时从 `RUNNING` 中移除自己的记录
+struct RunningGuard(String);
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        RUNNING.lock().remove(&self.0);
+    }
+}
+
+/// 登记批量测试，同一个 `batch_id` 正在运行时拒绝
+fn register(batch_id: &str) -> Result<(watch::Receiver<bool>, RunningGuard)> {
+    match RUNNING.lock().entry(batch_id.to_string()) {
+        Entry::Occupied(_) => bail!("batch delay test `{batch_id}` is already running"),
+        Entry::Vacant(entry) => {
+            let (cancel_tx, cancel_rx) = watch::channel(false);
+            entry.insert(cancel_tx);
+            Ok((cancel_rx, RunningGuard(batch_id.to_string())))
+        }
+    }
+}
+
+/// 取消批量测试，测试不存在时返回 false
+pub fn cancel(batch_id: &str) -> bool {
+    match RUNNING.lock().get(batch_id) {
+        Some(sender) => {
+            let _ = sender.send(true);
+            true
+        }
+        None => false,
+    }
+}
+
+fn to_result(name: String, result: Result<Value, String>) -> BatchDelayResult {
+    let delay = result
+        .as_ref()
+        .ok()
+        .and_then(|value| value.get("delay"))
+        .and_then(Value::as_u64)
+        .filter(|delay| *delay > 0)
+        .map(|delay| delay as u32);
+    let error = match (&result, delay) {
+        (_, Some(_)) => None,
+        (Err(err), None) => Some(err.clone()),
+        (Ok(value), None) => Some(
+            value
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or("timeout")
+                .to_string(),
+        ),
+    };
+    BatchDelayResult { name, delay, error }
+}
+
+/// 展开代理组并去重，保持原有顺序
+async fn expand_nodes(nodes: Vec<String>, groups: &[String]) -> Result<Vec<String>> {
+    let mut names = nodes;
+    if !groups.is_empty() {
+        let proxies = MihomoManager::global()
+            .get_refresh_proxies()
+            .await
+            .map_err(|err| anyhow!(err))?;
+        for group in groups {
+            let members = proxies
+                .get("proxies")
+                .and_then(|proxies| proxies.get(group))
+                .and_then(|group| group.get("all"))
+                .and_then(Value::as_array)
+                .ok_or(anyhow!("group `{group}` not found"))?;
+            names.extend(
+                members
+                    .iter()
+                    .filter_map(|name| name.as_str().map(str::to_string)),
+            );
+        }
+    }
+    Ok(dedup(names))
+}
+
+fn dedup(names: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    names
+        .into_iter()
+        .filter(|name| seen.insert(name.clone()))
+        .collect()
+}
+
+fn sort_results(results: &mut [BatchDelayResult]) {
+    results.sort_by(|a, b| match (a.delay, b.delay) {
+        (Some(a_delay), Some(b_delay)) => a_delay.cmp(&b_delay).then_with(|| a.name.cmp(&b.name)),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => a.name.cmp(&b.name),
+    });
+}
+
+#[test]
+fn test_dedup_and_sort() {
+    let names = dedup(vec![
+        "a".into(),
+        "b".into(),
+        "a".into(),
+        "c".into(),
+        "b".into(),
+    ]);
+    assert_eq!(names, vec!["a", "b", "c"]);
+
+    let result = |name: &str, delay: Option<u32>| BatchDelayResult {
+        name: name.into(),
+        delay,
+        error: None,
+    };
+    let mut results = vec![
+        result("d", None),
+        result("c", Some(300)),
+        result("b", None),
+        result("a", Some(100)),
+    ];
+    sort_results(&mut results);
+    let names = results.iter().map(|r| r.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, vec!["a", "c", "b", "d"]);
+}
+
+#[test]
+fn test_duplicate_batch_id() {
+    let (cancel_rx, guard) = register("test-duplicate").unwrap();
+    assert!(register("test-duplicate").is_err());
+
+    assert!(cancel("test-duplicate"));
+    assert!(*cancel_rx.borrow());
+
+    drop(guard);
+    assert!(!cancel("test-duplicate"));
+    assert!(register("test-duplicate").is_ok());
+}
@@ -0,0 +1,1004 @@
+use super::{country_code_to_emoji, UnlockCheck, UnlockItem};
+use crate::{logging, utils::logging::Type};
+use async_trait::async_trait;
+use regex::Regex;
+use reqwest::Client;
+use std::{collections::HashMap, sync::Arc};
+
+/// 内置检测，名称与检测函数一一对应
+macro_rules! builtin_check {
+    ($ty:ident, [$($name:expr),+], |$client:ident| $check:expr) => {
+        struct $ty;
+
+        #[async_trait]
+        impl UnlockCheck for $ty {
+            fn names(&self) -> Vec<String> {
+                vec![$($name.to_string()),+]
+            }
+
+            async fn check(&self, $client: &Client) -> Vec<UnlockItem> {
+                $check
+            }
+        }
+    };
+}
+
+builtin_check!(BilibiliChinaMainland, ["哔哩哔哩大陆"], |client| vec![
+    check_bilibili_china_mainland(client).await
+]);
+builtin_check!(BilibiliHkMcTw, ["哔哩哔哩港澳台"], |client| vec![
+    check_bilibili_hk_mc_tw(client).await
+]);
+builtin_check!(ChatGPT, ["ChatGPT iOS", "ChatGPT Web"], |client| {
+    check_chatgpt_combined(client).await
+});
+builtin_check!(Gemini, ["Gemini"], |client| vec![
+    check_gemini(client).await
+]);
+builtin_check!(YoutubePremium, ["Youtube Premium"], |client| vec![
+    check_youtube_premium(client).await
+]);
+builtin_check!(BahamutAnime, ["Bahamut Anime"], |client| vec![
+    check_bahamut_anime(client).await
+]);
+builtin_check!(Netflix, ["Netflix"], |client| vec![
+    check_netflix(client).await
+]);
+builtin_check!(DisneyPlus, ["Disney+"], |client| vec![
+    check_disney_plus(client).await
+]);
+builtin_check!(PrimeVideo, ["Prime Video"], |client| vec![
+    check_prime_video(client).await
+]);
+
+/// 所有内置检测，顺序即展示顺序
+pub fn checks() -> Vec<Arc<dyn UnlockCheck>> {
+    vec![
+        Arc::new(BilibiliChinaMainland),
+        Arc::new(BilibiliHkMcTw),
+        Arc::new(ChatGPT),
+        Arc::new(Gemini),
+        Arc::new(YoutubePremium),
+        Arc::new(BahamutAnime),
+        Arc::new(Netflix),
+        Arc::new(DisneyPlus),
+        Arc::new(PrimeVideo),
+    ]
+}
+
+// 测试哔哩哔哩中国大陆
+async fn check_bilibili_china_mainland(client: &Client) -> UnlockItem {
+    let url = "https://api.bilibili.com/pgc/player/web/playurl?avid=82846771&qn=0&type=&otype=json&ep_id=307247&fourk=1&fnver=0&fnval=16&module=bangumi";
+
+    let result = client.get(url).send().await;
+
+    match result {
+        Ok(response) => match response.json::<serde_json::Value>().await {
+            Ok(body) => UnlockItem::new("哔哩哔哩大陆", bilibili_status(&body), None),
+            Err(_) => UnlockItem::new("哔哩哔哩大陆", "Failed", None),
+        },
+        Err(_) => UnlockItem::new("哔哩哔哩大陆", "Failed", None),
+    }
+}
+
+// 哔哩哔哩播放接口的 code：0 可用，-10403 地区限制
+fn bilibili_status(body: &serde_json::Value) -> &'static str {
+    match body.get("code").and_then(|v| v.as_i64()) {
+        Some(0) => "Yes",
+        Some(-10403) => "No",
+        _ => "Failed",
+    }
+}
+
+// 测试哔哩哔哩港澳台
+async fn check_bilibili_hk_mc_tw(client: &Client) -> UnlockItem {
+    let url = "https://api.bilibili.com/pgc/player/web/playurl?avid=18281381&cid=29892777&qn=0&type=&otype=json&ep_id=183799&fourk=1&fnver=0&fnval=16&module=bangumi";
+
+    let result = client.get(url).send().await;
+
+    match result {
+        Ok(response) => match response.json::<serde_json::Value>().await {
+            Ok(body) => UnlockItem::new("哔哩哔哩港澳台", bilibili_status(&body), None),
+            Err(_) => UnlockItem::new("哔哩哔哩港澳台", "Failed", None),
+        },
+        Err(_) => UnlockItem::new("哔哩哔哩港澳台", "Failed", None),
+    }
+}
+
+// 合并的ChatGPT检测功能，包含iOS和Web测试以及国家代码获取
+async fn check_chatgpt_combined(client: &Client) -> Vec<UnlockItem> {
+    // 结果集
+    let mut results = Vec::new();
+
+    // 1. 获取国家代码
+    let url_country = "https://chat.openai.com/cdn-cgi/trace";
+    let result_country = client.get(url_country).send().await;
+
+    // 解析区域信息
+    let region = match result_country {
+        Ok(response) => match response.text().await {
+            Ok(body) => trace_region(&body),
+            Err(_) => None,
+        },
+        Err(_) => None,
+    };
+
+    // 2. 测试 ChatGPT iOS
+    let url_ios = "https://ios.chat.openai.com/";
+    let result_ios = client.get(url_ios).send().await;
+
+    // 解析iOS测试结果
+    let ios_status = match result_ios {
+        Ok(response) => match response.text().await {
+            Ok(body) => chatgpt_ios_status(&body),
+            Err(_) => "Failed",
+        },
+        Err(_) => "Failed",
+    };
+
+    // 3. 测试 ChatGPT Web
+    let url_web = "https://api.openai.com/compliance/cookie_requirements";
+    let result_web = client.get(url_web).send().await;
+
+    // 解析Web测试结果
+    let web_status = match result_web {
+        Ok(response) => match response.text().await {
+            Ok(body) => chatgpt_web_status(&body),
+            Err(_) => "Failed",
+        },
+        Err(_) => "Failed",
+    };
+
+    // 添加iOS测试结果
+    results.push(UnlockItem::new("ChatGPT iOS", ios_status, region.clone()));
+
+    // 添加Web测试结果
+    results.push(UnlockItem::new("ChatGPT Web", web_status, region));
+
+    results
+}
+
+// 解析 cdn-cgi/trace 中的 `loc=XX`
+fn trace_region(body: &str) -> Option<String> {
+    let map = body
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .collect::<HashMap<_, _>>();
+    map.get("loc").map(|loc| {
+        let emoji = country_code_to_emoji(loc);
+        format!("{emoji}{loc}")
+    })
+}
+
+fn chatgpt_ios_status(body: &str) -> &'static str {
+    let body_lower = body.to_lowercase();
+    if body_lower.contains("you may be connected to a disallowed isp") {
+        "Disallowed ISP"
+    } else if body_lower.contains("request is not allowed. please try again later.") {
+        "Yes"
+    } else if body_lower.contains("sorry, you have been blocked") {
+        "Blocked"
+    } else {
+        "Failed"
+    }
+}
+
+fn chatgpt_web_status(body: &str) -> &'static str {
+    if body.to_lowercase().contains("unsupported_country") {
+        "Unsupported Country/Region"
+    } else {
+        "Yes"
+    }
+}
+
+// 测试Gemini
+async fn check_gemini(client: &Client) -> UnlockItem {
+    let url = "https://gemini.google.com";
+
+    let result = client.get(url).send().await;
+
+    match result {
+        Ok(response) => match response.text().await {
+            Ok(body) => gemini_result(&body),
+            Err(_) => UnlockItem::new("Gemini", "Failed", None),
+        },
+        Err(_) => UnlockItem::new("Gemini", "Failed", None),
+    }
+}
+
+fn gemini_result(body: &str) -> UnlockItem {
+    let is_ok = body.contains("45631641,null,true");
+    let status = if is_ok { "Yes" } else { "No" };
+
+    // 尝试提取国家代码
+    let re = Regex::new(r#",2,1,200,"([A-Z]{3})""#).unwrap();
+    let region = re.captures(body).and_then(|caps| {
+        caps.get(1).map(|m| {
+            let country_code = m.as_str();
+            let emoji = country_code_to_emoji(country_code);
+            format!("{emoji}{country_code}")
+        })
+    });
+
+    UnlockItem::new("Gemini", status, region)
+}
+
+// 测试 YouTube Premium
+async fn check_youtube_premium(client: &Client) -> UnlockItem {
+    let url = "https://www.youtube.com/premium";
+
+    let result = client.get(url).send().await;
+
+    match result {
+        Ok(response) => match response.text().await {
+            Ok(body) => youtube_premium_result(&body),
+            Err(_) => UnlockItem::new("Youtube Premium", "Failed", None),
+        },
+        Err(_) => UnlockItem::new("Youtube Premium", "Failed", None),
+    }
+}
+
+fn youtube_premium_result(body: &str) -> UnlockItem {
+    let body_lower = body.to_lowercase();
+
+    if body_lower.contains("youtube premium is not available in your country") {
+        UnlockItem::new("Youtube Premium", "No", None)
+    } else if body_lower.contains("ad-free") {
+        // 尝试解析国家代码
+        let re = Regex::new(r#"id="country-code"[^>]*>([^<]+)<"#).unwrap();
+        let region = re.captures(body).and_then(|caps| {
+            caps.get(1).map(|m| {
+                let country_code = m.as_str().trim();
+                let emoji = country_code_to_emoji(country_code);
+                format!("{emoji}{country_code}")
+            })
+        });
+
+        UnlockItem::new("Youtube Premium", "Yes", region)
+    } else {
+        UnlockItem::new("Youtube Premium", "Failed", None)
+    }
+}
+
+// 测试动画疯(Bahamut Anime)
+async fn check_bahamut_anime(client: &Client) -> UnlockItem {
+    // 创建带Cookie存储的客户端
+    let cookie_store = Arc::new(reqwest::cookie::Jar::default());
+
+    // 使用带Cookie的客户端
+    let client_with_cookies = reqwest::Client::builder()
+        .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/122.0.0.0 Safari/537.36")
+        .cookie_provider(Arc::clone(&cookie_store))
+        .build()
+        .unwrap_or_else(|_| client.clone());
+
+    // 第一步：获取设备ID (会自动保存Cookie)
+    let device_url = "https://ani.gamer.com.tw/ajax/getdeviceid.php";
+    let device_id = match client_with_cookies.get(device_url).send().await {
+        Ok(response) => {
+            match response.text().await {
+                Ok(text) => {
+                    // 使用正则提取deviceid
+                    let re = Regex::new(r#""deviceid"\s*:\s*"([^"]+)"#).unwrap();
+                    re.captures(&text)
+                        .and_then(|caps| caps.get(1).map(|m| m.as_str().to_string()))
+                        .unwrap_or_default()
+                }
+                Err(_) => String::new(),
+            }
+        }
+        Err(_) => String::new(),
+    };
+
+    if device_id.is_empty() {
+        return UnlockItem::new("Bahamut Anime", "Failed", None);
+    }
+
+    // 第二步：使用设备ID检查访问权限 (使用相同的Cookie)
+    let url =
+        format!("https://ani.gamer.com.tw/ajax/token.php?adID=89422&sn=37783&device={device_id}");
+
+    let token_result = match client_with_cookies.get(&url).send().await {
+        Ok(response) => {
+            match response.text().await {
+                Ok(body) => {
+                    // 检查内容是否可访问 - 更精确地匹配animeSn
+                    if body.contains("animeSn") {
+                        Some(body)
+                    } else {
+                        None
+                    }
+                }
+                Err(_) => None,
+            }
+        }
+        Err(_) => None,
+    };
+
+    // 如果无法获取token或不包含animeSn，表示不支持
+    if token_result.is_none() {
+        return UnlockItem::new("Bahamut Anime", "No", None);
+    }
+
+    // 第三步：访问主页获取区域信息 (使用相同的Cookie)
+    let region = match client_with_cookies
+        .get("https://ani.gamer.com.tw/")
+        .send()
+        .await
+    {
+        Ok(response) => match response.text().await {
+            Ok(body) => {
+                let region_re = Regex::new(r#"data-geo="([^"]+)"#).unwrap();
+                region_re
+                    .captures(&body)
+                    .and_then(|caps| caps.get(1))
+                    .map(|m| {
+                        let country_code = m.as_str();
+                        let emoji = country_code_to_emoji(country_code);
+                        format!("{emoji}{country_code}")
+                    })
+            }
+            Err(_) => None,
+        },
+        Err(_) => None,
+    };
+
+    // 解锁成功
+    UnlockItem::new("Bahamut Anime", "Yes", region)
+}
+
+// 测试 Netflix
+async fn check_netflix(client: &Client) -> UnlockItem {
+    // 首先尝试使用Fast.com API检测Netflix CDN区域
+    let cdn_result = check_netflix_cdn(client, NETFLIX_CDN_URL).await;
+    if cdn_result.status == "Yes" {
+        return cdn_result;
+    }
+
+    // 如果CDN方法失败，尝试传统的内容检测方法
+    // 测试两个 Netflix 内容 (LEGO Ninjago 和 Breaking Bad)
+    let url1 = "https://www.netflix.com/title/81280792"; // LEGO Ninjago
+    let url2 = "https://www.netflix.com/title/70143836"; // Breaking Bad
+
+    // 创建简单的请求（不添加太多头部信息）
+    let result1 = client
+        .get(url1)
+        .timeout(std::time::Duration::from_secs(30))
+        .send()
+        .await;
+
+    // 检查连接失败情况
+    if let Err(e) = &result1 {
+        logging!(warn, Type::Network, true, "Netflix request failed: {}", e);
+        return UnlockItem::new("Netflix", "Failed", None);
+    }
+
+    // 如果第一个请求成功，尝试第二个请求
+    let result2 = client
+        .get(url2)
+        .timeout(std::time::Duration::from_secs(30))
+        .send()
+        .await;
+
+    if let Err(e) = &result2 {
+        logging!(warn, Type::Network, true, "Netflix request failed: {}", e);
+        return UnlockItem::new("Netflix", "Failed", None);
+    }
+
+    // 获取状态码
+    let status1 = result1.unwrap().status().as_u16();
+    let status2 = result2.unwrap().status().as_u16();
+
+    // 根据状态码判断解锁状况
+    if let Some(item) = netflix_title_result(status1, status2) {
+        return item;
+    }
+
+    // 成功解锁，尝试获取地区信息
+    // 使用Netflix测试内容获取区域
+    let test_url = "https://www.netflix.com/title/80018499";
+    match client
+        .get(test_url)
+        .timeout(std::time::Duration::from_secs(30))
+        .send()
+        .await
+    {
+        Ok(response) => {
+            // 检查重定向位置
+            let region = response
+                .headers()
+                .get("location")
+                .and_then(|location| location.to_str().ok())
+                .and_then(netflix_location_region);
+            if let Some(region) = region {
+                return UnlockItem::new("Netflix", "Yes", Some(region));
+            }
+            // 如果没有重定向，假设是美国
+            let emoji = country_code_to_emoji("us");
+            UnlockItem::new("Netflix", "Yes", Some(format!("{}{}", emoji, "us")))
+        }
+        Err(e) => {
+            logging!(
+                warn,
+                Type::Network,
+                true,
+                "Failed to get Netflix region: {}",
+                e
+            );
+            UnlockItem::new("Netflix", "Yes (但无法获取区域)", None)
+        }
+    }
+}
+
+/// 根据两个剧集页面的状态码判断，返回 None 表示已解锁，需要继续获取区域
+fn netflix_title_result(status1: u16, status2: u16) -> Option<UnlockItem> {
+    if status1 == 404 && status2 == 404 {
+        return Some(UnlockItem::new("Netflix", "Originals Only", None));
+    }
+
+    if status1 == 403 || status2 == 403 {
+        return Some(UnlockItem::new("Netflix", "No", None));
+    }
+
+    if status1 == 200 || status1 == 301 || status2 == 200 || status2 == 301 {
+        return None;
+    }
+
+    // 其他未知错误状态
+    Some(UnlockItem::new(
+        "Netflix",
+        format!("Failed (状态码: {status1}_{status2}"),
+        None,
+    ))
+}
+
+/// 从 `https://www.netflix.com/sg-zh/title/...` 这样的重定向中解析区域
+fn netflix_location_region(location: &str) -> Option<String> {
+    let parts: Vec<&str> = location.split('/').collect();
+    if parts.len() < 4 {
+        return None;
+    }
+    let region_code = parts[3].split('-').next().unwrap_or("unknown");
+    let emoji = country_code_to_emoji(region_code);
+    Some(format!("{emoji}{region_code}"))
+}
+
+/// Fast.com API URL
+const NETFLIX_CDN_URL: &str = "https://api.fast.com/netflix/speedtest/v2?https=true&token=YXNkZmFzZGxmbnNkYWZoYXNkZmhrYWxm&urlCount=5";
+
+// 使用Fast.com API检测Netflix CDN区域
+async fn check_netflix_cdn(client: &Client, url: &str) -> UnlockItem {
+    let result = client
+        .get(url)
+        .timeout(std::time::Duration::from_secs(30))
+        .send()
+        .await;
+
+    match result {
+        Ok(response) => {
+            // 检查状态码
+            if response.status().as_u16() == 403 {
+                return UnlockItem::new("Netflix", "No (IP Banned By Netflix)", None);
+            }
+
+            // 尝试解析响应
+            match response.json::<serde_json::Value>().await {
+                Ok(data) => {
+                    // 尝试从数据中提取区域信息
+                    if let Some(targets) = data.get("targets").and_then(|t| t.as_array()) {
+                        if !targets.is_empty() {
+                            if let Some(location) = targets[0].get("location") {
+                                if let Some(country) =
+                                    location.get("country").and_then(|c| c.as_str())
+                                {
+                                    let emoji = country_code_to_emoji(country);
+                                    return UnlockItem::new(
+                                        "Netflix",
+                                        "Yes",
+                                        Some(format!("{emoji}{country}")),
+                                    );
+                                }
+                            }
+                        }
+                    }
+
+                    // 如果无法解析区域信息
+                    UnlockItem::new("Netflix", "Unknown", None)
+                }
+                Err(e) => {
+                    logging!(
+                        warn,
+                        Type::Network,
+                        true,
+                        "Failed to parse Fast.com API response: {}",
+                        e
+                    );
+                    UnlockItem::new("Netflix", "Failed (解析错误)", None)
+                }
+            }
+        }
+        Err(e) => {
+            logging!(
+                warn,
+                Type::Network,
+                true,
+                "Fast.com API request failed: {}",
+                e
+            );
+            UnlockItem::new("Netflix", "Failed (CDN API)", None)
+        }
+    }
+}
+
+// 测试 Disney+
+async fn check_disney_plus(client: &Client) -> UnlockItem {
+    // Disney+ 不支持 IPv6，但这里不做额外检查，因为我们使用的是系统默认网络
+
+    // 第一步：获取 assertion
+    let device_api_url = "https://disney.api.edge.bamgrid.com/devices";
+    let auth_header =
+        "Bearer ZGlzbmV5JmJyb3dzZXImMS4wLjA.Cu56AgSfBTDag5NiRA81oLHkDZfu5L3CKadnefEAY84";
+
+    let device_req_body = serde_json::json!({
+        "deviceFamily": "browser",
+        "applicationRuntime": "chrome",
+        "deviceProfile": "windows",
+        "attributes": {}
+    });
+
+    let device_result = client
+        .post(device_api_url)
+        .header("authorization", auth_header)
+        .header("content-type", "application/json; charset=UTF-8")
+        .json(&device_req_body)
+        .send()
+        .await;
+
+    // 检查网络连接
+    if device_result.is_err() {
+        return UnlockItem::new("Disney+", "Failed (Network Connection)", None);
+    }
+
+    let device_response = device_result.unwrap();
+
+    // 检查是否 403 错误
+    if device_response.status().as_u16() == 403 {
+        return UnlockItem::new("Disney+", "No (IP Banned By Disney+)", None);
+    }
+
+    let device_body = match device_response.text().await {
+        Ok(body) => body,
+        Err(_) => {
+            return UnlockItem::new("Disney+", "Failed (Error: Cannot read response)", None);
+        }
+    };
+
+    // 提取 assertion
+    let re = Regex::new(r#""assertion"\s*:\s*"([^"]+)"#).unwrap();
+    let assertion = match re.captures(&device_body) {
+        Some(caps) => caps.get(1).map(|m| m.as_str().to_string()),
+        None => None,
+    };
+
+    if assertion.is_none() {
+        return UnlockItem::new("Disney+", "Failed (Error: Cannot extract assertion)", None);
+    }
+
+    // 第二步：获取 token
+    let token_url = "https://disney.api.edge.bamgrid.com/token";
+
+    // 构建请求体 - 使用表单数据格式而非 JSON
+    let assertion_str = assertion.unwrap();
+    let token_body = [
+        (
+            "grant_type",
+            "urn:ietf:params:oauth:grant-type:token-exchange",
+        ),
+        ("latitude", "0"),
+        ("longitude", "0"),
+        ("platform", "browser"),
+        ("subject_token", assertion_str.as_str()),
+        (
+            "subject_token_type",
+            "urn:bamtech:params:oauth:token-type:device",
+        ),
+    ];
+
+    let token_result = client
+        .post(token_url)
+        .header("authorization", auth_header)
+        .header("content-type", "application/x-www-form-urlencoded")
+        .form(&token_body) // 使用 form 而不是 json
+        .send()
+        .await;
+
+    if token_result.is_err() {
+        return UnlockItem::new("Disney+", "Failed (Network Connection)", None);
+    }
+
+    let token_response = token_result.unwrap();
+    let token_status = token_response.status();
+
+    // 保存原始响应用于调试
+    let token_body_text = match token_response.text().await {
+        Ok(body) => body,
+        Err(_) => {
+            return UnlockItem::new(
+                "Disney+",
+                "Failed (Error: Cannot read token response)",
+                None,
+            );
+        }
+    };
+
+    // 检查是否被禁止的地区
+    if token_body_text.contains("forbidden-location") || token_body_text.contains("403 ERROR") {
+        return UnlockItem::new("Disney+", "No (IP Banned By Disney+)", None);
+    }
+
+    // 尝试解析 JSON
+    let token_json: Result<serde_json::Value, _> = serde_json::from_str(&token_body_text);
+
+    let refresh_token = match token_json {
+        Ok(json) => json
+            .get("refresh_token")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string()),
+        Err(_) => {
+            // 如果 JSON 解析失败，尝试使用正则表达式
+            let refresh_token_re = Regex::new(r#""refresh_token"\s*:\s*"([^"]+)"#).unwrap();
+            refresh_token_re
+                .captures(&token_body_text)
+                .and_then(|caps| caps.get(1).map(|m| m.as_str().to_string()))
+        }
+    };
+
+    // 如果仍然无法获取 refresh token
+    if refresh_token.is_none() {
+        return UnlockItem::new(
+            "Disney+",
+            format!(
+                "Failed (Error: Cannot extract refresh token, status: {}, response: {})",
+                token_status.as_u16(),
+                token_body_text.chars().take(100).collect::<String>() + "..."
+            ),
+            None,
+        );
+    }
+
+    // 第三步：使用 GraphQL 获取地区信息
+    let graphql_url = "https://disney.api.edge.bamgrid.com/graph/v1/device/graphql";
+
+    // GraphQL API 通常接受 JSON 格式
+    let graphql_payload = format!(
+        r#"{{"query":"mutation refreshToken($input: RefreshTokenInput!) {{ refreshToken(refreshToken: $input) {{ activeSession {{ sessionId }} }} }}","variables":{{"input":{{"refreshToken":"{}"}}}}}}"#,
+        refresh_token.unwrap()
+    );
+
+    let graphql_result = client
+        .post(graphql_url)
+        .header("authorization", auth_header)
+        .header("content-type", "application/json")
+        .body(graphql_payload)
+        .send()
+        .await;
+
+    if graphql_result.is_err() {
+        return UnlockItem::new("Disney+", "Failed (Network Connection)", None);
+    }
+
+    // 检查 Disney+ 主页的可用性
+    let preview_check = client.get("https://disneyplus.com").send().await;
+
+    let is_unavailable = match preview_check {
+        Ok(response) => {
+            let url = response.url().to_string();
+            url.contains("preview") || url.contains("unavailable")
+        }
+        Err(_) => true,
+    };
+
+    // 解析 GraphQL 响应获取区域信息
+    let graphql_response = graphql_result.unwrap();
+    let graphql_status = graphql_response.status();
+    let graphql_body_text = (graphql_response.text().await).unwrap_or_default();
+
+    // 如果 GraphQL 响应为空或明显错误，尝试直接获取区域信息
+    if graphql_body_text.is_empty() || graphql_status.as_u16() >= 400 {
+        // 尝试直接从主页获取区域信息
+        let region_from_main = match client.get("https://www.disneyplus.com/").send().await {
+            Ok(response) => match response.text().await {
+                Ok(body) => {
+                    let region_re = Regex::new(r#"region"\s*:\s*"([^"]+)"#).unwrap();
+                    region_re
+                        .captures(&body)
+                        .and_then(|caps| caps.get(1).map(|m| m.as_str().to_string()))
+                }
+                Err(_) => None,
+            },
+            Err(_) => None,
+        };
+
+        if let Some(region) = region_from_main {
+            let emoji = country_code_to_emoji(&region);
+            return UnlockItem::new(
+                "Disney+",
+                "Yes",
+                Some(format!("{emoji}{region} (from main page)")),
+            );
+        }
+
+        // 如果主页也无法获取区域信息，返回详细错误
+        if graphql_body_text.is_empty() {
+            return UnlockItem::new(
+                "Disney+",
+                format!(
+                    "Failed (GraphQL error: empty response, status: {})",
+                    graphql_status.as_u16()
+                ),
+                None,
+            );
+        } else {
+            return UnlockItem::new(
+                "Disney+",
+                format!(
+                    "Failed (GraphQL error: {}, status: {})",
+                    graphql_body_text.chars().take(50).collect::<String>() + "...",
+                    graphql_status.as_u16()
+                ),
+                None,
+            );
+        }
+    }
+
+    // 提取国家代码
+    let region_re = Regex::new(r#""countryCode"\s*:\s*"([^"]+)"#).unwrap();
+    let region_code = region_re
+        .captures(&graphql_body_text)
+        .and_then(|caps| caps.get(1).map(|m| m.as_str().to_string()));
+
+    // 提取支持状态
+    let supported_re = Regex::new(r#""inSupportedLocation"\s*:\s*(false|true)"#).unwrap();
+    let in_supported_location = supported_re
+        .captures(&graphql_body_text)
+        .and_then(|caps| caps.get(1).map(|m| m.as_str() == "true"));
+
+    // 判断结果
+    if region_code.is_none() {
+        // 尝试直接从主页获取区域信息
+        let region_from_main = match client.get("https://www.disneyplus.com/").send().await {
+            Ok(response) => match response.text().await {
+                Ok(body) => {
+                    let region_re = Regex::new(r#"region"\s*:\s*"([^"]+)"#).unwrap();
+                    region_re
+                        .captures(&body)
+                        .and_then(|caps| caps.get(1).map(|m| m.as_str().to_string()))
+                }
+                Err(_) => None,
+            },
+            Err(_) => None,
+        };
+
+        if let Some(region) = region_from_main {
+            let emoji = country_code_to_emoji(&region);
+            return UnlockItem::new(
+                "Disney+",
+                "Yes",
+                Some(format!("{emoji}{region} (from main page)")),
+            );
+        }
+
+        return UnlockItem::new("Disney+", "No", None);
+    }
+
+    let region = region_code.unwrap();
+
+    // 判断日本地区
+    if region == "JP" {
+        let emoji = country_code_to_emoji("JP");
+        return UnlockItem::new("Disney+", "Yes", Some(format!("{emoji}{region}")));
+    }
+
+    // 判断不可用区域
+    if is_unavailable {
+        return UnlockItem::new("Disney+", "No", None);
+    }
+
+    // 判断支持状态
+    match in_supported_location {
+        Some(false) => {
+            let emoji = country_code_to_emoji(&region);
+            UnlockItem::new(
+                "Disney+",
+                "Soon",
+                Some(format!("{emoji}{region}（即将上线）")),
+            )
+        }
+        Some(true) => {
+            let emoji = country_code_to_emoji(&region);
+            UnlockItem::new("Disney+", "Yes", Some(format!("{emoji}{region}")))
+        }
+        None => UnlockItem::new(
+            "Disney+",
+            format!("Failed (Error: Unknown region status for {region})"),
+            None,
+        ),
+    }
+}
+
+// 测试 Amazon Prime Video
+async fn check_prime_video(client: &Client) -> UnlockItem {
+    // 访问 Prime Video 主页
+    let url = "https://www.primevideo.com";
+
+    let result = client.get(url).send().await;
+
+    // 检查网络连接
+    if result.is_err() {
+        return UnlockItem::new("Prime Video", "Failed (Network Connection)", None);
+    }
+
+    // 解析响应内容
+    match result.unwrap().text().await {
+        Ok(body) => prime_video_result(&body),
+        Err(_) => UnlockItem::new("Prime Video", "Failed (Error: Cannot read response)", None),
+    }
+}
+
+fn prime_video_result(body: &str) -> UnlockItem {
+    // 检查是否被地区限制
+    if body.contains("isServiceRestricted") {
+        return UnlockItem::new("Prime Video", "No (Service Not Available)", None);
+    }
+
+    // 提取地区信息
+    let region_re = Regex::new(r#""currentTerritory":"([^"]+)"#).unwrap();
+    match region_re.captures(body).and_then(|caps| caps.get(1)) {
+        Some(region) => {
+            let region = region.as_str();
+            let emoji = country_code_to_emoji(region);
+            UnlockItem::new("Prime Video", "Yes", Some(format!("{emoji}{region}")))
+        }
+        // 页面解析错误
+        None => UnlockItem::new("Prime Video", "Failed (Error: PAGE ERROR)", None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_server;
+    use serde_json::json;
+    use warp::{http::StatusCode, Filter};
+
+    fn status(item: UnlockItem) -> (String, Option<String>) {
+        (item.status, item.region)
+    }
+
+    #[test]
+    fn test_bilibili_status() {
+        assert_eq!(bilibili_status(&json!({"code": 0})), "Yes");
+        assert_eq!(bilibili_status(&json!({"code": -10403})), "No");
+        assert_eq!(bilibili_status(&json!({"code": -404})), "Failed");
+        assert_eq!(bilibili_status(&json!({"message": "ok"})), "Failed");
+    }
+
+    #[test]
+    fn test_chatgpt_status() {
+        assert_eq!(
+            trace_region("fl=1\nip=1.1.1.1\nloc=US\ntls=TLSv1.3\n"),
+            Some("🇺🇸US".into())
+        );
+        assert_eq!(trace_region("ip=1.1.1.1"), None);
+
+        assert_eq!(
+            chatgpt_ios_status("Request is not allowed. Please try again later."),
+            "Yes"
+        );
+        assert_eq!(
+            chatgpt_ios_status("You may be connected to a disallowed ISP"),
+            "Disallowed ISP"
+        );
+        assert_eq!(
+            chatgpt_ios_status("<title>Sorry, you have been blocked</title>"),
+            "Blocked"
+        );
+        assert_eq!(chatgpt_ios_status(""), "Failed");
+
+        assert_eq!(
+            chatgpt_web_status(r#"{"cause":{"code":"unsupported_country"}}"#),
+            "Unsupported Country/Region"
+        );
+        assert_eq!(chatgpt_web_status("{}"), "Yes");
+    }
+
+    #[test]
+    fn test_gemini_and_youtube_result() {
+        assert_eq!(
+            status(gemini_result(r#"[45631641,null,true],2,1,200,"USA""#)),
+            ("Yes".into(), Some("🇺🇸USA".into()))
+        );
+        assert_eq!(status(gemini_result("<html>")), ("No".into(), None));
+
+        assert_eq!(
+            status(youtube_premium_result(
+                r#"Ad-free <span id="country-code" class="x">JP</span>"#
+            )),
+            ("Yes".into(), Some("🇯🇵JP".into()))
+        );
+        assert_eq!(
+            status(youtube_premium_result(
+                "YouTube Premium is not available in your country"
+            )),
+            ("No".into(), None)
+        );
+        assert_eq!(
+            status(youtube_premium_result("<html>")),
+            ("Failed".into(), None)
+        );
+    }
+
+    #[test]
+    fn test_netflix_result() {
+        assert_eq!(
+            netflix_title_result(404, 404).map(status),
+            Some(("Originals Only".into(), None))
+        );
+        assert_eq!(
+            netflix_title_result(403, 200).map(status),
+            Some(("No".into(), None))
+        );
+        assert!(netflix_title_result(200, 404).is_none());
+        assert_eq!(
+            netflix_title_result(500, 502).map(status),
+            Some(("Failed (状态码: 500_502".into(), None))
+        );
+
+        assert_eq!(
+            netflix_location_region("https://www.netflix.com/sg-zh/title/80018499"),
+            Some("🇸🇬sg".into())
+        );
+        assert_eq!(netflix_location_region("/title/80018499"), None);
+    }
+
+    #[test]
+    fn test_prime_video_result() {
+        assert_eq!(
+            status(prime_video_result(r#"{"currentTerritory":"DE"}"#)),
+            ("Yes".into(), Some("🇩🇪DE".into()))
+        );
+        assert_eq!(
+            status(prime_video_result(r#"{"isServiceRestricted":true}"#)),
+            ("No (Service Not Available)".into(), None)
+        );
+        assert_eq!(
+            status(prime_video_result("<html>")),
+            ("Failed (Error: PAGE ERROR)".into(), None)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_netflix_cdn_check() {
+        let found = warp::path("found")
+            .map(|| warp::reply::json(&json!({"targets": [{"location": {"country": "SG"}}]})));
+        let empty = warp::path("empty").map(|| warp::reply::json(&json!({"targets": []})));
+        let banned =
+            warp::path("banned").map(|| warp::reply::with_status("", StatusCode::FORBIDDEN));
+        let invalid = warp::path("invalid").map(|| "<html>");
+        let addr = test_server::serve(found.or(empty).or(banned).or(invalid));
+
+        let client = Client::new();
+        let check = |path: &str| {
+            let url = format!("http://{addr}/{path}");
+            let client = client.clone();
+            async move { status(check_netflix_cdn(&client, &url).await) }
+        };
+        assert_eq!(check("found").await, ("Yes".into(), Some("🇸🇬SG".into())));
+        assert_eq!(check("empty").await, ("Unknown".into(), None));
+        assert_eq!(
+            check("banned").await,
+            ("No (IP Banned By Netflix)".into(), None)
+        );
+        assert_eq!(check("invalid").await, ("Failed (解析错误)".into(), None));
+    }
+}
@@ -0,0 +1,424 @@
+use super::{country_code_to_emoji, UnlockCheck, UnlockItem};
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use regex::Regex;
+use reqwest::{Client, Method};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{collections::HashMap, path::Path};
+
+/// 声明式检测，例如：
+///
+/// ```yaml
+/// name: Spotify
+/// request:
+///   url: https://spclient.wg.spotify.com/signup/public/v1/account
+/// rules:
+///   - json_path: status
+///     equals: 311
+///     result: "Yes"
+/// default: "No"
+/// region:
+///   json_path: country
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeclarativeCheck {
+    pub name: String,
+    pub request: CheckRequest,
+    /// 按顺序匹配，第一个满足的规则决定状态
+    #[serde(default)]
+    pub rules: Vec<MatchRule>,
+    /// 没有规则满足时的状态
+    #[serde(default = "default_status")]
+    pub default: String,
+    pub region: Option<RegionExtractor>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckRequest {
+    pub url: String,
+    #[serde(default = "default_method")]
+    pub method: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    pub body: Option<String>,
+}
+
+/// 规则中设置的条件需要全部满足
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchRule {
+    pub status: Option<Vec<u16>>,
+    pub body_contains: Option<String>,
+    pub body_regex: Option<String>,
+    /// 以 `.` 分隔的路径，如 `data.items.0.country`
+    pub json_path: Option<String>,
+    /// 未设置时只要求路径存在且不为 null
+    pub equals: Option<Value>,
+    pub result: String,
+}
+
+/// 按 header、body_regex、json_path、url_regex 的顺序提取地区，正则取第一个捕获组
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RegionExtractor {
+    pub header: Option<String>,
+    pub body_regex: Option<String>,
+    pub json_path: Option<String>,
+    pub url_regex: Option<String>,
+}
+
+struct CheckResponse {
+    status: u16,
+    url: String,
+    headers: reqwest::header::HeaderMap,
+    body: String,
+    json: Option<Value>,
+}
+
+fn default_status() -> String {
+    "No".into()
+}
+
+fn default_method() -> String {
+    "GET".into()
+}
+
+impl DeclarativeCheck {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        Self::from_yaml(&content)
+    }
+
+    pub fn from_yaml(content: &str) -> Result<Self> {
+        let check: Self = serde_yaml::from_str(content)?;
+        check.validate()?;
+        Ok(check)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.name.trim().is_empty() {
+            bail!("check name is empty");
+        }
+        Method::from_bytes(self.request.method.to_uppercase().as_bytes())
+            .with_context(|| format!("invalid method `{}`", self.request.method))?;
+
+        let patterns = self
+            .rules
+            .iter()
+            .filter_map(|rule| rule.body_regex.as_ref())
+            .chain(
+                self.region
+                    .iter()
+                    .flat_map(|region| region.body_regex.iter().chain(region.url_regex.iter())),
+            );
+        for pattern in patterns {
+            Regex::new(pattern).with_context(|| format!("invalid regex `{pattern}`"))?;
+        }
+        Ok(())
+    }
+
+    async fn send(&self, client: &Client) -> Result<CheckResponse> {
+        let method = Method::from_bytes(self.request.method.to_uppercase().as_bytes())?;
+        let mut request = client.request(method, &self.request.url);
+        for (key, value) in &self.request.headers {
+            request = request.header(key, value);
+        }
+        if let Some(body) = &self.request.body {
+            request = request.body(body.clone());
+        }
+
+        let response = request.send().await?;
+        let status = response.status().as_u16();
+        let url = response.url().to_string();
+        let headers = response.headers().clone();
+        let body = response.text().await?;
+        let json = serde_json::from_str(&body).ok();
+        Ok(CheckResponse {
+            status,
+            url,
+            headers,
+            body,
+            json,
+        })
+    }
+
+    fn evaluate(&self, response: &CheckResponse) -> UnlockItem {
+        let status = self
+            .rules
+            .iter()
+            .find(|rule| rule.matches(response))
+            .map(|rule| rule.result.clone())
+            .unwrap_or_else(|| self.default.clone());
+        let region = self
+            .region
+            .as_ref()
+            .and_then(|region| region.extract(response))
+            .map(|code| format_region(&code));
+
+        UnlockItem::new(self.name.clone(), status, region)
+    }
+}
+
+#[async_trait]
+impl UnlockCheck for DeclarativeCheck {
+    fn names(&self) -> Vec<String> {
+        vec![self.name.clone()]
+    }
+
+    async fn check(&self, client: &Client) -> Vec<UnlockItem> {
+        let item = match self.send(client).await {
+            Ok(response) => self.evaluate(&response),
+            Err(_) => UnlockItem::new(self.name.clone(), "Failed", None),
+        };
+        vec![item]
+    }
+}
+
+impl MatchRule {
+    fn matches(&self, response: &CheckResponse) -> bool {
+        if let Some(status) = &self.status {
+            if !status.contains(&response.status) {
+                return false;
+            }
+        }
+        if let Some(text) = &self.body_contains {
+            if !response.body.contains(text.as_str()) {
+                return false;
+            }
+        }
+        if let Some(pattern) = &self.body_regex {
+            if !Regex::new(pattern).is_ok_and(|re| re.is_match(&response.body)) {
+                return false;
+            }
+        }
+        if let Some(path) = &self.json_path {
+            let value = response
+                .json
+                .as_ref()
+                .and_then(|json| json_path(json, path));
+            let matched = match (&self.equals, value) {
+                (Some(expected), Some(value)) => expected == value,
+                (None, Some(value)) => !value.is_null(),
+                (_, None) => false,
+            };
+            if !matched {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl RegionExtractor {
+    fn extract(&self, response: &CheckResponse) -> Option<String> {
+        let from_header = || {
+            self.header
+                .as_ref()
+                .and_then(|name| response.headers.get(name.as_str()))
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let from_body = || {
+            self.body_regex
+                .as_ref()
+                .and_then(|pattern| capture(pattern, &response.body))
+        };
+        let from_json = || {
+            self.json_path
+                .as_ref()
+                .and_then(|path| {
+                    response
+                        .json
+                        .as_ref()
+                        .and_then(|json| json_path(json, path))
+                })
+                .and_then(|value| match value {
+                    Value::String(code) => Some(code.clone()),
+                    _ => None,
+                })
+        };
+        let from_url = || {
+            self.url_regex
+                .as_ref()
+                .and_then(|pattern| capture(pattern, &response.url))
+        };
+
+        from_header()
+            .or_else(from_body)
+            .or_else(from_json)
+            .or_else(from_url)
+            .map(|code| code.trim().to_string())
+            .filter(|code| !code.is_empty())
+    }
+}
+
+/// 正则的第一个捕获组
+fn capture(pattern: &str, text: &str) -> Option<String> {
+    Regex::new(pattern)
+        .ok()?
+        .captures(text)?
+        .get(1)
+        .map(|m| m.as_str().to_string())
+}
+
+fn json_path<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    let path = path.trim_start_matches('$').trim_start_matches('.');
+    if path.is_empty() {
+        return Some(value);
+    }
+    path.split('.').try_fold(value, |value, key| match value {
+        Value::Array(items) => key.parse::<usize>().ok().and_then(|index| items.get(index)),
+        _ => value.get(key),
+    })
+}
+
+/// 两位国家代码附带国旗
+fn format_region(code: &str) -> String {
+    let code = code.to_uppercase();
+    if code.len() == 2 && code.chars().all(|c| c.is_ascii_alphabetic()) {
+        format!("{}{code}", country_code_to_emoji(&code))
+    } else {
+        code
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_server;
+    use std::net::SocketAddr;
+    use warp::{http::StatusCode, Filter};
+
+    /// 启动本地模拟服务
+    fn mock_server() -> SocketAddr {
+        let json = warp::path("json").map(|| {
+            warp::reply::json(&serde_json::json!({
+                "status": 311,
+                "data": { "items": [{ "country": "jp" }] }
+            }))
+        });
+        let html = warp::path("html")
+            .map(|| warp::reply::html(r#"<html><span data-region="us">Available</span></html>"#));
+        let blocked = warp::path("blocked").map(|| {
+            warp::reply::with_status("not available in your region", StatusCode::FORBIDDEN)
+        });
+        let header = warp::path("header").map(|| warp::reply::with_header("ok", "x-region", "SG"));
+        let echo = warp::path("echo")
+            .and(warp::post())
+            .and(warp::header::<String>("x-token"))
+            .map(|token: String| token);
+
+        test_server::serve(json.or(html).or(blocked).or(header).or(echo))
+    }
+
+    async fn run(addr: SocketAddr, yaml: &str) -> UnlockItem {
+        let yaml = yaml.replace("{addr}", &addr.to_string());
+        let check = DeclarativeCheck::from_yaml(&yaml).unwrap();
+        check.check(&Client::new()).await.remove(0)
+    }
+
+    #[tokio::test]
+    async fn test_json_path_rule_and_region() {
+        let addr = mock_server();
+        let item = run(
+            addr,
+            r#"
+name: Spotify
+request:
+  url: http://{addr}/json
+rules:
+  - json_path: status
+    equals: 311
+    result: "Yes"
+region:
+  json_path: data.items.0.country
+"#,
+        )
+        .await;
+        assert_eq!(item.name, "Spotify");
+        assert_eq!(item.status, "Yes");
+        assert_eq!(item.region.as_deref(), Some("🇯🇵JP"));
+    }
+
+    #[tokio::test]
+    async fn test_status_and_body_rules() {
+        let addr = mock_server();
+        let yaml = r#"
+name: TikTok
+request:
+  url: http://{addr}/PATH
+rules:
+  - status: [403]
+    result: "No"
+  - status: [200]
+    body_contains: Available
+    result: "Yes"
+default: Failed
+region:
+  body_regex: 'data-region="(\w+)"'
+"#;
+        let item = run(addr, &yaml.replace("PATH", "html")).await;
+        assert_eq!(item.status, "Yes");
+        assert_eq!(item.region.as_deref(), Some("🇺🇸US"));
+
+        let item = run(addr, &yaml.replace("PATH", "blocked")).await;
+        assert_eq!(item.status, "No");
+        assert_eq!(item.region, None);
+
+        let item = run(addr, &yaml.replace("PATH", "json")).await;
+        assert_eq!(item.status, "Failed");
+    }
+
+    #[tokio::test]
+    async fn test_request_and_header_region() {
+        let addr = mock_server();
+        let item = run(
+            addr,
+            r#"
+name: Echo
+request:
+  url: http://{addr}/echo
+  method: post
+  headers:
+    x-token: secret
+rules:
+  - body_regex: '^secret$'
+    result: "Yes"
+"#,
+        )
+        .await;
+        assert_eq!(item.status, "Yes");
+
+        let item = run(
+            addr,
+            r#"
+name: Steam
+request:
+  url: http://{addr}/header
+rules:
+  - status: [200]
+    result: "Yes"
+region:
+  header: x-region
+  url_regex: '/(\w+)$'
+"#,
+        )
+        .await;
+        assert_eq!(item.region.as_deref(), Some("🇸🇬SG"));
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_and_invalid() {
+        let item = run(
+            ([127, 0, 0, 1], 9).into(),
+            "name: Down\nrequest:\n  url: http://{addr}/\n",
+        )
+        .await;
+        assert_eq!(item.status, "Failed");
+
+        assert!(DeclarativeCheck::from_yaml(
+            "name: X\nrequest:\n  url: http://x\nrules:\n  - body_regex: '('\n    result: 'Yes'\n"
+        )
+        .is_err());
+        assert!(DeclarativeCheck::from_yaml("name: ''\nrequest:\n  url: http://x\n").is_err());
+    }
+}
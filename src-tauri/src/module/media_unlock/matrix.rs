@@ -0,0 +1,395 @@
+use super::{create_client, UnlockItem, UnlockRegistry};
+use crate::{
+    config::Config,
+    logging,
+    module::mihomo::MihomoManager,
+    utils::{dirs, logging::Type},
+};
+use anyhow::{anyhow, bail, Result};
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{collections::HashMap, fs};
+
+const MATRIX_FILE: &str = "unlock_matrix.json";
+/// 无法访问任何服务的节点，不参与检测
+const SKIPPED_NODES: [&str; 2] = ["REJECT", "REJECT-DROP"];
+
+/// 单个节点的检测结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeUnlock {
+    pub node: String,
+    pub items: Vec<UnlockItem>,
+    /// 秒级时间戳
+    pub checked: i64,
+    /// 无法切换到该节点时的错误，此时没有检测结果
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 代理组的 节点 × 服务 检测结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnlockMatrix {
+    pub group: String,
+    pub nodes: Vec<NodeUnlock>,
+    pub updated: i64,
+}
+
+/// 可解锁某个服务的节点
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UnlockCandidate {
+    pub group: String,
+    pub node: String,
+    pub region: Option<String>,
+    pub checked: i64,
+}
+
+/// 代理组 -> 检测结果
+type MatrixMap = HashMap<String, UnlockMatrix>;
+
+pub struct UnlockMatrixCache {
+    matrices: Mutex<MatrixMap>,
+    /// 同一时间只允许一个检测切换节点
+    running: tokio::sync::Mutex<()>,
+}
+
+impl UnlockMatrixCache {
+    pub fn global() -> &'static UnlockMatrixCache {
+        static CACHE: OnceCell<UnlockMatrixCache> = OnceCell::new();
+        CACHE.get_or_init(|| UnlockMatrixCache {
+            matrices: Mutex::new(Self::load().unwrap_or_else(|err| {
+                logging!(
+                    warn,
+                    Type::Network,
+                    true,
+                    "Failed to load unlock matrix: {}",
+                    err
+                );
+                MatrixMap::new()
+            })),
+            running: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    fn load() -> Result<MatrixMap> {
+        let path = dirs::app_home_dir()?.join(MATRIX_FILE);
+        if !path.exists() {
+            return Ok(MatrixMap::new());
+        }
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    fn save_file(&self) -> Result<()> {
+        let data = serde_json::to_vec(&*self.matrices.lock())?;
+        fs::write(dirs::app_home_dir()?.join(MATRIX_FILE), data)?;
+        Ok(())
+    }
+
+    /// 缓存的检测结果，`group` 为空时返回全部
+    pub fn get(&self, group: Option<&str>) -> Vec<UnlockMatrix> {
+        let matrices = self.matrices.lock();
+        let mut result = matrices
+            .values()
+            .filter(|matrix| group.is_none_or(|group| matrix.group == group))
+            .cloned()
+            .collect::<Vec<_>>();
+        result.sort_by(|a, b| a.group.cmp(&b.group));
+        result
+    }
+
+    /// 缓存中可解锁该服务的节点，最近检测的排在前面
+    pub fn find_nodes(&self, service: &str) -> Vec<UnlockCandidate> {
+        find_nodes(&self.matrices.lock(), service)
+    }
+
+    /// 依次选中代理组的每个节点，通过混合端口执行解锁检测，完成后恢复原来的选择
+    ///
+    /// 检测的域名需要被规则分流到该代理组，结果才能反映节点的解锁情况
+    pub async fn check_group(
+        &self,
+        group: &str,
+        services: Option<Vec<String>>,
+    ) -> Result<UnlockMatrix> {
+        let _guard = self
+            .running
+            .try_lock()
+            .map_err(|_| anyhow!("unlock matrix check is already running"))?;
+
+        let port = Config::clash().latest().get_mixed_port();
+        let proxy = format!("http://127.0.0.1:{port}");
+        let nodes = check_selector(
+            &MihomoManager::global(),
+            UnlockRegistry::global(),
+            group,
+            &proxy,
+            services.as_deref(),
+        )
+        .await?;
+
+        let matrix = UnlockMatrix {
+            group: group.to_string(),
+            nodes,
+            updated: chrono::Local::now().timestamp(),
+        };
+        self.matrices
+            .lock()
+            .insert(group.to_string(), matrix.clone());
+        if let Err(err) = self.save_file() {
+            logging!(
+                error,
+                Type::Network,
+                true,
+                "Failed to save unlock matrix: {}",
+                err
+            );
+        }
+        Ok(matrix)
+    }
+}
+
+/// 读取代理组的信息
+async fn group_info(api: &mihomo_api::MihomoManager, group: &str) -> Result<Value> {
+    let proxies = api
+        .get_refresh_proxies()
+        .await
+        .map_err(|err| anyhow!(err))?;
+    proxies
+        .get("proxies")
+        .and_then(|proxies| proxies.get(group))
+        .cloned()
+        .ok_or(anyhow!("group `{group}` not found"))
+}
+
+/// 检测代理组的全部节点，完成后恢复原来的选择
+async fn check_selector(
+    api: &mihomo_api::MihomoManager,
+    registry: &UnlockRegistry,
+    group: &str,
+    proxy: &str,
+    services: Option<&[String]>,
+) -> Result<Vec<NodeUnlock>> {
+    let info = group_info(api, group).await?;
+    if info.get("type").and_then(Value::as_str) != Some("Selector") {
+        bail!("group `{group}` is not a selector");
+    }
+    let original = info.get("now").and_then(Value::as_str).map(str::to_string);
+    let members = info
+        .get("all")
+        .and_then(Value::as_array)
+        .map(|all| {
+            all.iter()
+                .filter_map(Value::as_str)
+                .filter(|name| !SKIPPED_NODES.contains(name))
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    logging!(
+        info,
+        Type::Network,
+        true,
+        "Unlock matrix: checking {} nodes of group {} via {}",
+        members.len(),
+        group,
+        proxy
+    );
+
+    let result = check_members(api, registry, group, &members, proxy, services).await;
+
+    if let Some(original) = original {
+        if let Err(err) = api.select_proxy(group, &original).await {
+            logging!(
+                error,
+                Type::Network,
+                true,
+                "Unlock matrix: failed to restore {} of group {}: {}",
+                original,
+                group,
+                err
+            );
+        }
+    }
+
+    result
+}
+
+async fn check_members(
+    api: &mihomo_api::MihomoManager,
+    registry: &UnlockRegistry,
+    group: &str,
+    members: &[String],
+    proxy: &str,
+    services: Option<&[String]>,
+) -> Result<Vec<NodeUnlock>> {
+    let mut nodes = Vec::with_capacity(members.len());
+    for node in members {
+        let (items, error) = match select_node(api, group, node).await {
+            Ok(()) => {
+                // 每个节点使用新的客户端，避免复用上一个节点的连接
+                let client = create_client(Some(proxy))?;
+                (registry.run_services(&client, services).await, None)
+            }
+            Err(err) => {
+                logging!(
+                    warn,
+                    Type::Network,
+                    true,
+                    "Unlock matrix: failed to select {} of group {}: {}",
+                    node,
+                    group,
+                    err
+                );
+                (Vec::new(), Some(err.to_string()))
+            }
+        };
+        nodes.push(NodeUnlock {
+            node: node.clone(),
+            items,
+            checked: chrono::Local::now().timestamp(),
+            error,
+        });
+    }
+    Ok(nodes)
+}
+
+/// 选中节点并确认代理组当前使用的就是该节点，否则检测结果不属于该节点
+async fn select_node(api: &mihomo_api::MihomoManager, group: &str, node: &str) -> Result<()> {
+    api.select_proxy(group, node)
+        .await
+        .map_err(|err| anyhow!(err))?;
+    let now = group_info(api, group).await?;
+    let now = now.get("now").and_then(Value::as_str);
+    if now != Some(node) {
+        bail!(
+            "group `{group}` is using `{}` instead",
+            now.unwrap_or_default()
+        );
+    }
+    Ok(())
+}
+
+fn find_nodes(matrices: &MatrixMap, service: &str) -> Vec<UnlockCandidate> {
+    let mut candidates = matrices
+        .values()
+        .flat_map(|matrix| {
+            matrix.nodes.iter().filter_map(|node| {
+                node.items
+                    .iter()
+                    .find(|item| item.name == service && item.status == "Yes")
+                    .map(|item| UnlockCandidate {
+                        group: matrix.group.clone(),
+                        node: node.node.clone(),
+                        region: item.region.clone(),
+                        checked: node.checked,
+                    })
+            })
+        })
+        .collect::<Vec<_>>();
+    candidates.sort_by(|a, b| b.checked.cmp(&a.checked).then_with(|| a.node.cmp(&b.node)));
+    candidates
+}
+
+#[test]
+fn test_find_nodes() {
+    let item = |name: &str, status: &str| UnlockItem::new(name, status, Some("US".into()));
+    let node = |node: &str, checked: i64, items: Vec<UnlockItem>| NodeUnlock {
+        node: node.into(),
+        items,
+        checked,
+        error: None,
+    };
+    let matrices = MatrixMap::from([(
+        "Proxy".to_string(),
+        UnlockMatrix {
+            group: "Proxy".into(),
+            nodes: vec![
+                node(
+                    "a",
+                    100,
+                    vec![item("Netflix", "Yes"), item("Disney+", "No")],
+                ),
+                node("b", 200, vec![item("Netflix", "Yes")]),
+                node("c", 300, vec![item("Netflix", "Failed")]),
+            ],
+            updated: 300,
+        },
+    )]);
+
+    let nodes = find_nodes(&matrices, "Netflix");
+    let names = nodes.iter().map(|c| c.node.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, vec!["b", "a"]);
+    assert_eq!(nodes[0].region.as_deref(), Some("US"));
+    assert!(find_nodes(&matrices, "Disney+").is_empty());
+}
+
+#[tokio::test]
+async fn test_check_selector() {
+    use super::UnlockCheck;
+    use crate::utils::test_server;
+    use async_trait::async_trait;
+    use reqwest::{header::HeaderMap, Client};
+    use std::sync::Arc;
+    use warp::{http::StatusCode, Filter};
+
+    struct Dummy;
+
+    #[async_trait]
+    impl UnlockCheck for Dummy {
+        fn names(&self) -> Vec<String> {
+            vec!["Dummy".into()]
+        }
+
+        async fn check(&self, _client: &Client) -> Vec<UnlockItem> {
+            vec![UnlockItem::new("Dummy", "Yes", None)]
+        }
+    }
+
+    // 模拟内核，选择 `b` 时不生效
+    let now = Arc::new(Mutex::new("c".to_string()));
+    let selected = Arc::new(Mutex::new(Vec::new()));
+    let get = {
+        let now = now.clone();
+        warp::get().and(warp::path("proxies")).map(move || {
+            warp::reply::json(&serde_json::json!({
+                "proxies": {
+                    "Proxy": { "type": "Selector", "now": *now.lock(), "all": ["a", "b", "c", "REJECT"] }
+                }
+            }))
+        })
+    };
+    let put = {
+        let now = now.clone();
+        let selected = selected.clone();
+        warp::put()
+            .and(warp::path!("proxies" / "Proxy"))
+            .and(warp::body::json())
+            .map(move |body: Value| {
+                let name = body["name"].as_str().unwrap_or_default().to_string();
+                selected.lock().push(name.clone());
+                if name != "b" {
+                    *now.lock() = name;
+                }
+                StatusCode::NO_CONTENT
+            })
+    };
+    let addr = test_server::serve(get.or(put));
+
+    let api = mihomo_api::MihomoManager::new(format!("http://{addr}"), HeaderMap::new());
+    let registry = UnlockRegistry::new(vec![Arc::new(Dummy)]);
+    let nodes = check_selector(&api, &registry, "Proxy", "http://127.0.0.1:1", None)
+        .await
+        .unwrap();
+
+    let rows = nodes
+        .iter()
+        .map(|node| (node.node.as_str(), node.items.len(), node.error.is_some()))
+        .collect::<Vec<_>>();
+    assert_eq!(rows, vec![("a", 1, false), ("b", 0, true), ("c", 1, false)]);
+    assert_eq!(*selected.lock(), vec!["a", "b", "c", "c"]);
+    assert_eq!(*now.lock(), "c");
+
+    let err = check_selector(&api, &registry, "Missing", "http://127.0.0.1:1", None).await;
+    assert!(err.is_err());
+}
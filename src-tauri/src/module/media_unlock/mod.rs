@@ -0,0 +1,323 @@
+pub mod builtin;
+pub mod declarative;
+pub mod matrix;
+
+use crate::{logging, utils::dirs, utils::logging::Type};
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Local;
+use declarative::DeclarativeCheck;
+use once_cell::sync::OnceCell;
+use parking_lot::RwLock;
+use reqwest::{Client, Proxy};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, path::Path, sync::Arc, time::Duration};
+use tokio::task::JoinSet;
+
+const USER_AGENT: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/122.0.0.0 Safari/537.36";
+
+/// 用户自定义检测的目录，每个 yaml 文件一个检测
+const USER_CHECKS_DIR: &str = "unlock_checks";
+
+// 定义解锁测试项目的结构
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnlockItem {
+    pub name: String,
+    pub status: String,
+    pub region: Option<String>,
+    pub check_time: Option<String>,
+}
+
+impl UnlockItem {
+    pub fn new(name: impl Into<String>, status: impl Into<String>, region: Option<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: status.into(),
+            region,
+            check_time: Some(get_local_date_string()),
+        }
+    }
+
+    /// 尚未检测的项目
+    pub fn pending(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: "Pending".into(),
+            region: None,
+            check_time: None,
+        }
+    }
+}
+
+/// 解锁检测插件，一个检测可以产生多个结果（如 ChatGPT iOS/Web）
+#[async_trait]
+pub trait UnlockCheck: Send + Sync {
+    /// 检测结果的项目名称
+    fn names(&self) -> Vec<String>;
+
+    async fn check(&self, client: &Client) -> Vec<UnlockItem>;
+}
+
+/// 检测注册表，用户检测与内置检测同名时覆盖内置检测
+pub struct UnlockRegistry {
+    builtin: Vec<Arc<dyn UnlockCheck>>,
+    /// 通过 `register` 添加的检测
+    registered: RwLock<Vec<Arc<dyn UnlockCheck>>>,
+    /// 从用户检测目录读取的检测
+    user: RwLock<Vec<Arc<dyn UnlockCheck>>>,
+}
+
+impl UnlockRegistry {
+    pub fn global() -> &'static UnlockRegistry {
+        static REGISTRY: OnceCell<UnlockRegistry> = OnceCell::new();
+
+        REGISTRY.get_or_init(|| {
+            let registry = UnlockRegistry::new(builtin::checks());
+            if let Err(err) = registry.reload_user_checks() {
+                logging!(
+                    warn,
+                    Type::Network,
+                    true,
+                    "Failed to load unlock checks: {}",
+                    err
+                );
+            }
+            registry
+        })
+    }
+
+    pub fn new(builtin: Vec<Arc<dyn UnlockCheck>>) -> Self {
+        Self {
+            builtin,
+            registered: RwLock::new(Vec::new()),
+            user: RwLock::new(Vec::new()),
+        }
+    }
+
+    pub fn register(&self, check: Arc<dyn UnlockCheck>) {
+        self.registered.write().push(check);
+    }
+
+    /// 重新读取用户检测目录，无效的文件会被跳过，不影响 `register` 添加的检测
+    pub fn reload_user_checks(&self) -> Result<()> {
+        let dir = dirs::app_home_dir()?.join(USER_CHECKS_DIR);
+        self.reload_user_checks_from(&dir)
+    }
+
+    fn reload_user_checks_from(&self, dir: &Path) -> Result<()> {
+        *self.user.write() = load_user_checks(dir)?;
+        Ok(())
+    }
+
+    pub fn checks(&self) -> Vec<Arc<dyn UnlockCheck>> {
+        let user = self
+            .registered
+            .read()
+            .iter()
+            .chain(self.user.read().iter())
+            .cloned()
+            .collect::<Vec<_>>();
+        let overridden = user
+            .iter()
+            .flat_map(|check| check.names())
+            .collect::<HashSet<_>>();
+
+        self.builtin
+            .iter()
+            .filter(|check| !check.names().iter().all(|name| overridden.contains(name)))
+            .cloned()
+            .chain(user)
+            .collect()
+    }
+
+    /// 所有项目的待检测状态
+    pub fn pending_items(&self) -> Vec<UnlockItem> {
+        self.checks()
+            .iter()
+            .flat_map(|check| check.names())
+            .map(UnlockItem::pending)
+            .collect()
+    }
+
+    /// 并行执行所有检测，结果按注册顺序返回
+    pub async fn run(&self, client: &Client) -> Vec<UnlockItem> {
+        self.run_services(client, None).await
+    }
+
+    /// 只执行包含指定项目的检测，`services` 为空时执行全部
+    pub async fn run_services(
+        &self,
+        client: &Client,
+        services: Option<&[String]>,
+    ) -> Vec<UnlockItem> {
+        let checks = self.checks().into_iter().filter(|check| {
+            services.is_none_or(|services| check.names().iter().any(|name| services.contains(name)))
+        });
+
+        let mut tasks = JoinSet::new();
+        for (index, check) in checks.enumerate() {
+            let client = client.clone();
+            tasks.spawn(async move { (index, check.check(&client).await) });
+        }
+
+        let mut results = Vec::new();
+        while let Some(res) = tasks.join_next().await {
+            match res {
+                Ok(result) => results.push(result),
+                Err(err) => {
+                    logging!(
+                        error,
+                        Type::Network,
+                        true,
+                        "Unlock check task failed: {}",
+                        err
+                    );
+                }
+            }
+        }
+        results.sort_by_key(|(index, _)| *index);
+        results.into_iter().flat_map(|(_, items)| items).collect()
+    }
+}
+
+fn load_user_checks(dir: &Path) -> Result<Vec<Arc<dyn UnlockCheck>>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut paths = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext == "yaml" || ext == "yml")
+        })
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    let mut checks: Vec<Arc<dyn UnlockCheck>> = Vec::new();
+    for path in paths {
+        match DeclarativeCheck::from_file(&path) {
+            Ok(check) => checks.push(Arc::new(check)),
+            Err(err) => {
+                logging!(
+                    warn,
+                    Type::Network,
+                    true,
+                    "Invalid unlock check {}: {}",
+                    path.display(),
+                    err
+                );
+            }
+        }
+    }
+    Ok(checks)
+}
+
+/// 检测使用的 http 客户端，`proxy` 为空时使用系统路由
+pub fn create_client(proxy: Option<&str>) -> Result<Client> {
+    let mut builder = Client::builder();
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(Proxy::all(proxy)?);
+    }
+    let client = builder
+        .user_agent(USER_AGENT)
+        .timeout(Duration::from_secs(30)) // 全局超时设置
+        .danger_accept_invalid_certs(true) // 接受无效证书，防止SSL错误
+        .danger_accept_invalid_hostnames(true) // 接受无效主机名
+        .tcp_keepalive(Duration::from_secs(60)) // TCP keepalive
+        .connection_verbose(true) // 详细连接信息
+        .build()?;
+    Ok(client)
+}
+
+// 获取当前本地时间字符串
+fn get_local_date_string() -> String {
+    let now = Local::now();
+    now.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+// 将国家代码转换为对应的emoji
+fn country_code_to_emoji(country_code: &str) -> String {
+    // 转换为大写
+    let country_code = country_code.to_uppercase();
+
+    // 确保使用国家代码的前两个字符来生成emoji
+    if country_code.len() < 2 {
+        return String::new();
+    }
+
+    // 使用前两个字符生成emoji
+    let bytes = country_code.as_bytes();
+    let c1 = 0x1F1E6 + (bytes[0] as u32) - ('A' as u32);
+    let c2 = 0x1F1E6 + (bytes[1] as u32) - ('A' as u32);
+
+    char::from_u32(c1)
+        .and_then(|c1| char::from_u32(c2).map(|c2| format!("{c1}{c2}")))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixed(&'static str, &'static str);
+
+    #[async_trait]
+    impl UnlockCheck for Fixed {
+        fn names(&self) -> Vec<String> {
+            vec![self.0.to_string()]
+        }
+
+        async fn check(&self, _client: &Client) -> Vec<UnlockItem> {
+            vec![UnlockItem::new(self.0, self.1, None)]
+        }
+    }
+
+    #[tokio::test]
+    async fn test_registry_override() {
+        let registry =
+            UnlockRegistry::new(vec![Arc::new(Fixed("A", "No")), Arc::new(Fixed("B", "No"))]);
+        registry.register(Arc::new(Fixed("A", "Yes")));
+        registry.register(Arc::new(Fixed("C", "Yes")));
+
+        let pending = registry.pending_items();
+        let names = pending
+            .iter()
+            .map(|item| item.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["B", "A", "C"]);
+
+        let results = registry.run(&Client::new()).await;
+        let statuses = results
+            .iter()
+            .map(|item| (item.name.as_str(), item.status.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(statuses, vec![("B", "No"), ("A", "Yes"), ("C", "Yes")]);
+    }
+
+    #[test]
+    fn test_reload_keeps_registered() {
+        let registry = UnlockRegistry::new(vec![Arc::new(Fixed("A", "No"))]);
+        registry.register(Arc::new(Fixed("B", "Yes")));
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("a.yaml"),
+            "name: A\nrequest:\n  url: http://127.0.0.1/\n",
+        )
+        .unwrap();
+        registry.reload_user_checks_from(dir.path()).unwrap();
+        let names = |registry: &UnlockRegistry| {
+            registry
+                .pending_items()
+                .into_iter()
+                .map(|item| item.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&registry), vec!["B", "A"]);
+
+        std::fs::remove_file(dir.path().join("a.yaml")).unwrap();
+        registry.reload_user_checks_from(dir.path()).unwrap();
+        assert_eq!(names(&registry), vec!["A", "B"]);
+    }
+}
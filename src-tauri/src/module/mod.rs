@@ -1,3 +1,9 @@
+pub mod diagnostics;
+pub mod health_check;
+pub mod latency;
+pub mod latency_batch;
 pub mod lightweight;
+pub mod media_unlock;
 pub mod mihomo;
+pub mod network_rules;
 pub mod sysinfo;
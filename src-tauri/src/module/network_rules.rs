@@ -0,0 +1,621 @@
+//! 网络环境规则：根据网卡、网关、网段、DNS 搜索域或内网主机可达性自动切换订阅与模式
+
+use crate::{
+    config::{Config, INetworkRules, NetworkCondition, NetworkRule},
+    core::{handle, network_monitor::NetworkSnapshot},
+    feat, logging,
+    utils::logging::Type,
+};
+use anyhow::{bail, Result};
+use futures::future::join_all;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    net::IpAddr,
+    time::Duration,
+};
+
+const REACHABLE_TIMEOUT: Duration = Duration::from_secs(2);
+const MODES: [&str; 3] = ["rule", "global", "direct"];
+
+/// 上一次生效的规则，匹配结果不变时不重复切换，保留用户之后的手动修改
+static LAST_APPLIED: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+/// 避免启动和网络变化同时触发时交错切换
+static EVALUATING: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+
+/// 规则匹配所需的网络环境
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct NetworkContext {
+    pub interfaces: BTreeMap<String, BTreeSet<IpAddr>>,
+    pub gateway_ip: Option<IpAddr>,
+    pub gateway_mac: Option<String>,
+    pub dns_suffixes: Vec<String>,
+}
+
+impl NetworkContext {
+    pub async fn capture() -> Self {
+        let gateway_ip = default_gateway().await;
+        let gateway_mac = match gateway_ip {
+            Some(ip) => gateway_mac(ip).await,
+            None => None,
+        };
+        Self {
+            interfaces: NetworkSnapshot::capture().interfaces,
+            gateway_ip,
+            gateway_mac,
+            dns_suffixes: dns_suffixes().await,
+        }
+    }
+}
+
+/// 简单通配，仅支持 `*`
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let Some((first, rest)) = pattern.split_once('*') else {
+        return pattern == text;
+    };
+    let Some(mut text) = text.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts = rest.split('*').collect::<Vec<_>>();
+    let last = parts.pop().unwrap_or_default();
+    for part in parts {
+        match text.find(part) {
+            Some(index) => text = &text[index + part.len()..],
+            None => return false,
+        }
+    }
+    text.len() >= last.len() && text.ends_with(last)
+}
+
+/// 判断地址是否落在 CIDR 网段内，不带前缀时按单个地址比较
+pub fn in_subnet(addr: &IpAddr, subnet: &str) -> bool {
+    let (network, prefix) = subnet.split_once('/').unwrap_or((subnet, ""));
+    let Ok(network) = network.trim().parse::<IpAddr>() else {
+        return false;
+    };
+    match (addr, network) {
+        (IpAddr::V4(addr), IpAddr::V4(network)) => {
+            let prefix = prefix.parse::<u32>().unwrap_or(32).min(32);
+            let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+            u32::from(*addr) & mask == u32::from(network) & mask
+        }
+        (IpAddr::V6(addr), IpAddr::V6(network)) => {
+            let prefix = prefix.parse::<u32>().unwrap_or(128).min(128);
+            let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+            u128::from(*addr) & mask == u128::from(network) & mask
+        }
+        _ => false,
+    }
+}
+
+/// 统一为小写冒号分隔、每段两位的格式，兼容 `a4-91-b1-02-03-04` 和 macOS 省略前导零的写法
+pub fn normalize_mac(mac: &str) -> Option<String> {
+    let parts = mac.trim().split([':', '-']).collect::<Vec<_>>();
+    if parts.len() != 6 {
+        return None;
+    }
+    parts
+        .iter()
+        .map(|part| {
+            (!part.is_empty() && part.len() <= 2)
+                .then(|| u8::from_str_radix(part, 16).ok())
+                .flatten()
+                .map(|byte| format!("{byte:02x}"))
+        })
+        .collect::<Option<Vec<_>>>()
+        .map(|parts| parts.join(":"))
+}
+
+fn normalize_suffix(suffix: &str) -> String {
+    suffix.trim().trim_matches('.').to_lowercase()
+}
+
+/// 除可达性外的条件是否全部满足
+pub fn static_matches(condition: &NetworkCondition, context: &NetworkContext) -> bool {
+    if let Some(pattern) = &condition.interface {
+        if !context
+            .interfaces
+            .keys()
+            .any(|name| wildcard_match(pattern, name))
+        {
+            return false;
+        }
+    }
+    if let Some(gateway) = &condition.gateway_ip {
+        if gateway.trim().parse::<IpAddr>().ok() != context.gateway_ip {
+            return false;
+        }
+    }
+    if let Some(mac) = &condition.gateway_mac {
+        if normalize_mac(mac).is_none() || normalize_mac(mac) != context.gateway_mac {
+            return false;
+        }
+    }
+    if let Some(subnet) = &condition.subnet {
+        if !context
+            .interfaces
+            .values()
+            .flatten()
+            .any(|addr| in_subnet(addr, subnet))
+        {
+            return false;
+        }
+    }
+    if let Some(suffix) = &condition.dns_suffix {
+        let suffix = normalize_suffix(suffix);
+        if !context
+            .dns_suffixes
+            .iter()
+            .any(|item| normalize_suffix(item) == suffix)
+        {
+            return false;
+        }
+    }
+    true
+}
+
+/// 按顺序返回第一条匹配的规则，`reachable` 为探测成功的目标
+pub fn select_rule<'a>(
+    rules: &[&'a NetworkRule],
+    context: &NetworkContext,
+    reachable: &HashSet<String>,
+) -> Option<&'a NetworkRule> {
+    rules.iter().copied().find(|rule| {
+        static_matches(&rule.conditions, context)
+            && rule
+                .conditions
+                .reachable
+                .as_ref()
+                .is_none_or(|target| reachable.contains(target))
+    })
+}
+
+async fn probe(target: &str) -> bool {
+    matches!(
+        tokio::time::timeout(REACHABLE_TIMEOUT, tokio::net::TcpStream::connect(target)).await,
+        Ok(Ok(_))
+    )
+}
+
+/// 开启 TUN 或透明代理时探测连接会被内核接管，无论目标是否在内网都能连上
+fn traffic_intercepted() -> bool {
+    let tun = Config::verge().latest().enable_tun_mode.unwrap_or(false);
+    #[cfg(target_os = "linux")]
+    let tun = tun || crate::core::tproxy::core_routing_mark().is_some();
+    tun
+}
+
+/// 只探测其他条件已满足的规则，避免无谓的连接
+///
+/// 流量被内核接管时无法判断可达性，`reachable` 条件一律视为不满足
+async fn probe_reachable(
+    rules: &[&NetworkRule],
+    context: &NetworkContext,
+    intercepted: bool,
+) -> HashSet<String> {
+    if intercepted {
+        logging!(
+            debug,
+            Type::Network,
+            true,
+            "Traffic is intercepted by the core, reachable conditions are not matched"
+        );
+        return HashSet::new();
+    }
+    let targets = rules
+        .iter()
+        .filter(|rule| static_matches(&rule.conditions, context))
+        .filter_map(|rule| rule.conditions.reachable.clone())
+        .collect::<BTreeSet<_>>();
+    let results = join_all(targets.iter().map(|target| probe(target))).await;
+    targets
+        .into_iter()
+        .zip(results)
+        .filter_map(|(target, ok)| ok.then_some(target))
+        .collect()
+}
+
+/// 评估规则，暂停时只返回匹配结果而不切换
+pub async fn evaluate() -> Result<Option<String>> {
+    let _guard = EVALUATING.lock().await;
+    let config = INetworkRules::new();
+    let rules = config.sorted_rules();
+    if rules.is_empty() {
+        return Ok(None);
+    }
+
+    let context = NetworkContext::capture().await;
+    let reachable = probe_reachable(&rules, &context, traffic_intercepted()).await;
+    let Some(rule) = select_rule(&rules, &context, &reachable) else {
+        logging!(
+            debug,
+            Type::Network,
+            true,
+            "No network rule matched: {:?}",
+            context
+        );
+        *LAST_APPLIED.lock() = None;
+        return Ok(None);
+    };
+
+    if config.is_paused() {
+        logging!(
+            info,
+            Type::Network,
+            true,
+            "Network rule {} matched, auto switching is paused",
+            rule.name
+        );
+        return Ok(Some(rule.name.clone()));
+    }
+    if LAST_APPLIED.lock().as_ref() == Some(&rule.name) {
+        return Ok(Some(rule.name.clone()));
+    }
+    apply(rule)?;
+    *LAST_APPLIED.lock() = Some(rule.name.clone());
+    Ok(Some(rule.name.clone()))
+}
+
+fn apply(rule: &NetworkRule) -> Result<()> {
+    let mut changed = false;
+
+    if let Some(mode) = &rule.mode {
+        let mode = mode.to_lowercase();
+        if !MODES.contains(&mode.as_str()) {
+            bail!("invalid mode `{mode}` in network rule {}", rule.name);
+        }
+        let current = Config::clash()
+            .latest()
+            .0
+            .get("mode")
+            .and_then(|mode| mode.as_str())
+            .map(String::from);
+        if current.as_deref() != Some(mode.as_str()) {
+            feat::change_clash_mode(mode);
+            changed = true;
+        }
+    }
+
+    if let Some(uid) = &rule.profile {
+        let profiles = Config::profiles().latest().clone();
+        if profiles.get_item(uid).is_err() {
+            bail!("profile `{uid}` in network rule {} not found", rule.name);
+        }
+        if profiles.get_current().as_ref() != Some(uid) {
+            feat::toggle_proxy_profile(uid.clone());
+            changed = true;
+        }
+    }
+
+    if changed {
+        logging!(
+            info,
+            Type::Network,
+            true,
+            "Applied network rule {}",
+            rule.name
+        );
+        handle::Handle::notice_message("network_rule_applied", &rule.name);
+    }
+    Ok(())
+}
+
+/// 切换手动暂停，恢复时立即重新评估
+pub async fn set_paused(paused: bool) -> Result<()> {
+    let mut config = INetworkRules::new();
+    config.paused = Some(paused);
+    config.save_file()?;
+    if !paused {
+        *LAST_APPLIED.lock() = None;
+        evaluate().await?;
+    }
+    Ok(())
+}
+
+/// 规则修改后清除记录，使新规则立即生效
+pub async fn save_rules(config: INetworkRules) -> Result<()> {
+    config.save_file()?;
+    *LAST_APPLIED.lock() = None;
+    evaluate().await?;
+    Ok(())
+}
+
+/// 解析 `route -n get default`（macOS）
+#[cfg(any(target_os = "macos", test))]
+pub fn parse_route_get(output: &str) -> Option<IpAddr> {
+    output.lines().find_map(|line| {
+        let value = line.trim().strip_prefix("gateway:")?;
+        value.trim().parse().ok()
+    })
+}
+
+/// 解析 `route print -4 0.0.0.0`（Windows），取跃点数最小的默认路由
+#[cfg(any(target_os = "windows", test))]
+pub fn parse_route_print(output: &str) -> Option<IpAddr> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            if fields.len() < 5 || fields[0] != "0.0.0.0" || fields[1] != "0.0.0.0" {
+                return None;
+            }
+            let gateway = fields[2].parse::<IpAddr>().ok()?;
+            let metric = fields[4].parse::<u32>().unwrap_or(u32::MAX);
+            Some((metric, gateway))
+        })
+        .min_by_key(|(metric, _)| *metric)
+        .map(|(_, gateway)| gateway)
+}
+
+/// 从 /proc/net/arp、`arp -n`、`arp -a` 的输出中找出网关的 MAC
+pub fn parse_arp(output: &str, ip: IpAddr) -> Option<String> {
+    let ip = ip.to_string();
+    output.lines().find_map(|line| {
+        let mut tokens = line
+            .split(|ch: char| ch.is_whitespace() || ch == '(' || ch == ')')
+            .filter(|token| !token.is_empty());
+        tokens.clone().find(|token| *token == ip)?;
+        tokens
+            .find_map(normalize_mac)
+            .filter(|mac| mac != "00:00:00:00:00:00")
+    })
+}
+
+/// resolv.conf 的 search / domain 行
+#[cfg(any(not(target_os = "windows"), test))]
+pub fn parse_resolv_conf(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            matches!(parts.next(), Some("search" | "domain")).then_some(parts)
+        })
+        .flatten()
+        .map(normalize_suffix)
+        .filter(|suffix| !suffix.is_empty())
+        .collect()
+}
+
+/// `ipconfig /all` 中的 DNS 后缀
+#[cfg(any(target_os = "windows", test))]
+pub fn parse_ipconfig(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter(|line| line.contains("DNS Suffix"))
+        .filter_map(|line| line.split_once(':'))
+        .map(|(_, value)| normalize_suffix(value))
+        .filter(|suffix| !suffix.is_empty())
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+async fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let mut command = tokio::process::Command::new(program);
+    command.args(args);
+    #[cfg(target_os = "windows")]
+    command.creation_flags(0x08000000);
+    let output = command.output().await.ok()?;
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(target_os = "linux")]
+async fn default_gateway() -> Option<IpAddr> {
+    let content = tokio::fs::read_to_string("/proc/net/route").await.ok()?;
+    crate::core::network_monitor::parse_proc_route(&content).map(|(_, gateway)| gateway.into())
+}
+
+#[cfg(target_os = "macos")]
+async fn default_gateway() -> Option<IpAddr> {
+    parse_route_get(&command_output("route", &["-n", "get", "default"]).await?)
+}
+
+#[cfg(target_os = "windows")]
+async fn default_gateway() -> Option<IpAddr> {
+    parse_route_print(&command_output("route", &["print", "-4", "0.0.0.0"]).await?)
+}
+
+#[cfg(target_os = "linux")]
+async fn gateway_mac(ip: IpAddr) -> Option<String> {
+    parse_arp(&tokio::fs::read_to_string("/proc/net/arp").await.ok()?, ip)
+}
+
+#[cfg(target_os = "macos")]
+async fn gateway_mac(ip: IpAddr) -> Option<String> {
+    parse_arp(&command_output("arp", &["-n", &ip.to_string()]).await?, ip)
+}
+
+#[cfg(target_os = "windows")]
+async fn gateway_mac(ip: IpAddr) -> Option<String> {
+    parse_arp(&command_output("arp", &["-a", &ip.to_string()]).await?, ip)
+}
+
+#[cfg(not(target_os = "windows"))]
+async fn dns_suffixes() -> Vec<String> {
+    tokio::fs::read_to_string("/etc/resolv.conf")
+        .await
+        .map(|content| parse_resolv_conf(&content))
+        .unwrap_or_default()
+}
+
+#[cfg(target_os = "windows")]
+async fn dns_suffixes() -> Vec<String> {
+    command_output("ipconfig", &["/all"])
+        .await
+        .map(|output| parse_ipconfig(&output))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> NetworkContext {
+        NetworkContext {
+            interfaces: BTreeMap::from([(
+                "wlan0".to_string(),
+                BTreeSet::from(["192.168.1.20".parse().unwrap()]),
+            )]),
+            gateway_ip: "192.168.1.1".parse().ok(),
+            gateway_mac: Some("a4:91:b1:02:03:04".into()),
+            dns_suffixes: vec!["corp.example.com".into()],
+        }
+    }
+
+    fn rule(name: &str, priority: i32, conditions: NetworkCondition) -> NetworkRule {
+        NetworkRule {
+            name: name.into(),
+            priority: Some(priority),
+            conditions,
+            ..NetworkRule::default()
+        }
+    }
+
+    #[test]
+    fn test_static_matches() {
+        let context = context();
+        let matches = |condition: NetworkCondition| static_matches(&condition, &context);
+        assert!(matches(NetworkCondition::default()));
+        assert!(matches(NetworkCondition {
+            interface: Some("wlan*".into()),
+            subnet: Some("192.168.0.0/16".into()),
+            gateway_mac: Some("A4-91-B1-02-03-04".into()),
+            ..Default::default()
+        }));
+        assert!(matches(NetworkCondition {
+            gateway_ip: Some("192.168.1.1".into()),
+            dns_suffix: Some("Corp.Example.com.".into()),
+            ..Default::default()
+        }));
+        assert!(!matches(NetworkCondition {
+            interface: Some("eth*".into()),
+            ..Default::default()
+        }));
+        assert!(!matches(NetworkCondition {
+            subnet: Some("10.0.0.0/8".into()),
+            ..Default::default()
+        }));
+        assert!(!matches(NetworkCondition {
+            gateway_mac: Some("invalid".into()),
+            ..Default::default()
+        }));
+    }
+
+    #[test]
+    fn test_select_rule() {
+        let office = rule(
+            "office",
+            10,
+            NetworkCondition {
+                reachable: Some("intranet.corp:443".into()),
+                ..Default::default()
+            },
+        );
+        let home = rule(
+            "home",
+            5,
+            NetworkCondition {
+                subnet: Some("192.168.1.0/24".into()),
+                ..Default::default()
+            },
+        );
+        let fallback = rule("fallback", 0, NetworkCondition::default());
+        let config = INetworkRules {
+            paused: None,
+            rules: Some(vec![fallback.clone(), home.clone(), office.clone()]),
+        };
+        let rules = config.sorted_rules();
+        assert_eq!(
+            rules
+                .iter()
+                .map(|rule| rule.name.as_str())
+                .collect::<Vec<_>>(),
+            ["office", "home", "fallback"]
+        );
+
+        let context = context();
+        let reachable = HashSet::from(["intranet.corp:443".to_string()]);
+        assert_eq!(select_rule(&rules, &context, &reachable), Some(&office));
+        assert_eq!(select_rule(&rules, &context, &HashSet::new()), Some(&home));
+        assert_eq!(
+            select_rule(&rules, &NetworkContext::default(), &HashSet::new()),
+            Some(&fallback)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_probe_reachable() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target = listener.local_addr().unwrap().to_string();
+        let office = rule(
+            "office",
+            0,
+            NetworkCondition {
+                reachable: Some(target.clone()),
+                ..NetworkCondition::default()
+            },
+        );
+        let rules = [&office];
+
+        let reachable = probe_reachable(&rules, &context(), false).await;
+        assert!(reachable.contains(&target));
+        assert!(probe_reachable(&rules, &context(), true).await.is_empty());
+    }
+
+    #[test]
+    fn test_in_subnet() {
+        let addr = "192.168.1.20".parse().unwrap();
+        assert!(in_subnet(&addr, "192.168.1.0/24"));
+        assert!(in_subnet(&addr, "0.0.0.0/0"));
+        assert!(in_subnet(&addr, "192.168.1.20"));
+        assert!(!in_subnet(&addr, "192.168.2.0/24"));
+        assert!(!in_subnet(&addr, "fd00::/8"));
+        assert!(in_subnet(&"fd00::1".parse().unwrap(), "fd00::/8"));
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("wlan0", "wlan0"));
+        assert!(wildcard_match("wl*", "wlp2s0"));
+        assert!(wildcard_match("*s0", "wlp2s0"));
+        assert!(wildcard_match("w*p*0", "wlp2s0"));
+        assert!(!wildcard_match("eth*", "wlp2s0"));
+        assert!(!wildcard_match("wlan", "wlan0"));
+    }
+
+    #[test]
+    fn test_parse_gateway() {
+        let route_get = "   route to: default\ndestination: default\n    gateway: 192.168.1.1\n  interface: en0\n";
+        assert_eq!(parse_route_get(route_get), "192.168.1.1".parse().ok());
+
+        let route_print = "IPv4 Route Table\n\
+Network Destination        Netmask          Gateway       Interface  Metric\n\
+          0.0.0.0          0.0.0.0      10.0.0.1      10.0.0.5     50\n\
+          0.0.0.0          0.0.0.0      192.168.1.1   192.168.1.20     25\n";
+        assert_eq!(parse_route_print(route_print), "192.168.1.1".parse().ok());
+    }
+
+    #[test]
+    fn test_parse_arp() {
+        let ip = "192.168.1.1".parse().unwrap();
+        let proc_arp =
+            "IP address       HW type     Flags       HW address            Mask     Device\n\
+192.168.1.1      0x1         0x2         a4:91:b1:02:03:04     *        wlan0\n";
+        let macos = "? (192.168.1.1) at a4:91:b1:2:3:4 on en0 ifscope [ethernet]\n";
+        let windows = "Interface: 192.168.1.20 --- 0x5\n  Internet Address      Physical Address      Type\n  192.168.1.1           a4-91-b1-02-03-04     dynamic\n";
+        for output in [proc_arp, macos, windows] {
+            assert_eq!(parse_arp(output, ip).as_deref(), Some("a4:91:b1:02:03:04"));
+        }
+        assert_eq!(parse_arp(proc_arp, "192.168.1.2".parse().unwrap()), None);
+    }
+
+    #[test]
+    fn test_parse_dns_suffix() {
+        let resolv = "# comment\nnameserver 127.0.0.53\nsearch corp.example.com lan.\n";
+        assert_eq!(parse_resolv_conf(resolv), ["corp.example.com", "lan"]);
+
+        let ipconfig = "   Primary Dns Suffix  . . . . . . . : \n   DNS Suffix Search List. . . . . . : corp.example.com\n   Connection-specific DNS Suffix  . : home.lan\n";
+        assert_eq!(parse_ipconfig(ipconfig), ["corp.example.com", "home.lan"]);
+    }
+}
@@ -0,0 +1,80 @@
+use once_cell::sync::OnceCell;
+use parking_lot::RwLock;
+use serde::Serialize;
+
+/// 应用的降级状态
+///
+/// 配置验证或内核启动失败、改用最近可用配置时进入降级状态，
+/// 直到新的配置成功应用到内核
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DegradedState {
+    pub degraded: bool,
+    /// 导致降级的原始错误
+    pub reason: Option<String>,
+    /// 正在使用的快照对应的订阅
+    pub uid: Option<String>,
+    /// 正在使用的快照的保存时间
+    pub snapshot_time: Option<i64>,
+}
+
+impl DegradedState {
+    fn global() -> &'static RwLock<DegradedState> {
+        static INSTANCE: OnceCell<RwLock<DegradedState>> = OnceCell::new();
+        INSTANCE.get_or_init(|| RwLock::new(DegradedState::default()))
+    }
+
+    pub fn get() -> DegradedState {
+        Self::global().read().clone()
+    }
+
+    pub fn is_degraded() -> bool {
+        Self::global().read().degraded
+    }
+
+    pub fn enter(reason: String, uid: Option<String>, snapshot_time: Option<i64>) {
+        *Self::global().write() = DegradedState {
+            degraded: true,
+            reason: Some(reason),
+            uid,
+            snapshot_time,
+        };
+    }
+
+    /// 记录新配置的应用结果，成功应用时离开降级状态并返回 true
+    pub fn on_config_applied(applied: bool) -> bool {
+        Self::global().write().leave_if_applied(applied)
+    }
+
+    fn leave_if_applied(&mut self, applied: bool) -> bool {
+        if !applied || !self.degraded {
+            return false;
+        }
+        *self = DegradedState::default();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_leave_only_when_applied() {
+        let mut state = DegradedState {
+            degraded: true,
+            reason: Some("core crash loop".into()),
+            uid: Some("profile".into()),
+            snapshot_time: Some(1_700_000_000),
+        };
+        assert!(!state.leave_if_applied(false));
+        assert!(state.degraded);
+        assert_eq!(state.reason.as_deref(), Some("core crash loop"));
+
+        assert!(state.leave_if_applied(true));
+        assert!(!state.degraded);
+        assert!(state.reason.is_none());
+
+        // 未降级时应用成功不算离开降级
+        assert!(!state.leave_if_applied(true));
+    }
+}
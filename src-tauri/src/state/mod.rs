@@ -1,5 +1,6 @@
 // Tauri Manager 会进行 Arc 管理，无需额外 Arc
 // https://tauri.app/develop/state-management/#do-you-need-arc
 
+pub mod degraded;
 pub mod lightweight;
 pub mod proxy;
@@ -19,6 +19,8 @@ pub static PORTABLE_FLAG: OnceCell<bool> = OnceCell::new();
 pub static CLASH_CONFIG: &str = "config.yaml";
 pub static VERGE_CONFIG: &str = "verge.yaml";
 pub static PROFILE_YAML: &str = "profiles.yaml";
+pub static LAST_GOOD_CONFIG: &str = "last-good.yaml";
+pub static NETWORK_RULES_CONFIG: &str = "network_rules.yaml";
 
 /// init portable flag
 pub fn init_portable_flag() -> Result<()> {
@@ -81,14 +83,11 @@ pub fn app_home_dir() -> Result<PathBuf> {
                 }
             }
 
+            // 与 tauri 的 data_dir 一致，遵循 XDG_DATA_HOME
             #[cfg(target_os = "linux")]
             {
-                if let Some(home) = std::env::var_os("HOME") {
-                    let path = PathBuf::from(home)
-                        .join(".local")
-                        .join("share")
-                        .join(APP_ID);
-                    return Ok(path);
+                if let Some(data_dir) = ::dirs::data_dir() {
+                    return Ok(data_dir.join(APP_ID));
                 }
             }
 
@@ -120,6 +119,13 @@ pub fn app_resources_dir() -> Result<PathBuf> {
                 .parent()
                 .ok_or(anyhow::anyhow!("failed to get executable directory"))?
                 .to_path_buf();
+            // 与 tauri 的 resource_dir 一致，deb/rpm 安装时资源位于 /usr/lib/<productName>
+            #[cfg(target_os = "linux")]
+            if exe_dir.ends_with("usr/bin") {
+                if let Some(usr_dir) = exe_dir.parent() {
+                    return Ok(usr_dir.join("lib").join("Koala Clash").join("resources"));
+                }
+            }
             return Ok(exe_dir.join("resources"));
         }
     };
@@ -185,6 +191,14 @@ pub fn profiles_path() -> Result<PathBuf> {
     Ok(app_home_dir()?.join(PROFILE_YAML))
 }
 
+pub fn last_good_path() -> Result<PathBuf> {
+    Ok(app_home_dir()?.join(LAST_GOOD_CONFIG))
+}
+
+pub fn network_rules_path() -> Result<PathBuf> {
+    Ok(app_home_dir()?.join(NETWORK_RULES_CONFIG))
+}
+
 #[cfg(target_os = "macos")]
 pub fn service_path() -> Result<PathBuf> {
     let res_dir = app_resources_dir()?;
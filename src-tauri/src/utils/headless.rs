@@ -0,0 +1,177 @@
+//! Linux 无界面守护模式：不创建窗口和托盘，通过本地接口或 CLI 控制
+
+use crate::{
+    config::IVerge,
+    core::{event_driven_proxy::EventDrivenProxyManager, handle},
+    feat, logging,
+    utils::{logging::Type, resolve},
+};
+use anyhow::{anyhow, Result};
+use once_cell::sync::OnceCell;
+use std::{
+    net::{SocketAddr, TcpStream},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tauri::async_runtime;
+
+pub const HEADLESS_ARG: &str = "--headless";
+/// 输出 systemd 用户单元
+pub const PRINT_UNIT_ARG: &str = "--print-systemd-unit";
+/// 写入 systemd 用户单元
+pub const INSTALL_UNIT_ARG: &str = "--install-systemd-unit";
+
+const UNIT_NAME: &str = "koala-clash.service";
+/// 检测单例端口的连接超时
+const PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+
+static HEADLESS: OnceCell<bool> = OnceCell::new();
+
+/// 根据启动参数初始化，仅 Linux 支持无界面模式
+pub fn init_headless_flag() {
+    let requested = std::env::args().any(|arg| arg == HEADLESS_ARG);
+    if requested && !cfg!(target_os = "linux") {
+        log::warn!(target: "app", "{HEADLESS_ARG} is only supported on Linux, ignored");
+    }
+    HEADLESS.get_or_init(|| requested && cfg!(target_os = "linux"));
+}
+
+pub fn is_headless() -> bool {
+    *HEADLESS.get().unwrap_or(&false)
+}
+
+/// 无界面模式入口，不构建 tauri 应用，避免没有 DISPLAY 时创建事件循环失败
+pub fn run() {
+    // 没有 tauri 的单例插件，已有实例监听单例端口时直接退出
+    let port = IVerge::get_singleton_port();
+    if instance_running(port) {
+        logging!(
+            error,
+            Type::Setup,
+            true,
+            "Another instance is already running on port {}",
+            port
+        );
+        eprintln!("error: another instance is already running on port {port}");
+        std::process::exit(1);
+    }
+
+    logging!(info, Type::Setup, true, "Starting in headless mode");
+    async_runtime::block_on(async {
+        resolve::resolve_setup_headless().await;
+        wait_for_shutdown().await;
+    });
+
+    logging!(
+        info,
+        Type::System,
+        true,
+        "Shutdown signal received, cleaning up"
+    );
+    handle::Handle::global().set_is_exiting();
+    EventDrivenProxyManager::global().notify_app_stopping();
+    feat::clean();
+}
+
+fn instance_running(port: u16) -> bool {
+    TcpStream::connect_timeout(&SocketAddr::from(([127, 0, 0, 1], port)), PROBE_TIMEOUT).is_ok()
+}
+
+#[cfg(unix)]
+async fn wait_for_shutdown() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    match signal(SignalKind::terminate()) {
+        Ok(mut terminate) => {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = terminate.recv() => {}
+            }
+        }
+        Err(err) => {
+            log::warn!(target: "app", "Failed to listen for SIGTERM: {err}");
+            let _ = tokio::signal::ctrl_c().await;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_shutdown() {
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+/// 处理 systemd 单元相关参数，返回 true 时不再启动应用
+pub fn handle_unit_args() -> bool {
+    let args = std::env::args().collect::<Vec<_>>();
+    let result = if args.iter().any(|arg| arg == PRINT_UNIT_ARG) {
+        current_unit().map(|unit| println!("{unit}"))
+    } else if args.iter().any(|arg| arg == INSTALL_UNIT_ARG) {
+        install_unit().map(|path| {
+            println!("Installed {}", path.display());
+            println!(
+                "Run: systemctl --user daemon-reload && systemctl --user enable --now {UNIT_NAME}"
+            );
+        })
+    } else {
+        return false;
+    };
+    if let Err(err) = result {
+        eprintln!("error: {err}");
+        std::process::exit(1);
+    }
+    true
+}
+
+fn current_unit() -> Result<String> {
+    let exe = dunce::canonicalize(tauri::utils::platform::current_exe()?)?;
+    Ok(systemd_unit(&exe))
+}
+
+fn install_unit() -> Result<PathBuf> {
+    let dir = dirs::config_dir()
+        .ok_or(anyhow!("failed to get the config dir"))?
+        .join("systemd")
+        .join("user");
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(UNIT_NAME);
+    std::fs::write(&path, current_unit()?)?;
+    Ok(path)
+}
+
+/// 生成 systemd 用户单元
+pub fn systemd_unit(exe: &Path) -> String {
+    format!(
+        "[Unit]
+Description=Koala Clash (headless)
+After=network-online.target
+Wants=network-online.target
+
+[Service]
+Type=simple
+ExecStart=\"{}\" {HEADLESS_ARG}
+Restart=on-failure
+RestartSec=5
+
+[Install]
+WantedBy=default.target
+",
+        exe.display()
+    )
+}
+
+#[test]
+fn test_systemd_unit() {
+    let unit = systemd_unit(Path::new("/opt/koala clash/koala-clash"));
+    assert!(unit.contains("ExecStart=\"/opt/koala clash/koala-clash\" --headless\n"));
+    assert!(unit.contains("WantedBy=default.target"));
+    assert!(unit.starts_with("[Unit]\n"));
+}
+
+#[test]
+fn test_instance_running() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    assert!(instance_running(port));
+    drop(listener);
+    assert!(!instance_running(port));
+}
@@ -145,6 +145,28 @@ pub fn linux_elevator() -> String {
     }
 }
 
+/// 以 root 执行脚本，非 root 时通过 pkexec/sudo 提权
+#[cfg(target_os = "linux")]
+pub async fn run_elevated_script(name: &str, script: &str) -> Result<()> {
+    use tokio::process::Command;
+
+    let path = crate::utils::dirs::app_home_dir()?.join(name);
+    fs::write(&path, script)?;
+    let output = if unsafe { libc::geteuid() } == 0 {
+        Command::new("sh").arg(&path).output().await?
+    } else {
+        Command::new(linux_elevator())
+            .arg("sh")
+            .arg(&path)
+            .output()
+            .await?
+    };
+    if !output.status.success() {
+        bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
 /// return the string literal error
 #[macro_export]
 macro_rules! ret_err {
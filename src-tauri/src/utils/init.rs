@@ -297,6 +297,13 @@ pub fn init_config() -> Result<()> {
         <Result<()>>::Ok(())
     }));
 
+    crate::log_err!(dirs::network_rules_path().map(|path| {
+        if !path.exists() {
+            INetworkRules::template().save_file()?;
+        }
+        <Result<()>>::Ok(())
+    }));
+
     // 初始化DNS配置文件
     let _ = init_dns_config();
 
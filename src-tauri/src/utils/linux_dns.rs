@@ -0,0 +1,479 @@
+//! Linux 系统 DNS 接管：优先使用 systemd-resolved 的链路配置，否则托管 /etc/resolv.conf
+//!
+//! 接管状态写入 app_home，异常退出后下次启动会据此恢复
+
+use crate::{
+    config::Config,
+    utils::{dirs, help},
+};
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+};
+use tokio::process::Command;
+
+const RESOLV_CONF: &str = "/etc/resolv.conf";
+const STATE_FILE: &str = "dns_takeover.json";
+const SCRIPT_NAME: &str = "dns_takeover.sh";
+const DEFAULT_FAKE_IP_RANGE: &str = "198.18.0.1/16";
+/// 写入 resolv.conf 的 heredoc 结束标记
+const HEREDOC_DELIMITER: &str = "KOALA_EOF";
+
+/// 已生效的接管方式
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum DnsTakeover {
+    /// systemd-resolved 单链路配置，revert 即可恢复
+    Resolved { link: String, target: SocketAddr },
+    /// 原 /etc/resolv.conf，符号链接时记录链接目标
+    ResolvConf {
+        symlink: Option<String>,
+        content: String,
+        target: SocketAddr,
+    },
+}
+
+impl DnsTakeover {
+    pub fn target(&self) -> SocketAddr {
+        match self {
+            Self::Resolved { target, .. } | Self::ResolvConf { target, .. } => *target,
+        }
+    }
+}
+
+/// 计算系统解析器应指向的地址：优先 dns.listen，否则借助 TUN 的 dns-hijack
+pub fn resolver_target(dns: &Mapping, tun: &Mapping) -> Option<SocketAddr> {
+    if let Some(listen) = dns.get("listen").and_then(Value::as_str) {
+        let (host, port) = listen.rsplit_once(':')?;
+        let port = port.parse().ok()?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let ip = match host.parse::<IpAddr>() {
+            Ok(ip) if !ip.is_unspecified() => ip,
+            Ok(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+            Err(_) if host.is_empty() => IpAddr::V4(Ipv4Addr::LOCALHOST),
+            Err(_) => return None,
+        };
+        return Some(SocketAddr::new(ip, port));
+    }
+
+    let hijacks = tun.get("dns-hijack").and_then(Value::as_sequence)?;
+    hijacks.iter().filter_map(Value::as_str).find_map(|hijack| {
+        let hijack = hijack.split_once("://").map_or(hijack, |(_, rest)| rest);
+        let (host, port) = hijack.rsplit_once(':')?;
+        let port = port.parse::<u16>().ok()?;
+        match host {
+            // 任意地址都会被劫持，使用 fake-ip 网段中的地址保证流量进入 TUN
+            "any" | "0.0.0.0" => {
+                let range = dns
+                    .get("fake-ip-range")
+                    .and_then(Value::as_str)
+                    .unwrap_or(DEFAULT_FAKE_IP_RANGE);
+                let gateway = range.split('/').next()?.parse::<Ipv4Addr>().ok()?;
+                let ip = Ipv4Addr::from(u32::from(gateway).checked_add(1)?);
+                Some(SocketAddr::new(IpAddr::V4(ip), port))
+            }
+            host => Some(SocketAddr::new(host.parse().ok()?, port)),
+        }
+    })
+}
+
+/// 运行时配置开启 TUN 且使用 fake-ip 时系统解析器应指向的地址
+pub fn takeover_target(config: &Mapping) -> Option<SocketAddr> {
+    let tun = config.get("tun").and_then(Value::as_mapping)?;
+    if !tun.get("enable").and_then(Value::as_bool).unwrap_or(false) {
+        return None;
+    }
+    let dns = config
+        .get("dns")
+        .and_then(Value::as_mapping)
+        .cloned()
+        .unwrap_or_default();
+    let mode = dns
+        .get("enhanced-mode")
+        .and_then(Value::as_str)
+        .unwrap_or("fake-ip");
+    if mode != "fake-ip" {
+        return None;
+    }
+    resolver_target(&dns, tun)
+}
+
+/// 从 `ip route show default` 的输出中取出默认路由网卡
+pub fn parse_default_route(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        parts.find(|part| *part == "dev")?;
+        parts.next().map(String::from)
+    })
+}
+
+fn sh_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// 内容中出现结束标记时 heredoc 会提前结束，之后的行会被当作命令执行
+fn check_heredoc(content: &str) -> Result<()> {
+    if content.lines().any(|line| line == HEREDOC_DELIMITER) {
+        bail!("resolv.conf content contains the heredoc delimiter");
+    }
+    Ok(())
+}
+
+/// 覆盖 resolv.conf 的脚本，原文件为符号链接时先删除链接
+pub fn write_resolv_conf_script(content: &str) -> Result<String> {
+    check_heredoc(content)?;
+    let mut content = content.to_string();
+    if !content.ends_with('\n') {
+        content.push('\n');
+    }
+    Ok(format!(
+        "#!/bin/sh\nset -e\nrm -f {path}\ncat > {path} <<'{HEREDOC_DELIMITER}'\n{content}{HEREDOC_DELIMITER}\n",
+        path = sh_quote(RESOLV_CONF)
+    ))
+}
+
+/// 接管后的 resolv.conf，保留原文件中的 search/domain/options
+pub fn resolv_conf_content(ip: IpAddr, original: &str) -> String {
+    let mut content = format!("# Generated by Koala Clash, restored on exit\nnameserver {ip}\n");
+    for line in original.lines() {
+        let key = line.split_whitespace().next();
+        if matches!(key, Some("search" | "domain" | "options")) {
+            content += line.trim();
+            content.push('\n');
+        }
+    }
+    content
+}
+
+/// 恢复 resolv.conf 的脚本，systemd-resolved 不需要脚本
+pub fn restore_script(takeover: &DnsTakeover) -> Result<Option<String>> {
+    Ok(match takeover {
+        DnsTakeover::Resolved { .. } => None,
+        DnsTakeover::ResolvConf {
+            symlink: Some(target),
+            ..
+        } => Some(format!(
+            "#!/bin/sh\nset -e\nrm -f {path}\nln -s {target} {path}\n",
+            path = sh_quote(RESOLV_CONF),
+            target = sh_quote(target)
+        )),
+        DnsTakeover::ResolvConf { content, .. } => Some(write_resolv_conf_script(content)?),
+    })
+}
+
+fn state_path() -> Result<PathBuf> {
+    Ok(dirs::app_home_dir()?.join(STATE_FILE))
+}
+
+fn load_state() -> Option<DnsTakeover> {
+    let content = std::fs::read_to_string(state_path().ok()?).ok()?;
+    parse_state(&content)
+}
+
+/// 状态文件会以提权脚本执行，无法安全生成恢复脚本的记录直接丢弃
+fn parse_state(content: &str) -> Option<DnsTakeover> {
+    let state = serde_json::from_str::<DnsTakeover>(content).ok()?;
+    if let DnsTakeover::ResolvConf { content, .. } = &state {
+        if let Err(err) = check_heredoc(content) {
+            log::warn!(target: "app", "invalid dns takeover state: {err}");
+            return None;
+        }
+    }
+    Some(state)
+}
+
+fn save_state(state: &DnsTakeover) -> Result<()> {
+    std::fs::write(state_path()?, serde_json::to_string(state)?)?;
+    Ok(())
+}
+
+async fn resolved_available() -> bool {
+    Path::new("/run/systemd/resolve").is_dir()
+        && Command::new("resolvectl")
+            .arg("--version")
+            .output()
+            .await
+            .is_ok_and(|output| output.status.success())
+}
+
+async fn default_link() -> Result<String> {
+    let output = Command::new("ip")
+        .args(["route", "show", "default"])
+        .output()
+        .await?;
+    parse_default_route(&String::from_utf8_lossy(&output.stdout))
+        .ok_or(anyhow!("failed to find the default route interface"))
+}
+
+/// 先直接调用 resolvectl（由 polkit 鉴权），失败时再提权执行
+async fn resolvectl(args: &[&str]) -> Result<()> {
+    let output = Command::new("resolvectl").args(args).output().await?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let command = std::iter::once("resolvectl")
+        .chain(args.iter().copied())
+        .map(sh_quote)
+        .collect::<Vec<_>>()
+        .join(" ");
+    help::run_elevated_script(SCRIPT_NAME, &format!("#!/bin/sh\n{command}\n")).await
+}
+
+/// 按运行时配置接管或恢复系统 DNS，内核启动或配置更新成功后调用
+pub async fn sync() -> Result<()> {
+    let target = Config::runtime()
+        .latest()
+        .config
+        .as_ref()
+        .and_then(takeover_target);
+    match target {
+        Some(target) => takeover(target).await,
+        None => restore().await,
+    }
+}
+
+/// systemd-resolved 接管的网卡已不是默认路由网卡
+async fn link_changed(state: &DnsTakeover) -> bool {
+    match state {
+        DnsTakeover::Resolved { link, .. } => {
+            default_link().await.is_ok_and(|current| current != *link)
+        }
+        DnsTakeover::ResolvConf { .. } => false,
+    }
+}
+
+/// 将系统解析器指向内核 DNS，目标或默认网卡变化时重新接管
+pub async fn takeover(target: SocketAddr) -> Result<()> {
+    let previous = load_state();
+    // 目标与网卡都未变化时避免重复提权
+    if let Some(state) = &previous {
+        if state.target() == target && !link_changed(state).await {
+            return Ok(());
+        }
+    }
+
+    if resolved_available().await {
+        if previous.is_some() {
+            restore().await?;
+        }
+        let link = default_link().await?;
+        let state = DnsTakeover::Resolved {
+            link: link.clone(),
+            target,
+        };
+        // 先记录状态，设置中途崩溃也能恢复；设置失败时撤销
+        save_state(&state)?;
+        let applied = async {
+            resolvectl(&["dns", &link, &target.to_string()]).await?;
+            resolvectl(&["domain", &link, "~."]).await
+        };
+        if let Err(err) = applied.await {
+            if let Err(err) = resolvectl(&["revert", &link]).await {
+                log::warn!(target: "app", "resolvectl revert {link} failed: {err}");
+            }
+            std::fs::remove_file(state_path()?)?;
+            return Err(err);
+        }
+        if let Err(err) = resolvectl(&["default-route", &link, "yes"]).await {
+            log::debug!(target: "app", "resolvectl default-route failed: {err}");
+        }
+        log::info!(target: "app", "dns takeover via systemd-resolved on {link}: {target}");
+        return Ok(());
+    }
+
+    if target.port() != 53 {
+        bail!(
+            "{RESOLV_CONF} only supports port 53, dns listener is {}",
+            target
+        );
+    }
+    // 已经接管 resolv.conf 时保留最初的原配置直接重写，避免恢复再接管两次提权
+    let (symlink, content) = match previous.clone() {
+        Some(DnsTakeover::ResolvConf {
+            symlink, content, ..
+        }) => (symlink, content),
+        other => {
+            if other.is_some() {
+                restore().await?;
+            }
+            let path = Path::new(RESOLV_CONF);
+            let symlink = path
+                .symlink_metadata()
+                .is_ok_and(|meta| meta.file_type().is_symlink())
+                .then(|| std::fs::read_link(path).ok())
+                .flatten()
+                .map(|target| target.to_string_lossy().into_owned());
+            (symlink, std::fs::read_to_string(path).unwrap_or_default())
+        }
+    };
+    let script = write_resolv_conf_script(&resolv_conf_content(target.ip(), &content))?;
+    let state = DnsTakeover::ResolvConf {
+        symlink,
+        content,
+        target,
+    };
+    // 先记录原状态，写入中途崩溃也能恢复
+    save_state(&state)?;
+    if let Err(err) = help::run_elevated_script(SCRIPT_NAME, &script).await {
+        // 写入失败时 resolv.conf 仍是之前的状态
+        match previous.filter(|state| matches!(state, DnsTakeover::ResolvConf { .. })) {
+            Some(previous) => save_state(&previous)?,
+            None => std::fs::remove_file(state_path()?)?,
+        }
+        return Err(err);
+    }
+    log::info!(target: "app", "dns takeover via {RESOLV_CONF}: {}", target.ip());
+    Ok(())
+}
+
+/// 恢复接管前的 DNS 配置，没有接管记录时什么都不做
+pub async fn restore() -> Result<()> {
+    let Some(state) = load_state() else {
+        return Ok(());
+    };
+    match &state {
+        DnsTakeover::Resolved { link, .. } => {
+            // 网卡已消失时配置随之失效，忽略错误
+            if let Err(err) = resolvectl(&["revert", link]).await {
+                log::warn!(target: "app", "resolvectl revert {link} failed: {err}");
+            }
+        }
+        DnsTakeover::ResolvConf { .. } => {
+            if let Some(script) = restore_script(&state)? {
+                help::run_elevated_script(SCRIPT_NAME, &script).await?;
+            }
+        }
+    }
+    std::fs::remove_file(state_path()?)?;
+    log::info!(target: "app", "system dns restored");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping(yaml: &str) -> Mapping {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_resolver_target() {
+        let tun = mapping("dns-hijack: ['any:53']");
+        let target = |dns: &str| resolver_target(&mapping(dns), &tun).map(|addr| addr.to_string());
+        assert_eq!(target("listen: ':53'").as_deref(), Some("127.0.0.1:53"));
+        assert_eq!(
+            target("listen: 0.0.0.0:1053").as_deref(),
+            Some("127.0.0.1:1053")
+        );
+        assert_eq!(
+            target("listen: 127.0.0.2:53").as_deref(),
+            Some("127.0.0.2:53")
+        );
+        assert_eq!(
+            target("fake-ip-range: 198.18.0.1/16").as_deref(),
+            Some("198.18.0.2:53")
+        );
+        assert_eq!(target("enable: true").as_deref(), Some("198.18.0.2:53"));
+
+        let tun = mapping("dns-hijack: ['tcp://10.0.0.1:5353']");
+        assert_eq!(
+            resolver_target(&Mapping::new(), &tun).map(|addr| addr.to_string()),
+            Some("10.0.0.1:5353".into())
+        );
+        assert_eq!(resolver_target(&Mapping::new(), &Mapping::new()), None);
+    }
+
+    #[test]
+    fn test_takeover_target() {
+        let config =
+            mapping("tun: {enable: true, dns-hijack: ['any:53']}\ndns: {listen: '127.0.0.1:1053'}");
+        assert_eq!(
+            takeover_target(&config).map(|addr| addr.to_string()),
+            Some("127.0.0.1:1053".into())
+        );
+        let redir_host = mapping(
+            "tun: {enable: true, dns-hijack: ['any:53']}\ndns: {enhanced-mode: redir-host}",
+        );
+        assert_eq!(takeover_target(&redir_host), None);
+        let disabled = mapping("tun: {enable: false, dns-hijack: ['any:53']}");
+        assert_eq!(takeover_target(&disabled), None);
+    }
+
+    #[test]
+    fn test_resolv_conf_content() {
+        let original = "nameserver 192.168.1.1\nsearch lan corp.example\n# comment\noptions edns0 trust-ad\ndomain lan\n";
+        let content = resolv_conf_content("127.0.0.1".parse().unwrap(), original);
+        assert_eq!(
+            content,
+            "# Generated by Koala Clash, restored on exit\nnameserver 127.0.0.1\nsearch lan corp.example\noptions edns0 trust-ad\ndomain lan\n"
+        );
+    }
+
+    #[test]
+    fn test_default_route() {
+        let output = "default via 192.168.1.1 dev wlp2s0 proto dhcp src 192.168.1.20 metric 600\n";
+        assert_eq!(parse_default_route(output).as_deref(), Some("wlp2s0"));
+        assert_eq!(parse_default_route(""), None);
+    }
+
+    #[test]
+    fn test_restore_script() {
+        let linked = DnsTakeover::ResolvConf {
+            symlink: Some("../run/systemd/resolve/stub-resolv.conf".into()),
+            content: "nameserver 127.0.0.53\n".into(),
+            target: "127.0.0.1:53".parse().unwrap(),
+        };
+        let script = restore_script(&linked).unwrap().unwrap();
+        assert!(script
+            .ends_with("ln -s '../run/systemd/resolve/stub-resolv.conf' '/etc/resolv.conf'\n"));
+
+        let plain = DnsTakeover::ResolvConf {
+            symlink: None,
+            content: "nameserver 1.1.1.1".into(),
+            target: "127.0.0.1:53".parse().unwrap(),
+        };
+        let script = restore_script(&plain).unwrap().unwrap();
+        assert!(script.contains("<<'KOALA_EOF'\nnameserver 1.1.1.1\nKOALA_EOF\n"));
+
+        let resolved = DnsTakeover::Resolved {
+            link: "eth0".into(),
+            target: "127.0.0.1:1053".parse().unwrap(),
+        };
+        assert_eq!(restore_script(&resolved).unwrap(), None);
+        let json = serde_json::to_string(&resolved).unwrap();
+        assert_eq!(
+            json,
+            r#"{"method":"resolved","link":"eth0","target":"127.0.0.1:1053"}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<DnsTakeover>(&json).unwrap(),
+            resolved
+        );
+    }
+
+    #[test]
+    fn test_reject_heredoc_delimiter() {
+        let injected = DnsTakeover::ResolvConf {
+            symlink: None,
+            content: "nameserver 1.1.1.1\nKOALA_EOF\nrm -rf /\n".into(),
+            target: "127.0.0.1:53".parse().unwrap(),
+        };
+        assert!(restore_script(&injected).is_err());
+        assert!(write_resolv_conf_script("KOALA_EOF").is_err());
+        assert!(write_resolv_conf_script("# KOALA_EOF\n").is_ok());
+
+        let json = serde_json::to_string(&injected).unwrap();
+        assert_eq!(parse_state(&json), None);
+        let valid = DnsTakeover::ResolvConf {
+            symlink: None,
+            content: "nameserver 1.1.1.1\n".into(),
+            target: "127.0.0.1:53".parse().unwrap(),
+        };
+        let json = serde_json::to_string(&valid).unwrap();
+        assert_eq!(parse_state(&json), Some(valid));
+    }
+}
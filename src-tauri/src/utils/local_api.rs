@@ -0,0 +1,519 @@
+use crate::{
+    config::{Config, IVerge},
+    core::handle,
+    feat, logging,
+    module::{lightweight, mihomo::MihomoManager},
+    utils::logging::Type,
+};
+use anyhow::anyhow;
+use percent_encoding::percent_decode_str;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::future::Future;
+use warp::{
+    http::StatusCode,
+    reply::{self, Response},
+    Filter, Rejection, Reply,
+};
+
+const CLASH_MODES: [&str; 3] = ["rule", "global", "direct"];
+
+/// 接口错误，统一返回 `{"error": "..."}`
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, message)
+    }
+
+    fn to_response(&self) -> Response {
+        reply::with_status(reply::json(&json!({ "error": self.message })), self.status)
+            .into_response()
+    }
+}
+
+impl warp::reject::Reject for ApiError {}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(err: anyhow::Error) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+    }
+}
+
+type ApiResult<T = Value> = Result<T, ApiError>;
+
+#[derive(Debug, Deserialize)]
+struct SwitchProfile {
+    uid: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ImportProfile {
+    url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SetMode {
+    mode: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Toggle {
+    enable: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct SelectProxy {
+    name: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ProfileInfo {
+    uid: String,
+    name: Option<String>,
+    #[serde(rename = "type")]
+    itype: Option<String>,
+    url: Option<String>,
+    updated: Option<usize>,
+    current: bool,
+}
+
+/// 读取接口是否开启以及 token
+type AuthSource = fn() -> (bool, String);
+
+fn verge_auth() -> (bool, String) {
+    let verge = Config::verge();
+    let verge = verge.latest();
+    (
+        verge.enable_local_api.unwrap_or(false),
+        verge.local_api_token.clone().unwrap_or_default(),
+    )
+}
+
+/// 校验 `Authorization: Bearer <token>`，未开启接口时返回 404
+fn authorize(authorization: Option<&str>, source: AuthSource) -> ApiResult<()> {
+    let (enabled, token) = source();
+    if !enabled {
+        return Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            "local api is disabled",
+        ));
+    }
+    let provided = authorization
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    if token.is_empty() || !constant_time_eq(provided.as_bytes(), token.as_bytes()) {
+        return Err(ApiError::new(StatusCode::UNAUTHORIZED, "invalid token"));
+    }
+    Ok(())
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// 在解析请求体之前完成鉴权，未通过时直接以 `ApiError` 拒绝
+fn authorized(source: AuthSource) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::header::optional::<String>("authorization")
+        .and_then(move |authorization: Option<String>| async move {
+            authorize(authorization.as_deref(), source).map_err(warp::reject::custom)
+        })
+        .untuple_one()
+}
+
+async fn respond<F>(action: F) -> Result<Response, Rejection>
+where
+    F: Future<Output = ApiResult>,
+{
+    Ok(match action.await {
+        Ok(value) => reply::json(&value).into_response(),
+        Err(err) => {
+            logging!(
+                warn,
+                Type::Network,
+                true,
+                "Local api error: {}",
+                err.message
+            );
+            err.to_response()
+        }
+    })
+}
+
+async fn recover(rejection: Rejection) -> Result<Response, Rejection> {
+    match rejection.find::<ApiError>() {
+        Some(err) => Ok(err.to_response()),
+        None => Err(rejection),
+    }
+}
+
+fn decode(segment: &str) -> String {
+    percent_decode_str(segment).decode_utf8_lossy().into_owned()
+}
+
+async fn status() -> ApiResult {
+    let (system_proxy, tun) = {
+        let verge = Config::verge();
+        let verge = verge.latest();
+        (
+            verge.enable_system_proxy.unwrap_or(false),
+            verge.enable_tun_mode.unwrap_or(false),
+        )
+    };
+    let (mode, mixed_port) = {
+        let clash = Config::clash();
+        let clash = clash.latest();
+        (
+            clash
+                .0
+                .get("mode")
+                .and_then(|mode| mode.as_str())
+                .map(str::to_string),
+            clash.get_mixed_port(),
+        )
+    };
+    let core_running = MihomoManager::global().is_mihomo_running().await.is_ok();
+
+    Ok(json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "core_running": core_running,
+        "mode": mode,
+        "mixed_port": mixed_port,
+        "system_proxy": system_proxy,
+        "tun": tun,
+        "lightweight": lightweight::is_in_lightweight_mode(),
+        "current_profile": Config::profiles().latest().get_current(),
+    }))
+}
+
+fn profiles() -> Vec<ProfileInfo> {
+    let profiles = Config::profiles();
+    let profiles = profiles.latest();
+    let current = profiles.get_current();
+    profiles
+        .get_items()
+        .map(|items| {
+            items
+                .iter()
+                .filter(|item| matches!(item.itype.as_deref(), Some("remote" | "local")))
+                .filter_map(|item| {
+                    let uid = item.uid.clone()?;
+                    Some(ProfileInfo {
+                        current: current.as_ref() == Some(&uid),
+                        uid,
+                        name: item.name.clone(),
+                        itype: item.itype.clone(),
+                        url: item.url.clone(),
+                        updated: item.updated,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+async fn list_profiles() -> ApiResult {
+    Ok(json!(profiles()))
+}
+
+async fn current_profile() -> ApiResult {
+    let current = profiles().into_iter().find(|profile| profile.current);
+    Ok(json!(current))
+}
+
+async fn switch_profile(body: SwitchProfile) -> ApiResult {
+    Config::profiles()
+        .latest()
+        .get_item(&body.uid)
+        .map_err(|_| ApiError::new(StatusCode::NOT_FOUND, "profile not found"))?;
+    if !feat::switch_profile(body.uid.clone()).await? {
+        return Err(anyhow!("failed to switch to profile {}", body.uid).into());
+    }
+    handle::Handle::refresh_verge();
+    Ok(json!({ "current": body.uid }))
+}
+
+async fn update_profile(uid: String) -> ApiResult {
+    let uid = decode(&uid);
+    Config::profiles()
+        .latest()
+        .get_item(&uid)
+        .map_err(|_| ApiError::new(StatusCode::NOT_FOUND, "profile not found"))?;
+    feat::update_profile(uid.clone(), None, Some(true)).await?;
+    Ok(json!({ "updated": uid }))
+}
+
+async fn import_profile(body: ImportProfile) -> ApiResult {
+    url::Url::parse(&body.url).map_err(|err| ApiError::bad_request(err.to_string()))?;
+    feat::import_profile(body.url.clone(), None).await?;
+    handle::Handle::refresh_verge();
+    Ok(json!({ "imported": body.url }))
+}
+
+/// 创建备份并上传到当前的备份存储
+async fn create_backup() -> ApiResult {
+    feat::create_backup_and_upload().await?;
+    Ok(json!({ "uploaded": true }))
+}
+
+async fn set_mode(body: SetMode) -> ApiResult {
+    let mode = body.mode.to_lowercase();
+    if !CLASH_MODES.contains(&mode.as_str()) {
+        return Err(ApiError::bad_request(format!(
+            "invalid mode `{}`, expected one of {}",
+            body.mode,
+            CLASH_MODES.join(", ")
+        )));
+    }
+    feat::change_clash_mode(mode.clone());
+    Ok(json!({ "mode": mode }))
+}
+
+async fn set_system_proxy(body: Toggle) -> ApiResult {
+    feat::patch_verge(
+        IVerge {
+            enable_system_proxy: Some(body.enable),
+            ..IVerge::default()
+        },
+        false,
+    )
+    .await?;
+    handle::Handle::refresh_verge();
+    Ok(json!({ "system_proxy": body.enable }))
+}
+
+async fn set_tun(body: Toggle) -> ApiResult {
+    feat::patch_verge(
+        IVerge {
+            enable_tun_mode: Some(body.enable),
+            ..IVerge::default()
+        },
+        false,
+    )
+    .await?;
+    handle::Handle::refresh_verge();
+    Ok(json!({ "tun": body.enable }))
+}
+
+async fn select_proxy(group: String, body: SelectProxy) -> ApiResult {
+    let group = decode(&group);
+    MihomoManager::global()
+        .select_proxy(&group, &body.name)
+        .await
+        .map_err(ApiError::bad_request)?;
+    handle::Handle::refresh_clash();
+    Ok(json!({ "group": group, "now": body.name }))
+}
+
+/// 订阅流量信息以及内核统计的累计流量
+async fn usage() -> ApiResult {
+    let subscription = {
+        let profiles = Config::profiles();
+        let profiles = profiles.latest();
+        profiles.get_current().and_then(|uid| {
+            profiles
+                .get_item(&uid)
+                .ok()
+                .and_then(|item| item.extra.clone())
+        })
+    };
+    let connections = MihomoManager::global()
+        .get_connections()
+        .await
+        .unwrap_or(Value::Null);
+
+    Ok(json!({
+        "subscription": subscription,
+        "upload_total": connections.get("uploadTotal"),
+        "download_total": connections.get("downloadTotal"),
+        "connections": connections
+            .get("connections")
+            .and_then(Value::as_array)
+            .map(Vec::len),
+    }))
+}
+
+/// `/api` 下的本地控制接口，需要在设置中开启并使用 token 访问
+pub fn routes() -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    routes_with(verge_auth)
+}
+
+fn routes_with(
+    source: AuthSource,
+) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let api = warp::path("api");
+
+    let status = api
+        .and(warp::path!("status"))
+        .and(warp::get())
+        .and(authorized(source))
+        .and_then(|| respond(status()));
+    let list_profiles = api
+        .and(warp::path!("profiles"))
+        .and(warp::get())
+        .and(authorized(source))
+        .and_then(|| respond(list_profiles()));
+    let current_profile = api
+        .and(warp::path!("profiles" / "current"))
+        .and(warp::get())
+        .and(authorized(source))
+        .and_then(|| respond(current_profile()));
+    let switch_profile = api
+        .and(warp::path!("profiles" / "current"))
+        .and(warp::put())
+        .and(authorized(source))
+        .and(warp::body::json())
+        .and_then(|body| respond(switch_profile(body)));
+    let update_profile = api
+        .and(warp::path!("profiles" / String / "update"))
+        .and(warp::post())
+        .and(authorized(source))
+        .and_then(|uid| respond(update_profile(uid)));
+    let import_profile = api
+        .and(warp::path!("profiles" / "import"))
+        .and(warp::post())
+        .and(authorized(source))
+        .and(warp::body::json())
+        .and_then(|body| respond(import_profile(body)));
+    let create_backup = api
+        .and(warp::path!("backup"))
+        .and(warp::post())
+        .and(authorized(source))
+        .and_then(|| respond(create_backup()));
+    let set_mode = api
+        .and(warp::path!("mode"))
+        .and(warp::put())
+        .and(authorized(source))
+        .and(warp::body::json())
+        .and_then(|body| respond(set_mode(body)));
+    let set_system_proxy = api
+        .and(warp::path!("sysproxy"))
+        .and(warp::put())
+        .and(authorized(source))
+        .and(warp::body::json())
+        .and_then(|body| respond(set_system_proxy(body)));
+    let set_tun = api
+        .and(warp::path!("tun"))
+        .and(warp::put())
+        .and(authorized(source))
+        .and(warp::body::json())
+        .and_then(|body| respond(set_tun(body)));
+    let select_proxy = api
+        .and(warp::path!("proxies" / String))
+        .and(warp::put())
+        .and(authorized(source))
+        .and(warp::body::json())
+        .and_then(|group, body| respond(select_proxy(group, body)));
+    let usage = api
+        .and(warp::path!("usage"))
+        .and(warp::get())
+        .and(authorized(source))
+        .and_then(|| respond(usage()));
+
+    status
+        .or(list_profiles)
+        .unify()
+        .or(current_profile)
+        .unify()
+        .or(switch_profile)
+        .unify()
+        .or(update_profile)
+        .unify()
+        .or(import_profile)
+        .unify()
+        .or(create_backup)
+        .unify()
+        .or(set_mode)
+        .unify()
+        .or(set_system_proxy)
+        .unify()
+        .or(set_tun)
+        .unify()
+        .or(select_proxy)
+        .unify()
+        .or(usage)
+        .unify()
+        .recover(recover)
+        .unify()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "test-token";
+
+    /// 不读写全局配置，直接使用测试的 token
+    fn routes() -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+        routes_with(|| (true, TOKEN.to_string()))
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"token", b"token"));
+        assert!(!constant_time_eq(b"token", b"tokex"));
+        assert!(!constant_time_eq(b"token", b"token1"));
+        assert!(!constant_time_eq(b"", b"token"));
+    }
+
+    #[tokio::test]
+    async fn test_routes_reject_unauthorized() {
+        let routes = routes();
+
+        let missing = warp::test::request()
+            .method("GET")
+            .path("/api/status")
+            .reply(&routes)
+            .await;
+        assert_eq!(missing.status(), StatusCode::UNAUTHORIZED);
+
+        let wrong = warp::test::request()
+            .method("GET")
+            .path("/api/profiles")
+            .header("authorization", "Bearer wrong-token")
+            .reply(&routes)
+            .await;
+        assert_eq!(wrong.status(), StatusCode::UNAUTHORIZED);
+
+        // 鉴权先于请求体解析，非法请求体也应返回 401
+        let invalid_body = warp::test::request()
+            .method("PUT")
+            .path("/api/mode")
+            .body("not json")
+            .reply(&routes)
+            .await;
+        assert_eq!(invalid_body.status(), StatusCode::UNAUTHORIZED);
+
+        let disabled = warp::test::request()
+            .method("GET")
+            .path("/api/status")
+            .header("authorization", format!("Bearer {TOKEN}"))
+            .reply(&routes_with(|| (false, TOKEN.to_string())))
+            .await;
+        assert_eq!(disabled.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_routes_list_profiles() {
+        let resp = warp::test::request()
+            .method("GET")
+            .path("/api/profiles")
+            .header("authorization", format!("Bearer {TOKEN}"))
+            .reply(&routes())
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: Value = serde_json::from_slice(resp.body()).unwrap();
+        assert!(body.is_array());
+    }
+}
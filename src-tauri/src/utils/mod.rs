@@ -1,13 +1,20 @@
 pub mod autostart;
 pub mod dirs;
+pub mod headless;
 pub mod help;
 pub mod i18n;
 pub mod init;
+#[cfg(target_os = "linux")]
+pub mod linux_dns;
+pub mod local_api;
 pub mod logging;
 pub mod network;
 pub mod notification;
+pub mod redact;
 pub mod resolve;
 pub mod server;
 pub mod sys_info;
+#[cfg(test)]
+pub mod test_server;
 pub mod tmpl;
 pub mod window_manager;
@@ -7,7 +7,7 @@ use crate::{
     logging, logging_error,
     module::lightweight::{self, auto_lightweight_mode_init},
     process::AsyncHandler,
-    utils::{init, logging::Type, server, window_manager::WindowManager},
+    utils::{headless, init, logging::Type, server, window_manager::WindowManager},
     wrap_err,
 };
 use anyhow::{bail, Result};
@@ -299,49 +299,7 @@ pub async fn resolve_setup_async(app_handle: &AppHandle) {
 
     logging_error!(Type::Setup, true, init::startup_script().await);
 
-    if let Err(err) = resolve_random_port_config().await {
-        logging!(
-            error,
-            Type::System,
-            true,
-            "Failed to resolve random port config: {}",
-            err
-        );
-    }
-
-    logging!(trace, Type::Config, true, "Initializing configuration...");
-    logging_error!(Type::Config, true, Config::init_config().await);
-
-    // 启动时清理冗余的 Profile 文件
-    logging!(
-        info,
-        Type::Setup,
-        true,
-        "Cleaning redundant profile files..."
-    );
-    let profiles = Config::profiles();
-    if let Err(e) = profiles.latest().auto_cleanup() {
-        logging!(
-            warn,
-            Type::Setup,
-            true,
-            "Failed to clean profile files at startup: {}",
-            e
-        );
-    } else {
-        logging!(
-            info,
-            Type::Setup,
-            true,
-            "Startup profile files cleanup completed"
-        );
-    }
-
-    logging!(trace, Type::Core, true, "Starting core manager...");
-    logging_error!(Type::Core, true, CoreManager::global().init().await);
-
-    log::trace!(target: "app", "Starting embedded server...");
-    server::embed_server();
+    resolve_setup_services().await;
 
     logging_error!(Type::Tray, true, tray::Tray::global().init());
 
@@ -368,18 +326,6 @@ pub async fn resolve_setup_async(app_handle: &AppHandle) {
         );
     }
 
-    // 更新系统代理
-    logging_error!(
-        Type::System,
-        true,
-        sysopt::Sysopt::global().update_sysproxy().await
-    );
-    logging_error!(
-        Type::System,
-        true,
-        sysopt::Sysopt::global().init_guard_sysproxy()
-    );
-
     // 创建窗口
     let is_silent_start = { Config::verge().data().enable_silent_start }.unwrap_or(false);
     #[cfg(target_os = "macos")]
@@ -392,9 +338,6 @@ pub async fn resolve_setup_async(app_handle: &AppHandle) {
     }
     create_window(!is_silent_start);
 
-    // 初始化定时器
-    logging_error!(Type::System, true, timer::Timer::global().init());
-
     // 自动进入轻量模式
     auto_lightweight_mode_init();
 
@@ -424,6 +367,133 @@ pub async fn resolve_setup_async(app_handle: &AppHandle) {
     }
 }
 
+/// 无界面模式的启动流程，没有 AppHandle，不创建托盘、窗口和快捷键
+pub async fn resolve_setup_headless() {
+    logging!(info, Type::Setup, true, "Starting headless setup tasks...");
+    VERSION.get_or_init(|| env!("CARGO_PKG_VERSION").to_string());
+    logging_error!(Type::Setup, true, init::init_config());
+    logging_error!(Type::Setup, true, init::init_resources());
+
+    resolve_setup_services().await;
+
+    AsyncHandler::spawn(|| async {
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        if let Err(e) = crate::cmd::update_profiles_on_startup().await {
+            log::error!(target: "app", "Failed to update profiles on startup: {e}");
+        }
+    });
+    logging!(info, Type::Setup, true, "Headless setup completed");
+}
+
+/// 界面无关的启动任务：配置、内核、本地服务、系统代理与定时器
+async fn resolve_setup_services() {
+    if let Err(err) = resolve_random_port_config().await {
+        logging!(
+            error,
+            Type::System,
+            true,
+            "Failed to resolve random port config: {}",
+            err
+        );
+    }
+
+    logging!(trace, Type::Config, true, "Initializing configuration...");
+    logging_error!(Type::Config, true, Config::init_config().await);
+
+    // 启动时清理冗余的 Profile 文件
+    logging!(
+        info,
+        Type::Setup,
+        true,
+        "Cleaning redundant profile files..."
+    );
+    let profiles = Config::profiles();
+    if let Err(e) = profiles.latest().auto_cleanup() {
+        logging!(
+            warn,
+            Type::Setup,
+            true,
+            "Failed to clean profile files at startup: {}",
+            e
+        );
+    } else {
+        logging!(
+            info,
+            Type::Setup,
+            true,
+            "Startup profile files cleanup completed"
+        );
+    }
+
+    // 先回滚上次残留的透明代理规则，内核启动后会重新应用
+    #[cfg(target_os = "linux")]
+    logging_error!(
+        Type::System,
+        true,
+        crate::core::tproxy::TransparentProxy::global().init().await
+    );
+
+    logging!(trace, Type::Core, true, "Starting core manager...");
+    if let Err(err) = CoreManager::global().init().await {
+        logging!(
+            error,
+            Type::Core,
+            true,
+            "Failed to start core, retrying with last known good config: {}",
+            err
+        );
+        let error_msg = err.to_string();
+        match CoreManager::global()
+            .use_last_good_config("config_validate::boot_error", &error_msg)
+            .await
+        {
+            Ok(_) => logging_error!(Type::Core, true, CoreManager::global().start_core().await),
+            Err(err) => {
+                logging!(
+                    error,
+                    Type::Core,
+                    true,
+                    "Failed to load last known good config: {}",
+                    err
+                );
+            }
+        }
+    }
+    // 内核成功启动时 start_core 已按配置接管或恢复 DNS，否则恢复上次异常退出遗留的接管
+    #[cfg(target_os = "linux")]
+    if CoreManager::global().get_running_mode().await == RunningMode::NotRunning {
+        restore_public_dns().await;
+    }
+    watchdog::CoreWatchdog::global().start();
+
+    log::trace!(target: "app", "Starting embedded server...");
+    server::embed_server();
+
+    crate::core::network_monitor::NetworkMonitor::global().start();
+    AsyncHandler::spawn(|| async {
+        logging_error!(
+            Type::Network,
+            true,
+            crate::module::network_rules::evaluate().await
+        );
+    });
+
+    // 更新系统代理
+    logging_error!(
+        Type::System,
+        true,
+        sysopt::Sysopt::global().update_sysproxy().await
+    );
+    logging_error!(
+        Type::System,
+        true,
+        sysopt::Sysopt::global().init_guard_sysproxy()
+    );
+
+    // 初始化定时器
+    logging_error!(Type::System, true, timer::Timer::global().init());
+}
+
 /// reset system proxy (异步)
 pub async fn resolve_reset_async() {
     #[cfg(target_os = "macos")]
@@ -437,7 +507,7 @@ pub async fn resolve_reset_async() {
         sysopt::Sysopt::global().reset_sysproxy().await
     );
     logging_error!(Type::Core, true, CoreManager::global().stop_core().await);
-    #[cfg(target_os = "macos")]
+    #[cfg(any(target_os = "macos", target_os = "linux"))]
     {
         logging!(info, Type::System, true, "Restoring system DNS settings");
         restore_public_dns().await;
@@ -446,6 +516,16 @@ pub async fn resolve_reset_async() {
 
 /// Create the main window
 pub fn create_window(is_show: bool) -> bool {
+    if headless::is_headless() {
+        logging!(
+            debug,
+            Type::Window,
+            true,
+            "Headless mode, skip window creation"
+        );
+        return false;
+    }
+
     logging!(
         info,
         Type::Window,
@@ -885,6 +965,13 @@ pub async fn set_public_dns(dns_server: String) {
     }
 }
 
+#[cfg(target_os = "linux")]
+pub async fn restore_public_dns() {
+    if let Err(err) = crate::utils::linux_dns::restore().await {
+        log::error!(target: "app", "unset system dns failed: {err}");
+    }
+}
+
 #[cfg(target_os = "macos")]
 pub async fn restore_public_dns() {
     use crate::{core::handle, utils::dirs};
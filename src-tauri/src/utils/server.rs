@@ -1,13 +1,15 @@
 extern crate warp;
 
-use super::resolve;
+use super::{local_api, resolve};
 use crate::{
     config::{Config, IVerge, DEFAULT_PAC},
+    enhance::pac,
     logging_error,
     process::AsyncHandler,
     utils::logging::Type,
 };
 use anyhow::Result;
+use sha2::{Digest, Sha256};
 use std::convert::Infallible;
 use warp::Filter;
 
@@ -16,8 +18,28 @@ struct QueryParam {
     param: String,
 }
 
-/// The embed server only be used to implement singleton process
-/// maybe it can be used as pac server later
+/// PAC 缓存时间，规则更新后通过 ETag 刷新
+const PAC_MAX_AGE: u32 = 300;
+
+/// 开启生成 PAC 时使用根据规则生成的脚本，否则使用用户配置的脚本
+fn pac_content() -> (String, bool) {
+    let verge = Config::verge().latest().clone();
+    if verge.pac_generated.unwrap_or(false) {
+        let generated = pac::generated_pac().or_else(|| {
+            let runtime = Config::runtime().latest().config.clone()?;
+            pac::refresh_pac(&runtime);
+            pac::generated_pac()
+        });
+        if let Some(content) = generated {
+            return (content, true);
+        }
+    }
+    let content = verge.pac_file_content.unwrap_or(DEFAULT_PAC.to_string());
+    (content, false)
+}
+
+/// The embed server is used to implement singleton process, serve pac
+/// and the optional local api
 pub fn embed_server() {
     let port = IVerge::get_singleton_port();
 
@@ -27,22 +49,29 @@ pub fn embed_server() {
             "ok"
         });
 
-        let pac = warp::path!("commands" / "pac").map(move || {
-            let content = Config::verge()
-                .latest()
-                .pac_file_content
-                .clone()
-                .unwrap_or(DEFAULT_PAC.to_string());
-            let port = Config::verge()
-                .latest()
-                .verge_mixed_port
-                .unwrap_or(Config::clash().data().get_mixed_port());
-            let content = content.replace("%mixed-port%", &format!("{port}"));
-            warp::http::Response::builder()
-                .header("Content-Type", "application/x-ns-proxy-autoconfig")
-                .body(content)
-                .unwrap_or_default()
-        });
+        let pac = warp::path!("commands" / "pac")
+            .and(warp::header::optional::<String>("if-none-match"))
+            .map(move |if_none_match: Option<String>| {
+                let (content, generated) = pac_content();
+                let port = Config::verge()
+                    .latest()
+                    .verge_mixed_port
+                    .unwrap_or(Config::clash().data().get_mixed_port());
+                let content = content.replace("%mixed-port%", &format!("{port}"));
+                let etag = format!("\"{}\"", &hex::encode(Sha256::digest(&content))[..16]);
+                let builder = warp::http::Response::builder()
+                    .header("Content-Type", "application/x-ns-proxy-autoconfig")
+                    .header("Cache-Control", format!("max-age={PAC_MAX_AGE}"))
+                    .header("ETag", &etag)
+                    .header("X-Pac-Generated", generated.to_string());
+                if if_none_match.as_deref() == Some(etag.as_str()) {
+                    return builder
+                        .status(warp::http::StatusCode::NOT_MODIFIED)
+                        .body(String::new())
+                        .unwrap_or_default();
+                }
+                builder.body(content).unwrap_or_default()
+            });
         async fn scheme_handler(query: QueryParam) -> Result<impl warp::Reply, Infallible> {
             logging_error!(
                 Type::Setup,
@@ -55,7 +84,7 @@ pub fn embed_server() {
         let scheme = warp::path!("commands" / "scheme")
             .and(warp::query::<QueryParam>())
             .and_then(scheme_handler);
-        let commands = visible.or(scheme).or(pac);
+        let commands = visible.or(scheme).or(pac).or(local_api::routes());
         warp::serve(commands).run(([127, 0, 0, 1], port)).await;
     });
 }
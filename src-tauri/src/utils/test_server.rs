@@ -0,0 +1,15 @@
+//! 测试用的本地 HTTP 替身服务
+
+use std::net::SocketAddr;
+use warp::{Filter, Rejection, Reply};
+
+/// 在本机随机端口启动服务，返回监听地址
+pub fn serve<F>(routes: F) -> SocketAddr
+where
+    F: Filter<Error = Rejection> + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+{
+    let (addr, server) = warp::serve(routes).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+    addr
+}
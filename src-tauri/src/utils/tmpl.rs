@@ -12,13 +12,18 @@ rules: []
 
 /// enhanced profile
 pub const ITEM_MERGE: &str = "# Profile Enhancement Merge Template for Koala Clash
+# `key!` replace, `+key` prepend, `key+` append, `<key>` deep merge (any level except hosts / nameserver-policy / fallback-filter)
+
+merge-version: 2
 
 profile:
   store-selected: true
 ";
 
 pub const ITEM_MERGE_EMPTY: &str = "# Profile Enhancement Merge Template for Koala Clash
+# `key!` replace, `+key` prepend, `key+` append, `<key>` deep merge (any level except hosts / nameserver-policy / fallback-filter)
 
+merge-version: 2
 ";
 
 /// enhanced profile
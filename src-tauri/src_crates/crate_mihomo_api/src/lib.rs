@@ -1,3 +1,4 @@
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
 use reqwest::{Method, header::HeaderMap};
 use serde_json::{Value, json};
 use std::time::Duration;
@@ -47,7 +48,18 @@ impl MihomoManager {
                         .map_err(|e| e.to_string())?
                 }
             }
-            Method::PUT => json!(client_response.text().await.map_err(|e| e.to_string())?),
+            Method::PUT => {
+                let status = client_response.status();
+                let body = client_response.text().await.map_err(|e| e.to_string())?;
+                if !status.is_success() {
+                    return Err(if body.is_empty() {
+                        status.to_string()
+                    } else {
+                        body
+                    });
+                }
+                json!(body)
+            }
             _ => {
                 let status = client_response.status();
                 if status.as_u16() == 204 {
@@ -133,21 +145,65 @@ impl MihomoManager {
         test_url: Option<String>,
         timeout: i32,
     ) -> Result<serde_json::Value, String> {
+        let url = self.proxy_delay_url(name, test_url, timeout);
+        let response = self.send_request(Method::GET, url, None).await?;
+        Ok(response)
+    }
+
+    fn proxy_delay_url(&self, name: &str, test_url: Option<String>, timeout: i32) -> String {
         let test_url = test_url.unwrap_or("https://cp.cloudflare.com/generate_204".to_string());
-        let url = format!(
+        format!(
             "{}/proxies/{}/delay?url={}&timeout={}",
-            self.mihomo_server, name, test_url, timeout
+            self.mihomo_server,
+            encode_component(name),
+            encode_component(&test_url),
+            encode_component(&timeout.to_string())
+        )
+    }
+
+    pub async fn test_group_delay(
+        &self,
+        group: &str,
+        test_url: Option<String>,
+        timeout: i32,
+    ) -> Result<serde_json::Value, String> {
+        let test_url = test_url.unwrap_or("https://cp.cloudflare.com/generate_204".to_string());
+        let url = format!(
+            "{}/group/{}/delay?url={}&timeout={}",
+            self.mihomo_server,
+            encode_component(group),
+            encode_component(&test_url),
+            timeout
         );
         let response = self.send_request(Method::GET, url, None).await?;
         Ok(response)
     }
 
+    pub async fn select_proxy(&self, group: &str, name: &str) -> Result<(), String> {
+        let url = format!("{}/proxies/{}", self.mihomo_server, encode_component(group));
+        let payload = json!({ "name": name });
+        self.send_request(Method::PUT, url, Some(payload)).await?;
+        Ok(())
+    }
+
     pub async fn get_connections(&self) -> Result<serde_json::Value, String> {
         let url = format!("{}/connections", self.mihomo_server);
         let response = self.send_request(Method::GET, url, None).await?;
         Ok(response)
     }
 
+    pub async fn flush_fakeip_cache(&self) -> Result<(), String> {
+        let url = format!("{}/cache/fakeip/flush", self.mihomo_server);
+        self.send_request(Method::POST, url, None).await?;
+        Ok(())
+    }
+
+    pub async fn flush_dns_cache(&self) -> Result<(), String> {
+        let url = format!("{}/cache/dns/flush", self.mihomo_server);
+        self.send_request(Method::POST, url, None).await?;
+        Ok(())
+    }
+
     pub async fn delete_connection(&self, id: &str) -> Result<(), String> {
         let url = format!("{}/connections/{}", self.mihomo_server, id);
         let response = self.send_request(Method::DELETE, url, None).await?;
@@ -161,3 +217,43 @@ impl MihomoManager {
         }
     }
 }
+
+/// 除 RFC 3986 非保留字符外全部编码
+const COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+/// 百分号编码路径段或查询参数，代理组名称中可能包含空格、`/`、`#` 等字符
+fn encode_component(value: &str) -> String {
+    utf8_percent_encode(value, COMPONENT).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_component() {
+        assert_eq!(encode_component("Proxy"), "Proxy");
+        assert_eq!(
+            encode_component("🚀 节点/A#1"),
+            "%F0%9F%9A%80%20%E8%8A%82%E7%82%B9%2FA%231"
+        );
+        assert_eq!(
+            encode_component("https://cp.cloudflare.com/generate_204?a=1&b=2"),
+            "https%3A%2F%2Fcp.cloudflare.com%2Fgenerate_204%3Fa%3D1%26b%3D2"
+        );
+    }
+
+    #[test]
+    fn test_proxy_delay_url() {
+        let manager = MihomoManager::new("http://127.0.0.1:9097".into(), HeaderMap::new());
+        assert_eq!(
+            manager.proxy_delay_url("HK 01/a#b?c&d", Some("http://x.com/?a=1&b=2".into()), 5000),
+            "http://127.0.0.1:9097/proxies/HK%2001%2Fa%23b%3Fc%26d/delay\
+             ?url=http%3A%2F%2Fx.com%2F%3Fa%3D1%26b%3D2&timeout=5000"
+        );
+    }
+}
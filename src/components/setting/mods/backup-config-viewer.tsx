@@ -1,15 +1,27 @@
-import { useState, useRef, memo, useEffect } from "react";
+import { useState, memo, useEffect, useMemo } from "react";
 import { useTranslation } from "react-i18next";
-import { useForm } from "react-hook-form";
+import { FieldPath, useForm } from "react-hook-form";
 import { useVerge } from "@/hooks/use-verge";
 import { isValidUrl } from "@/utils/helper";
 import { useLockFn } from "ahooks";
 
 // Новые импорты
-import { saveWebdavConfig, createWebdavBackup } from "@/services/cmds";
+import {
+  saveWebdavConfig,
+  saveBackupStorage,
+  createBackup,
+} from "@/services/cmds";
 import { showNotice } from "@/services/noticeService";
 import { Button } from "@/components/ui/button";
 import { Input } from "@/components/ui/input";
+import { Switch } from "@/components/ui/switch";
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from "@/components/ui/select";
 import {
   Form,
   FormControl,
@@ -19,7 +31,6 @@ import {
   FormMessage,
 } from "@/components/ui/form";
 import { Eye, EyeOff } from "lucide-react";
-import { cn } from "@root/lib/utils";
 
 export interface BackupConfigViewerProps {
   onBackupSuccess: () => Promise<void>;
@@ -29,6 +40,49 @@ export interface BackupConfigViewerProps {
   setLoading: (loading: boolean) => void;
 }
 
+const STORAGE_TYPES: { value: IBackupStorageType; label: string }[] = [
+  { value: "webdav", label: "WebDAV" },
+  { value: "local", label: "Local Directory" },
+  { value: "s3", label: "S3 Compatible" },
+  { value: "sftp", label: "SFTP" },
+];
+
+interface IBackupForm extends IWebDavConfig {
+  storage: IBackupStorageType;
+  local_dir: string;
+  s3: IBackupS3Config;
+  // Порт хранится строкой, пока пользователь его редактирует
+  sftp: Omit<IBackupSftpConfig, "port"> & { port: string };
+}
+
+const toForm = (verge?: IVergeConfig): IBackupForm => ({
+  storage: verge?.backup_storage ?? "webdav",
+  url: verge?.webdav_url ?? "",
+  username: verge?.webdav_username ?? "",
+  password: verge?.webdav_password ?? "",
+  local_dir: verge?.backup_local_dir ?? "",
+  s3: {
+    endpoint: "",
+    region: "",
+    bucket: "",
+    access_key: "",
+    secret_key: "",
+    path_style: true,
+    ...verge?.backup_s3,
+  },
+  sftp: {
+    host: "",
+    username: "",
+    password: "",
+    private_key: "",
+    passphrase: "",
+    dir: "",
+    host_key: "",
+    ...verge?.backup_sftp,
+    port: verge?.backup_sftp?.port?.toString() ?? "",
+  },
+});
+
 export const BackupConfigViewer = memo(
   ({
     onBackupSuccess,
@@ -39,53 +93,64 @@ export const BackupConfigViewer = memo(
   }: BackupConfigViewerProps) => {
     const { t } = useTranslation();
     const { verge } = useVerge();
-    const { webdav_url, webdav_username, webdav_password } = verge || {};
     const [showPassword, setShowPassword] = useState(false);
 
-    const form = useForm<IWebDavConfig>({
-      defaultValues: { url: "", username: "", password: "" },
-    });
+    const saved = useMemo(() => toForm(verge), [verge]);
+    const form = useForm<IBackupForm>({ defaultValues: saved });
 
     // Синхронизируем форму с данными из verge
     useEffect(() => {
-      form.reset({
-        url: webdav_url,
-        username: webdav_username,
-        password: webdav_password,
-      });
-    }, [webdav_url, webdav_username, webdav_password, form.reset]);
-
-    const { register, handleSubmit, watch, getValues } = form;
-    const url = watch("url");
-    const username = watch("username");
-    const password = watch("password");
-
-    const webdavChanged =
-      webdav_url !== url ||
-      webdav_username !== username ||
-      webdav_password !== password;
+      form.reset(saved);
+    }, [saved, form.reset]);
+
+    const { handleSubmit, watch, getValues } = form;
+    const values = watch();
+    const storage = values.storage;
+
+    const configChanged = JSON.stringify(values) !== JSON.stringify(saved);
+    const configured =
+      storage === "webdav"
+        ? !!verge?.webdav_url
+        : verge?.backup_storage === storage;
+
+    const fail = (message: string) => {
+      showNotice("error", t(message));
+      throw new Error(message);
+    };
 
     const checkForm = () => {
       const values = getValues();
-      if (!values.url) {
-        showNotice("error", t("WebDAV URL Required"));
-        throw new Error("URL Required");
-      }
-      if (!isValidUrl(values.url)) {
-        showNotice("error", t("Invalid WebDAV URL"));
-        throw new Error("Invalid URL");
-      }
-      if (!values.username) {
-        showNotice("error", t("Username Required"));
-        throw new Error("Username Required");
-      }
-      if (!values.password) {
-        showNotice("error", t("Password Required"));
-        throw new Error("Password Required");
+      switch (values.storage) {
+        case "webdav":
+          if (!values.url) fail("WebDAV URL Required");
+          if (!isValidUrl(values.url)) fail("Invalid WebDAV URL");
+          if (!values.username) fail("Username Required");
+          if (!values.password) fail("Password Required");
+          break;
+        case "local":
+          if (!values.local_dir.trim()) fail("Backup Directory Required");
+          break;
+        case "s3":
+          if (!isValidUrl(values.s3.endpoint)) fail("Invalid S3 Endpoint");
+          if (
+            !values.s3.bucket ||
+            !values.s3.access_key ||
+            !values.s3.secret_key
+          ) {
+            fail("S3 Bucket and Keys Required");
+          }
+          break;
+        case "sftp":
+          if (!values.sftp.host) fail("SFTP Host Required");
+          if (!values.sftp.username) fail("Username Required");
+          if (!values.sftp.password && !values.sftp.private_key) {
+            fail("Password Required");
+          }
+          break;
       }
     };
 
-    const save = useLockFn(async (data: IWebDavConfig) => {
+    const save = useLockFn(async (data: IBackupForm) => {
       try {
         checkForm();
       } catch {
@@ -93,15 +158,37 @@ export const BackupConfigViewer = memo(
       }
       try {
         setLoading(true);
-        await saveWebdavConfig(
-          data.url.trim(),
-          data.username.trim(),
-          data.password,
-        );
-        showNotice("success", t("WebDAV Config Saved"));
+        if (data.storage === "webdav") {
+          await saveWebdavConfig(
+            data.url.trim(),
+            data.username.trim(),
+            data.password,
+          );
+        }
+        await saveBackupStorage(data.storage, {
+          localDir: data.storage === "local" ? data.local_dir.trim() : null,
+          s3:
+            data.storage === "s3"
+              ? {
+                  ...data.s3,
+                  endpoint: data.s3.endpoint.trim(),
+                  bucket: data.s3.bucket.trim(),
+                  region: data.s3.region?.trim() || null,
+                }
+              : null,
+          sftp:
+            data.storage === "sftp"
+              ? {
+                  ...data.sftp,
+                  host: data.sftp.host.trim(),
+                  port: data.sftp.port ? Number(data.sftp.port) : null,
+                }
+              : null,
+        });
+        showNotice("success", t("Backup Storage Saved"));
         await onSaveSuccess();
       } catch (error) {
-        showNotice("error", t("WebDAV Config Save Failed", { error }), 3000);
+        showNotice("error", t("Backup Storage Save Failed", { error }), 3000);
       } finally {
         setLoading(false);
       }
@@ -115,7 +202,7 @@ export const BackupConfigViewer = memo(
       }
       try {
         setLoading(true);
-        await createWebdavBackup();
+        await createBackup();
         showNotice("success", t("Backup Created"));
         await onBackupSuccess();
       } catch (error) {
@@ -125,6 +212,48 @@ export const BackupConfigViewer = memo(
       }
     });
 
+    const renderInput = (
+      name: FieldPath<IBackupForm>,
+      label: string,
+      secret = false,
+    ) => (
+      <FormField
+        control={form.control}
+        name={name}
+        render={({ field }) => (
+          <FormItem>
+            <FormLabel>{t(label)}</FormLabel>
+            <div className="relative">
+              <FormControl>
+                <Input
+                  {...field}
+                  value={(field.value as string | undefined) ?? ""}
+                  type={secret && !showPassword ? "password" : "text"}
+                  className={secret ? "pr-10" : undefined}
+                />
+              </FormControl>
+              {secret && (
+                <Button
+                  type="button"
+                  variant="ghost"
+                  size="icon"
+                  className="absolute top-0 right-0 h-full px-3"
+                  onClick={() => setShowPassword(!showPassword)}
+                >
+                  {showPassword ? (
+                    <EyeOff className="h-4 w-4" />
+                  ) : (
+                    <Eye className="h-4 w-4" />
+                  )}
+                </Button>
+              )}
+            </div>
+            <FormMessage />
+          </FormItem>
+        )}
+      />
+    );
+
     return (
       <Form {...form}>
         <form
@@ -135,69 +264,89 @@ export const BackupConfigViewer = memo(
           <div className="flex-1 space-y-4">
             <FormField
               control={form.control}
-              name="url"
+              name="storage"
               render={({ field }) => (
                 <FormItem>
-                  <FormLabel>{t("WebDAV Server URL")}</FormLabel>
-                  <FormControl>
-                    <Input {...field} />
-                  </FormControl>
-                  <FormMessage />
+                  <FormLabel>{t("Backup Storage")}</FormLabel>
+                  <Select onValueChange={field.onChange} value={field.value}>
+                    <FormControl>
+                      <SelectTrigger>
+                        <SelectValue />
+                      </SelectTrigger>
+                    </FormControl>
+                    <SelectContent>
+                      {STORAGE_TYPES.map((item) => (
+                        <SelectItem key={item.value} value={item.value}>
+                          {t(item.label)}
+                        </SelectItem>
+                      ))}
+                    </SelectContent>
+                  </Select>
                 </FormItem>
               )}
             />
-            <div className="grid grid-cols-1 sm:grid-cols-2 gap-4">
-              <FormField
-                control={form.control}
-                name="username"
-                render={({ field }) => (
-                  <FormItem>
-                    <FormLabel>{t("Username")}</FormLabel>
-                    <FormControl>
-                      <Input {...field} />
-                    </FormControl>
-                    <FormMessage />
-                  </FormItem>
-                )}
-              />
-              <FormField
-                control={form.control}
-                name="password"
-                render={({ field }) => (
-                  <FormItem>
-                    <FormLabel>{t("Password")}</FormLabel>
-                    <div className="relative">
+
+            {storage === "webdav" && (
+              <>
+                {renderInput("url", "WebDAV Server URL")}
+                <div className="grid grid-cols-1 sm:grid-cols-2 gap-4">
+                  {renderInput("username", "Username")}
+                  {renderInput("password", "Password", true)}
+                </div>
+              </>
+            )}
+
+            {storage === "local" &&
+              renderInput("local_dir", "Backup Directory")}
+
+            {storage === "s3" && (
+              <>
+                {renderInput("s3.endpoint", "S3 Endpoint")}
+                <div className="grid grid-cols-1 sm:grid-cols-2 gap-4">
+                  {renderInput("s3.bucket", "S3 Bucket")}
+                  {renderInput("s3.region", "S3 Region")}
+                  {renderInput("s3.access_key", "Access Key")}
+                  {renderInput("s3.secret_key", "Secret Key", true)}
+                </div>
+                <FormField
+                  control={form.control}
+                  name="s3.path_style"
+                  render={({ field }) => (
+                    <FormItem className="flex flex-row items-center justify-between">
+                      <FormLabel>{t("Path Style Access")}</FormLabel>
                       <FormControl>
-                        <Input
-                          type={showPassword ? "text" : "password"}
-                          {...field}
-                          className="pr-10"
+                        <Switch
+                          checked={field.value ?? true}
+                          onCheckedChange={field.onChange}
                         />
                       </FormControl>
-                      <Button
-                        type="button"
-                        variant="ghost"
-                        size="icon"
-                        className="absolute top-0 right-0 h-full px-3"
-                        onClick={() => setShowPassword(!showPassword)}
-                      >
-                        {showPassword ? (
-                          <EyeOff className="h-4 w-4" />
-                        ) : (
-                          <Eye className="h-4 w-4" />
-                        )}
-                      </Button>
-                    </div>
-                    <FormMessage />
-                  </FormItem>
-                )}
-              />
-            </div>
+                    </FormItem>
+                  )}
+                />
+              </>
+            )}
+
+            {storage === "sftp" && (
+              <>
+                <div className="grid grid-cols-1 sm:grid-cols-[1fr_8rem] gap-4">
+                  {renderInput("sftp.host", "SFTP Host")}
+                  {renderInput("sftp.port", "Port")}
+                </div>
+                <div className="grid grid-cols-1 sm:grid-cols-2 gap-4">
+                  {renderInput("sftp.username", "Username")}
+                  {renderInput("sftp.password", "Password", true)}
+                  {renderInput("sftp.private_key", "Private Key Path")}
+                  {renderInput("sftp.passphrase", "Key Passphrase", true)}
+                </div>
+                {renderInput("sftp.dir", "Remote Directory")}
+                {renderInput("sftp.host_key", "Host Key Fingerprint")}
+              </>
+            )}
           </div>
 
           {/* Правая часть: кнопки действий */}
           <div className="flex sm:flex-col gap-2">
-            {webdavChanged || !webdav_url ? (
+            {configChanged || !configured ? (
               <Button
                 type="button"
                 className="w-full h-full"
@@ -3,7 +3,7 @@ import { useLockFn } from "ahooks";
 import { useTranslation } from "react-i18next";
 import dayjs from "dayjs";
 import { restartApp } from "@/services/cmds";
-import { deleteWebdavBackup, restoreWebDavBackup } from "@/services/cmds";
+import { deleteBackup, restoreBackup } from "@/services/cmds";
 import { showNotice } from "@/services/noticeService";
 
 // Новые импорты
@@ -24,7 +24,7 @@ import {
 } from "@/components/ui/tooltip";
 import { Trash2, History } from "lucide-react";
 
-export type BackupFile = IWebDavFile & {
+export type BackupFile = IBackupFile & {
   platform: string;
   backup_time: dayjs.Dayjs;
   allow_apply: boolean;
@@ -136,12 +136,12 @@ export const BackupTableViewer = memo(
     const { t } = useTranslation();
 
     const handleDelete = useLockFn(async (filename: string) => {
-      await deleteWebdavBackup(filename);
+      await deleteBackup(filename);
       await onRefresh();
     });
 
     const handleRestore = useLockFn(async (filename: string) => {
-      await restoreWebDavBackup(filename);
+      await restoreBackup(filename);
       showNotice("success", t("Restore Success, App will restart in 1s"));
       await restartApp();
     });
@@ -11,7 +11,7 @@ import customParseFormat from "dayjs/plugin/customParseFormat";
 import { useLockFn } from "ahooks";
 
 // Новые импорты
-import { listWebDavBackup } from "@/services/cmds";
+import { listBackup } from "@/services/cmds";
 import {
   Dialog,
   DialogContent,
@@ -62,7 +62,7 @@ export const BackupViewer = forwardRef<DialogRef>((props, ref) => {
   );
 
   const getAllBackupFiles = async (): Promise<BackupFile[]> => {
-    const files = await listWebDavBackup();
+    const files = await listBackup();
     return files
       .map((file) => {
         const platform = file.filename.split("-")[0];
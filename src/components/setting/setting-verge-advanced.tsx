@@ -101,8 +101,12 @@ const SettingVergeAdvanced = ({ onError }: Props) => {
   };
 
   const onExportDiagnosticInfo = useCallback(async () => {
-    await exportDiagnosticInfo();
-    showNotice("success", t("Copy Success"), 1000);
+    try {
+      const path = await exportDiagnosticInfo();
+      showNotice("success", `${t("Diagnostic Bundle Exported")}: ${path}`);
+    } catch (err: any) {
+      showNotice("error", err.message || err.toString());
+    }
   }, []);
 
   // Вспомогательная функция для создания лейбла с иконкой
@@ -145,6 +145,24 @@ const handleNoticeMessage = (
     case "config_core::change_error":
       showNotice("error", `${t("Failed to Change Core")}: ${msg}`);
       break;
+    case "network_rule_applied":
+      showNotice("info", `${t("Network Rule Applied")}: ${msg}`);
+      break;
+    case "core_watchdog::restarting":
+      showNotice("info", `${t("Core Crashed, Restarting")} ${msg}`);
+      break;
+    case "core_watchdog::crash_loop":
+      showNotice("error", t("Core Crash Loop Detected"));
+      break;
+    case "core_watchdog::recovered":
+      showNotice("success", t("Core Recovered"));
+      break;
+    case "core_watchdog::sysproxy_disabled":
+      showNotice("error", t("Core Unavailable, System Proxy Disabled"));
+      break;
+    case "health_check::failover":
+      showNotice("info", `${t("Health Check Failover")}: ${msg}`);
+      break;
     default: // Optional: Log unhandled statuses
       console.warn(`[Notification Listener V2] Unprocessed state: ${status}`);
       break;
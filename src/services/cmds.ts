@@ -194,7 +194,7 @@ export async function cmdGetProxyDelay(
   const testUrl = url || "https://cp.cloudflare.com/generate_204";
 
   try {
-    name = encodeURIComponent(name);
+    // 后端负责编码节点名称
     const result = await invoke<{ delay: number }>(
       "clash_api_get_proxy_delay",
       {
@@ -249,8 +249,14 @@ export async function exitApp() {
   return invoke("exit_app");
 }
 
-export async function exportDiagnosticInfo() {
-  return invoke("export_diagnostic_info");
+export async function exportDiagnosticInfo(options?: {
+  redactServers?: boolean;
+  logLimitMb?: number;
+}) {
+  return invoke<string>("export_diagnostic_info", {
+    redactServers: options?.redactServers,
+    logLimitMb: options?.logLimitMb,
+  });
 }
 
 export async function getSystemInfo() {
@@ -292,16 +298,16 @@ export async function getNetworkInterfacesInfo() {
   return invoke<INetworkInterface[]>("get_network_interfaces_info");
 }
 
-export async function createWebdavBackup() {
-  return invoke<void>("create_webdav_backup");
+export async function createBackup() {
+  return invoke<void>("create_backup");
 }
 
-export async function deleteWebdavBackup(filename: string) {
-  return invoke<void>("delete_webdav_backup", { filename });
+export async function deleteBackup(filename: string) {
+  return invoke<void>("delete_backup", { filename });
 }
 
-export async function restoreWebDavBackup(filename: string) {
-  return invoke<void>("restore_webdav_backup", { filename });
+export async function restoreBackup(filename: string) {
+  return invoke<void>("restore_backup", { filename });
 }
 
 export async function saveWebdavConfig(
@@ -316,12 +322,19 @@ export async function saveWebdavConfig(
   });
 }
 
-export async function listWebDavBackup() {
-  let list: IWebDavFile[] = await invoke<IWebDavFile[]>("list_webdav_backup");
-  list.map((item) => {
-    item.filename = item.href.split("/").pop() as string;
-  });
-  return list;
+export async function saveBackupStorage(
+  storage: IBackupStorageType,
+  options: {
+    localDir?: string | null;
+    s3?: IBackupS3Config | null;
+    sftp?: IBackupSftpConfig | null;
+  } = {},
+) {
+  return invoke<void>("save_backup_storage", { storage, ...options });
+}
+
+export async function listBackup() {
+  return invoke<IBackupFile[]>("list_backup");
 }
 
 export async function scriptValidateNotice(status: string, msg: string) {
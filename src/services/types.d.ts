@@ -801,17 +801,41 @@ interface IVergeConfig {
   webdav_url?: string;
   webdav_username?: string;
   webdav_password?: string;
+  backup_storage?: IBackupStorageType;
+  backup_local_dir?: string;
+  backup_s3?: IBackupS3Config;
+  backup_sftp?: IBackupSftpConfig;
   home_cards?: Record<string, boolean>;
   enable_hover_jump_navigator?: boolean;
 }
 
-interface IWebDavFile {
+interface IBackupFile {
   filename: string;
-  href: string;
-  last_modified: string;
+  // unix timestamp in seconds
+  last_modified: number;
   content_length: number;
-  content_type: string;
-  tag: string;
+}
+
+type IBackupStorageType = "webdav" | "local" | "s3" | "sftp";
+
+interface IBackupS3Config {
+  endpoint: string;
+  region?: string | null;
+  bucket: string;
+  access_key: string;
+  secret_key: string;
+  path_style?: boolean | null;
+}
+
+interface IBackupSftpConfig {
+  host: string;
+  port?: number | null;
+  username: string;
+  password?: string | null;
+  private_key?: string | null;
+  passphrase?: string | null;
+  dir?: string | null;
+  host_key?: string | null;
 }
 
 interface IWebDavConfig {